@@ -6,6 +6,7 @@
 #include "method-utils.hpp"
 #include "field-utils.hpp"
 #include "unity-utils.hpp"
+#include "pinvoke-utils.hpp"
 
 #include "beatsaber-hook/shared/utils/byref.hpp"
 #include "beatsaber-hook/shared/utils/il2cpp-utils-methods.hpp"
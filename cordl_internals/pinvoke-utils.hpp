@@ -0,0 +1,38 @@
+#pragma once
+
+#include "config.hpp"
+#include "exceptions.hpp"
+#include <dlfcn.h>
+#include <string>
+#include <string_view>
+
+namespace {
+namespace cordl_internals {
+
+struct PInvokeException : public ::il2cpp_utils::exceptions::StackTraceException {
+  using StackTraceException::StackTraceException;
+};
+
+/// @brief finds the function of a DllImport, trying the library names il2cpp tries on Android.
+/// __Internal functions are looked up in the loaded libraries
+CORDL_HIDDEN inline void* ResolvePInvoke(std::string_view library, char const* entryPoint) {
+  void* handle = nullptr;
+  if (library == "__Internal") {
+    handle = dlopen(nullptr, RTLD_LAZY);
+  } else {
+    std::string name(library);
+    for (auto const& candidate : { "lib" + name + ".so", name + ".so", name }) {
+      handle = dlopen(candidate.c_str(), RTLD_LAZY);
+      if (handle) break;
+    }
+  }
+
+  void* function = handle ? dlsym(handle, entryPoint) : nullptr;
+  if (!function) {
+    throw PInvokeException("Unable to find " + std::string(entryPoint) + " in " + std::string(library));
+  }
+  return function;
+}
+
+} // namespace cordl_internals
+} // end anonymous namespace
@@ -0,0 +1,437 @@
+//! Public API differences between two builds, for updating mods after a game update
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write as _,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use color_eyre::eyre::Result;
+use itertools::{EitherOrBoth, Itertools};
+use serde::Serialize;
+
+use crate::generate::{
+    cs_context_collection::TypeContextCollection,
+    json::{
+        is_real_declaring_type,
+        json_gen::{JsonMethod, JsonType, make_type},
+    },
+    metadata::CordlMetadata,
+};
+
+#[derive(Debug, Default, Serialize)]
+pub struct ApiDiff {
+    pub added_types: Vec<String>,
+    pub removed_types: Vec<String>,
+    pub changed_types: Vec<TypeDiff>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TypeDiff {
+    pub full_name: String,
+    /// (old, new) instance size
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<(u32, u32)>,
+    pub fields: Vec<MemberDiff>,
+    pub properties: Vec<MemberDiff>,
+    pub methods: Vec<MemberDiff>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MemberDiff {
+    pub name: String,
+    pub change: MemberChange,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind")]
+pub enum MemberChange {
+    Added {
+        signature: String,
+    },
+    Removed {
+        signature: String,
+    },
+    /// type, static/instance or parameters changed
+    Changed {
+        old: String,
+        new: String,
+    },
+    /// estimated code size of a method that kept its signature
+    SizeChanged {
+        signature: String,
+        old: usize,
+        new: usize,
+    },
+    /// instance field moved, e.g a field was added before it
+    OffsetShifted {
+        old: u32,
+        new: u32,
+    },
+}
+
+impl TypeDiff {
+    fn is_empty(&self) -> bool {
+        self.size.is_none()
+            && self.fields.is_empty()
+            && self.properties.is_empty()
+            && self.methods.is_empty()
+    }
+}
+
+/// Every non compiler generated type by full name
+pub fn collect_types(
+    metadata: &CordlMetadata,
+    collection: &TypeContextCollection,
+) -> BTreeMap<String, JsonType> {
+    collection
+        .get()
+        .values()
+        .flat_map(|c| c.get_types().values())
+        .filter(|t| is_real_declaring_type(t, metadata))
+        .map(|t| make_type(t, metadata, collection))
+        .map(|t| (t.full_name.clone(), t))
+        .collect()
+}
+
+/// Members keyed by name, changed when the signature differs
+fn diff_named<'a, T: 'a>(
+    old: impl Iterator<Item = &'a T>,
+    new: impl Iterator<Item = &'a T>,
+    name: impl Fn(&T) -> &str,
+    signature: impl Fn(&T) -> String,
+) -> Vec<MemberDiff> {
+    let old: BTreeMap<&str, &T> = old.map(|m| (name(m), m)).collect();
+    let new: BTreeMap<&str, &T> = new.map(|m| (name(m), m)).collect();
+
+    old.keys()
+        .chain(new.keys())
+        .copied()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter_map(|member| {
+            let change = match (old.get(member), new.get(member)) {
+                (Some(o), None) => MemberChange::Removed {
+                    signature: signature(o),
+                },
+                (None, Some(n)) => MemberChange::Added {
+                    signature: signature(n),
+                },
+                (Some(o), Some(n)) if signature(o) != signature(n) => MemberChange::Changed {
+                    old: signature(o),
+                    new: signature(n),
+                },
+                _ => return None,
+            };
+
+            Some(MemberDiff {
+                name: member.to_string(),
+                change,
+            })
+        })
+        .collect()
+}
+
+/// Identity of a method. Parameter names are left out, renaming one isn't a change
+fn method_key(method: &JsonMethod) -> String {
+    let modifiers = if method.instance { "" } else { "static " };
+    let params = method.parameters.iter().map(|p| p.ty.as_str()).join(", ");
+
+    format!("{modifiers}{} {}({params})", method.ret, method.name)
+}
+
+/// Methods can be overloaded, so they are keyed by their full signature.
+/// An overload removed and added under the same name is reported as changed
+fn diff_methods(old: &[JsonMethod], new: &[JsonMethod]) -> Vec<MemberDiff> {
+    let old_by_key = old.iter().into_group_map_by(|m| method_key(m));
+    let new_by_key = new.iter().into_group_map_by(|m| method_key(m));
+    let keys: BTreeSet<&String> = old_by_key.keys().chain(new_by_key.keys()).collect();
+
+    let mut removed: BTreeMap<&str, Vec<&JsonMethod>> = BTreeMap::new();
+    let mut added: BTreeMap<&str, Vec<&JsonMethod>> = BTreeMap::new();
+    let mut resized = vec![];
+    for key in keys {
+        let old_methods = old_by_key.get(key).map(Vec::as_slice).unwrap_or_default();
+        let new_methods = new_by_key.get(key).map(Vec::as_slice).unwrap_or_default();
+
+        for pair in old_methods.iter().zip_longest(new_methods) {
+            match pair {
+                EitherOrBoth::Both(o, n) => {
+                    if let (Some(old_size), Some(new_size)) =
+                        (o.method_info.estimated_size, n.method_info.estimated_size)
+                        && old_size != new_size
+                    {
+                        resized.push(MemberDiff {
+                            name: n.name.clone(),
+                            change: MemberChange::SizeChanged {
+                                signature: n.signature(None),
+                                old: old_size,
+                                new: new_size,
+                            },
+                        });
+                    }
+                }
+                EitherOrBoth::Left(o) => removed.entry(&o.name).or_default().push(o),
+                EitherOrBoth::Right(n) => added.entry(&n.name).or_default().push(n),
+            }
+        }
+    }
+
+    let names: BTreeSet<&str> = removed.keys().chain(added.keys()).copied().collect();
+
+    names
+        .into_iter()
+        .flat_map(|name| {
+            let removed = removed.get(name).map(Vec::as_slice).unwrap_or_default();
+            let added = added.get(name).map(Vec::as_slice).unwrap_or_default();
+
+            let changes = match (removed, added) {
+                ([o], [n]) => vec![MemberChange::Changed {
+                    old: o.signature(None),
+                    new: n.signature(None),
+                }],
+                _ => removed
+                    .iter()
+                    .map(|m| MemberChange::Removed {
+                        signature: m.signature(None),
+                    })
+                    .chain(added.iter().map(|m| MemberChange::Added {
+                        signature: m.signature(None),
+                    }))
+                    .collect_vec(),
+            };
+
+            changes.into_iter().map(move |change| MemberDiff {
+                name: name.to_string(),
+                change,
+            })
+        })
+        .chain(resized)
+        .sorted_by(|a, b| a.name.cmp(&b.name))
+        .collect()
+}
+
+fn diff_type(old: &JsonType, new: &JsonType) -> TypeDiff {
+    let mut fields = diff_named(
+        old.fields.iter(),
+        new.fields.iter(),
+        |f| &f.name,
+        |f| f.signature(None),
+    );
+
+    // offsets as computed by the offsets module
+    let new_offsets: BTreeMap<&str, u32> = new
+        .fields
+        .iter()
+        .filter_map(|f| Some((f.name.as_str(), f.offset?)))
+        .collect();
+    fields.extend(old.fields.iter().filter_map(|f| {
+        let old_offset = f.offset?;
+        let new_offset = *new_offsets.get(f.name.as_str())?;
+
+        (old_offset != new_offset).then(|| MemberDiff {
+            name: f.name.clone(),
+            change: MemberChange::OffsetShifted {
+                old: old_offset,
+                new: new_offset,
+            },
+        })
+    }));
+
+    TypeDiff {
+        full_name: old.full_name.clone(),
+        size: (old.size != new.size).then_some((old.size, new.size)),
+        fields,
+        properties: diff_named(
+            old.properties.iter(),
+            new.properties.iter(),
+            |p| &p.name,
+            |p| p.signature(None),
+        ),
+        methods: diff_methods(&old.methods, &new.methods),
+    }
+}
+
+pub fn diff(old: &BTreeMap<String, JsonType>, new: &BTreeMap<String, JsonType>) -> ApiDiff {
+    ApiDiff {
+        added_types: new
+            .keys()
+            .filter(|name| !old.contains_key(*name))
+            .cloned()
+            .collect(),
+        removed_types: old
+            .keys()
+            .filter(|name| !new.contains_key(*name))
+            .cloned()
+            .collect(),
+        changed_types: old
+            .iter()
+            .filter_map(|(name, old_ty)| Some(diff_type(old_ty, new.get(name)?)))
+            .filter(|d| !d.is_empty())
+            .collect(),
+    }
+}
+
+fn write_members(out: &mut String, kind: &str, members: &[MemberDiff]) -> std::fmt::Result {
+    for member in members {
+        match &member.change {
+            MemberChange::Added { signature } => writeln!(out, "- added {kind} `{signature}`")?,
+            MemberChange::Removed { signature } => writeln!(out, "- removed {kind} `{signature}`")?,
+            MemberChange::Changed { old, new } => {
+                writeln!(out, "- changed {kind} `{old}` -> `{new}`")?
+            }
+            MemberChange::SizeChanged {
+                signature,
+                old,
+                new,
+            } => writeln!(out, "- {kind} `{signature}` size 0x{old:x} -> 0x{new:x}")?,
+            MemberChange::OffsetShifted { old, new } => writeln!(
+                out,
+                "- {kind} `{}` moved 0x{old:x} -> 0x{new:x}",
+                member.name
+            )?,
+        }
+    }
+
+    Ok(())
+}
+
+pub fn to_markdown(diff: &ApiDiff) -> Result<String> {
+    let mut out = String::new();
+
+    writeln!(out, "# API diff")?;
+    writeln!(out)?;
+    writeln!(
+        out,
+        "{} types added, {} removed, {} changed",
+        diff.added_types.len(),
+        diff.removed_types.len(),
+        diff.changed_types.len()
+    )?;
+
+    for (title, types) in [
+        ("Added types", &diff.added_types),
+        ("Removed types", &diff.removed_types),
+    ] {
+        if types.is_empty() {
+            continue;
+        }
+
+        writeln!(out)?;
+        writeln!(out, "## {title}")?;
+        writeln!(out)?;
+        for ty in types {
+            writeln!(out, "- `{ty}`")?;
+        }
+    }
+
+    if !diff.changed_types.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "## Changed types")?;
+    }
+    for ty in &diff.changed_types {
+        writeln!(out)?;
+        writeln!(out, "### `{}`", ty.full_name)?;
+        writeln!(out)?;
+        if let Some((old, new)) = ty.size {
+            writeln!(out, "- size 0x{old:x} -> 0x{new:x}")?;
+        }
+        write_members(&mut out, "field", &ty.fields)?;
+        write_members(&mut out, "property", &ty.properties)?;
+        write_members(&mut out, "method", &ty.methods)?;
+    }
+
+    Ok(out)
+}
+
+pub fn write_diff(diff: &ApiDiff, markdown: &Path, json: &Path, format: bool) -> Result<()> {
+    File::create(markdown)?.write_all(to_markdown(diff)?.as_bytes())?;
+
+    let mut buf_writer = BufWriter::new(File::create(json)?);
+    match format {
+        true => serde_json::to_writer_pretty(&mut buf_writer, diff)?,
+        false => serde_json::to_writer(&mut buf_writer, diff)?,
+    };
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn method(name: &str, params: &[(&str, &str)], size: Option<usize>) -> JsonMethod {
+        let parameters = params
+            .iter()
+            .map(|(ty, name)| json!({ "name": name, "ty": ty, "ty_tag": { "Primitive": "I4" } }))
+            .collect_vec();
+
+        serde_json::from_value(json!({
+            "name": name,
+            "ret": "void",
+            "ret_ty_tag": { "Primitive": "Void" },
+            "parameters": parameters,
+            "instance": true,
+            "method_info": { "estimated_size": size },
+        }))
+        .unwrap()
+    }
+
+    fn changes(diff: &[MemberDiff]) -> Vec<String> {
+        diff.iter()
+            .map(|d| serde_json::to_string(&d.change).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn renamed_parameters_are_unchanged() {
+        let old = [method("Foo", &[("int", "a")], None)];
+        let new = [method("Foo", &[("int", "b")], None)];
+
+        assert!(diff_methods(&old, &new).is_empty());
+    }
+
+    #[test]
+    fn pairs_overloads_by_signature() {
+        let old = [
+            method("Foo", &[], Some(0x10)),
+            method("Foo", &[("int", "a")], Some(0x20)),
+        ];
+        let new = [
+            method("Foo", &[("int", "a")], Some(0x20)),
+            method("Foo", &[("float", "a")], None),
+            method("Foo", &[], Some(0x18)),
+        ];
+
+        assert_eq!(
+            changes(&diff_methods(&old, &new)),
+            [
+                r#"{"kind":"Added","signature":"void Foo(float a)"}"#,
+                r#"{"kind":"SizeChanged","signature":"void Foo()","old":16,"new":24}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn single_overload_replaced_is_changed() {
+        let old = [method("Foo", &[("int", "a")], Some(0x10))];
+        let new = [method("Foo", &[("long", "a")], Some(0x20))];
+
+        assert_eq!(
+            changes(&diff_methods(&old, &new)),
+            [r#"{"kind":"Changed","old":"void Foo(int a)","new":"void Foo(long a)"}"#]
+        );
+    }
+
+    #[test]
+    fn unknown_sizes_are_unchanged() {
+        let old = [method("Foo", &[], None)];
+        let new = [method("Foo", &[], Some(0x20))];
+
+        assert!(diff_methods(&old, &new).is_empty());
+    }
+}
@@ -0,0 +1,507 @@
+//! Inheritance, interface and usage graph of types, written as GraphViz DOT and GraphML
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use brocolib::global_metadata::TypeDefinitionIndex;
+use color_eyre::eyre::Result;
+use itertools::Itertools;
+use log::warn;
+
+use crate::{
+    data::type_resolver::{ResolvedType, ResolvedTypeData},
+    generate::{
+        cs_context_collection::TypeContextCollection, cs_type::CsType, cs_type_tag::CsTypeTag,
+    },
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, clap::ValueEnum)]
+pub enum EdgeKind {
+    /// Base type
+    Inherits,
+    /// Implemented interface
+    Implements,
+    /// Type of an instance field
+    Field,
+    /// Static fields, method and property signatures
+    Uses,
+}
+
+impl EdgeKind {
+    fn name(self) -> &'static str {
+        match self {
+            EdgeKind::Inherits => "inherits",
+            EdgeKind::Implements => "implements",
+            EdgeKind::Field => "field",
+            EdgeKind::Uses => "uses",
+        }
+    }
+
+    fn dot_style(self) -> &'static str {
+        match self {
+            EdgeKind::Inherits => "arrowhead=empty",
+            EdgeKind::Implements => "arrowhead=empty, style=dashed",
+            EdgeKind::Field => "color=blue",
+            EdgeKind::Uses => "color=gray, style=dotted",
+        }
+    }
+}
+
+pub struct TypeNode {
+    pub full_name: String,
+    pub namespace: String,
+}
+
+/// Generic instantiations are folded into their type definition
+#[derive(Default)]
+pub struct TypeGraph {
+    pub nodes: BTreeMap<TypeDefinitionIndex, TypeNode>,
+    /// (from, to, kind)
+    pub edges: BTreeSet<(TypeDefinitionIndex, TypeDefinitionIndex, EdgeKind)>,
+}
+
+pub fn make_graph(collection: &TypeContextCollection, kinds: &[EdgeKind]) -> TypeGraph {
+    let mut graph = TypeGraph::default();
+
+    for ty in collection
+        .get()
+        .values()
+        .flat_map(|c| c.get_types().values())
+    {
+        let CsTypeTag::TypeDefinitionIndex(tdi) = ty.self_tag else {
+            continue;
+        };
+
+        graph.nodes.insert(
+            tdi,
+            TypeNode {
+                full_name: ty.cs_name_components.combine_all(),
+                namespace: ty.namespace(),
+            },
+        );
+
+        for (kind, target) in type_edges(ty) {
+            if kinds.contains(&kind) && target != tdi {
+                graph.edges.insert((tdi, target, kind));
+            }
+        }
+    }
+
+    // blacklisted types and types only referenced through generic instantiations
+    graph
+        .edges
+        .retain(|(_, to, _)| graph.nodes.contains_key(to));
+
+    graph
+}
+
+fn type_edges(ty: &CsType) -> Vec<(EdgeKind, TypeDefinitionIndex)> {
+    let mut references: Vec<(EdgeKind, &ResolvedType)> = vec![];
+
+    references.extend(ty.parent.iter().map(|p| (EdgeKind::Inherits, p)));
+    references.extend(ty.interfaces.iter().map(|i| (EdgeKind::Implements, i)));
+    references.extend(ty.fields.iter().map(|f| match f.instance {
+        true => (EdgeKind::Field, &f.field_ty),
+        false => (EdgeKind::Uses, &f.field_ty),
+    }));
+    references.extend(ty.properties.iter().map(|p| (EdgeKind::Uses, &p.prop_ty)));
+    for method in &ty.methods {
+        references.push((EdgeKind::Uses, &method.return_type));
+        references.extend(
+            method
+                .parameters
+                .iter()
+                .map(|p| (EdgeKind::Uses, &p.il2cpp_ty)),
+        );
+    }
+
+    let mut edges = vec![];
+    for (kind, resolved) in references {
+        let mut targets = vec![];
+        referenced_types(resolved, &mut targets);
+        edges.extend(targets.into_iter().map(|t| (kind, t)));
+    }
+    edges.extend(
+        ty.requirements
+            .depending_types
+            .iter()
+            .map(|tag| (EdgeKind::Uses, tag.get_tdi())),
+    );
+
+    edges
+}
+
+/// Type definitions named by `ty`, including generic arguments and element types
+fn referenced_types(ty: &ResolvedType, out: &mut Vec<TypeDefinitionIndex>) {
+    match &ty.data {
+        ResolvedTypeData::Type(tag) => out.push(tag.get_tdi()),
+        ResolvedTypeData::GenericInst(inner, args) => {
+            referenced_types(inner, out);
+            for (arg, _) in args {
+                referenced_types(arg, out);
+            }
+        }
+        ResolvedTypeData::Array(inner)
+        | ResolvedTypeData::Ptr(inner)
+        | ResolvedTypeData::ByRef(inner)
+        | ResolvedTypeData::ByRefConst(inner) => referenced_types(inner, out),
+        ResolvedTypeData::Primitive(_)
+        | ResolvedTypeData::GenericArg(_, _)
+        | ResolvedTypeData::GenericMethodArg(_, _, _)
+        | ResolvedTypeData::Blacklisted(_) => {}
+    }
+}
+
+impl TypeGraph {
+    /// Keeps types in `namespaces` or their sub namespaces,
+    /// and the `roots` with every type deriving from or implementing them
+    pub fn filter(&mut self, namespaces: &[String], roots: &[String]) {
+        if namespaces.is_empty() && roots.is_empty() {
+            return;
+        }
+
+        let mut keep: HashSet<TypeDefinitionIndex> = self
+            .nodes
+            .iter()
+            .filter(|(_, node)| {
+                namespaces.iter().any(|ns| {
+                    node.namespace == *ns || node.namespace.starts_with(&format!("{ns}."))
+                })
+            })
+            .map(|(tdi, _)| *tdi)
+            .collect();
+
+        for root in roots {
+            if !self.nodes.values().any(|n| n.full_name == *root) {
+                warn!("Root type {root} not found");
+            }
+        }
+
+        let derived: HashMap<TypeDefinitionIndex, Vec<TypeDefinitionIndex>> = self
+            .edges
+            .iter()
+            .filter(|(_, _, kind)| matches!(kind, EdgeKind::Inherits | EdgeKind::Implements))
+            .map(|(from, to, _)| (*to, *from))
+            .into_group_map();
+
+        let mut visited = HashSet::new();
+        let mut stack = self
+            .nodes
+            .iter()
+            .filter(|(_, node)| roots.contains(&node.full_name))
+            .map(|(tdi, _)| *tdi)
+            .collect_vec();
+        while let Some(tdi) = stack.pop() {
+            if !visited.insert(tdi) {
+                continue;
+            }
+            keep.insert(tdi);
+            stack.extend(derived.get(&tdi).into_iter().flatten().copied());
+        }
+
+        self.retain_nodes(|tdi| keep.contains(tdi));
+    }
+
+    /// Keeps only types that are part of a cycle, like include cycles between value types
+    pub fn retain_cycles(&mut self) {
+        let adjacency: HashMap<TypeDefinitionIndex, Vec<TypeDefinitionIndex>> = self
+            .edges
+            .iter()
+            .map(|(from, to, _)| (*from, *to))
+            .into_group_map();
+
+        let in_cycle: HashSet<TypeDefinitionIndex> =
+            strongly_connected(self.nodes.keys().copied(), &adjacency)
+                .into_iter()
+                .filter(|component| component.len() > 1)
+                .flatten()
+                .collect();
+
+        self.retain_nodes(|tdi| in_cycle.contains(tdi));
+    }
+
+    fn retain_nodes(&mut self, keep: impl Fn(&TypeDefinitionIndex) -> bool) {
+        self.nodes.retain(|tdi, _| keep(tdi));
+        self.edges.retain(|(from, to, _)| keep(from) && keep(to));
+    }
+}
+
+/// Tarjan's algorithm, iterative since inheritance and field chains get deep
+fn strongly_connected(
+    nodes: impl Iterator<Item = TypeDefinitionIndex>,
+    adjacency: &HashMap<TypeDefinitionIndex, Vec<TypeDefinitionIndex>>,
+) -> Vec<Vec<TypeDefinitionIndex>> {
+    let mut index: HashMap<TypeDefinitionIndex, usize> = HashMap::new();
+    let mut lowlink: HashMap<TypeDefinitionIndex, usize> = HashMap::new();
+    let mut on_stack = HashSet::new();
+    let mut stack = vec![];
+    let mut components = vec![];
+
+    for start in nodes {
+        if index.contains_key(&start) {
+            continue;
+        }
+
+        // (node, index of the next successor to visit)
+        let mut call_stack = vec![(start, 0usize)];
+        while let Some((node, next_successor)) = call_stack.last().copied() {
+            if next_successor == 0 && !index.contains_key(&node) {
+                index.insert(node, index.len());
+                lowlink.insert(node, index[&node]);
+                stack.push(node);
+                on_stack.insert(node);
+            }
+
+            let successors = adjacency.get(&node).map(Vec::as_slice).unwrap_or_default();
+            if let Some(&successor) = successors.get(next_successor) {
+                call_stack.last_mut().unwrap().1 += 1;
+
+                if !index.contains_key(&successor) {
+                    call_stack.push((successor, 0));
+                } else if on_stack.contains(&successor) {
+                    let low = lowlink[&node].min(index[&successor]);
+                    lowlink.insert(node, low);
+                }
+                continue;
+            }
+
+            call_stack.pop();
+            if let Some(&(parent, _)) = call_stack.last() {
+                let low = lowlink[&parent].min(lowlink[&node]);
+                lowlink.insert(parent, low);
+            }
+
+            if lowlink[&node] == index[&node] {
+                let mut component = vec![];
+                loop {
+                    let member = stack.pop().unwrap();
+                    on_stack.remove(&member);
+                    component.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+                components.push(component);
+            }
+        }
+    }
+
+    components
+}
+
+fn node_id(tdi: TypeDefinitionIndex) -> String {
+    format!("t{}", tdi.index())
+}
+
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Namespaces become clusters, base types are drawn above the types deriving from them
+pub fn write_dot(graph: &TypeGraph, file: &Path) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(file)?);
+
+    writeln!(writer, "digraph cordl {{")?;
+    writeln!(writer, "    rankdir=BT;")?;
+    writeln!(writer, "    node [shape=box, fontname=\"monospace\"];")?;
+
+    let namespaces = graph
+        .nodes
+        .iter()
+        .into_group_map_by(|(_, node)| node.namespace.as_str());
+    for (namespace, nodes) in namespaces.into_iter().sorted_by_key(|(ns, _)| *ns) {
+        let label = match namespace.is_empty() {
+            true => "GlobalNamespace",
+            false => namespace,
+        };
+
+        writeln!(writer, "    subgraph \"cluster_{}\" {{", dot_escape(label))?;
+        writeln!(writer, "        label=\"{}\";", dot_escape(label))?;
+        for (tdi, node) in nodes {
+            writeln!(
+                writer,
+                "        {} [label=\"{}\"];",
+                node_id(*tdi),
+                dot_escape(&node.full_name)
+            )?;
+        }
+        writeln!(writer, "    }}")?;
+    }
+
+    for (from, to, kind) in &graph.edges {
+        writeln!(
+            writer,
+            "    {} -> {} [{}];",
+            node_id(*from),
+            node_id(*to),
+            kind.dot_style()
+        )?;
+    }
+
+    writeln!(writer, "}}")?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+pub fn write_graphml(graph: &TypeGraph, file: &Path) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(file)?);
+
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        writer,
+        r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+    )?;
+    writeln!(
+        writer,
+        r#"  <key id="name" for="node" attr.name="name" attr.type="string"/>"#
+    )?;
+    writeln!(
+        writer,
+        r#"  <key id="namespace" for="node" attr.name="namespace" attr.type="string"/>"#
+    )?;
+    writeln!(
+        writer,
+        r#"  <key id="kind" for="edge" attr.name="kind" attr.type="string"/>"#
+    )?;
+    writeln!(writer, r#"  <graph id="cordl" edgedefault="directed">"#)?;
+
+    for (tdi, node) in &graph.nodes {
+        writeln!(
+            writer,
+            r#"    <node id="{}"><data key="name">{}</data><data key="namespace">{}</data></node>"#,
+            node_id(*tdi),
+            xml_escape(&node.full_name),
+            xml_escape(&node.namespace)
+        )?;
+    }
+    for (from, to, kind) in &graph.edges {
+        writeln!(
+            writer,
+            r#"    <edge source="{}" target="{}"><data key="kind">{}</data></edge>"#,
+            node_id(*from),
+            node_id(*to),
+            kind.name()
+        )?;
+    }
+
+    writeln!(writer, "  </graph>")?;
+    writeln!(writer, "</graphml>")?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tdi(i: u32) -> TypeDefinitionIndex {
+        TypeDefinitionIndex::new(i)
+    }
+
+    fn graph(nodes: &[(u32, &str, &str)], edges: &[(u32, u32, EdgeKind)]) -> TypeGraph {
+        TypeGraph {
+            nodes: nodes
+                .iter()
+                .map(|&(i, namespace, name)| {
+                    let node = TypeNode {
+                        full_name: format!("{namespace}.{name}"),
+                        namespace: namespace.to_string(),
+                    };
+                    (tdi(i), node)
+                })
+                .collect(),
+            edges: edges
+                .iter()
+                .map(|&(from, to, kind)| (tdi(from), tdi(to), kind))
+                .collect(),
+        }
+    }
+
+    fn components(edges: &[(u32, u32)], node_count: u32) -> Vec<Vec<u32>> {
+        let adjacency = edges
+            .iter()
+            .map(|&(from, to)| (tdi(from), tdi(to)))
+            .into_group_map();
+
+        strongly_connected((0..node_count).map(tdi), &adjacency)
+            .into_iter()
+            .map(|component| component.iter().map(|t| t.index()).sorted().collect())
+            .collect()
+    }
+
+    #[test]
+    fn finds_strongly_connected_components() {
+        // 0 -> 1 -> 2 -> 0 is a cycle reaching 3 <-> 4, 5 stands alone
+        let edges = [(0, 1), (1, 2), (2, 0), (2, 3), (3, 4), (4, 3)];
+
+        // components come out after every component they reach
+        assert_eq!(components(&edges, 6), [vec![3, 4], vec![0, 1, 2], vec![5]]);
+    }
+
+    #[test]
+    fn deep_chains_dont_overflow() {
+        let edges = (0..100_000).map(|i| (i, i + 1)).collect_vec();
+
+        assert_eq!(components(&edges, 100_001).len(), 100_001);
+    }
+
+    #[test]
+    fn keeps_types_in_cycles() {
+        let mut graph = graph(
+            &[(0, "A", "Node"), (1, "A", "Edge"), (2, "A", "Leaf")],
+            &[
+                (0, 1, EdgeKind::Field),
+                (1, 0, EdgeKind::Field),
+                (1, 2, EdgeKind::Uses),
+                (2, 2, EdgeKind::Field),
+            ],
+        );
+        graph.retain_cycles();
+
+        assert_eq!(graph.nodes.keys().copied().collect_vec(), [tdi(0), tdi(1)]);
+        assert_eq!(graph.edges.len(), 2);
+    }
+
+    #[test]
+    fn filters_by_namespace_and_root() {
+        let mut graph = graph(
+            &[
+                (0, "Game", "Player"),
+                (1, "Game.UI", "Menu"),
+                (2, "GameData", "Save"),
+                (3, "System", "Object"),
+                (4, "Lib", "IThing"),
+                (5, "Lib", "Thing"),
+                (6, "Lib", "Other"),
+            ],
+            &[
+                (0, 3, EdgeKind::Inherits),
+                (5, 4, EdgeKind::Implements),
+                (6, 5, EdgeKind::Uses),
+            ],
+        );
+        graph.filter(&["Game".to_string()], &["Lib.IThing".to_string()]);
+
+        assert_eq!(
+            graph.nodes.keys().map(|t| t.index()).collect_vec(),
+            [0, 1, 4, 5]
+        );
+        assert_eq!(
+            graph.edges.iter().copied().collect_vec(),
+            [(tdi(5), tdi(4), EdgeKind::Implements)]
+        );
+    }
+}
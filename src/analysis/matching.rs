@@ -0,0 +1,624 @@
+//! Pairs types and methods between two builds of the same game,
+//! so addresses found in one build can be ported to the next.
+//!
+//! Types are paired first, by full name then by a name free shape,
+//! so renamed or obfuscated types still pair up.
+//! Methods are only paired inside paired types, most confident first:
+//! exact signature, name, then structure.
+
+use std::{
+    collections::{HashMap, HashSet},
+    iter,
+};
+
+use brocolib::global_metadata::{Il2CppTypeDefinition, MethodIndex, TypeDefinitionIndex};
+use itertools::Itertools;
+use log::info;
+
+use crate::generate::{cs_type_tag::CsTypeTag, metadata::CordlMetadata};
+
+use super::xrefs::XrefAnalysis;
+
+/// Structural matches scoring below this are discarded
+const MIN_STRUCTURE_SCORE: f32 = 0.6;
+/// Parents included in a type shape, guarding against malformed cyclic hierarchies
+const MAX_SHAPE_DEPTH: usize = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+pub enum MatchKind {
+    /// Same full name and signature
+    Signature,
+    /// Same name in the paired type, signature changed
+    Name,
+    /// Renamed or obfuscated, paired by shape
+    Structure,
+}
+
+#[derive(Clone, Debug)]
+pub struct TypeMatch {
+    pub old: TypeDefinitionIndex,
+    pub new: TypeDefinitionIndex,
+    pub confidence: f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct MethodMatch {
+    pub old: MethodIndex,
+    pub new: MethodIndex,
+    pub kind: MatchKind,
+    pub confidence: f32,
+}
+
+#[derive(Debug, Default)]
+pub struct BuildMatches {
+    pub types: Vec<TypeMatch>,
+    pub methods: Vec<MethodMatch>,
+}
+
+/// A side of the match, the metadata of one build and its call graph
+pub struct Build<'a, 'b> {
+    pub metadata: &'b CordlMetadata<'a>,
+    pub xrefs: &'b XrefAnalysis,
+}
+
+struct TypeEntry {
+    tdi: TypeDefinitionIndex,
+    full_name: String,
+    /// Field layouts and member counts of the type and its parents, without any names
+    shape: String,
+}
+
+struct MethodEntry {
+    method_index: MethodIndex,
+    tdi: TypeDefinitionIndex,
+    name: String,
+    /// return type, name and parameter types, without the declaring type
+    signature: String,
+    params: Vec<String>,
+    return_type: String,
+    is_static: bool,
+    size: Option<usize>,
+}
+
+impl Build<'_, '_> {
+    fn types(&self) -> Vec<TypeEntry> {
+        let metadata = self.metadata.metadata;
+        let types = &self.metadata.metadata_registration.types;
+        let type_definitions = metadata.global_metadata.type_definitions.as_vec();
+
+        let own_shapes = type_definitions
+            .iter()
+            .map(|td| self.own_shape(td))
+            .collect_vec();
+        let parents = type_definitions
+            .iter()
+            .map(|td| {
+                (td.parent_index != u32::MAX).then(|| {
+                    CsTypeTag::from_type_data(types[td.parent_index as usize].data, metadata)
+                        .get_tdi()
+                        .index() as usize
+                })
+            })
+            .collect_vec();
+
+        type_definitions
+            .iter()
+            .zip(chained_shapes(&own_shapes, &parents))
+            .enumerate()
+            .map(|(tdi, (td, shape))| TypeEntry {
+                tdi: TypeDefinitionIndex::new(tdi as u32),
+                full_name: td.full_name(metadata, true),
+                shape,
+            })
+            .collect()
+    }
+
+    /// Kind, member counts and field layouts. Primitive fields keep their type,
+    /// other fields only their category since their type may be renamed too
+    fn own_shape(&self, td: &Il2CppTypeDefinition) -> String {
+        let metadata = self.metadata.metadata;
+        let types = &self.metadata.metadata_registration.types;
+
+        let kind = if td.is_enum_type() {
+            "enum"
+        } else if td.is_value_type() {
+            "struct"
+        } else if td.is_interface() {
+            "interface"
+        } else {
+            "class"
+        };
+        let generic = match td.generic_container_index.is_valid() {
+            true => "<>",
+            false => "",
+        };
+        let fields = td
+            .fields(metadata)
+            .iter()
+            .map(|f| {
+                let ty = &types[f.type_index as usize];
+                match ty.is_static() {
+                    true => format!("static {:?}", ty.ty),
+                    false => format!("{:?}", ty.ty),
+                }
+            })
+            .join(",");
+
+        format!(
+            "{kind}{generic} [{fields}] m{} p{} n{} i{}",
+            td.method_count, td.property_count, td.nested_type_count, td.interfaces_count
+        )
+    }
+
+    /// `type_names` rewrites type names into those of the other build
+    fn methods(&self, type_names: &HashMap<String, String>) -> Vec<MethodEntry> {
+        let metadata = self.metadata.metadata;
+        let types = &self.metadata.metadata_registration.types;
+        let type_name = |type_index: u32| {
+            let name = types[type_index as usize].full_name(metadata);
+            type_names.get(&name).cloned().unwrap_or(name)
+        };
+
+        metadata
+            .global_metadata
+            .methods
+            .as_vec()
+            .iter()
+            .enumerate()
+            .map(|(i, method)| {
+                let method_index = MethodIndex::new(i as u32);
+                let name = method.name(metadata).to_string();
+                let return_type = type_name(method.return_type);
+                let params = method
+                    .parameters(metadata)
+                    .iter()
+                    .map(|p| type_name(p.type_index))
+                    .collect_vec();
+
+                MethodEntry {
+                    method_index,
+                    tdi: method.declaring_type,
+                    signature: format!("{return_type} {name}({})", params.join(", ")),
+                    name,
+                    params,
+                    return_type,
+                    is_static: method.is_static_method(),
+                    size: self
+                        .metadata
+                        .method_calculations
+                        .get(&method_index)
+                        .filter(|c| c.estimated_size != usize::MAX)
+                        .map(|c| c.estimated_size),
+                }
+            })
+            .collect()
+    }
+
+    fn callees(&self, method_index: MethodIndex) -> impl Iterator<Item = MethodIndex> + '_ {
+        self.xrefs
+            .calls
+            .get(&method_index.index())
+            .into_iter()
+            .flatten()
+            .map(|m| MethodIndex::new(*m))
+    }
+}
+
+/// Shape of each type followed by the shapes of its parents
+fn chained_shapes(own_shapes: &[String], parents: &[Option<usize>]) -> Vec<String> {
+    (0..own_shapes.len())
+        .map(|i| {
+            iter::successors(Some(i), |p| parents[*p])
+                .take(MAX_SHAPE_DEPTH)
+                .map(|p| own_shapes[p].as_str())
+                .join(" : ")
+        })
+        .collect()
+}
+
+/// Keys that occur exactly once, mapped to their position
+fn unique_keys<'e, T>(entries: &'e [T], key: impl Fn(&T) -> &str) -> HashMap<&'e str, usize> {
+    let mut counts: HashMap<&str, Option<usize>> = HashMap::new();
+    for (i, entry) in entries.iter().enumerate() {
+        counts
+            .entry(key(entry))
+            .and_modify(|c| *c = None)
+            .or_insert(Some(i));
+    }
+
+    counts
+        .into_iter()
+        .filter_map(|(k, i)| Some((k, i?)))
+        .collect()
+}
+
+/// Pairs keys unique on both sides
+fn match_unique<'e, T>(
+    old: &'e [T],
+    new: &'e [T],
+    key: impl Fn(&T) -> &str + Copy,
+) -> Vec<(usize, usize)> {
+    let new_keys = unique_keys(new, key);
+
+    unique_keys(old, key)
+        .into_iter()
+        .filter_map(|(k, i)| Some((i, *new_keys.get(k)?)))
+        .sorted()
+        .collect()
+}
+
+pub fn match_builds(old: &Build, new: &Build) -> BuildMatches {
+    let mut matches = BuildMatches::default();
+
+    // types
+    let old_types = old.types();
+    let new_types = new.types();
+
+    let mut matched_old_types = HashSet::new();
+    let mut matched_new_types = HashSet::new();
+    for (o, n) in match_unique(&old_types, &new_types, |t| &t.full_name) {
+        matched_old_types.insert(old_types[o].tdi);
+        matched_new_types.insert(new_types[n].tdi);
+        matches.types.push(TypeMatch {
+            old: old_types[o].tdi,
+            new: new_types[n].tdi,
+            confidence: 1.0,
+        });
+    }
+
+    // shapes only need to be unique among the types left over
+    let unmatched_old_types = old_types
+        .iter()
+        .filter(|t| !matched_old_types.contains(&t.tdi))
+        .collect_vec();
+    let unmatched_new_types = new_types
+        .iter()
+        .filter(|t| !matched_new_types.contains(&t.tdi))
+        .collect_vec();
+    let renamed = match_unique(&unmatched_old_types, &unmatched_new_types, |t| &t.shape);
+    for (o, n) in renamed {
+        matches.types.push(TypeMatch {
+            old: unmatched_old_types[o].tdi,
+            new: unmatched_new_types[n].tdi,
+            confidence: 0.7,
+        });
+    }
+
+    // type names as written in the new build, so parameters of renamed types still compare equal
+    let type_names: HashMap<String, String> = matches
+        .types
+        .iter()
+        .map(|m| {
+            let old_td = &old.metadata.metadata.global_metadata.type_definitions[m.old];
+            let new_td = &new.metadata.metadata.global_metadata.type_definitions[m.new];
+            (
+                old_td.full_name(old.metadata.metadata, true),
+                new_td.full_name(new.metadata.metadata, true),
+            )
+        })
+        .collect();
+
+    info!("Matched {}/{} types", matches.types.len(), old_types.len());
+
+    // methods, inside each pair of types
+    let old_methods = old.methods(&type_names);
+    let new_methods = new.methods(&HashMap::new());
+    let old_by_type = old_methods.iter().into_group_map_by(|m| m.tdi);
+    let new_by_type = new_methods.iter().into_group_map_by(|m| m.tdi);
+
+    let mut method_map: HashMap<MethodIndex, MethodIndex> = HashMap::new();
+    let mut matched_new_methods: HashSet<MethodIndex> = HashSet::new();
+    let type_pairs = matches.types.iter().map(|m| (m.old, m.new)).collect_vec();
+    for (old_tdi, new_tdi) in type_pairs {
+        let (Some(old_group), Some(new_group)) =
+            (old_by_type.get(&old_tdi), new_by_type.get(&new_tdi))
+        else {
+            continue;
+        };
+
+        for (kind, confidence) in [(MatchKind::Signature, 1.0), (MatchKind::Name, 0.8)] {
+            let old_rest = old_group
+                .iter()
+                .filter(|m| !method_map.contains_key(&m.method_index))
+                .collect_vec();
+            let new_rest = new_group
+                .iter()
+                .filter(|m| !matched_new_methods.contains(&m.method_index))
+                .collect_vec();
+
+            let pairs = match_unique(&old_rest, &new_rest, |m| match kind {
+                MatchKind::Signature => &m.signature,
+                _ => &m.name,
+            });
+            for (o, n) in pairs {
+                add_method_match(
+                    &mut matches,
+                    &mut method_map,
+                    &mut matched_new_methods,
+                    MethodMatch {
+                        old: old_rest[o].method_index,
+                        new: new_rest[n].method_index,
+                        kind,
+                        confidence,
+                    },
+                );
+            }
+        }
+
+        // structure, scored greedily so each method is used once
+        let mut candidates = vec![];
+        for old_method in old_group {
+            if method_map.contains_key(&old_method.method_index) {
+                continue;
+            }
+
+            for new_method in new_group {
+                if matched_new_methods.contains(&new_method.method_index) {
+                    continue;
+                }
+
+                let score = structure_score(old, new, old_method, new_method, &method_map);
+                if let Some(score) = score
+                    && score >= MIN_STRUCTURE_SCORE
+                {
+                    candidates.push((score, old_method.method_index, new_method.method_index));
+                }
+            }
+        }
+
+        candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+        for (score, old_method, new_method) in candidates {
+            if method_map.contains_key(&old_method) || matched_new_methods.contains(&new_method) {
+                continue;
+            }
+
+            add_method_match(
+                &mut matches,
+                &mut method_map,
+                &mut matched_new_methods,
+                MethodMatch {
+                    old: old_method,
+                    new: new_method,
+                    kind: MatchKind::Structure,
+                    // never as confident as a name match
+                    confidence: score * 0.75,
+                },
+            );
+        }
+    }
+
+    info!(
+        "Matched {}/{} methods",
+        matches.methods.len(),
+        old_methods.len()
+    );
+
+    matches
+        .methods
+        .sort_by_key(|m| (m.old.index(), m.new.index()));
+    matches
+}
+
+fn add_method_match(
+    matches: &mut BuildMatches,
+    method_map: &mut HashMap<MethodIndex, MethodIndex>,
+    matched_new_methods: &mut HashSet<MethodIndex>,
+    method_match: MethodMatch,
+) {
+    method_map.insert(method_match.old, method_match.new);
+    matched_new_methods.insert(method_match.new);
+    matches.methods.push(method_match);
+}
+
+/// Similarity of two methods between 0 and 1,
+/// None if they can't be the same method
+fn structure_score(
+    old: &Build,
+    new: &Build,
+    old_method: &MethodEntry,
+    new_method: &MethodEntry,
+    method_map: &HashMap<MethodIndex, MethodIndex>,
+) -> Option<f32> {
+    if old_method.is_static != new_method.is_static
+        || old_method.params.len() != new_method.params.len()
+    {
+        return None;
+    }
+
+    let params = match old_method.params.is_empty() {
+        true => 1.0,
+        false => {
+            let equal = old_method
+                .params
+                .iter()
+                .zip(&new_method.params)
+                .filter(|(o, n)| o == n)
+                .count();
+            equal as f32 / old_method.params.len() as f32
+        }
+    };
+    let return_type = match old_method.return_type == new_method.return_type {
+        true => 1.0,
+        false => 0.0,
+    };
+    let size = match (old_method.size, new_method.size) {
+        (Some(o), Some(n)) if o.max(n) > 0 => o.min(n) as f32 / o.max(n) as f32,
+        // abstract or unknown, neutral
+        _ => 0.5,
+    };
+
+    let old_callees = old.callees(old_method.method_index).collect_vec();
+    let new_callees: HashSet<MethodIndex> = new.callees(new_method.method_index).collect();
+    let callee_count = match old_callees.len().max(new_callees.len()) {
+        0 => 1.0,
+        max => old_callees.len().min(new_callees.len()) as f32 / max as f32,
+    };
+    // callees already matched should be called by both
+    let mapped_callees = old_callees
+        .iter()
+        .filter_map(|c| method_map.get(c))
+        .collect_vec();
+    let callee_agreement = match mapped_callees.is_empty() {
+        true => 0.5,
+        false => {
+            mapped_callees
+                .iter()
+                .filter(|c| new_callees.contains(**c))
+                .count() as f32
+                / mapped_callees.len() as f32
+        }
+    };
+
+    Some(
+        params * 0.35
+            + return_type * 0.15
+            + size * 0.25
+            + callee_count * 0.1
+            + callee_agreement * 0.15,
+    )
+}
+
+#[cfg(feature = "json")]
+pub mod json {
+    use std::{collections::HashSet, fs::File, io::BufWriter, path::Path};
+
+    use color_eyre::eyre::Result;
+    use itertools::Itertools;
+    use serde::Serialize;
+
+    use super::{Build, BuildMatches, MatchKind};
+
+    #[derive(Serialize)]
+    pub struct JsonBuildMatches {
+        pub types: Vec<JsonTypeMatch>,
+        pub methods: Vec<JsonMethodMatch>,
+        /// methods of the old build without a match
+        pub unmatched: Vec<String>,
+    }
+
+    #[derive(Serialize)]
+    pub struct JsonTypeMatch {
+        pub old_name: String,
+        pub new_name: String,
+        pub confidence: f32,
+    }
+
+    #[derive(Serialize)]
+    pub struct JsonMethodMatch {
+        pub old_name: String,
+        pub new_name: String,
+        pub old_addrs: Option<u64>,
+        pub new_addrs: Option<u64>,
+        pub kind: MatchKind,
+        pub confidence: f32,
+    }
+
+    pub fn make_json(
+        matches: &BuildMatches,
+        old: &Build,
+        new: &Build,
+        file: &Path,
+        format: bool,
+    ) -> Result<()> {
+        let types = matches
+            .types
+            .iter()
+            .map(|m| JsonTypeMatch {
+                old_name: old.metadata.metadata.global_metadata.type_definitions[m.old]
+                    .full_name(old.metadata.metadata, true),
+                new_name: new.metadata.metadata.global_metadata.type_definitions[m.new]
+                    .full_name(new.metadata.metadata, true),
+                confidence: m.confidence,
+            })
+            .collect_vec();
+
+        let addrs = |build: &Build, method_index| {
+            build
+                .metadata
+                .method_calculations
+                .get(&method_index)
+                .map(|c| c.addrs)
+                .filter(|a| *a != 0x0)
+        };
+
+        let methods = matches
+            .methods
+            .iter()
+            .map(|m| JsonMethodMatch {
+                old_name: old.metadata.method_full_signature(m.old),
+                new_name: new.metadata.method_full_signature(m.new),
+                old_addrs: addrs(old, m.old),
+                new_addrs: addrs(new, m.new),
+                kind: m.kind,
+                confidence: m.confidence,
+            })
+            .collect_vec();
+
+        let matched: HashSet<_> = matches.methods.iter().map(|m| m.old).collect();
+        let unmatched = old
+            .metadata
+            .metadata
+            .global_metadata
+            .methods
+            .as_vec()
+            .iter()
+            .enumerate()
+            .map(|(i, _)| brocolib::global_metadata::MethodIndex::new(i as u32))
+            .filter(|m| !matched.contains(m))
+            .map(|m| old.metadata.method_full_signature(m))
+            .collect_vec();
+
+        let json = JsonBuildMatches {
+            types,
+            methods,
+            unmatched,
+        };
+
+        let mut buf_writer = BufWriter::new(File::create(file)?);
+        match format {
+            true => serde_json::to_writer_pretty(&mut buf_writer, &json)?,
+            false => serde_json::to_writer(&mut buf_writer, &json)?,
+        };
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pairs_keys_unique_on_both_sides() {
+        let old = ["a", "b", "b", "c", "d"];
+        let new = ["d", "c", "b", "a", "a"];
+
+        // b is ambiguous in the old build and a in the new one
+        assert_eq!(match_unique(&old, &new, |k| k), [(3, 1), (4, 0)]);
+    }
+
+    #[test]
+    fn chains_parent_shapes() {
+        let own_shapes = ["class [] m1", "class [I4] m2", "struct [] m0"].map(String::from);
+        let parents = [None, Some(0), Some(1)];
+
+        assert_eq!(
+            chained_shapes(&own_shapes, &parents),
+            [
+                "class [] m1",
+                "class [I4] m2 : class [] m1",
+                "struct [] m0 : class [I4] m2 : class [] m1",
+            ]
+        );
+    }
+
+    #[test]
+    fn cyclic_parents_terminate() {
+        let own_shapes = ["a", "b"].map(String::from);
+        let parents = [Some(1), Some(0)];
+
+        let shapes = chained_shapes(&own_shapes, &parents);
+        assert_eq!(shapes[0].split(" : ").count(), MAX_SHAPE_DEPTH);
+    }
+}
@@ -0,0 +1,11 @@
+#[cfg(feature = "json")]
+pub mod api_diff;
+pub mod graph;
+pub mod matching;
+#[cfg(feature = "json")]
+pub mod search;
+pub mod signatures;
+pub mod stats;
+pub mod string_literals;
+pub mod symbolize;
+pub mod xrefs;
@@ -0,0 +1,501 @@
+//! Name and signature queries over every type and member, for `cordl search`
+
+use std::collections::HashMap;
+
+use color_eyre::eyre::Result;
+use itertools::Itertools;
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+
+use brocolib::global_metadata::TypeDefinitionIndex;
+
+use crate::generate::{
+    cs_context_collection::TypeContextCollection,
+    cs_type_tag::CsTypeTag,
+    json::json_gen::{JsonType, make_type},
+    metadata::{CordlMetadata, Il2cppFullName},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchKind {
+    Type,
+    Field,
+    Property,
+    Method,
+}
+
+/// A type or member, with everything the filters look at
+#[derive(Debug, Serialize)]
+pub struct SearchEntry {
+    pub kind: SearchKind,
+    pub name: String,
+    /// `Namespace.Type` or `Namespace.Type::Member`
+    pub full_name: String,
+    pub signature: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u32>,
+    /// Instance size of the type, or of the declaring type of a member
+    pub size: u32,
+
+    #[serde(skip)]
+    namespace: String,
+    /// Return type of methods, type of fields and properties
+    #[serde(skip)]
+    value_type: Option<String>,
+    #[serde(skip)]
+    parameter_types: Vec<String>,
+    /// None for types
+    #[serde(skip)]
+    is_static: Option<bool>,
+}
+
+/// Matches whole names, as a regex or a glob where `*` is any text and `?` any character
+pub struct Pattern {
+    regex: Regex,
+    /// Set when the pattern can only match itself, so it can be looked up by name
+    literal: Option<String>,
+}
+
+impl Pattern {
+    pub fn new(pattern: &str, glob: bool, ignore_case: bool) -> Result<Self> {
+        let regex = match glob {
+            true => pattern
+                .chars()
+                .map(|c| match c {
+                    '*' => ".*".to_string(),
+                    '?' => ".".to_string(),
+                    _ => regex::escape(&c.to_string()),
+                })
+                .collect(),
+            false => pattern.to_string(),
+        };
+        let is_literal = match glob {
+            true => !pattern.contains(['*', '?']),
+            false => regex::escape(pattern) == pattern,
+        };
+
+        Ok(Self {
+            regex: RegexBuilder::new(&format!("^(?:{regex})$"))
+                .case_insensitive(ignore_case)
+                .build()?,
+            literal: (is_literal && !ignore_case).then(|| pattern.to_string()),
+        })
+    }
+
+    pub fn is_match(&self, s: &str) -> bool {
+        self.regex.is_match(s)
+    }
+}
+
+pub struct SearchQuery {
+    /// Matched against the name and the full name
+    pub pattern: Pattern,
+    /// Empty for every kind
+    pub kinds: Vec<SearchKind>,
+    pub returns: Option<Pattern>,
+    /// Any parameter of the method
+    pub param_type: Option<Pattern>,
+    pub only_static: bool,
+    /// Namespaces and their sub namespaces
+    pub namespaces: Vec<String>,
+    pub min_size: Option<u32>,
+}
+
+impl SearchQuery {
+    fn matches(&self, entry: &SearchEntry) -> bool {
+        (self.kinds.is_empty() || self.kinds.contains(&entry.kind))
+            && (self.pattern.is_match(&entry.name) || self.pattern.is_match(&entry.full_name))
+            && self.returns.as_ref().is_none_or(|returns| {
+                entry
+                    .value_type
+                    .as_ref()
+                    .is_some_and(|ty| returns.is_match(ty))
+            })
+            && self.param_type.as_ref().is_none_or(|param_type| {
+                entry
+                    .parameter_types
+                    .iter()
+                    .any(|ty| param_type.is_match(ty))
+            })
+            && (!self.only_static || entry.is_static == Some(true))
+            && (self.namespaces.is_empty()
+                || self.namespaces.iter().any(|ns| {
+                    entry.namespace == *ns || entry.namespace.starts_with(&format!("{ns}."))
+                }))
+            && self.min_size.is_none_or(|min_size| entry.size >= min_size)
+    }
+}
+
+/// Every type definition and member. Types are looked up by name through
+/// [`CordlMetadata::name_to_tdi`], members by name and full name
+pub struct SearchIndex<'a> {
+    entries: Vec<SearchEntry>,
+    name_to_tdi: &'a HashMap<Il2cppFullName<'a>, TypeDefinitionIndex>,
+    types: HashMap<TypeDefinitionIndex, usize>,
+    members_by_name: HashMap<String, Vec<usize>>,
+}
+
+impl<'a> SearchIndex<'a> {
+    pub fn new(metadata: &'a CordlMetadata, collection: &TypeContextCollection) -> Self {
+        let mut entries = vec![];
+        let mut types = HashMap::new();
+
+        // generic instantiations share the names of their definition
+        for (tdi, ty) in collection
+            .get()
+            .values()
+            .flat_map(|c| c.get_types().values())
+            .filter_map(|ty| match ty.self_tag {
+                CsTypeTag::TypeDefinitionIndex(tdi) => Some((tdi, ty)),
+                _ => None,
+            })
+            .map(|(tdi, ty)| (tdi, make_type(ty, metadata, collection)))
+            .sorted_by(|(_, a), (_, b)| a.full_name.cmp(&b.full_name))
+        {
+            types.insert(tdi, entries.len());
+            add_type(&ty, &mut entries);
+        }
+
+        let mut members_by_name: HashMap<String, Vec<usize>> = HashMap::new();
+        for (i, entry) in entries.iter().enumerate() {
+            if entry.kind == SearchKind::Type {
+                continue;
+            }
+            members_by_name
+                .entry(entry.name.clone())
+                .or_default()
+                .push(i);
+            members_by_name
+                .entry(entry.full_name.clone())
+                .or_default()
+                .push(i);
+        }
+
+        Self {
+            entries,
+            name_to_tdi: &metadata.name_to_tdi,
+            types,
+            members_by_name,
+        }
+    }
+
+    /// Types named `literal` or with `literal` as full name
+    fn find_types(&self, literal: &str) -> Vec<usize> {
+        let tdis = match literal.rsplit_once('.') {
+            Some((namespace, name)) if !name.contains(['/', ':']) => self
+                .name_to_tdi
+                .get(&Il2cppFullName(namespace, name))
+                .into_iter()
+                .copied()
+                .collect_vec(),
+            // nested types have no namespace of their own
+            _ => {
+                let name = literal.rsplit('/').next().unwrap_or(literal);
+                self.name_to_tdi
+                    .iter()
+                    .filter(|(full_name, _)| full_name.1 == name)
+                    .map(|(_, tdi)| *tdi)
+                    .collect_vec()
+            }
+        };
+
+        tdis.iter()
+            .filter_map(|tdi| self.types.get(tdi).copied())
+            .collect()
+    }
+
+    /// In order of type full name, then fields, properties and methods
+    pub fn search(&self, query: &SearchQuery) -> Vec<&SearchEntry> {
+        let candidates = match &query.pattern.literal {
+            Some(literal) => self
+                .find_types(literal)
+                .into_iter()
+                .chain(
+                    self.members_by_name
+                        .get(literal)
+                        .into_iter()
+                        .flatten()
+                        .copied(),
+                )
+                .collect(),
+            None => (0..self.entries.len()).collect_vec(),
+        };
+
+        candidates
+            .into_iter()
+            .sorted()
+            .dedup()
+            .map(|i| &self.entries[i])
+            .filter(|entry| query.matches(entry))
+            .collect()
+    }
+}
+
+fn add_type(ty: &JsonType, entries: &mut Vec<SearchEntry>) {
+    let kind = if ty.is_interface {
+        "interface"
+    } else if ty.enum_backing_type.is_some() {
+        "enum"
+    } else if ty.value_type {
+        "struct"
+    } else {
+        "class"
+    };
+    entries.push(SearchEntry {
+        kind: SearchKind::Type,
+        name: ty.name.clone(),
+        full_name: ty.full_name.clone(),
+        signature: format!("{kind} {}", ty.full_name),
+        address: None,
+        offset: None,
+        size: ty.size,
+        namespace: ty.namespace.clone(),
+        value_type: None,
+        parameter_types: vec![],
+        is_static: None,
+    });
+    let member_entry = |kind, name: &str, signature| SearchEntry {
+        kind,
+        name: name.to_string(),
+        full_name: format!("{}::{name}", ty.full_name),
+        signature,
+        address: None,
+        offset: None,
+        size: ty.size,
+        namespace: ty.namespace.clone(),
+        value_type: None,
+        parameter_types: vec![],
+        is_static: None,
+    };
+
+    for field in &ty.fields {
+        entries.push(SearchEntry {
+            offset: field.offset.filter(|_| field.instance),
+            value_type: Some(field.ty_name.clone()),
+            is_static: Some(!field.instance),
+            ..member_entry(
+                SearchKind::Field,
+                &field.name,
+                field.signature(Some(&ty.full_name)),
+            )
+        });
+    }
+    for property in &ty.properties {
+        entries.push(SearchEntry {
+            value_type: Some(property.ty_name.clone()),
+            is_static: Some(!property.instance),
+            ..member_entry(
+                SearchKind::Property,
+                &property.name,
+                property.signature(Some(&ty.full_name)),
+            )
+        });
+    }
+    for method in &ty.methods {
+        entries.push(SearchEntry {
+            address: method.method_info.addrs.filter(|a| *a != 0x0),
+            value_type: Some(method.ret.clone()),
+            parameter_types: method.parameters.iter().map(|p| p.ty.clone()).collect(),
+            is_static: Some(!method.instance),
+            ..member_entry(
+                SearchKind::Method,
+                &method.name,
+                method.signature(Some(&ty.full_name)),
+            )
+        });
+    }
+}
+
+/// One result per line, with the address of methods, offset of instance fields and size of types
+pub fn to_text(results: &[&SearchEntry]) -> String {
+    results
+        .iter()
+        .map(|entry| {
+            let location = match entry.kind {
+                SearchKind::Type => format!("size 0x{:X}", entry.size),
+                SearchKind::Field => entry
+                    .offset
+                    .map(|o| format!("+0x{o:X}"))
+                    .unwrap_or_default(),
+                SearchKind::Property => String::new(),
+                SearchKind::Method => entry
+                    .address
+                    .map(|a| format!("0x{a:X}"))
+                    .unwrap_or_default(),
+            };
+
+            format!("{location:>12}  {}\n", entry.signature)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(kind: SearchKind, full_name: &str, value_type: Option<&str>) -> SearchEntry {
+        let (namespace, name) = full_name
+            .split_once("::")
+            .map_or(full_name, |(ty, _)| ty)
+            .rsplit_once('.')
+            .unwrap();
+        let name = full_name
+            .rsplit_once("::")
+            .map_or(name, |(_, member)| member);
+
+        SearchEntry {
+            kind,
+            name: name.to_string(),
+            full_name: full_name.to_string(),
+            signature: full_name.to_string(),
+            address: None,
+            offset: None,
+            size: 0x10,
+            namespace: namespace.to_string(),
+            value_type: value_type.map(str::to_string),
+            parameter_types: vec![],
+            is_static: None,
+        }
+    }
+
+    fn query(pattern: &str) -> SearchQuery {
+        SearchQuery {
+            pattern: Pattern::new(pattern, true, false).unwrap(),
+            kinds: vec![],
+            returns: None,
+            param_type: None,
+            only_static: false,
+            namespaces: vec![],
+            min_size: None,
+        }
+    }
+
+    #[test]
+    fn globs_match_whole_names() {
+        let pattern = Pattern::new("Get*", true, false).unwrap();
+        assert!(pattern.is_match("GetComponent"));
+        assert!(pattern.is_match("Get"));
+        assert!(!pattern.is_match("TryGetComponent"));
+        assert!(!pattern.is_match("getComponent"));
+
+        let pattern = Pattern::new("List`?", true, false).unwrap();
+        assert!(pattern.is_match("List`1"));
+        assert!(!pattern.is_match("List`10"));
+    }
+
+    #[test]
+    fn globs_escape_regex_syntax() {
+        let pattern = Pattern::new("System.Int32[]", true, false).unwrap();
+        assert!(pattern.is_match("System.Int32[]"));
+        assert!(!pattern.is_match("SystemXInt32[]"));
+        assert_eq!(pattern.literal.as_deref(), Some("System.Int32[]"));
+
+        let pattern = Pattern::new("(Get|Set)+", true, false).unwrap();
+        assert!(pattern.is_match("(Get|Set)+"));
+        assert!(!pattern.is_match("Get"));
+    }
+
+    #[test]
+    fn regexes_and_case() {
+        let pattern = Pattern::new("get_.*", false, true).unwrap();
+        assert!(pattern.is_match("GET_Health"));
+        assert!(!pattern.is_match("_get_Health"));
+        assert_eq!(pattern.literal, None);
+
+        assert_eq!(
+            Pattern::new("Update", false, false)
+                .unwrap()
+                .literal
+                .as_deref(),
+            Some("Update")
+        );
+        // case insensitive patterns can't be looked up by name
+        assert_eq!(Pattern::new("Update", true, true).unwrap().literal, None);
+        assert!(Pattern::new("(", false, false).is_err());
+    }
+
+    #[test]
+    fn queries_filter_entries() {
+        let field = SearchEntry {
+            is_static: Some(true),
+            ..entry(
+                SearchKind::Field,
+                "Game.UI.Menu::instance",
+                Some("Game.UI.Menu"),
+            )
+        };
+        let method = entry(
+            SearchKind::Method,
+            "Game.Player::Update",
+            Some("System.Void"),
+        );
+
+        assert!(query("*Menu::*").matches(&field));
+        assert!(query("instance").matches(&field));
+        assert!(!query("Menu").matches(&field));
+
+        let mut by_kind = query("*");
+        by_kind.kinds = vec![SearchKind::Method];
+        assert!(!by_kind.matches(&field));
+        assert!(by_kind.matches(&method));
+
+        let mut by_namespace = query("*");
+        by_namespace.namespaces = vec!["Game".to_string()];
+        assert!(by_namespace.matches(&field));
+        by_namespace.namespaces = vec!["Game.U".to_string()];
+        assert!(!by_namespace.matches(&field));
+
+        let mut by_return = query("*");
+        by_return.returns = Some(Pattern::new("System.*", true, false).unwrap());
+        by_return.only_static = true;
+        assert!(!by_return.matches(&field));
+        assert!(!by_return.matches(&method));
+        by_return.only_static = false;
+        assert!(by_return.matches(&method));
+    }
+
+    #[test]
+    fn literal_patterns_use_the_name_index() {
+        let entries = vec![
+            entry(SearchKind::Type, "Game.Player", None),
+            entry(SearchKind::Method, "Game.Player::Update", None),
+            entry(SearchKind::Method, "Game.Enemy::Update", None),
+        ];
+        let name_to_tdi = HashMap::from([
+            (
+                Il2cppFullName("Game", "Player"),
+                TypeDefinitionIndex::new(0),
+            ),
+            (Il2cppFullName("Game", "Enemy"), TypeDefinitionIndex::new(1)),
+        ]);
+        let index = SearchIndex {
+            entries,
+            name_to_tdi: &name_to_tdi,
+            types: HashMap::from([(TypeDefinitionIndex::new(0), 0)]),
+            members_by_name: HashMap::from([
+                ("Update".to_string(), vec![1, 2]),
+                ("Game.Player::Update".to_string(), vec![1]),
+                ("Game.Enemy::Update".to_string(), vec![2]),
+            ]),
+        };
+        let full_names = |pattern| {
+            index
+                .search(&query(pattern))
+                .iter()
+                .map(|e| e.full_name.as_str())
+                .collect_vec()
+        };
+
+        assert_eq!(
+            full_names("Update"),
+            ["Game.Player::Update", "Game.Enemy::Update"]
+        );
+        assert_eq!(full_names("Game.Player"), ["Game.Player"]);
+        assert_eq!(full_names("Player"), ["Game.Player"]);
+        assert_eq!(full_names("Game.*::Update"), full_names("Update"));
+        assert!(full_names("Missing").is_empty());
+    }
+}
@@ -0,0 +1,395 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use color_eyre::eyre::{ContextCompat, Result};
+use itertools::Itertools;
+use log::{info, warn};
+use rayon::{
+    iter::{IntoParallelIterator, ParallelIterator},
+    slice::ParallelSliceMut,
+};
+
+use crate::{
+    data::elf_image::ElfImage,
+    generate::metadata::CordlMetadata,
+    helpers::arm64::{self, INSTRUCTION_SIZE, Instruction},
+};
+
+/// Shortest pattern emitted, in instructions
+const MIN_PATTERN_LEN: usize = 4;
+/// Longest pattern emitted, in instructions
+/// Methods that are still not unique by then are marked as such
+const MAX_PATTERN_LEN: usize = 64;
+
+/// Byte pattern of a method with relocation dependent bytes wildcarded
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+pub struct MethodSignature {
+    /// Full signature of the method, not unique for overloads differing only by return type
+    /// such as `op_Implicit`
+    pub name: String,
+    pub addrs: u64,
+    /// IDA style, e.g `FF 43 01 D1 ?? ?? ?? 94`
+    pub pattern: String,
+    /// Whether the pattern matches exactly once in `.text`
+    pub unique: bool,
+}
+
+/// `.text` split into instruction words,
+/// indexed by word so candidate matches can be found without a full scan
+struct TextIndex {
+    base: u64,
+    words: Vec<u32>,
+    /// (word << 32 | word index), sorted
+    sorted: Vec<u64>,
+}
+
+impl TextIndex {
+    fn new(base: u64, data: &[u8]) -> Self {
+        let words = data
+            .chunks_exact(INSTRUCTION_SIZE)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+            .collect_vec();
+
+        let mut sorted = words
+            .iter()
+            .enumerate()
+            .map(|(i, w)| ((*w as u64) << 32) | i as u64)
+            .collect_vec();
+        sorted.par_sort_unstable();
+
+        TextIndex {
+            base,
+            words,
+            sorted,
+        }
+    }
+
+    /// Word indices of every occurrence of `word`
+    fn occurrences(&self, word: u32) -> &[u64] {
+        let start = self.sorted.partition_point(|e| (e >> 32) < word as u64);
+        let end = self.sorted.partition_point(|e| (e >> 32) <= word as u64);
+        &self.sorted[start..end]
+    }
+
+    fn word_index(&self, addr: u64) -> Option<usize> {
+        let offset = addr.checked_sub(self.base)? as usize;
+        (offset.is_multiple_of(INSTRUCTION_SIZE) && offset / INSTRUCTION_SIZE < self.words.len())
+            .then_some(offset / INSTRUCTION_SIZE)
+    }
+
+    /// Counts matches of `words`/`masks` at instruction aligned positions, up to `limit`
+    fn count_matches(&self, words: &[u32], masks: &[u32], limit: usize) -> usize {
+        // anchor on the rarest fully concrete word
+        let Some((anchor_offset, anchor_occurrences)) = words
+            .iter()
+            .zip(masks)
+            .enumerate()
+            .filter(|(_, (_, mask))| **mask == 0)
+            .map(|(i, (word, _))| (i, self.occurrences(*word)))
+            .min_by_key(|(_, occurrences)| occurrences.len())
+        else {
+            // no concrete word, assume ambiguous
+            return limit;
+        };
+
+        let mut count = 0;
+        for entry in anchor_occurrences {
+            let Some(start) = ((entry & 0xFFFF_FFFF) as usize).checked_sub(anchor_offset) else {
+                continue;
+            };
+            let Some(candidate) = self.words.get(start..start + words.len()) else {
+                continue;
+            };
+
+            let matches = candidate
+                .iter()
+                .zip(words)
+                .zip(masks)
+                .all(|((c, w), m)| c & !m == w & !m);
+
+            if matches {
+                count += 1;
+                if count >= limit {
+                    break;
+                }
+            }
+        }
+
+        count
+    }
+}
+
+/// Bits of each instruction that depend on where code or data ends up
+/// Widened to whole bytes since patterns wildcard per byte
+fn relocation_masks(code: &[u8], addr: u64) -> Vec<u32> {
+    let end = addr + code.len() as u64;
+    // registers holding an ADRP page, whose users have relocated offsets
+    let mut adrp_registers = [false; 32];
+
+    arm64::disassemble(code, addr)
+        .map(|(_pc, _insn, instruction)| {
+            let mask: u32 = match instruction {
+                Instruction::Bl { .. } => {
+                    adrp_registers = [false; 32];
+                    0x03FF_FFFF
+                }
+                Instruction::B { target } if target < addr || target >= end => 0x03FF_FFFF,
+                Instruction::Adrp { rd, .. } => {
+                    adrp_registers[rd as usize] = true;
+                    0x60FF_FFE0
+                }
+                Instruction::Adr { .. } | Instruction::LdrLiteral { .. } => 0x60FF_FFE0,
+                Instruction::AddImm { rd, rn, .. } => {
+                    let relocated = adrp_registers[rn as usize];
+                    adrp_registers[rd as usize] = false;
+                    if relocated { 0x003F_FC00 } else { 0 }
+                }
+                Instruction::LoadStore { rt, rn, load, .. } => {
+                    let relocated = adrp_registers[rn as usize];
+                    if load {
+                        adrp_registers[rt as usize] = false;
+                    }
+                    if relocated { 0x003F_FC00 } else { 0 }
+                }
+                Instruction::Write { rd }
+                | Instruction::MovImm { rd, .. }
+                | Instruction::MovK { rd, .. } => {
+                    adrp_registers[rd as usize] = false;
+                    0
+                }
+                _ => 0,
+            };
+
+            (0..4)
+                .map(|byte| match (mask >> (byte * 8)) & 0xFF {
+                    0 => 0,
+                    _ => 0xFF << (byte * 8),
+                })
+                .fold(0, |acc, m| acc | m)
+        })
+        .collect()
+}
+
+fn format_pattern(words: &[u32], masks: &[u32]) -> String {
+    words
+        .iter()
+        .zip(masks)
+        .flat_map(|(word, mask)| {
+            (0..4).map(move |byte| match (mask >> (byte * 8)) & 0xFF {
+                0 => format!("{:02X}", (word >> (byte * 8)) & 0xFF),
+                _ => "??".to_string(),
+            })
+        })
+        .join(" ")
+}
+
+fn make_signature(
+    text: &TextIndex,
+    name: String,
+    addr: u64,
+    size: usize,
+) -> Option<MethodSignature> {
+    let start = text.word_index(addr)?;
+    let len = (size / INSTRUCTION_SIZE).min(MAX_PATTERN_LEN);
+    if len == 0 {
+        return None;
+    }
+    let words = text.words.get(start..start + len)?;
+
+    let code = words.iter().flat_map(|w| w.to_le_bytes()).collect_vec();
+    let masks = relocation_masks(&code, addr);
+
+    // grow until unique
+    let mut pattern_len = MIN_PATTERN_LEN.min(len);
+    loop {
+        let unique = text.count_matches(&words[..pattern_len], &masks[..pattern_len], 2) == 1;
+
+        if unique || pattern_len == len {
+            return Some(MethodSignature {
+                name,
+                addrs: addr,
+                pattern: format_pattern(&words[..pattern_len], &masks[..pattern_len]),
+                unique,
+            });
+        }
+
+        pattern_len = (pattern_len * 2).min(len);
+    }
+}
+
+/// Byte patterns of every method, sorted by name
+pub fn make_signatures(metadata: &CordlMetadata) -> Result<Vec<MethodSignature>> {
+    let image = ElfImage::parse(metadata.elf_data)?;
+    let (text_addr, text_data) = image.text.context("No .text section found")?;
+    let text = TextIndex::new(text_addr, text_data);
+
+    let methods = metadata
+        .method_calculations
+        .iter()
+        .filter(|(_, calc)| calc.addrs != 0x0 && calc.estimated_size != usize::MAX)
+        .map(|(method_index, calc)| {
+            (
+                metadata.method_full_signature(*method_index),
+                calc.addrs,
+                calc.estimated_size,
+            )
+        })
+        .collect_vec();
+
+    info!("Making signatures for {} methods", methods.len());
+
+    let mut signatures: Vec<MethodSignature> = methods
+        .into_par_iter()
+        .filter_map(|(name, addr, size)| make_signature(&text, name, addr, size))
+        .collect();
+    signatures.par_sort_unstable_by(|a, b| a.name.cmp(&b.name).then(a.addrs.cmp(&b.addrs)));
+
+    let not_unique = signatures.iter().filter(|s| !s.unique).count();
+    if not_unique > 0 {
+        warn!("{not_unique} methods have no unique signature");
+    }
+    let shared_names = signatures
+        .iter()
+        .dedup_by_with_count(|a, b| a.name == b.name)
+        .filter(|(count, _)| *count > 1)
+        .count();
+    if shared_names > 0 {
+        warn!("{shared_names} full signatures are shared by more than one method, all are kept");
+    }
+
+    Ok(signatures)
+}
+
+/// C++ string literal contents. Universal character names and octal escapes have a fixed length,
+/// unlike `\x` escapes which would swallow following hex digits
+pub fn cpp_string(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            ' '..='~' => escaped.push(c),
+            // UCNs can't name control characters
+            '\0'..='\u{9F}' => escaped.push_str(&format!("\\{:03o}", c as u32)),
+            '\u{A0}'..='\u{FFFF}' => escaped.push_str(&format!("\\u{:04X}", c as u32)),
+            _ => escaped.push_str(&format!("\\U{:08X}", c as u32)),
+        }
+    }
+
+    escaped
+}
+
+pub fn write_cpp(signatures: &[MethodSignature], file: &Path) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(file)?);
+
+    writeln!(writer, "#pragma once")?;
+    writeln!(writer)?;
+    writeln!(writer, "#include <cstdint>")?;
+    writeln!(writer)?;
+    writeln!(writer, "namespace cordl_signatures {{")?;
+    writeln!(writer, "struct MethodSignature {{")?;
+    writeln!(writer, "  char const* name;")?;
+    writeln!(writer, "  char const* pattern;")?;
+    writeln!(writer, "  uintptr_t address;")?;
+    writeln!(writer, "  bool unique;")?;
+    writeln!(writer, "}};")?;
+    writeln!(writer)?;
+    writeln!(writer, "/// Sorted by name")?;
+    writeln!(writer, "static constexpr MethodSignature signatures[] = {{")?;
+    for signature in signatures {
+        writeln!(
+            writer,
+            "  {{ \"{}\", \"{}\", 0x{:x}, {} }},",
+            cpp_string(&signature.name),
+            signature.pattern,
+            signature.addrs,
+            signature.unique
+        )?;
+    }
+    writeln!(writer, "}};")?;
+    writeln!(writer, "}} // namespace cordl_signatures")?;
+
+    Ok(())
+}
+
+#[cfg(feature = "json")]
+pub fn write_json(signatures: &[MethodSignature], file: &Path, format: bool) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(file)?);
+
+    match format {
+        true => serde_json::to_writer_pretty(&mut writer, signatures)?,
+        false => serde_json::to_writer(&mut writer, signatures)?,
+    };
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code(words: &[u32]) -> Vec<u8> {
+        words.iter().flat_map(|w| w.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn wildcards_relocated_bytes() {
+        let words = [
+            0xD100_83FF, // sub sp, sp, #0x20
+            0x9000_0008, // adrp x8, page
+            0xF940_0108, // ldr x8, [x8]
+            0x9100_0000, // add x0, x0, #0
+            0x9400_0010, // bl +0x40
+            0x1400_0001, // b +4, inside the method
+            0xD65F_03C0, // ret
+        ];
+        let masks = relocation_masks(&code(&words), 0x1000);
+
+        assert_eq!(masks, [0, 0xFFFF_FFFF, 0x00FF_FF00, 0, 0xFFFF_FFFF, 0, 0]);
+        assert_eq!(
+            format_pattern(&words[..2], &masks[..2]),
+            "FF 83 00 D1 ?? ?? ?? ??"
+        );
+    }
+
+    #[test]
+    fn call_clears_adrp_registers() {
+        let words = [
+            0x9000_0008, // adrp x8, page
+            0x9400_0010, // bl +0x40
+            0xF940_0108, // ldr x8, [x8]
+        ];
+        let masks = relocation_masks(&code(&words), 0x1000);
+
+        assert_eq!(masks[2], 0);
+    }
+
+    #[test]
+    fn grows_until_unique() {
+        let ret = 0xD65F_03C0;
+        let nop = 0xD503_201F;
+        let words = [nop, nop, nop, nop, ret, nop, nop, nop, nop, 0xD100_83FF];
+        let text = TextIndex::new(0x1000, &code(&words));
+
+        let first = make_signature(&text, "A".into(), 0x1000, 40).unwrap();
+        assert!(first.unique);
+        assert_eq!(first.pattern.split(' ').count(), 8 * INSTRUCTION_SIZE);
+
+        let repeated = make_signature(&text, "B".into(), 0x1014, 16).unwrap();
+        assert!(!repeated.unique);
+    }
+
+    #[test]
+    fn escapes_cpp_strings() {
+        assert_eq!(
+            cpp_string("A.B::op_Implicit(\"\\\n\u{7F})"),
+            "A.B::op_Implicit(\\\"\\\\\\012\\177)"
+        );
+        assert_eq!(cpp_string("<Método>d__0"), "<M\\u00E9todo>d__0");
+        assert_eq!(cpp_string("\u{1F600}1"), "\\U0001F6001");
+    }
+}
@@ -0,0 +1,444 @@
+//! Counts and code sizes of a build, for judging the impact of game updates
+
+use std::{collections::HashMap, fmt::Write as _};
+
+use brocolib::{global_metadata::TypeDefinitionIndex, runtime_metadata::TypeData};
+use color_eyre::eyre::Result;
+use itertools::Itertools;
+
+use crate::{data::elf_functions::MethodSizeSource, generate::metadata::CordlMetadata};
+
+const METADATA_SANITY: u32 = 0xFAB11BAF;
+
+/// Sections of `Il2CppGlobalMetadataHeader` since version 29
+const HEADER_SECTIONS_V29: [&str; 31] = [
+    "stringLiteral",
+    "stringLiteralData",
+    "string",
+    "events",
+    "properties",
+    "methods",
+    "parameterDefaultValues",
+    "fieldDefaultValues",
+    "fieldAndParameterDefaultValueData",
+    "fieldMarshaledSizes",
+    "parameters",
+    "fields",
+    "genericParameters",
+    "genericParameterConstraints",
+    "genericContainers",
+    "nestedTypes",
+    "interfaces",
+    "vtableMethods",
+    "interfaceOffsets",
+    "typeDefinitions",
+    "images",
+    "assemblies",
+    "fieldRefs",
+    "referencedAssemblies",
+    "attributeData",
+    "attributeDataRange",
+    "unresolvedVirtualCallParameterTypes",
+    "unresolvedVirtualCallParameterRanges",
+    "windowsRuntimeTypeNames",
+    "windowsRuntimeStrings",
+    "exportedTypeDefinitions",
+];
+
+/// Guards against cycles in broken metadata
+const MAX_NESTING_DEPTH: usize = 64;
+
+#[derive(Debug)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+pub struct Stats {
+    pub metadata_version: u32,
+    pub header_sections: Vec<HeaderSection>,
+    pub counts: Counts,
+    /// Sum of the estimated sizes of every method body, folded bodies counted once
+    pub code_size: usize,
+    pub largest_methods: Vec<MethodSize>,
+    pub namespaces: Vec<CodeSize>,
+    pub assemblies: Vec<CodeSize>,
+    pub generic_types: Vec<GenericTypeCount>,
+    pub blacklisted_types: Vec<String>,
+    /// Nested in a blacklisted type
+    pub skipped_types: Vec<String>,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+pub struct HeaderSection {
+    pub name: String,
+    pub offset: u32,
+    pub size: u32,
+}
+
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+pub struct Counts {
+    pub images: usize,
+    pub types: usize,
+    pub generic_class_instances: usize,
+    pub generic_method_instances: usize,
+    pub methods: usize,
+    /// Methods with a body in libil2cpp.so
+    pub methods_with_code: usize,
+    pub fields: usize,
+    pub string_literals: usize,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+pub struct MethodSize {
+    pub name: String,
+    pub address: u64,
+    pub size: usize,
+    pub size_source: MethodSizeSource,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+pub struct CodeSize {
+    pub name: String,
+    pub methods: usize,
+    pub size: usize,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+pub struct GenericTypeCount {
+    pub name: String,
+    pub instances: usize,
+}
+
+/// `top` limits every list, except the blacklisted and skipped types
+pub fn make_stats(
+    metadata: &CordlMetadata,
+    global_metadata_data: &[u8],
+    top: usize,
+) -> Result<Stats> {
+    let md = metadata.metadata;
+    let gm = &md.global_metadata;
+    let mr = metadata.metadata_registration;
+
+    let (metadata_version, header_sections) = read_header(global_metadata_data)?;
+
+    let type_definitions = gm.type_definitions.as_vec();
+    let counts = Counts {
+        images: gm.images.as_vec().len(),
+        types: type_definitions.len(),
+        generic_class_instances: mr.generic_classes.len(),
+        generic_method_instances: mr.generic_method_table.len(),
+        methods: gm.methods.as_vec().len(),
+        methods_with_code: metadata
+            .method_calculations
+            .values()
+            .filter(|calc| calc.addrs != 0x0)
+            .count(),
+        fields: type_definitions.iter().map(|td| td.fields(md).len()).sum(),
+        string_literals: gm.string_literals.as_vec().len(),
+    };
+
+    let mut image_of_type: HashMap<TypeDefinitionIndex, String> = HashMap::new();
+    for image in gm.images.as_vec() {
+        let start = image.type_start.index();
+        for tdi in (start..start + image.type_count).map(TypeDefinitionIndex::new) {
+            image_of_type.insert(tdi, image.name(md).to_string());
+        }
+    }
+
+    // identical code folding points many methods at the same body,
+    // which is attributed to the method with the lowest index
+    let bodies = metadata
+        .method_calculations
+        .iter()
+        .filter(|(_, calc)| calc.addrs != 0x0 && calc.estimated_size != usize::MAX)
+        .sorted_by_key(|(method_index, _)| method_index.index())
+        .unique_by(|(_, calc)| calc.addrs)
+        .collect_vec();
+    let code_size = bodies.iter().map(|(_, calc)| calc.estimated_size).sum();
+
+    let largest_methods = bodies
+        .iter()
+        .sorted_by_key(|(_, calc)| std::cmp::Reverse(calc.estimated_size))
+        .take(top)
+        .map(|(method_index, calc)| MethodSize {
+            name: metadata.method_full_signature(**method_index),
+            address: calc.addrs,
+            size: calc.estimated_size,
+            size_source: calc.size_source,
+        })
+        .collect_vec();
+
+    let mut namespaces: HashMap<String, CodeSize> = HashMap::new();
+    let mut assemblies: HashMap<String, CodeSize> = HashMap::new();
+    for (method_index, calc) in &bodies {
+        let declaring_type = gm.methods[**method_index].declaring_type;
+        let namespace = root_type(metadata, declaring_type).namespace(md);
+        let assembly = image_of_type
+            .get(&declaring_type)
+            .cloned()
+            .unwrap_or_default();
+
+        for (map, name) in [
+            (&mut namespaces, namespace.to_string()),
+            (&mut assemblies, assembly),
+        ] {
+            let code_size = map.entry(name.clone()).or_insert(CodeSize {
+                name,
+                methods: 0,
+                size: 0,
+            });
+            code_size.methods += 1;
+            code_size.size += calc.estimated_size;
+        }
+    }
+
+    let generic_types = mr
+        .generic_classes
+        .iter()
+        .filter_map(
+            |generic_class| match mr.types[generic_class.type_index].data {
+                TypeData::TypeDefinitionIndex(tdi) => Some(tdi),
+                _ => None,
+            },
+        )
+        .counts()
+        .into_iter()
+        .sorted_by_key(|(tdi, instances)| (std::cmp::Reverse(*instances), tdi.index()))
+        .take(top)
+        .map(|(tdi, instances)| GenericTypeCount {
+            name: gm.type_definitions[tdi].full_name(md, true),
+            instances,
+        })
+        .collect_vec();
+
+    let blacklisted_types = metadata
+        .blacklisted_types
+        .iter()
+        .sorted_by_key(|tdi| tdi.index())
+        .map(|tdi| gm.type_definitions[*tdi].full_name(md, true))
+        .collect_vec();
+    let skipped_types = (0..type_definitions.len() as u32)
+        .map(TypeDefinitionIndex::new)
+        .filter(|tdi| !metadata.blacklisted_types.contains(tdi))
+        .filter(|tdi| {
+            declaring_types(metadata, *tdi)
+                .any(|parent| metadata.blacklisted_types.contains(&parent))
+        })
+        .map(|tdi| gm.type_definitions[tdi].full_name(md, true))
+        .collect_vec();
+
+    Ok(Stats {
+        metadata_version,
+        header_sections,
+        counts,
+        code_size,
+        largest_methods,
+        namespaces: largest_code_sizes(namespaces, top),
+        assemblies: largest_code_sizes(assemblies, top),
+        generic_types,
+        blacklisted_types,
+        skipped_types,
+    })
+}
+
+/// Version and `(offset, size)` sections, which end where the first section starts
+fn read_header(data: &[u8]) -> Result<(u32, Vec<HeaderSection>)> {
+    let read_u32 = |offset: usize| {
+        data.get(offset..offset + 4)
+            .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+    };
+
+    let (Some(sanity), Some(version), Some(first_offset)) = (read_u32(0), read_u32(4), read_u32(8))
+    else {
+        color_eyre::eyre::bail!("global-metadata.dat is too short");
+    };
+    if sanity != METADATA_SANITY {
+        color_eyre::eyre::bail!("Invalid global-metadata.dat sanity {sanity:#X}");
+    }
+
+    let section_count = (first_offset as usize).saturating_sub(8) / 8;
+    let sections = (0..section_count)
+        .map_while(|i| {
+            let offset = read_u32(8 + i * 8)?;
+            let size = read_u32(12 + i * 8)?;
+            let name = match section_count == HEADER_SECTIONS_V29.len() {
+                true => HEADER_SECTIONS_V29[i].to_string(),
+                false => format!("section{i}"),
+            };
+
+            Some(HeaderSection { name, offset, size })
+        })
+        .collect_vec();
+
+    Ok((version, sections))
+}
+
+/// Outermost declaring type, whose namespace nested types share
+fn root_type<'a>(
+    metadata: &CordlMetadata<'a>,
+    tdi: TypeDefinitionIndex,
+) -> &'a brocolib::global_metadata::Il2CppTypeDefinition {
+    let root = declaring_types(metadata, tdi).last().unwrap_or(tdi);
+
+    &metadata.metadata.global_metadata.type_definitions[root]
+}
+
+/// Declaring type, its declaring type and so on
+fn declaring_types<'b>(
+    metadata: &'b CordlMetadata,
+    tdi: TypeDefinitionIndex,
+) -> impl Iterator<Item = TypeDefinitionIndex> + 'b {
+    std::iter::successors(
+        metadata.child_to_parent_map.get(&tdi).map(|p| p.tdi),
+        move |tdi| metadata.child_to_parent_map.get(tdi).map(|p| p.tdi),
+    )
+    .take(MAX_NESTING_DEPTH)
+}
+
+fn largest_code_sizes(sizes: HashMap<String, CodeSize>, top: usize) -> Vec<CodeSize> {
+    sizes
+        .into_values()
+        .sorted_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)))
+        .take(top)
+        .collect()
+}
+
+pub fn to_text(stats: &Stats) -> Result<String> {
+    let mut out = String::new();
+
+    writeln!(out, "Metadata version {}", stats.metadata_version)?;
+    writeln!(out, "\nHeader sections")?;
+    for section in &stats.header_sections {
+        writeln!(
+            out,
+            "  {:<40} 0x{:08X} {:>12}",
+            section.name, section.offset, section.size
+        )?;
+    }
+
+    let counts = &stats.counts;
+    writeln!(out, "\nCounts")?;
+    for (name, count) in [
+        ("Images", counts.images),
+        ("Types", counts.types),
+        ("Generic class instances", counts.generic_class_instances),
+        ("Generic method instances", counts.generic_method_instances),
+        ("Methods", counts.methods),
+        ("Methods with code", counts.methods_with_code),
+        ("Fields", counts.fields),
+        ("String literals", counts.string_literals),
+    ] {
+        writeln!(out, "  {name:<40} {count:>12}")?;
+    }
+    writeln!(out, "  {:<40} {:>12}", "Code size", stats.code_size)?;
+
+    writeln!(out, "\nLargest methods")?;
+    for method in &stats.largest_methods {
+        writeln!(
+            out,
+            "  {:>10} 0x{:X} {} ({:?})",
+            method.size, method.address, method.name, method.size_source
+        )?;
+    }
+
+    for (title, sizes) in [
+        ("Code size by namespace", &stats.namespaces),
+        ("Code size by assembly", &stats.assemblies),
+    ] {
+        writeln!(out, "\n{title}")?;
+        for size in sizes {
+            let name = match size.name.is_empty() {
+                true => "GlobalNamespace",
+                false => &size.name,
+            };
+            writeln!(out, "  {:>10} {name} ({} methods)", size.size, size.methods)?;
+        }
+    }
+
+    writeln!(out, "\nMost instantiated generic types")?;
+    for generic_type in &stats.generic_types {
+        writeln!(
+            out,
+            "  {:>10} {}",
+            generic_type.instances, generic_type.name
+        )?;
+    }
+
+    for (title, types) in [
+        ("Blacklisted types", &stats.blacklisted_types),
+        ("Skipped types", &stats.skipped_types),
+    ] {
+        writeln!(out, "\n{title}")?;
+        for ty in types {
+            writeln!(out, "  {ty}")?;
+        }
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Header of `version` with `section_count` sections, the i-th at `0x1000 * (i + 1)`
+    fn header(version: u32, section_count: u32) -> Vec<u8> {
+        let first_offset = 8 + section_count * 8;
+
+        [METADATA_SANITY, version, first_offset, 0x10]
+            .into_iter()
+            .chain((1..section_count).flat_map(|i| [0x1000 * (i + 1), 0x10 + i]))
+            .flat_map(u32::to_le_bytes)
+            .collect()
+    }
+
+    fn names(sections: &[HeaderSection]) -> Vec<&str> {
+        sections.iter().map(|s| s.name.as_str()).collect()
+    }
+
+    #[test]
+    fn names_v29_sections() {
+        for version in [29, 31] {
+            let (read_version, sections) = read_header(&header(version, 31)).unwrap();
+
+            assert_eq!(read_version, version);
+            assert_eq!(names(&sections), HEADER_SECTIONS_V29);
+
+            let last = sections.last().unwrap();
+            assert_eq!(last.name, "exportedTypeDefinitions");
+            assert_eq!((last.offset, last.size), (0x1F000, 0x10 + 30));
+        }
+    }
+
+    #[test]
+    fn numbers_sections_of_other_layouts() {
+        // v24 has a different set of sections
+        let (version, sections) = read_header(&header(24, 33)).unwrap();
+
+        assert_eq!(version, 24);
+        assert_eq!(sections.len(), 33);
+        assert_eq!(sections[0].name, "section0");
+        assert_eq!(sections[32].name, "section32");
+        assert_eq!((sections[1].offset, sections[1].size), (0x2000, 0x11));
+    }
+
+    #[test]
+    fn stops_at_truncated_sections() {
+        let mut data = header(29, 31);
+        data.truncate(8 + 10 * 8 + 4);
+
+        let (_, sections) = read_header(&data).unwrap();
+        assert_eq!(sections.len(), 10);
+    }
+
+    #[test]
+    fn rejects_invalid_headers() {
+        assert!(read_header(&[]).is_err());
+
+        let mut data = header(29, 31);
+        data[0] ^= 0xFF;
+        assert!(read_header(&data).is_err());
+    }
+}
@@ -0,0 +1,172 @@
+//! Every C# string literal of the build, for finding the code behind UI text or network keys
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use brocolib::global_metadata::MethodIndex;
+use color_eyre::eyre::Result;
+use itertools::Itertools;
+
+use crate::{data::metadata_usage::MetadataUsage, generate::metadata::CordlMetadata};
+
+use super::xrefs::XrefAnalysis;
+
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "json",
+    derive(serde::Serialize, serde::Deserialize, schemars::JsonSchema)
+)]
+pub struct StringLiteral {
+    /// Index into the string literal table
+    pub index: u32,
+    pub value: String,
+    /// Addresses of the metadata usage slots holding the literal, found by disassembling methods
+    #[cfg_attr(
+        feature = "json",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub slots: Vec<u64>,
+    /// `MethodIndex` of every method loading the literal
+    #[cfg_attr(
+        feature = "json",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub used_by: Vec<u32>,
+}
+
+/// Slots and users are only known when `xrefs` is given
+pub fn collect_string_literals(
+    metadata: &CordlMetadata,
+    xrefs: Option<&XrefAnalysis>,
+) -> Vec<StringLiteral> {
+    let count = metadata
+        .metadata
+        .global_metadata
+        .string_literals
+        .as_vec()
+        .len();
+    // literal index -> slots, in address order
+    let slots: HashMap<u32, Vec<u64>> = xrefs
+        .into_iter()
+        .flat_map(|xrefs| &xrefs.usage_slots)
+        .filter_map(|(addr, usage)| match usage {
+            MetadataUsage::StringLiteral(index) => Some((*index, *addr)),
+            _ => None,
+        })
+        .into_group_map();
+
+    (0..count as u32)
+        .map(|index| {
+            let usage = MetadataUsage::StringLiteral(index);
+
+            StringLiteral {
+                index,
+                value: metadata.string_literal(index as usize).unwrap_or_default(),
+                slots: slots.get(&index).cloned().unwrap_or_default(),
+                used_by: xrefs
+                    .and_then(|xrefs| xrefs.used_by.get(&usage))
+                    .into_iter()
+                    .flatten()
+                    .copied()
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+/// `index,slots,used_by,value`, with slots and methods separated by `;`
+/// since generic method names contain spaces and commas
+pub fn write_csv(literals: &[StringLiteral], metadata: &CordlMetadata, file: &Path) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(file)?);
+
+    writeln!(writer, "index,slots,used_by,value")?;
+    for literal in literals {
+        let used_by = literal
+            .used_by
+            .iter()
+            .map(|m| metadata.method_full_name(MethodIndex::new(*m)))
+            .collect_vec();
+
+        writeln!(writer, "{}", csv_row(literal, &used_by))?;
+    }
+
+    Ok(())
+}
+
+fn csv_row(literal: &StringLiteral, used_by: &[String]) -> String {
+    let slots = literal.slots.iter().map(|a| format!("0x{a:X}")).join(";");
+
+    format!(
+        "{},{slots},{},{}",
+        literal.index,
+        csv_escape(&used_by.join(";")),
+        csv_escape(&literal.value)
+    )
+}
+
+#[cfg(feature = "json")]
+pub fn write_json(literals: &[StringLiteral], file: &Path, format: bool) -> Result<()> {
+    let mut buf_writer = BufWriter::new(File::create(file)?);
+    match format {
+        true => serde_json::to_writer_pretty(&mut buf_writer, literals)?,
+        false => serde_json::to_writer(&mut buf_writer, literals)?,
+    };
+
+    Ok(())
+}
+
+/// Quotes fields with separators, quotes or line breaks, as in RFC 4180
+fn csv_escape(s: &str) -> String {
+    match s.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", s.replace('"', "\"\"")),
+        false => s.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_csv_fields() {
+        assert_eq!(csv_escape("Play"), "Play");
+        assert_eq!(csv_escape(""), "");
+        assert_eq!(csv_escape(" padded "), " padded ");
+        assert_eq!(csv_escape("Hello, world"), "\"Hello, world\"");
+        assert_eq!(csv_escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_escape("line\nbreak"), "\"line\nbreak\"");
+        assert_eq!(csv_escape("crlf\r\n"), "\"crlf\r\n\"");
+    }
+
+    #[test]
+    fn separates_methods_in_csv_rows() {
+        let literal = StringLiteral {
+            index: 7,
+            value: "Hello, world".to_string(),
+            slots: vec![0x1A00, 0x2B08],
+            used_by: vec![1, 2],
+        };
+        let used_by = [
+            "System.Collections.Generic.List<System.Int32, System.String>::Add".to_string(),
+            "Game.Player::Update".to_string(),
+        ];
+
+        let row = csv_row(&literal, &used_by);
+        assert_eq!(
+            row,
+            "7,0x1A00;0x2B08,\"System.Collections.Generic.List<System.Int32, System.String>::Add;\
+             Game.Player::Update\",\"Hello, world\""
+        );
+
+        let unused = StringLiteral {
+            slots: vec![],
+            used_by: vec![],
+            ..literal
+        };
+        assert_eq!(csv_row(&unused, &[]), "7,,,\"Hello, world\"");
+    }
+}
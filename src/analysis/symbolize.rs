@@ -0,0 +1,293 @@
+//! Address to method lookup, for the libil2cpp.so frames of crash logs
+
+use std::{collections::HashMap, sync::LazyLock};
+
+use brocolib::global_metadata::MethodIndex;
+use itertools::Itertools;
+use regex::Regex;
+
+use crate::generate::metadata::CordlMetadata;
+
+/// `#00 pc 0000000001a2b3c4  /data/app/.../libil2cpp.so` in tombstones and logcat backtraces
+static FRAME_PC: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\bpc\s+(?:0x)?([0-9a-fA-F]+)\b").unwrap());
+static HEX_ADDRESS: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b0x([0-9a-fA-F]+)\b").unwrap());
+
+struct Symbol {
+    name: String,
+    /// Known for method bodies, otherwise the function ends at the next function start
+    size: Option<u64>,
+}
+
+pub struct Symbolizer<'a> {
+    function_starts: &'a [u64],
+    symbols: HashMap<u64, Vec<Symbol>>,
+}
+
+impl<'a> Symbolizer<'a> {
+    pub fn new(metadata: &'a CordlMetadata) -> Self {
+        let mut symbols: HashMap<u64, Vec<Symbol>> = HashMap::new();
+        let mut add = |addr: u64, name: String, size: Option<u64>| {
+            if addr != 0x0 {
+                symbols.entry(addr).or_default().push(Symbol { name, size });
+            }
+        };
+
+        // invoker index -> every method it calls
+        let mut invoker_users: HashMap<u32, Vec<String>> = HashMap::new();
+        for (method_index, calc) in metadata
+            .method_calculations
+            .iter()
+            .sorted_by_key(|(method_index, _)| method_index.index())
+        {
+            let name = metadata.method_full_name(*method_index);
+            let size = (calc.estimated_size != usize::MAX).then_some(calc.estimated_size as u64);
+
+            add(calc.addrs, name.clone(), size);
+            if let Some(thunk) = calc.adjustor_thunk_addrs {
+                add(thunk, format!("{name} (adjustor thunk)"), None);
+            }
+            if let Some(wrapper) = calc.reverse_pinvoke_wrapper_addrs {
+                add(wrapper, format!("{name} (reverse P/Invoke wrapper)"), None);
+            }
+            if let Some(invoker_index) = calc.invoker_index {
+                invoker_users.entry(invoker_index).or_default().push(name);
+            }
+        }
+
+        let mr = metadata.metadata_registration;
+        for generic_method in &mr.generic_method_table {
+            let Some(&addrs) = metadata
+                .code_registration
+                .generic_method_pointers
+                .get(generic_method.indices.method_index as usize)
+            else {
+                continue;
+            };
+            let Some(method_spec) = mr
+                .method_specs
+                .get(generic_method.generic_method_index as usize)
+            else {
+                continue;
+            };
+
+            add(
+                addrs,
+                generic_method_name(
+                    metadata,
+                    method_spec.method_definition_index,
+                    method_spec.class_inst_index,
+                    method_spec.method_inst_index,
+                ),
+                None,
+            );
+        }
+
+        // invokers are shared by every method with the same signature
+        for (i, &invoker) in metadata
+            .code_registration
+            .invoker_pointers
+            .iter()
+            .enumerate()
+        {
+            let name = match invoker_users.get(&(i as u32)).map(Vec::as_slice) {
+                Some([method]) => format!("{method} (invoker)"),
+                _ => format!("RuntimeInvoker_{i}"),
+            };
+            add(invoker, name, None);
+        }
+
+        Self {
+            function_starts: &metadata.function_starts,
+            symbols,
+        }
+    }
+
+    /// `Namespace.Type::Method(+0x1c)`, or None if the address isn't in a known function
+    pub fn symbolize(&self, addr: u64) -> Option<String> {
+        let next = self.function_starts.partition_point(|start| *start <= addr);
+        let start = *self.function_starts.get(next.checked_sub(1)?)?;
+        let offset = addr - start;
+
+        let symbols = self.symbols.get(&start)?;
+        if symbols
+            .iter()
+            .any(|symbol| symbol.size.is_some_and(|size| offset >= size))
+        {
+            return None;
+        }
+
+        let mut name = format!("{}(+0x{offset:x})", symbols[0].name);
+        // identical code folding, or shared generic code
+        if symbols.len() > 1 {
+            name += &format!(" [{} more at this address]", symbols.len() - 1);
+        }
+
+        Some(name)
+    }
+
+    /// Appends the method to libil2cpp.so frames, and to lines that are only an address
+    pub fn symbolize_line(&self, line: &str) -> String {
+        let trimmed = line.trim();
+        let addr = if let Some(addr) = parse_bare_address(trimmed) {
+            Some(addr)
+        } else if line.contains("libil2cpp.so") {
+            FRAME_PC
+                .captures(line)
+                .or_else(|| HEX_ADDRESS.captures(line))
+                .and_then(|captures| parse_hex(&captures[1]))
+        } else {
+            None
+        };
+
+        match addr.and_then(|addr| self.symbolize(addr)) {
+            Some(symbol) => format!("{line}  {symbol}"),
+            None => line.to_string(),
+        }
+    }
+}
+
+/// `0x` prefixed or at least 8 digits, so words like `dead` or `1234` aren't taken for addresses
+fn parse_bare_address(s: &str) -> Option<u64> {
+    match s.starts_with("0x") || s.len() >= 8 {
+        true => parse_hex(s),
+        false => None,
+    }
+}
+
+fn parse_hex(s: &str) -> Option<u64> {
+    let digits = s.strip_prefix("0x").unwrap_or(s);
+    if digits.is_empty() {
+        return None;
+    }
+
+    u64::from_str_radix(digits, 16).ok()
+}
+
+/// `Namespace.Type<System.Int32>::Method<System.String>`
+fn generic_method_name(
+    metadata: &CordlMetadata,
+    method_index: MethodIndex,
+    class_inst_index: u32,
+    method_inst_index: u32,
+) -> String {
+    let gm = &metadata.metadata.global_metadata;
+    let mr = metadata.metadata_registration;
+    let method = &gm.methods[method_index];
+    let td = &gm.type_definitions[method.declaring_type];
+
+    // u32::MAX when that part isn't generic
+    let args = |inst_index: u32| match mr.generic_insts.get(inst_index as usize) {
+        Some(inst) if inst_index != u32::MAX => format!(
+            "<{}>",
+            inst.types
+                .iter()
+                .map(|ty| mr.types[*ty].full_name(metadata.metadata))
+                .join(", ")
+        ),
+        _ => String::new(),
+    };
+
+    format!(
+        "{}{}::{}{}",
+        td.full_name(metadata.metadata, false),
+        args(class_inst_index),
+        method.name(metadata.metadata),
+        args(method_inst_index)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FUNCTION_STARTS: &[u64] = &[0x1000, 0x2000, 0x3000];
+
+    fn symbolizer() -> Symbolizer<'static> {
+        let symbol = |name: &str, size| Symbol {
+            name: name.to_string(),
+            size,
+        };
+
+        Symbolizer {
+            function_starts: FUNCTION_STARTS,
+            symbols: HashMap::from([
+                (0x1000, vec![symbol("Game.Player::Update", Some(0x100))]),
+                (
+                    0x2000,
+                    vec![
+                        symbol("System.Collections.Generic.List`1::Add", None),
+                        symbol("System.Collections.Generic.List`1::Remove", None),
+                    ],
+                ),
+            ]),
+        }
+    }
+
+    #[test]
+    fn symbolizes_addresses_inside_methods() {
+        let symbolizer = symbolizer();
+
+        assert_eq!(
+            symbolizer.symbolize(0x101c).as_deref(),
+            Some("Game.Player::Update(+0x1c)")
+        );
+        assert_eq!(
+            symbolizer.symbolize(0x2fff).as_deref(),
+            Some("System.Collections.Generic.List`1::Add(+0xfff) [1 more at this address]")
+        );
+        // past the end of the method body
+        assert_eq!(symbolizer.symbolize(0x1100), None);
+        // before the first function, and in a function without symbols
+        assert_eq!(symbolizer.symbolize(0x10), None);
+        assert_eq!(symbolizer.symbolize(0x3004), None);
+    }
+
+    #[test]
+    fn symbolizes_crash_log_lines() {
+        let symbolizer = symbolizer();
+
+        assert_eq!(
+            symbolizer.symbolize_line(
+                "    #03 pc 0000000000001010  /data/app/com.game/lib/arm64/libil2cpp.so"
+            ),
+            "    #03 pc 0000000000001010  /data/app/com.game/lib/arm64/libil2cpp.so  \
+             Game.Player::Update(+0x10)"
+        );
+        assert_eq!(
+            symbolizer.symbolize_line("at libil2cpp.so (0x1008)"),
+            "at libil2cpp.so (0x1008)  Game.Player::Update(+0x8)"
+        );
+        assert_eq!(
+            symbolizer.symbolize_line("  0x1004 "),
+            "  0x1004   Game.Player::Update(+0x4)"
+        );
+        assert_eq!(
+            symbolizer.symbolize_line("00001004"),
+            "00001004  Game.Player::Update(+0x4)"
+        );
+    }
+
+    #[test]
+    fn leaves_other_lines_alone() {
+        let symbolizer = symbolizer();
+
+        for line in [
+            "",
+            "0x",
+            // hex looking words and short numbers
+            "dead",
+            "face",
+            "add",
+            "1234",
+            "1004",
+            "  1010 ",
+            "    #00 pc 0000000000001010  /system/lib64/libc.so",
+            "Abort message: 'at 0x1010'",
+            "    #01 pc 0000000000001100  /data/app/com.game/lib/arm64/libil2cpp.so",
+        ] {
+            assert_eq!(symbolizer.symbolize_line(line), line);
+        }
+    }
+}
@@ -0,0 +1,337 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use brocolib::{global_metadata::MethodIndex, runtime_metadata::TypeData};
+use color_eyre::eyre::Result;
+use itertools::Itertools;
+use log::info;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+use crate::{
+    data::{elf_image::ElfImage, metadata_usage::MetadataUsage},
+    generate::metadata::CordlMetadata,
+    helpers::arm64::{self, Instruction, RegisterTracker},
+};
+
+/// Anything larger is almost certainly a bad size estimate
+const MAX_METHOD_SIZE: usize = 0x10_0000;
+
+/// Caller/callee graph and metadata usages found by disassembling method bodies
+///
+/// Keys and values are `MethodIndex` values
+#[derive(Debug, Default)]
+pub struct XrefAnalysis {
+    /// caller -> callees
+    pub calls: BTreeMap<u32, BTreeSet<u32>>,
+    /// callee -> callers
+    pub called_by: BTreeMap<u32, BTreeSet<u32>>,
+    /// method -> metadata it references
+    pub usages: BTreeMap<u32, BTreeSet<MetadataUsage>>,
+    /// metadata -> methods referencing it
+    pub used_by: BTreeMap<MetadataUsage, BTreeSet<u32>>,
+    /// address of the `.data` slot holding each usage
+    pub usage_slots: BTreeMap<u64, MetadataUsage>,
+}
+
+#[derive(Default)]
+struct BodyXrefs {
+    callees: BTreeSet<u32>,
+    usages: BTreeSet<MetadataUsage>,
+    usage_slots: BTreeMap<u64, MetadataUsage>,
+}
+
+impl XrefAnalysis {
+    pub fn analyze(metadata: &CordlMetadata) -> Result<XrefAnalysis> {
+        let image = ElfImage::parse(metadata.elf_data)?;
+        let address_to_methods = method_address_map(metadata);
+
+        // identical code folding means multiple methods may share a body
+        // so disassemble each address once
+        let bodies = metadata
+            .method_calculations
+            .iter()
+            .filter(|(_, calc)| {
+                calc.addrs != 0x0
+                    && calc.estimated_size > 0
+                    && calc.estimated_size <= MAX_METHOD_SIZE
+            })
+            .map(|(_, calc)| (calc.addrs, calc.estimated_size))
+            .unique()
+            .collect_vec();
+
+        info!("Disassembling {} method bodies", bodies.len());
+
+        let body_xrefs: HashMap<u64, BodyXrefs> = bodies
+            .par_iter()
+            .map(|(addr, size)| {
+                let xrefs = analyze_body(&image, *addr, *size, &address_to_methods);
+                (*addr, xrefs)
+            })
+            .collect();
+
+        let mut analysis = XrefAnalysis::default();
+        for (addr, xrefs) in &body_xrefs {
+            let usages = xrefs
+                .usages
+                .iter()
+                .filter(|u| is_valid_usage(u, metadata))
+                .copied()
+                .collect::<BTreeSet<_>>();
+
+            analysis
+                .usage_slots
+                .extend(xrefs.usage_slots.iter().filter(|(_, u)| usages.contains(u)));
+
+            for &caller in address_to_methods.get(addr).into_iter().flatten() {
+                for &callee in &xrefs.callees {
+                    analysis.calls.entry(caller).or_default().insert(callee);
+                    analysis.called_by.entry(callee).or_default().insert(caller);
+                }
+                for usage in &usages {
+                    analysis.used_by.entry(*usage).or_default().insert(caller);
+                }
+                if !usages.is_empty() {
+                    analysis.usages.insert(caller, usages.clone());
+                }
+            }
+        }
+
+        info!(
+            "Found {} call edges and {} metadata usages",
+            analysis.calls.values().map(|c| c.len()).sum::<usize>(),
+            analysis.used_by.len()
+        );
+
+        Ok(analysis)
+    }
+
+    /// Human readable name of what a usage points to
+    pub fn usage_name(usage: &MetadataUsage, metadata: &CordlMetadata) -> String {
+        let mr = metadata.metadata_registration;
+        let gm = &metadata.metadata.global_metadata;
+
+        match *usage {
+            MetadataUsage::TypeInfo(i) | MetadataUsage::Il2CppType(i) => {
+                mr.types[i as usize].full_name(metadata.metadata)
+            }
+            MetadataUsage::MethodDef(i) => metadata.method_full_name(MethodIndex::new(i)),
+            MetadataUsage::MethodRef(i) => {
+                let method_spec = &mr.method_specs[i as usize];
+                metadata.method_full_name(method_spec.method_definition_index)
+            }
+            MetadataUsage::FieldInfo(i) | MetadataUsage::FieldRva(i) => {
+                let field_ref = &gm.field_refs.as_vec()[i as usize];
+                let ty = &mr.types[field_ref.type_index as usize];
+
+                let field_name = match ty.data {
+                    TypeData::TypeDefinitionIndex(tdi) => gm.type_definitions[tdi]
+                        .fields(metadata.metadata)
+                        .get(field_ref.field_index as usize)
+                        .map(|f| f.name(metadata.metadata).to_string()),
+                    _ => None,
+                }
+                .unwrap_or_else(|| format!("field_{}", field_ref.field_index));
+
+                format!("{}::{field_name}", ty.full_name(metadata.metadata))
+            }
+            MetadataUsage::StringLiteral(i) => format!("StringLiteral_{i}"),
+        }
+    }
+}
+
+/// Address -> every method compiled to that address
+/// including generic method instantiations, mapped to their definition
+pub fn method_address_map(metadata: &CordlMetadata) -> HashMap<u64, Vec<u32>> {
+    let mut map: HashMap<u64, Vec<u32>> = HashMap::new();
+
+    for (method_index, calc) in &metadata.method_calculations {
+        if calc.addrs == 0x0 {
+            continue;
+        }
+        map.entry(calc.addrs)
+            .or_default()
+            .push(method_index.index());
+    }
+
+    let mr = metadata.metadata_registration;
+    for generic_method in &mr.generic_method_table {
+        let Some(&addrs) = metadata
+            .code_registration
+            .generic_method_pointers
+            .get(generic_method.indices.method_index as usize)
+        else {
+            continue;
+        };
+        let Some(method_spec) = mr
+            .method_specs
+            .get(generic_method.generic_method_index as usize)
+        else {
+            continue;
+        };
+
+        if addrs == 0x0 {
+            continue;
+        }
+        map.entry(addrs)
+            .or_default()
+            .push(method_spec.method_definition_index.index());
+    }
+
+    for methods in map.values_mut() {
+        methods.sort();
+        methods.dedup();
+    }
+
+    map
+}
+
+fn analyze_body(
+    image: &ElfImage,
+    addr: u64,
+    size: usize,
+    address_to_methods: &HashMap<u64, Vec<u32>>,
+) -> BodyXrefs {
+    let mut xrefs = BodyXrefs::default();
+    let Some(code) = image.read(addr, size) else {
+        return xrefs;
+    };
+
+    let mut registers = RegisterTracker::default();
+    for (_pc, _insn, instruction) in arm64::disassemble(code, addr) {
+        match instruction {
+            Instruction::Bl { target } => {
+                xrefs
+                    .callees
+                    .extend(address_to_methods.get(&target).into_iter().flatten());
+            }
+            // branches inside the body are control flow, outside are tail calls
+            Instruction::B { target } if target < addr || target >= addr + size as u64 => {
+                xrefs
+                    .callees
+                    .extend(address_to_methods.get(&target).into_iter().flatten());
+            }
+            Instruction::LoadStore { rn, offset, .. } => {
+                if let Some(base) = registers.get(rn)
+                    && let Some(usage) = image
+                        .read_u64(base + offset)
+                        .and_then(MetadataUsage::decode)
+                {
+                    xrefs.usages.insert(usage);
+                    xrefs.usage_slots.insert(base + offset, usage);
+                }
+            }
+            _ => {}
+        }
+        registers.step(instruction);
+    }
+
+    xrefs
+}
+
+/// Reject slots that happen to look like encoded usages
+fn is_valid_usage(usage: &MetadataUsage, metadata: &CordlMetadata) -> bool {
+    let mr = metadata.metadata_registration;
+    let gm = &metadata.metadata.global_metadata;
+    let index = usage.index() as usize;
+
+    match usage {
+        MetadataUsage::TypeInfo(_) | MetadataUsage::Il2CppType(_) => index < mr.types.len(),
+        MetadataUsage::MethodDef(_) => index < gm.methods.as_vec().len(),
+        MetadataUsage::FieldInfo(_) | MetadataUsage::FieldRva(_) => {
+            index < gm.field_refs.as_vec().len()
+        }
+        MetadataUsage::StringLiteral(_) => index < gm.string_literals.as_vec().len(),
+        MetadataUsage::MethodRef(_) => index < mr.method_specs.len(),
+    }
+}
+
+#[cfg(feature = "json")]
+pub mod json {
+    use std::{fs::File, io::BufWriter, path::Path};
+
+    use brocolib::global_metadata::MethodIndex;
+    use color_eyre::eyre::Result;
+    use itertools::Itertools;
+    use serde::Serialize;
+
+    use crate::{data::metadata_usage::MetadataUsage, generate::metadata::CordlMetadata};
+
+    use super::XrefAnalysis;
+
+    #[derive(Serialize)]
+    pub struct JsonXrefs {
+        pub methods: Vec<JsonMethodXrefs>,
+        /// metadata usages with every method referencing them
+        pub used_by: Vec<JsonUsedBy>,
+    }
+
+    #[derive(Serialize)]
+    pub struct JsonMethodXrefs {
+        pub method_index: u32,
+        pub name: String,
+        pub addrs: Option<u64>,
+        pub calls: Vec<u32>,
+        pub called_by: Vec<u32>,
+        pub usages: Vec<MetadataUsage>,
+    }
+
+    #[derive(Serialize)]
+    pub struct JsonUsedBy {
+        pub usage: MetadataUsage,
+        pub name: String,
+        pub used_by: Vec<u32>,
+    }
+
+    pub fn make_json(
+        analysis: &XrefAnalysis,
+        metadata: &CordlMetadata,
+        file: &Path,
+        format: bool,
+    ) -> Result<()> {
+        let method_indices = analysis
+            .calls
+            .keys()
+            .chain(analysis.called_by.keys())
+            .chain(analysis.usages.keys())
+            .copied()
+            .sorted()
+            .dedup();
+
+        let methods = method_indices
+            .map(|method_index| JsonMethodXrefs {
+                method_index,
+                name: metadata.method_full_name(MethodIndex::new(method_index)),
+                addrs: metadata
+                    .method_calculations
+                    .get(&MethodIndex::new(method_index))
+                    .map(|c| c.addrs),
+                calls: collect(analysis.calls.get(&method_index)),
+                called_by: collect(analysis.called_by.get(&method_index)),
+                usages: collect(analysis.usages.get(&method_index)),
+            })
+            .collect_vec();
+
+        let used_by = analysis
+            .used_by
+            .iter()
+            .map(|(usage, methods)| JsonUsedBy {
+                usage: *usage,
+                name: XrefAnalysis::usage_name(usage, metadata),
+                used_by: methods.iter().copied().collect(),
+            })
+            .collect_vec();
+
+        let json = JsonXrefs { methods, used_by };
+
+        let mut buf_writer = BufWriter::new(File::create(file)?);
+        match format {
+            true => serde_json::to_writer_pretty(&mut buf_writer, &json)?,
+            false => serde_json::to_writer(&mut buf_writer, &json)?,
+        };
+
+        Ok(())
+    }
+
+    fn collect<T: Copy>(set: Option<&std::collections::BTreeSet<T>>) -> Vec<T> {
+        set.into_iter().flatten().copied().collect()
+    }
+}
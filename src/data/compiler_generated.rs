@@ -0,0 +1,419 @@
+//! Methods owning the closures, lambdas and state machines the C# compiler generates,
+//! read from the owners' state machine attributes when the metadata has them
+//! and recovered from Roslyn's naming scheme (`GeneratedNames.cs`) otherwise
+
+use std::{collections::HashMap, fmt};
+
+use brocolib::{
+    Metadata,
+    global_metadata::{Il2CppTypeDefinition, MethodIndex, TypeDefinitionIndex},
+    runtime_metadata::TypeData,
+};
+use itertools::Itertools;
+
+use crate::{
+    data::custom_attributes::{AttributeValue, CustomAttributes, METHOD_DEF_TOKEN},
+    generate::cs_type_tag::CsTypeTag,
+};
+
+const STATE_MACHINE_PREFIX: &str = "d__";
+const LAMBDA_PREFIX: &str = "b__";
+const LOCAL_FUNCTION_PREFIX: &str = "g__";
+const LAMBDA_CACHE_NAME: &str = "<>c";
+const GENERIC_LAMBDA_CACHE_PREFIX: &str = "<>c__";
+const DISPLAY_CLASS_PREFIX: &str = "<>c__DisplayClass";
+const ASYNC_STATE_MACHINE_ATTRIBUTE: &str = "AsyncStateMachineAttribute";
+const ITERATOR_STATE_MACHINE_ATTRIBUTE: &str = "IteratorStateMachineAttribute";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GeneratedKind {
+    /// `<Method>d__N` implementing `IAsyncStateMachine`
+    AsyncStateMachine,
+    /// `<Method>d__N` of a `yield` method
+    Iterator,
+    /// `<>c__DisplayClassN_M`, holding the variables captured by lambdas
+    Closure,
+    /// `<Method>b__N_M`
+    Lambda,
+    /// `<Method>g__Name|N_M`
+    LocalFunction,
+}
+
+impl fmt::Display for GeneratedKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            GeneratedKind::AsyncStateMachine => "async state machine",
+            GeneratedKind::Iterator => "iterator",
+            GeneratedKind::Closure => "closure",
+            GeneratedKind::Lambda => "lambda",
+            GeneratedKind::LocalFunction => "local function",
+        })
+    }
+}
+
+/// A compiler generated type or method and the method its code was written in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeneratedMember {
+    pub kind: GeneratedKind,
+    pub owner: MethodIndex,
+    /// The generated type, or the type declaring the generated method
+    pub tdi: TypeDefinitionIndex,
+    /// Set for lambdas and local functions
+    pub method: Option<MethodIndex>,
+}
+
+impl GeneratedMember {
+    /// `Namespace.Type/<Method>d__3` or `Namespace.Type/<>c::<Method>b__3_0`
+    pub fn full_name(&self, metadata: &Metadata) -> String {
+        let gm = &metadata.global_metadata;
+        let type_name = gm.type_definitions[self.tdi].full_name(metadata, true);
+
+        match self.method {
+            Some(method_index) => {
+                format!("{type_name}::{}", gm.methods[method_index].name(metadata))
+            }
+            None => type_name,
+        }
+    }
+}
+
+/// A generated member before its owner is picked among the overloads of `owner_name`
+struct Candidate<'a> {
+    kind: GeneratedKind,
+    owner_tdi: TypeDefinitionIndex,
+    owner_name: &'a str,
+    /// Roslyn's method ordinal, the index of the owner among the members of its type
+    ordinal: Option<u32>,
+    tdi: TypeDefinitionIndex,
+    method: Option<MethodIndex>,
+}
+
+/// Compiler generated members, by the method owning them
+pub fn find_generated_members(
+    metadata: &Metadata,
+    custom_attributes: &CustomAttributes,
+) -> HashMap<MethodIndex, Vec<GeneratedMember>> {
+    let gm = &metadata.global_metadata;
+    let state_machines = state_machine_owners(metadata, custom_attributes);
+
+    let mut members = vec![];
+    let mut candidates = vec![];
+    for (i, td) in gm.type_definitions.as_vec().iter().enumerate() {
+        let tdi = TypeDefinitionIndex::new(i as u32);
+        let name = td.name(metadata);
+        let declaring_tdi = declaring_tdi(td, metadata);
+
+        if let Some(&(kind, owner)) = state_machines.get(&tdi) {
+            members.push(GeneratedMember {
+                kind,
+                owner,
+                tdi,
+                method: None,
+            });
+        } else if let Some((owner_name, suffix)) = split_generated_name(name)
+            && let Some(ordinal) = suffix.strip_prefix(STATE_MACHINE_PREFIX)
+            && let Some(owner_tdi) = declaring_tdi
+        {
+            candidates.push(Candidate {
+                kind: state_machine_kind(td, metadata),
+                owner_tdi,
+                owner_name,
+                // generic methods give their state machine a generic arity too
+                ordinal: ordinal.split('`').next().and_then(|o| o.parse().ok()),
+                tdi,
+                method: None,
+            });
+        }
+
+        // lambdas are emitted into the `<>c` cache when they capture nothing,
+        // into display classes when they capture locals and into the type itself otherwise
+        let (owner_tdi, type_ordinal) =
+            if let Some(suffix) = name.strip_prefix(DISPLAY_CLASS_PREFIX) {
+                (declaring_tdi, method_ordinal(suffix))
+            } else if name == LAMBDA_CACHE_NAME || name.starts_with(GENERIC_LAMBDA_CACHE_PREFIX) {
+                // generic methods get a `<>c__N` cache of their own
+                (declaring_tdi, None)
+            } else {
+                (Some(tdi), None)
+            };
+        let Some(owner_tdi) = owner_tdi else {
+            continue;
+        };
+
+        for (j, method) in td.methods(metadata).iter().enumerate() {
+            let Some((owner_name, suffix)) = split_generated_name(method.name(metadata)) else {
+                continue;
+            };
+            let (kind, ordinal) = if let Some(ordinal) = suffix.strip_prefix(LAMBDA_PREFIX) {
+                (GeneratedKind::Lambda, ordinal)
+            } else if let Some(rest) = suffix.strip_prefix(LOCAL_FUNCTION_PREFIX)
+                && let Some((_, ordinal)) = rest.split_once('|')
+            {
+                (GeneratedKind::LocalFunction, ordinal)
+            } else {
+                continue;
+            };
+
+            candidates.push(Candidate {
+                kind,
+                owner_tdi,
+                owner_name,
+                // lambdas of display classes only carry their own index
+                ordinal: method_ordinal(ordinal).or(type_ordinal),
+                tdi,
+                method: Some(MethodIndex::new(td.method_start.index() + j as u32)),
+            });
+        }
+    }
+
+    members.extend(
+        candidates
+            .into_iter()
+            .into_group_map_by(|c| (c.owner_tdi, c.owner_name))
+            .into_iter()
+            .flat_map(|((owner_tdi, owner_name), candidates)| {
+                let overloads = overloads_of(owner_tdi, owner_name, metadata);
+                resolve_overloads(&overloads, candidates)
+            }),
+    );
+
+    // display classes belong to the method of the lambdas they hold
+    let closure_owners: HashMap<TypeDefinitionIndex, MethodIndex> = members
+        .iter()
+        .filter(|m| m.method.is_some())
+        .map(|m| (m.tdi, m.owner))
+        .collect();
+    for (i, td) in gm.type_definitions.as_vec().iter().enumerate() {
+        let tdi = TypeDefinitionIndex::new(i as u32);
+        if !td.name(metadata).starts_with(DISPLAY_CLASS_PREFIX) {
+            continue;
+        }
+        if let Some(&owner) = closure_owners.get(&tdi) {
+            members.push(GeneratedMember {
+                kind: GeneratedKind::Closure,
+                owner,
+                tdi,
+                method: None,
+            });
+        }
+    }
+
+    members
+        .into_iter()
+        .sorted_by_key(|m| (m.tdi.index(), m.method.map(|m| m.index())))
+        .into_group_map_by(|m| m.owner)
+}
+
+/// State machines named by the `typeof` argument of their owner's
+/// `AsyncStateMachineAttribute` or `IteratorStateMachineAttribute`
+fn state_machine_owners(
+    metadata: &Metadata,
+    custom_attributes: &CustomAttributes,
+) -> HashMap<TypeDefinitionIndex, (GeneratedKind, MethodIndex)> {
+    let gm = &metadata.global_metadata;
+    let types = &metadata.runtime_metadata.metadata_registration.types;
+
+    let mut owners = HashMap::new();
+    for (image_index, image) in gm.images.as_vec().iter().enumerate() {
+        let start = image.type_start.index();
+        for tdi in start..start + image.type_count {
+            let td = &gm.type_definitions[TypeDefinitionIndex::new(tdi)];
+
+            for (i, method) in td.methods(metadata).iter().enumerate() {
+                let token = METHOD_DEF_TOKEN | method.token.rid() as u32;
+                for attribute in custom_attributes.get(image_index, token) {
+                    let attribute_tdi = gm.methods[attribute.constructor].declaring_type;
+                    let kind = match gm.type_definitions[attribute_tdi].name(metadata) {
+                        ASYNC_STATE_MACHINE_ATTRIBUTE => GeneratedKind::AsyncStateMachine,
+                        ITERATOR_STATE_MACHINE_ATTRIBUTE => GeneratedKind::Iterator,
+                        _ => continue,
+                    };
+                    let [AttributeValue::Type(Some(type_index))] = attribute.arguments.as_slice()
+                    else {
+                        continue;
+                    };
+                    let state_machine = match types[*type_index].data {
+                        TypeData::TypeDefinitionIndex(tdi) => tdi,
+                        data @ TypeData::GenericClassIndex(_) => {
+                            CsTypeTag::from_type_data(data, metadata).get_tdi()
+                        }
+                        _ => continue,
+                    };
+
+                    let owner = MethodIndex::new(td.method_start.index() + i as u32);
+                    owners.insert(state_machine, (kind, owner));
+                }
+            }
+        }
+    }
+
+    owners
+}
+
+/// Matches each candidate to an overload. Ordinals increase in declaration order
+/// like method indices, so overloads are only told apart when each has generated members
+fn resolve_overloads(
+    overloads: &[MethodIndex],
+    candidates: Vec<Candidate>,
+) -> Vec<GeneratedMember> {
+    let ordinals = candidates
+        .iter()
+        .map(|c| c.ordinal)
+        .collect::<Option<Vec<_>>>()
+        .map(|ordinals| ordinals.into_iter().sorted().dedup().collect_vec());
+
+    let owner_of = |candidate: &Candidate| match overloads {
+        [owner] => Some(*owner),
+        _ => {
+            let ordinals = ordinals.as_ref().filter(|o| o.len() == overloads.len())?;
+            let position = ordinals
+                .iter()
+                .position(|o| Some(*o) == candidate.ordinal)?;
+            Some(overloads[position])
+        }
+    };
+
+    candidates
+        .iter()
+        .filter_map(|candidate| {
+            Some(GeneratedMember {
+                kind: candidate.kind,
+                owner: owner_of(candidate)?,
+                tdi: candidate.tdi,
+                method: candidate.method,
+            })
+        })
+        .collect()
+}
+
+/// Methods of `tdi` named `name`, in declaration order
+fn overloads_of(tdi: TypeDefinitionIndex, name: &str, metadata: &Metadata) -> Vec<MethodIndex> {
+    let td = &metadata.global_metadata.type_definitions[tdi];
+
+    td.methods(metadata)
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| m.name(metadata) == name)
+        .map(|(i, _)| MethodIndex::new(td.method_start.index() + i as u32))
+        .collect()
+}
+
+/// `<Method>d__3` -> `("Method", "d__3")`
+fn split_generated_name(name: &str) -> Option<(&str, &str)> {
+    let (owner_name, suffix) = name.strip_prefix('<')?.split_once('>')?;
+
+    (!owner_name.is_empty()).then_some((owner_name, suffix))
+}
+
+/// `3_0` -> 3. Older compilers only numbered generated members,
+/// so a lone number isn't an ordinal
+fn method_ordinal(suffix: &str) -> Option<u32> {
+    suffix.split_once('_')?.0.parse().ok()
+}
+
+/// Fallback for metadata without attributes: only async state machines
+/// implement `IAsyncStateMachine`
+fn state_machine_kind(td: &Il2CppTypeDefinition, metadata: &Metadata) -> GeneratedKind {
+    let gm = &metadata.global_metadata;
+    let is_async = td.interfaces(metadata).iter().any(|&interface| {
+        match metadata.runtime_metadata.metadata_registration.types[interface as usize].data {
+            TypeData::TypeDefinitionIndex(tdi) => {
+                gm.type_definitions[tdi].name(metadata) == "IAsyncStateMachine"
+            }
+            _ => false,
+        }
+    });
+
+    match is_async {
+        true => GeneratedKind::AsyncStateMachine,
+        false => GeneratedKind::Iterator,
+    }
+}
+
+fn declaring_tdi(td: &Il2CppTypeDefinition, metadata: &Metadata) -> Option<TypeDefinitionIndex> {
+    if td.declaring_type_index == u32::MAX {
+        return None;
+    }
+
+    let declaring_ty =
+        &metadata.runtime_metadata.metadata_registration.types[td.declaring_type_index as usize];
+    Some(CsTypeTag::from_type_data(declaring_ty.data, metadata).get_tdi())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(ordinal: Option<u32>, tdi: u32) -> Candidate<'static> {
+        Candidate {
+            kind: GeneratedKind::AsyncStateMachine,
+            owner_tdi: TypeDefinitionIndex::new(0),
+            owner_name: "Load",
+            ordinal,
+            tdi: TypeDefinitionIndex::new(tdi),
+            method: None,
+        }
+    }
+
+    fn owners(members: &[GeneratedMember]) -> Vec<(u32, u32)> {
+        members
+            .iter()
+            .map(|m| (m.tdi.index(), m.owner.index()))
+            .collect()
+    }
+
+    #[test]
+    fn splits_generated_names() {
+        assert_eq!(split_generated_name("<Load>d__3"), Some(("Load", "d__3")));
+        assert_eq!(
+            split_generated_name("<Load>g__Parse|3_0"),
+            Some(("Load", "g__Parse|3_0"))
+        );
+        assert_eq!(split_generated_name("<>c"), None);
+        assert_eq!(split_generated_name("Load"), None);
+    }
+
+    #[test]
+    fn reads_method_ordinals() {
+        assert_eq!(method_ordinal("3_0"), Some(3));
+        assert_eq!(method_ordinal("12_1"), Some(12));
+        assert_eq!(method_ordinal("3"), None);
+        assert_eq!(method_ordinal("x_0"), None);
+    }
+
+    #[test]
+    fn single_overload_owns_everything() {
+        let overloads = [MethodIndex::new(10)];
+        let members =
+            resolve_overloads(&overloads, vec![candidate(None, 1), candidate(Some(4), 2)]);
+
+        assert_eq!(owners(&members), [(1, 10), (2, 10)]);
+    }
+
+    #[test]
+    fn overloads_follow_ordinal_order() {
+        let overloads = [MethodIndex::new(10), MethodIndex::new(11)];
+        let candidates = vec![
+            candidate(Some(7), 1),
+            candidate(Some(3), 2),
+            candidate(Some(7), 3),
+        ];
+        let members = resolve_overloads(&overloads, candidates);
+
+        assert_eq!(owners(&members), [(1, 11), (2, 10), (3, 11)]);
+    }
+
+    #[test]
+    fn ambiguous_overloads_are_dropped() {
+        let overloads = [MethodIndex::new(10), MethodIndex::new(11)];
+
+        // only one of the overloads has generated members
+        let members = resolve_overloads(&overloads, vec![candidate(Some(3), 1)]);
+        assert!(members.is_empty());
+
+        // older compilers don't number the owner
+        let members =
+            resolve_overloads(&overloads, vec![candidate(Some(3), 1), candidate(None, 2)]);
+        assert!(members.is_empty());
+    }
+}
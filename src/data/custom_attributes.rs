@@ -0,0 +1,481 @@
+//! Custom attributes of the game, decoded from the `attributeData` blobs of metadata v29+.
+//! Each blob holds the constructors of every attribute on a member, then their arguments,
+//! written with their own type like the values of `object` parameters
+
+use std::{collections::HashMap, io::Cursor};
+
+use brocolib::{
+    Metadata,
+    global_metadata::{MethodIndex, TypeDefinitionIndex},
+    runtime_metadata::{Il2CppTypeEnum, TypeData},
+};
+use byteorder::ReadBytesExt;
+use color_eyre::eyre::{ContextCompat, Result, bail};
+use log::warn;
+
+use crate::{Endian, helpers::cursor::ReadBytesExtensions};
+
+pub const TYPE_DEF_TOKEN: u32 = 0x0200_0000;
+pub const FIELD_DEF_TOKEN: u32 = 0x0400_0000;
+pub const METHOD_DEF_TOKEN: u32 = 0x0600_0000;
+pub const PARAM_DEF_TOKEN: u32 = 0x0800_0000;
+pub const EVENT_TOKEN: u32 = 0x1400_0000;
+pub const PROPERTY_TOKEN: u32 = 0x1700_0000;
+/// Images hold a single assembly
+pub const ASSEMBLY_TOKEN: u32 = 0x2000_0001;
+
+/// `(offset, size)` of the sections in `Il2CppGlobalMetadataHeader`
+const IMAGES_HEADER_OFFSET: usize = 168;
+const ATTRIBUTE_DATA_HEADER_OFFSET: usize = 200;
+const ATTRIBUTE_DATA_RANGE_HEADER_OFFSET: usize = 208;
+
+/// `Il2CppImageDefinition`, ending with `customAttributeStart` and `customAttributeCount`
+const IMAGE_DEFINITION_SIZE: usize = 40;
+const IMAGE_CUSTOM_ATTRIBUTE_START: usize = 32;
+/// `Il2CppCustomAttributeDataRange`, a token and the offset of its blob
+const ATTRIBUTE_DATA_RANGE_SIZE: usize = 8;
+
+const IL2CPP_TYPE_SZARRAY: u8 = 0x1D;
+const IL2CPP_TYPE_ENUM: u8 = 0x55;
+const IL2CPP_TYPE_INDEX: u8 = 0xFF;
+
+/// Attributes by image index and the token of the member they're applied to
+#[derive(Default)]
+pub struct CustomAttributes {
+    attributes: HashMap<(usize, u32), Vec<CustomAttribute>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CustomAttribute {
+    pub constructor: MethodIndex,
+    pub arguments: Vec<AttributeValue>,
+    pub fields: Vec<NamedArgument>,
+    pub properties: Vec<NamedArgument>,
+}
+
+/// Field or property set by an attribute. `index` is relative to the fields or properties
+/// of `declaring_type`, which is only set when it's a base of the attribute type
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamedArgument {
+    pub declaring_type: Option<TypeDefinitionIndex>,
+    pub index: u32,
+    pub value: AttributeValue,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttributeValue {
+    /// A null object, il2cpp writes no other values of reference types but strings and types
+    Null,
+    Bool(bool),
+    Char(u16),
+    I8(i8),
+    U8(u8),
+    I16(i16),
+    U16(u16),
+    I32(i32),
+    U32(u32),
+    I64(i64),
+    U64(u64),
+    F32(f32),
+    F64(f64),
+    String(Option<String>),
+    /// `typeof`, index into `metadata_registration.types`
+    Type(Option<usize>),
+    /// The underlying value and the enum's index into `metadata_registration.types`
+    Enum(usize, Box<AttributeValue>),
+    Array(ElementType, Option<Vec<AttributeValue>>),
+}
+
+/// Declared type of array elements, `Object` elements each carry their own type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElementType {
+    Bool,
+    Char,
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    I64,
+    U64,
+    F32,
+    F64,
+    String,
+    Type,
+    Object,
+    Enum(usize),
+}
+
+impl ElementType {
+    fn from_il2cpp(ty: u8) -> ElementType {
+        match ty {
+            0x02 => ElementType::Bool,
+            0x03 => ElementType::Char,
+            0x04 => ElementType::I8,
+            0x05 => ElementType::U8,
+            0x06 => ElementType::I16,
+            0x07 => ElementType::U16,
+            0x08 => ElementType::I32,
+            0x09 => ElementType::U32,
+            0x0A => ElementType::I64,
+            0x0B => ElementType::U64,
+            0x0C => ElementType::F32,
+            0x0D => ElementType::F64,
+            0x0E => ElementType::String,
+            IL2CPP_TYPE_INDEX => ElementType::Type,
+            _ => ElementType::Object,
+        }
+    }
+}
+
+impl CustomAttributes {
+    /// Blobs that fail to decode are skipped with a warning
+    pub fn parse(global_metadata_data: &[u8], metadata: &Metadata) -> Result<CustomAttributes> {
+        let data = global_metadata_data;
+        let version = read_u32(data, 4).context("global-metadata.dat is too short")?;
+        if version < 29 {
+            bail!("Custom attributes of metadata v{version} are not supported");
+        }
+
+        let section = |header_offset: usize| {
+            let offset = read_u32(data, header_offset)? as usize;
+            let size = read_u32(data, header_offset + 4)? as usize;
+            data.get(offset..offset + size)
+        };
+        let (Some(images), Some(attribute_data), Some(ranges)) = (
+            section(IMAGES_HEADER_OFFSET),
+            section(ATTRIBUTE_DATA_HEADER_OFFSET),
+            section(ATTRIBUTE_DATA_RANGE_HEADER_OFFSET),
+        ) else {
+            bail!("global-metadata.dat sections are out of bounds");
+        };
+
+        let ranges = ranges
+            .chunks_exact(ATTRIBUTE_DATA_RANGE_SIZE)
+            .map(|range| (read_u32(range, 0).unwrap(), read_u32(range, 4).unwrap()))
+            .collect::<Vec<_>>();
+        let enum_type = |type_index| enum_underlying_type(metadata, type_index);
+
+        let mut attributes = HashMap::new();
+        for (image_index, image) in images.chunks_exact(IMAGE_DEFINITION_SIZE).enumerate() {
+            let start = read_u32(image, IMAGE_CUSTOM_ATTRIBUTE_START).unwrap() as usize;
+            let count = read_u32(image, IMAGE_CUSTOM_ATTRIBUTE_START + 4).unwrap() as usize;
+
+            for i in start..start + count {
+                let Some(&(token, offset)) = ranges.get(i) else {
+                    bail!("Attribute range {i} of image {image_index} is out of bounds");
+                };
+                // blobs are contiguous, each ends where the next starts
+                let end = ranges
+                    .get(i + 1)
+                    .map_or(attribute_data.len(), |(_, next)| *next as usize);
+
+                let decoded = attribute_data
+                    .get(offset as usize..end)
+                    .context("Attribute data is out of bounds")
+                    .and_then(|blob| read_attributes(blob, &enum_type));
+                match decoded {
+                    Ok(decoded) => {
+                        attributes.insert((image_index, token), decoded);
+                    }
+                    Err(e) => {
+                        warn!(
+                            "Unable to decode attributes of token {token:#X} in image {image_index}: {e:?}"
+                        )
+                    }
+                }
+            }
+        }
+
+        Ok(CustomAttributes { attributes })
+    }
+
+    /// Attributes of the member with `token` in the image at `image_index`
+    pub fn get(&self, image_index: usize, token: u32) -> &[CustomAttribute] {
+        self.attributes
+            .get(&(image_index, token))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+/// Enums are written as their underlying type
+fn enum_underlying_type(metadata: &Metadata, type_index: usize) -> Option<ElementType> {
+    let types = &metadata.runtime_metadata.metadata_registration.types;
+    let TypeData::TypeDefinitionIndex(tdi) = types.get(type_index)?.data else {
+        return None;
+    };
+    let td = &metadata.global_metadata.type_definitions[tdi];
+
+    Some(match types.get(td.element_type_index as usize)?.ty {
+        Il2CppTypeEnum::Boolean => ElementType::Bool,
+        Il2CppTypeEnum::Char => ElementType::Char,
+        Il2CppTypeEnum::I1 => ElementType::I8,
+        Il2CppTypeEnum::U1 => ElementType::U8,
+        Il2CppTypeEnum::I2 => ElementType::I16,
+        Il2CppTypeEnum::U2 => ElementType::U16,
+        Il2CppTypeEnum::I4 => ElementType::I32,
+        Il2CppTypeEnum::U4 => ElementType::U32,
+        Il2CppTypeEnum::I8 => ElementType::I64,
+        Il2CppTypeEnum::U8 => ElementType::U64,
+        _ => return None,
+    })
+}
+
+/// `enum_type` gives the underlying type of enums by type index
+fn read_attributes(
+    blob: &[u8],
+    enum_type: &impl Fn(usize) -> Option<ElementType>,
+) -> Result<Vec<CustomAttribute>> {
+    let mut cursor = Cursor::new(blob);
+
+    let count = cursor.read_compressed_u32::<Endian>()?;
+    let constructors = (0..count)
+        .map(|_| cursor.read_u32::<Endian>().map(MethodIndex::new))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    constructors
+        .into_iter()
+        .map(|constructor| {
+            let argument_count = cursor.read_compressed_u32::<Endian>()?;
+            let field_count = cursor.read_compressed_u32::<Endian>()?;
+            let property_count = cursor.read_compressed_u32::<Endian>()?;
+
+            Ok(CustomAttribute {
+                constructor,
+                arguments: (0..argument_count)
+                    .map(|_| read_tagged_value(&mut cursor, enum_type))
+                    .collect::<Result<_>>()?,
+                fields: (0..field_count)
+                    .map(|_| read_named_argument(&mut cursor, enum_type))
+                    .collect::<Result<_>>()?,
+                properties: (0..property_count)
+                    .map(|_| read_named_argument(&mut cursor, enum_type))
+                    .collect::<Result<_>>()?,
+            })
+        })
+        .collect()
+}
+
+fn read_named_argument(
+    cursor: &mut Cursor<&[u8]>,
+    enum_type: &impl Fn(usize) -> Option<ElementType>,
+) -> Result<NamedArgument> {
+    let value = read_tagged_value(cursor, enum_type)?;
+
+    // members of base types are negative and followed by their declaring type
+    let index = cursor.read_compressed_i32::<Endian>()?;
+    let (declaring_type, index) = match index {
+        0.. => (None, index as u32),
+        _ => (
+            Some(TypeDefinitionIndex::new(
+                cursor.read_compressed_u32::<Endian>()?,
+            )),
+            -(index + 1) as u32,
+        ),
+    };
+
+    Ok(NamedArgument {
+        declaring_type,
+        index,
+        value,
+    })
+}
+
+/// Enums are followed by their type index
+fn read_element_type(cursor: &mut Cursor<&[u8]>, ty: u8) -> Result<ElementType> {
+    Ok(match ty {
+        IL2CPP_TYPE_ENUM => ElementType::Enum(cursor.read_compressed_i32::<Endian>()? as usize),
+        ty => ElementType::from_il2cpp(ty),
+    })
+}
+
+fn read_tagged_value(
+    cursor: &mut Cursor<&[u8]>,
+    enum_type: &impl Fn(usize) -> Option<ElementType>,
+) -> Result<AttributeValue> {
+    let ty = match cursor.read_u8()? {
+        IL2CPP_TYPE_SZARRAY => return read_array(cursor, enum_type),
+        ty => read_element_type(cursor, ty)?,
+    };
+
+    read_value(cursor, ty, enum_type)
+}
+
+fn read_array(
+    cursor: &mut Cursor<&[u8]>,
+    enum_type: &impl Fn(usize) -> Option<ElementType>,
+) -> Result<AttributeValue> {
+    let len = cursor.read_compressed_i32::<Endian>()?;
+    if len == -1 {
+        return Ok(AttributeValue::Array(ElementType::Object, None));
+    }
+
+    let ty = cursor.read_u8()?;
+    let element = read_element_type(cursor, ty)?;
+    let different_types = cursor.read_u8()? == 1;
+    let values = (0..len)
+        .map(|_| match different_types {
+            true => read_tagged_value(cursor, enum_type),
+            false => read_value(cursor, element, enum_type),
+        })
+        .collect::<Result<_>>()?;
+
+    Ok(AttributeValue::Array(element, Some(values)))
+}
+
+fn read_value(
+    cursor: &mut Cursor<&[u8]>,
+    ty: ElementType,
+    enum_type: &impl Fn(usize) -> Option<ElementType>,
+) -> Result<AttributeValue> {
+    Ok(match ty {
+        ElementType::Bool => AttributeValue::Bool(cursor.read_u8()? != 0),
+        ElementType::Char => AttributeValue::Char(cursor.read_u16::<Endian>()?),
+        ElementType::I8 => AttributeValue::I8(cursor.read_i8()?),
+        ElementType::U8 => AttributeValue::U8(cursor.read_u8()?),
+        ElementType::I16 => AttributeValue::I16(cursor.read_i16::<Endian>()?),
+        ElementType::U16 => AttributeValue::U16(cursor.read_u16::<Endian>()?),
+        ElementType::I32 => AttributeValue::I32(cursor.read_compressed_i32::<Endian>()?),
+        ElementType::U32 => AttributeValue::U32(cursor.read_compressed_u32::<Endian>()?),
+        ElementType::I64 => AttributeValue::I64(cursor.read_i64::<Endian>()?),
+        ElementType::U64 => AttributeValue::U64(cursor.read_u64::<Endian>()?),
+        ElementType::F32 => AttributeValue::F32(cursor.read_f32::<Endian>()?),
+        ElementType::F64 => AttributeValue::F64(cursor.read_f64::<Endian>()?),
+        ElementType::String => {
+            let len = cursor.read_compressed_i32::<Endian>()?;
+            if len == -1 {
+                return Ok(AttributeValue::String(None));
+            }
+
+            let start = cursor.position() as usize;
+            let bytes = usize::try_from(len)
+                .ok()
+                .and_then(|len| cursor.get_ref().get(start..start + len))
+                .context("String is out of bounds")?;
+            cursor.set_position((start + bytes.len()) as u64);
+            AttributeValue::String(Some(String::from_utf8_lossy(bytes).into_owned()))
+        }
+        ElementType::Type => {
+            let type_index = cursor.read_compressed_i32::<Endian>()?;
+            AttributeValue::Type((type_index != -1).then_some(type_index as usize))
+        }
+        ElementType::Object => AttributeValue::Null,
+        ElementType::Enum(type_index) => {
+            let underlying = enum_type(type_index)
+                .with_context(|| format!("Type {type_index} is not an enum"))?;
+            let value = read_value(cursor, underlying, enum_type)?;
+            AttributeValue::Enum(type_index, Box::new(value))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Type 3 is an enum of `int`
+    fn read(blob: &[&[u8]]) -> Vec<CustomAttribute> {
+        let enum_type = |type_index| (type_index == 3).then_some(ElementType::I32);
+        read_attributes(&blob.concat(), &enum_type).unwrap()
+    }
+
+    /// One attribute with constructor 0 and 5 arguments
+    const ARGUMENTS_ONLY: [u8; 8] = [0x01, 0x00, 0x00, 0x00, 0x00, 0x05, 0x00, 0x00];
+
+    #[test]
+    fn reads_arguments_and_named_members() {
+        let attributes = read(&[
+            // constructors 5 and 7
+            &[0x02, 0x05, 0x00, 0x00, 0x00, 0x07, 0x00, 0x00, 0x00],
+            // "hi" and -3, ints are compressed
+            &[0x02, 0x00, 0x00],
+            &[0x0E, 0x04, b'h', b'i'],
+            &[0x08, 0x05],
+            // field 0 of base type 9 set to true, property 1 to enum value 2
+            &[0x00, 0x01, 0x01],
+            &[0x02, 0x01, 0x01, 0x09],
+            &[0x55, 0x06, 0x04, 0x02],
+        ]);
+
+        assert_eq!(
+            attributes,
+            [
+                CustomAttribute {
+                    constructor: MethodIndex::new(5),
+                    arguments: vec![
+                        AttributeValue::String(Some("hi".to_string())),
+                        AttributeValue::I32(-3),
+                    ],
+                    fields: vec![],
+                    properties: vec![],
+                },
+                CustomAttribute {
+                    constructor: MethodIndex::new(7),
+                    arguments: vec![],
+                    fields: vec![NamedArgument {
+                        declaring_type: Some(TypeDefinitionIndex::new(9)),
+                        index: 0,
+                        value: AttributeValue::Bool(true),
+                    }],
+                    properties: vec![NamedArgument {
+                        declaring_type: None,
+                        index: 1,
+                        value: AttributeValue::Enum(3, Box::new(AttributeValue::I32(2))),
+                    }],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_arrays_and_nulls() {
+        let attributes = read(&[
+            &ARGUMENTS_ONLY,
+            // uint[] { 1, 200 }
+            &[0x1D, 0x04, 0x09, 0x00, 0x01, 0x80, 0xC8],
+            // object[] { "a", typeof(7) }
+            &[0x1D, 0x04, 0x1C, 0x01, 0x0E, 0x02, b'a', 0xFF, 0x0E],
+            // null array, string and type
+            &[0x1D, 0x01],
+            &[0x0E, 0x01],
+            &[0xFF, 0x01],
+        ]);
+
+        assert_eq!(
+            attributes[0].arguments,
+            [
+                AttributeValue::Array(
+                    ElementType::U32,
+                    Some(vec![AttributeValue::U32(1), AttributeValue::U32(200)])
+                ),
+                AttributeValue::Array(
+                    ElementType::Object,
+                    Some(vec![
+                        AttributeValue::String(Some("a".to_string())),
+                        AttributeValue::Type(Some(7)),
+                    ])
+                ),
+                AttributeValue::Array(ElementType::Object, None),
+                AttributeValue::String(None),
+                AttributeValue::Type(None),
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_blobs_are_errors() {
+        let enum_type = |_| None;
+        let string_past_end = [&ARGUMENTS_ONLY[..], &[0x0E, 0x08, b'a']].concat();
+        let enum_of_other_type = [&ARGUMENTS_ONLY[..], &[0x55, 0x02, 0x00]].concat();
+
+        assert!(read_attributes(&string_past_end, &enum_type).is_err());
+        assert!(read_attributes(&enum_of_other_type, &enum_type).is_err());
+    }
+}
@@ -0,0 +1,383 @@
+use std::collections::BTreeMap;
+
+use color_eyre::eyre::{Context, Result, eyre};
+use gimli::UnwindSection;
+use log::{debug, warn};
+use object::{
+    Endianness, Object, ObjectSection, ObjectSegment, ObjectSymbol, SymbolKind,
+    elf::PT_GNU_EH_FRAME,
+    read::elf::{ElfFile, FileHeader, ProgramHeader},
+};
+
+/// Function start -> function size, as found in the shared object itself
+/// rather than guessed from the method pointer tables
+#[derive(Debug, Default, Clone)]
+pub struct FunctionExtents {
+    /// from `.symtab` and `.dynsym`
+    pub symbols: BTreeMap<u64, u64>,
+    /// from the FDEs in `.eh_frame`
+    pub unwind: BTreeMap<u64, u64>,
+
+    /// Bounds of the `.text` section, if present
+    pub text_range: Option<(u64, u64)>,
+}
+
+impl FunctionExtents {
+    pub fn parse(elf_data: &[u8]) -> Result<FunctionExtents> {
+        let file = object::File::parse(elf_data).context("Unable to parse ELF file")?;
+
+        let mut extents = FunctionExtents {
+            text_range: file
+                .section_by_name(".text")
+                .map(|s| (s.address(), s.address() + s.size())),
+            ..Default::default()
+        };
+
+        // stripped binaries usually still have .dynsym for exported functions
+        for symbol in file.symbols().chain(file.dynamic_symbols()) {
+            if symbol.kind() != SymbolKind::Text || !symbol.is_definition() || symbol.size() == 0 {
+                continue;
+            }
+
+            extents.symbols.insert(symbol.address(), symbol.size());
+        }
+
+        match extents.parse_unwind(&file) {
+            Ok(()) => {}
+            Err(e) => warn!("Unable to parse unwind info: {e:?}"),
+        }
+
+        debug!(
+            "Found {} function symbols and {} FDEs",
+            extents.symbols.len(),
+            extents.unwind.len()
+        );
+
+        Ok(extents)
+    }
+
+    /// Exact size of the function starting at `addr`, if known
+    pub fn get_size(&self, addr: u64) -> Option<(u64, MethodSizeSource)> {
+        if let Some(size) = self.symbols.get(&addr) {
+            return Some((*size, MethodSizeSource::Symbol));
+        }
+        if let Some(size) = self.unwind.get(&addr) {
+            return Some((*size, MethodSizeSource::UnwindInfo));
+        }
+
+        None
+    }
+
+    /// All function starts known from the binary
+    pub fn function_starts(&self) -> impl Iterator<Item = u64> + '_ {
+        self.symbols.keys().chain(self.unwind.keys()).copied()
+    }
+
+    fn parse_unwind(&mut self, file: &object::File) -> Result<()> {
+        let text_addr = self.text_range.map(|(start, _)| start).unwrap_or_default();
+        let address_size = if file.is_64() { 8 } else { 4 };
+
+        // prefer the section, fall back to the pointer in .eh_frame_hdr
+        // which is always reachable through PT_GNU_EH_FRAME even with stripped section headers
+        let (eh_frame_addr, eh_frame_data) = match file.section_by_name(".eh_frame") {
+            Some(section) => (section.address(), section.data()?),
+            None => {
+                let Some((hdr_addr, hdr_data)) = eh_frame_hdr(file)? else {
+                    return Ok(());
+                };
+
+                let bases = gimli::BaseAddresses::default()
+                    .set_eh_frame_hdr(hdr_addr)
+                    .set_text(text_addr);
+                let hdr = gimli::EhFrameHdr::new(hdr_data, gimli::LittleEndian)
+                    .parse(&bases, address_size)?;
+
+                let addr = match hdr.eh_frame_ptr() {
+                    gimli::Pointer::Direct(addr) => Some(addr),
+                    gimli::Pointer::Indirect(ptr_addr) => {
+                        read_mapped(file, ptr_addr, address_size as u64).map(|ptr| {
+                            ptr.iter()
+                                .rev()
+                                .fold(0, |addr, byte| (addr << 8) | *byte as u64)
+                        })
+                    }
+                };
+                let data = addr.and_then(|addr| Some((addr, read_mapped_to_end(file, addr)?)));
+                let Some((addr, data)) = data else {
+                    warn!("Unable to find .eh_frame data from .eh_frame_hdr at 0x{hdr_addr:x}");
+                    return Ok(());
+                };
+
+                (addr, data)
+            }
+        };
+
+        let mut eh_frame = gimli::EhFrame::new(eh_frame_data, gimli::LittleEndian);
+        eh_frame.set_address_size(address_size);
+        let bases = gimli::BaseAddresses::default()
+            .set_eh_frame(eh_frame_addr)
+            .set_text(text_addr);
+
+        let mut entries = eh_frame.entries(&bases);
+        while let Some(entry) = entries.next()? {
+            let gimli::CieOrFde::Fde(partial) = entry else {
+                continue;
+            };
+
+            let fde = match partial.parse(|_, bases, o| eh_frame.cie_from_offset(bases, o)) {
+                Ok(fde) => fde,
+                Err(e) => {
+                    debug!("Skipping malformed FDE: {e:?}");
+                    continue;
+                }
+            };
+
+            if fde.len() == 0 {
+                continue;
+            }
+
+            self.unwind.insert(fde.initial_address(), fde.len());
+        }
+
+        Ok(())
+    }
+}
+
+/// `.eh_frame_hdr`, located through its program header instead of the section headers
+fn eh_frame_hdr<'data>(file: &object::File<'data>) -> Result<Option<(u64, &'data [u8])>> {
+    match file {
+        object::File::Elf32(elf) => elf_eh_frame_hdr(elf),
+        object::File::Elf64(elf) => elf_eh_frame_hdr(elf),
+        _ => Ok(None),
+    }
+}
+
+fn elf_eh_frame_hdr<'data, Elf: FileHeader<Endian = Endianness>>(
+    elf: &ElfFile<'data, Elf>,
+) -> Result<Option<(u64, &'data [u8])>> {
+    let endian = elf.endian();
+
+    let Some(segment) = elf
+        .elf_program_headers()
+        .iter()
+        .find(|ph| ph.p_type(endian) == PT_GNU_EH_FRAME)
+    else {
+        return Ok(None);
+    };
+    let data = segment
+        .data(endian, elf.data())
+        .map_err(|()| eyre!("PT_GNU_EH_FRAME is out of bounds"))?;
+
+    Ok(Some((segment.p_vaddr(endian).into(), data)))
+}
+
+/// `len` bytes at the virtual address `addr`
+fn read_mapped<'data>(file: &object::File<'data>, addr: u64, len: u64) -> Option<&'data [u8]> {
+    file.segments()
+        .find(|s| s.address() <= addr && addr < s.address() + s.size())?
+        .data_range(addr, len)
+        .ok()
+        .flatten()
+}
+
+/// Everything from the virtual address `addr` to the end of its segment
+fn read_mapped_to_end<'data>(file: &object::File<'data>, addr: u64) -> Option<&'data [u8]> {
+    let segment = file
+        .segments()
+        .find(|s| s.address() <= addr && addr < s.address() + s.size())?;
+
+    segment
+        .data_range(addr, segment.address() + segment.size() - addr)
+        .ok()
+        .flatten()
+}
+
+/// Where a method's size was taken from, most accurate first
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(
+    feature = "json",
+    derive(serde::Serialize, serde::Deserialize, schemars::JsonSchema)
+)]
+pub enum MethodSizeSource {
+    /// ELF symbol table entry
+    Symbol,
+    /// `.eh_frame` FDE address range
+    UnwindInfo,
+    /// Distance to the next known function start
+    NextFunction,
+    /// No address or no following function
+    #[default]
+    Unknown,
+}
+
+#[cfg(test)]
+mod tests {
+    use gimli::{
+        Encoding, Format, LittleEndian, Register,
+        write::{
+            Address, CommonInformationEntry, EhFrame, EndianVec, FrameDescriptionEntry, FrameTable,
+        },
+    };
+    use object::elf::SHF_EXECINSTR;
+
+    use super::*;
+    use crate::data::test_elf::{TestElf, TestSection};
+
+    const TEXT: u64 = 0x1000;
+    const EH_FRAME_HDR: u64 = 0x2000;
+    const EH_FRAME: u64 = 0x2010;
+
+    const TEXT_DATA: [u8; 0x100] = [0; 0x100];
+
+    fn text() -> TestSection<'static> {
+        TestSection {
+            name: ".text",
+            addr: TEXT,
+            flags: SHF_EXECINSTR,
+            data: &TEXT_DATA,
+        }
+    }
+
+    /// `.eh_frame` with an FDE for each `(start, size)`
+    fn eh_frame(address_size: u8, fdes: &[(u64, u32)]) -> Vec<u8> {
+        let encoding = Encoding {
+            format: Format::Dwarf32,
+            version: 1,
+            address_size,
+        };
+
+        let mut frames = FrameTable::default();
+        let cie = frames.add_cie(CommonInformationEntry::new(encoding, 4, -8, Register(30)));
+        for (start, size) in fdes {
+            frames.add_fde(
+                cie,
+                FrameDescriptionEntry::new(Address::Constant(*start), *size),
+            );
+        }
+
+        let mut eh_frame = EhFrame(EndianVec::new(LittleEndian));
+        frames.write_eh_frame(&mut eh_frame).unwrap();
+        eh_frame.0.into_vec()
+    }
+
+    /// `.eh_frame_hdr` pointing at `EH_FRAME`, without a search table
+    fn eh_frame_hdr() -> Vec<u8> {
+        let eh_frame_ptr = (EH_FRAME as i64 - (EH_FRAME_HDR as i64 + 4)) as i32;
+
+        [
+            1,
+            (gimli::DW_EH_PE_pcrel.0 | gimli::DW_EH_PE_sdata4.0),
+            gimli::DW_EH_PE_omit.0,
+            gimli::DW_EH_PE_omit.0,
+        ]
+        .into_iter()
+        .chain(eh_frame_ptr.to_le_bytes())
+        .collect()
+    }
+
+    #[test]
+    fn reads_symbol_sizes() {
+        let elf = TestElf {
+            sections: vec![text()],
+            symbols: vec![
+                ("Foo", TEXT, 0x20),
+                ("Bar", TEXT + 0x20, 0x40),
+                // labels and stripped sizes
+                ("Baz", TEXT + 0x60, 0),
+            ],
+            ..Default::default()
+        }
+        .write();
+
+        let extents = FunctionExtents::parse(&elf).unwrap();
+
+        assert_eq!(extents.text_range, Some((TEXT, TEXT + 0x100)));
+        assert_eq!(
+            extents.symbols,
+            BTreeMap::from([(TEXT, 0x20), (TEXT + 0x20, 0x40)])
+        );
+        assert_eq!(
+            extents.get_size(TEXT + 0x20),
+            Some((0x40, MethodSizeSource::Symbol))
+        );
+        assert_eq!(extents.get_size(TEXT + 0x60), None);
+    }
+
+    #[test]
+    fn reads_fde_sizes() {
+        let eh_frame = eh_frame(8, &[(TEXT, 0x20), (TEXT + 0x80, 0x10)]);
+        let elf = TestElf {
+            sections: vec![
+                text(),
+                TestSection {
+                    name: ".eh_frame",
+                    addr: EH_FRAME,
+                    flags: 0,
+                    data: &eh_frame,
+                },
+            ],
+            // symbols take precedence
+            symbols: vec![("Foo", TEXT, 0x24)],
+            ..Default::default()
+        }
+        .write();
+
+        let extents = FunctionExtents::parse(&elf).unwrap();
+
+        assert_eq!(
+            extents.unwind,
+            BTreeMap::from([(TEXT, 0x20), (TEXT + 0x80, 0x10)])
+        );
+        assert_eq!(
+            extents.get_size(TEXT),
+            Some((0x24, MethodSizeSource::Symbol))
+        );
+        assert_eq!(
+            extents.get_size(TEXT + 0x80),
+            Some((0x10, MethodSizeSource::UnwindInfo))
+        );
+    }
+
+    #[test]
+    fn finds_eh_frame_through_program_header() {
+        let eh_frame_hdr = eh_frame_hdr();
+
+        for elf32 in [false, true] {
+            let eh_frame = eh_frame(
+                if elf32 { 4 } else { 8 },
+                &[(TEXT, 0x20), (TEXT + 0x80, 0x10)],
+            );
+            let elf = TestElf {
+                sections: vec![
+                    text(),
+                    TestSection {
+                        name: ".eh_frame_hdr",
+                        addr: EH_FRAME_HDR,
+                        flags: 0,
+                        data: &eh_frame_hdr,
+                    },
+                    TestSection {
+                        name: ".eh_frame",
+                        addr: EH_FRAME,
+                        flags: 0,
+                        data: &eh_frame,
+                    },
+                ],
+                eh_frame_hdr: Some(".eh_frame_hdr"),
+                strip_section_headers: true,
+                elf32,
+                ..Default::default()
+            }
+            .write();
+
+            let extents = FunctionExtents::parse(&elf).unwrap();
+
+            assert_eq!(extents.text_range, None);
+            assert_eq!(
+                extents.unwind,
+                BTreeMap::from([(TEXT, 0x20), (TEXT + 0x80, 0x10)]),
+                "elf32: {elf32}"
+            );
+        }
+    }
+}
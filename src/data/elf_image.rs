@@ -0,0 +1,61 @@
+use color_eyre::eyre::{Context, Result};
+use object::{Object, ObjectSection, ObjectSegment};
+
+/// Virtual address view over the file backed parts of a shared object
+pub struct ElfImage<'a> {
+    /// (virtual address, file data)
+    segments: Vec<(u64, &'a [u8])>,
+
+    /// (virtual address, data) of `.text`
+    pub text: Option<(u64, &'a [u8])>,
+}
+
+impl<'a> ElfImage<'a> {
+    pub fn parse(elf_data: &'a [u8]) -> Result<ElfImage<'a>> {
+        let file = object::File::parse(elf_data).context("Unable to parse ELF file")?;
+
+        let segments = file
+            .segments()
+            .filter_map(|s| Some((s.address(), s.data().ok()?)))
+            .collect();
+
+        let text = file
+            .section_by_name(".text")
+            .and_then(|s| Some((s.address(), s.data().ok()?)));
+
+        Ok(ElfImage { segments, text })
+    }
+
+    #[cfg(test)]
+    pub fn from_segments(segments: Vec<(u64, &'a [u8])>) -> ElfImage<'a> {
+        ElfImage {
+            segments,
+            text: None,
+        }
+    }
+
+    /// Reads `len` bytes at virtual address `addr`
+    /// Returns None if the range is not entirely backed by the file (e.g `.bss`)
+    pub fn read(&self, addr: u64, len: usize) -> Option<&'a [u8]> {
+        self.segments.iter().find_map(|(start, data)| {
+            let offset = addr.checked_sub(*start)? as usize;
+            data.get(offset..offset.checked_add(len)?)
+        })
+    }
+
+    pub fn read_u64(&self, addr: u64) -> Option<u64> {
+        let bytes = self.read(addr, 8)?;
+        Some(u64::from_le_bytes(bytes.try_into().ok()?))
+    }
+
+    /// Reads the NUL terminated UTF-8 string at `addr`, of at most `max_len` bytes
+    pub fn read_c_str(&self, addr: u64, max_len: usize) -> Option<&'a str> {
+        let bytes = self.segments.iter().find_map(|(start, data)| {
+            let offset = addr.checked_sub(*start)? as usize;
+            data.get(offset..)
+        })?;
+        let len = bytes.iter().take(max_len).position(|b| *b == 0)?;
+
+        std::str::from_utf8(&bytes[..len]).ok()
+    }
+}
@@ -0,0 +1,223 @@
+//! Native functions of internal calls, read from the registration tables of libunity.so
+
+use std::collections::{HashMap, HashSet};
+
+use color_eyre::eyre::{Context, ContextCompat, Result};
+use object::{
+    Object, ObjectSection, ObjectSymbol, ObjectSymbolTable, RelocationFlags, RelocationTarget,
+    SectionKind,
+    elf::{R_AARCH64_ABS64, R_AARCH64_RELATIVE},
+};
+
+use super::elf_image::ElfImage;
+
+/// Longest registration name read
+const MAX_NAME_LEN: usize = 1024;
+/// Slots searched after an array of names for the start of its functions
+const MAX_FUNCTIONS_GAP: usize = 4;
+
+/// Finds the function registered for each of `names`.
+///
+/// Bindings register their icalls from a table of `{name, function}` pairs,
+/// or from a NULL terminated array of names followed by the array of their functions
+pub fn find_icalls(libunity_data: &[u8], names: &HashSet<String>) -> Result<HashMap<String, u64>> {
+    let file = object::File::parse(libunity_data).context("Unable to parse libunity.so")?;
+    let image = ElfImage::parse(libunity_data)?;
+    let (text_start, text) = image.text.context("libunity.so has no .text section")?;
+    let text_range = text_start..text_start + text.len() as u64;
+
+    // RELA relocated pointers are 0 in the file, RELR ones already hold their target
+    let dynamic_symbols = file.dynamic_symbol_table();
+    let relocated: HashMap<u64, u64> = file
+        .dynamic_relocations()
+        .into_iter()
+        .flatten()
+        .filter_map(|(offset, reloc)| {
+            let RelocationFlags::Elf { r_type } = reloc.flags() else {
+                return None;
+            };
+            let base = match (r_type, reloc.target()) {
+                (R_AARCH64_RELATIVE, _) => 0x0,
+                // exported functions
+                (R_AARCH64_ABS64, RelocationTarget::Symbol(index)) => dynamic_symbols
+                    .as_ref()?
+                    .symbol_by_index(index)
+                    .ok()?
+                    .address(),
+                _ => return None,
+            };
+
+            Some((offset, base.wrapping_add(reloc.addend() as u64)))
+        })
+        .collect();
+    let pointer = |addr: u64| {
+        relocated
+            .get(&addr)
+            .copied()
+            .or_else(|| image.read_u64(addr))
+            .unwrap_or_default()
+    };
+    let name_at = |addr: u64| {
+        let target = pointer(addr);
+        if target == 0x0 || text_range.contains(&target) {
+            return None;
+        }
+
+        image
+            .read_c_str(target, MAX_NAME_LEN)
+            .filter(|name| names.contains(*name))
+    };
+
+    let mut icalls = HashMap::new();
+    for section in file.sections().filter(|s| {
+        matches!(
+            s.kind(),
+            SectionKind::Data | SectionKind::ReadOnlyDataWithRel
+        )
+    }) {
+        let end = section.address() + section.size();
+        let mut addr = section.address().next_multiple_of(8);
+
+        while addr + 8 <= end {
+            let run = (addr..end - 7)
+                .step_by(8)
+                .map_while(name_at)
+                .collect::<Vec<_>>();
+            if run.is_empty() {
+                addr += 8;
+                continue;
+            }
+
+            // a pair table is a run of one name directly followed by its function,
+            // name arrays are followed by their NULL terminator and alignment padding
+            let run_end = addr + run.len() as u64 * 8;
+            let Some(functions_start) = (run_end..end - 7)
+                .step_by(8)
+                .take(MAX_FUNCTIONS_GAP)
+                .find(|a| pointer(*a) != 0x0)
+            else {
+                addr = run_end;
+                continue;
+            };
+            let functions = (0..run.len() as u64)
+                .map(|i| Some(pointer(functions_start + i * 8)).filter(|f| text_range.contains(f)))
+                .collect::<Option<Vec<_>>>();
+
+            if let Some(functions) = functions {
+                for (name, function) in run.into_iter().zip(functions) {
+                    icalls.entry(name.to_string()).or_insert(function);
+                }
+            }
+            addr = run_end;
+        }
+    }
+
+    Ok(icalls)
+}
+
+#[cfg(test)]
+mod tests {
+    use object::elf::{SHF_EXECINSTR, SHF_WRITE};
+
+    use super::*;
+    use crate::data::test_elf::{TestElf, TestSection};
+
+    const TEXT: u64 = 0x1000;
+    const STRINGS: u64 = 0x2000;
+    const DATA: u64 = 0x3000;
+
+    const NAMES: [&str; 3] = [
+        "UnityEngine.Transform::get_position_Injected",
+        "UnityEngine.Transform::set_position_Injected",
+        "UnityEngine.Object::GetName",
+    ];
+
+    /// Address of `NAMES[i]` in `.rodata`
+    fn name(i: usize) -> u64 {
+        STRINGS + NAMES[..i].iter().map(|n| n.len() as u64 + 1).sum::<u64>()
+    }
+
+    fn function(i: usize) -> u64 {
+        TEXT + i as u64 * 0x10
+    }
+
+    /// Finds `NAMES` in a libunity.so with `pointers` in `.data`
+    fn find(pointers: &[u64]) -> HashMap<String, u64> {
+        let text = [0; 0x100];
+        let strings = NAMES
+            .iter()
+            .flat_map(|n| n.bytes().chain([0]))
+            .collect::<Vec<_>>();
+        let data = pointers
+            .iter()
+            .flat_map(|p| p.to_le_bytes())
+            .collect::<Vec<_>>();
+
+        let elf = TestElf {
+            sections: vec![
+                TestSection {
+                    name: ".text",
+                    addr: TEXT,
+                    flags: SHF_EXECINSTR,
+                    data: &text,
+                },
+                TestSection {
+                    name: ".rodata",
+                    addr: STRINGS,
+                    flags: 0,
+                    data: &strings,
+                },
+                TestSection {
+                    name: ".data",
+                    addr: DATA,
+                    flags: SHF_WRITE,
+                    data: &data,
+                },
+            ],
+            ..Default::default()
+        }
+        .write();
+
+        let names = NAMES.iter().map(|n| n.to_string()).collect();
+        find_icalls(&elf, &names).unwrap()
+    }
+
+    fn icalls(found: &[usize]) -> HashMap<String, u64> {
+        found
+            .iter()
+            .map(|i| (NAMES[*i].to_string(), function(*i)))
+            .collect()
+    }
+
+    #[test]
+    fn finds_pair_tables() {
+        let found = find(&[name(0), function(0), name(1), function(1), 0, 0]);
+
+        assert_eq!(found, icalls(&[0, 1]));
+    }
+
+    #[test]
+    fn finds_name_arrays() {
+        let found = find(&[
+            name(0),
+            name(1),
+            name(2),
+            // NULL terminator and padding
+            0,
+            0,
+            function(0),
+            function(1),
+            function(2),
+        ]);
+
+        assert_eq!(found, icalls(&[0, 1, 2]));
+    }
+
+    #[test]
+    fn skips_names_without_functions() {
+        // a name followed by a pointer to data, then a pair
+        let found = find(&[name(0), DATA, name(2), function(2)]);
+
+        assert_eq!(found, icalls(&[2]));
+    }
+}
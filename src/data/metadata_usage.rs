@@ -0,0 +1,99 @@
+/// A reference from generated code to metadata, resolved lazily by il2cpp at runtime
+///
+/// Every usage is a pointer sized slot in `.data`. Until the method that owns
+/// it runs for the first time the slot holds an encoded token instead of the pointer,
+/// see `il2cpp::utils::Il2CppMetadataUsage`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "json", derive(serde::Serialize, serde::Deserialize))]
+pub enum MetadataUsage {
+    /// Il2CppClass*, index into `metadata_registration.types`
+    TypeInfo(u32),
+    /// Il2CppType*, index into `metadata_registration.types`
+    Il2CppType(u32),
+    /// MethodInfo* of a method definition, `MethodIndex`
+    MethodDef(u32),
+    /// FieldInfo*, index into `global_metadata.field_refs`
+    FieldInfo(u32),
+    /// Il2CppString*, index into the string literal table
+    StringLiteral(u32),
+    /// MethodInfo* of a generic method, index into `metadata_registration.method_specs`
+    MethodRef(u32),
+    /// Pointer to a field's RVA data, index into `global_metadata.field_refs`
+    FieldRva(u32),
+}
+
+impl MetadataUsage {
+    /// Decodes the initial value of a metadata usage slot.
+    /// Returns None for anything that doesn't look like an encoded usage,
+    /// callers should still bounds check the index against the matching table.
+    pub fn decode(slot_value: u64) -> Option<MetadataUsage> {
+        // initialized slots hold pointers which are always aligned
+        if slot_value & 1 == 0 || slot_value > u32::MAX as u64 {
+            return None;
+        }
+
+        let encoded = slot_value as u32;
+        let index = (encoded & 0x1FFF_FFFE) >> 1;
+
+        match encoded >> 29 {
+            1 => Some(MetadataUsage::TypeInfo(index)),
+            2 => Some(MetadataUsage::Il2CppType(index)),
+            3 => Some(MetadataUsage::MethodDef(index)),
+            4 => Some(MetadataUsage::FieldInfo(index)),
+            5 => Some(MetadataUsage::StringLiteral(index)),
+            6 => Some(MetadataUsage::MethodRef(index)),
+            7 => Some(MetadataUsage::FieldRva(index)),
+            _ => None,
+        }
+    }
+
+    pub fn index(&self) -> u32 {
+        match self {
+            MetadataUsage::TypeInfo(i)
+            | MetadataUsage::Il2CppType(i)
+            | MetadataUsage::MethodDef(i)
+            | MetadataUsage::FieldInfo(i)
+            | MetadataUsage::StringLiteral(i)
+            | MetadataUsage::MethodRef(i)
+            | MetadataUsage::FieldRva(i) => *i,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(kind: u32, index: u32) -> u64 {
+        ((kind << 29) | (index << 1) | 1) as u64
+    }
+
+    #[test]
+    fn decodes_every_usage_kind() {
+        let usages = [
+            MetadataUsage::TypeInfo(5),
+            MetadataUsage::Il2CppType(5),
+            MetadataUsage::MethodDef(5),
+            MetadataUsage::FieldInfo(5),
+            MetadataUsage::StringLiteral(5),
+            MetadataUsage::MethodRef(5),
+            MetadataUsage::FieldRva(5),
+        ];
+
+        for (kind, usage) in (1..).zip(usages) {
+            assert_eq!(MetadataUsage::decode(encode(kind, 5)), Some(usage));
+        }
+        assert_eq!(
+            MetadataUsage::decode(encode(3, 0x0FFF_FFFF)).map(|u| u.index()),
+            Some(0x0FFF_FFFF)
+        );
+    }
+
+    #[test]
+    fn rejects_initialized_slots() {
+        // pointers written once the owning method ran
+        assert_eq!(MetadataUsage::decode(0x7F12_3456_7890), None);
+        assert_eq!(MetadataUsage::decode(0x2000_000A), None);
+        assert_eq!(MetadataUsage::decode(encode(0, 5)), None);
+    }
+}
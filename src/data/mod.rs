@@ -1,2 +1,11 @@
+pub mod compiler_generated;
+pub mod custom_attributes;
+pub mod elf_functions;
+pub mod elf_image;
+pub mod icalls;
+pub mod metadata_usage;
 pub mod name_components;
+pub mod pinvoke;
+#[cfg(test)]
+pub mod test_elf;
 pub mod type_resolver;
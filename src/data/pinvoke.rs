@@ -0,0 +1,460 @@
+//! `DllImport` targets of extern methods, read from their il2cpp marshalling wrappers
+
+use std::collections::HashMap;
+
+use brocolib::global_metadata::MethodIndex;
+use color_eyre::eyre::{Context, Result};
+use object::{Object, ObjectSymbol, SymbolKind};
+
+use crate::{
+    generate::{metadata::CordlMetadata, type_extensions::MethodDefintionExtensions},
+    helpers::arm64::{self, Instruction, RegisterTracker},
+};
+
+use super::elf_image::ElfImage;
+
+/// Library of plugins linked into libil2cpp.so, which wrappers call directly
+pub const INTERNAL_LIBRARY: &str = "__Internal";
+
+/// Wrappers only marshal the parameters, anything larger is a bad size estimate
+const MAX_WRAPPER_SIZE: usize = 0x4000;
+const MAX_NAME_LEN: usize = 256;
+/// `il2cpp::utils::StringView`, a pointer and a length
+const STRING_VIEW_SIZE: i64 = 16;
+/// Offsets in `PInvokeArguments`, after the module name and entry point
+const CALLING_CONVENTION_OFFSET: i64 = 2 * STRING_VIEW_SIZE;
+const CHAR_SET_OFFSET: i64 = CALLING_CONVENTION_OFFSET + 4;
+/// After `parameterSize`
+const IS_NO_MANGLE_OFFSET: i64 = CHAR_SET_OFFSET + 8;
+
+/// `Il2CppCallConvention`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallingConvention {
+    Default,
+    C,
+    StdCall,
+    ThisCall,
+    FastCall,
+}
+
+impl CallingConvention {
+    fn from_raw(value: u64) -> Option<Self> {
+        Some(match value {
+            0 => Self::Default,
+            1 => Self::C,
+            2 => Self::StdCall,
+            3 => Self::ThisCall,
+            4 => Self::FastCall,
+            _ => return None,
+        })
+    }
+}
+
+/// `Il2CppCharSet`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharSet {
+    Ansi,
+    Utf8,
+    Unicode,
+    NotSpecified,
+}
+
+impl CharSet {
+    fn from_raw(value: u64) -> Option<Self> {
+        Some(match value {
+            0 => Self::Ansi,
+            1 => Self::Utf8,
+            2 => Self::Unicode,
+            3 => Self::NotSpecified,
+            _ => return None,
+        })
+    }
+}
+
+/// Library and entry point of a P/Invoke method.
+///
+/// il2cpp doesn't keep the ImplMap table, but `il2cpp_codegen_resolve_pinvoke`,
+/// inlined into the wrapper, builds `PInvokeArguments` from both on the stack
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PInvokeImport {
+    pub library: String,
+    /// Unknown for `__Internal` imports of functions that aren't exported
+    pub entry_point: Option<String>,
+    /// Only known when `PInvokeArguments` is built from constants,
+    /// `__Internal` functions are called directly without one
+    pub calling_convention: Option<CallingConvention>,
+    pub char_set: Option<CharSet>,
+    /// `ExactSpelling`, the entry point isn't suffixed with `A` or `W`
+    pub is_no_mangle: Option<bool>,
+}
+
+pub fn find_pinvoke_imports(
+    metadata: &CordlMetadata,
+) -> Result<HashMap<MethodIndex, PInvokeImport>> {
+    let image = ElfImage::parse(metadata.elf_data)?;
+    let exported = exported_functions(metadata.elf_data)?;
+    let gm = &metadata.metadata.global_metadata;
+
+    let imports = metadata
+        .method_calculations
+        .iter()
+        .filter(|(method_index, calc)| {
+            gm.methods[**method_index].is_pinvoke_impl()
+                && calc.addrs != 0x0
+                && calc.estimated_size <= MAX_WRAPPER_SIZE
+        })
+        .filter_map(|(method_index, calc)| {
+            let import = read_wrapper(&image, &exported, calc.addrs, calc.estimated_size)?;
+            Some((*method_index, import))
+        })
+        .collect();
+
+    Ok(imports)
+}
+
+/// Address -> name of the functions exported by plugins linked into libil2cpp.so
+fn exported_functions(elf_data: &[u8]) -> Result<HashMap<u64, String>> {
+    let file = object::File::parse(elf_data).context("Unable to parse ELF file")?;
+
+    Ok(file
+        .dynamic_symbols()
+        .filter(|s| s.kind() == SymbolKind::Text && s.is_definition())
+        .filter_map(|s| Some((s.address(), s.name().ok()?)))
+        .filter(|(_, name)| !is_runtime_symbol(name))
+        .map(|(addr, name)| (addr, name.to_string()))
+        .collect())
+}
+
+/// The il2cpp API and mangled names in the `il2cpp` namespace, e.g `_ZN6il2cpp2vm...`
+fn is_runtime_symbol(name: &str) -> bool {
+    let unmangled = ["_ZNK", "_ZN", "_Z"]
+        .iter()
+        .find_map(|prefix| name.strip_prefix(prefix))
+        .map_or(name, |rest| {
+            rest.trim_start_matches(|c: char| c.is_ascii_digit())
+        });
+
+    unmangled.starts_with("il2cpp")
+}
+
+fn read_wrapper(
+    image: &ElfImage,
+    exported: &HashMap<u64, String>,
+    addr: u64,
+    size: usize,
+) -> Option<PInvokeImport> {
+    let code = image.read(addr, size)?;
+
+    let mut registers = RegisterTracker::default();
+    // (base register, offset, string) of strings stored to memory
+    let mut stored_strings: Vec<(u8, i64, &str)> = vec![];
+    // (base register, offset) -> byte of known values stored to memory
+    let mut stored_bytes: HashMap<(u8, i64), u8> = HashMap::new();
+    let mut calls = vec![];
+    // the resolved function is cached and called through a register
+    let mut resolves = false;
+    for (_pc, _insn, instruction) in arm64::disassemble(code, addr) {
+        let string = |register| {
+            registers
+                .get(register)
+                .and_then(|a| image.read_c_str(a, MAX_NAME_LEN))
+                .filter(|s| is_name(s))
+        };
+
+        let mut store = |rt, rn, offset, size| {
+            if let Some(s) = string(rt) {
+                stored_strings.push((rn, offset, s));
+            }
+
+            // register 31 is the zero register as a source
+            let value = match rt {
+                31 => Some(0),
+                _ => registers.get(rt),
+            };
+            for i in 0..size {
+                match value {
+                    Some(value) => stored_bytes.insert((rn, offset + i), (value >> (i * 8)) as u8),
+                    None => stored_bytes.remove(&(rn, offset + i)),
+                };
+            }
+        };
+
+        match instruction {
+            Instruction::LoadStore {
+                rt,
+                rn,
+                offset,
+                size,
+                load: false,
+            } => store(rt, rn, offset as i64, size as i64),
+            Instruction::LoadStorePair {
+                rt,
+                rt2,
+                rn,
+                offset,
+                size,
+                load: false,
+            } => {
+                store(rt, rn, offset, size as i64);
+                store(rt2, rn, offset + size as i64, size as i64);
+            }
+            Instruction::Bl { target } => calls.push(target),
+            Instruction::Blr { .. } => resolves = true,
+            _ => {}
+        }
+        registers.step(instruction);
+    }
+
+    // `PInvokeArguments` starts with the module name, followed by the entry point
+    let arguments = stored_strings.iter().find_map(|(base, offset, library)| {
+        let (_, _, entry_point) = stored_strings
+            .iter()
+            .find(|(b, o, _)| b == base && *o == offset + STRING_VIEW_SIZE)?;
+        Some((*base, *offset, library, entry_point))
+    });
+    // little endian value of `size` bytes at `offset` in `PInvokeArguments`
+    let read_argument = |base, start, offset, size| {
+        (0..size).try_fold(0u64, |value, i| {
+            let byte = *stored_bytes.get(&(base, start + offset + i))?;
+            Some(value | ((byte as u64) << (i * 8)))
+        })
+    };
+
+    match (arguments, resolves) {
+        (Some((base, start, library, entry_point)), _) => Some(PInvokeImport {
+            library: library.to_string(),
+            entry_point: Some(entry_point.to_string()),
+            calling_convention: read_argument(base, start, CALLING_CONVENTION_OFFSET, 4)
+                .and_then(CallingConvention::from_raw),
+            char_set: read_argument(base, start, CHAR_SET_OFFSET, 4).and_then(CharSet::from_raw),
+            is_no_mangle: read_argument(base, start, IS_NO_MANGLE_OFFSET, 1)
+                .map(|value| value != 0),
+        }),
+        (None, true) => None,
+        // `__Internal` functions are called directly
+        (None, false) => Some(PInvokeImport {
+            library: INTERNAL_LIBRARY.to_string(),
+            entry_point: calls
+                .iter()
+                .find_map(|target| exported.get(target))
+                .cloned(),
+            calling_convention: None,
+            char_set: None,
+            is_no_mangle: None,
+        }),
+    }
+}
+
+/// Library names and symbols, which rules out code and data that happens to decode as text
+fn is_name(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_graphic())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODE: u64 = 0x1000;
+    const STRINGS: u64 = 0x2000;
+    const RESOLVE: u64 = 0x3000;
+
+    fn adrp(rd: u32, pc: u64, target: u64) -> u32 {
+        let pages = (((target & !0xFFF) as i64 - (pc & !0xFFF) as i64) >> 12) as u32;
+        0x9000_0000 | ((pages & 0x3) << 29) | (((pages >> 2) & 0x7_FFFF) << 5) | rd
+    }
+
+    fn add(rd: u32, rn: u32, imm: u64) -> u32 {
+        0x9100_0000 | ((imm as u32 & 0xFFF) << 10) | (rn << 5) | rd
+    }
+
+    fn str(rt: u32, rn: u32, offset: u32) -> u32 {
+        0xF900_0000 | ((offset / 8) << 10) | (rn << 5) | rt
+    }
+
+    fn stp(rt: u32, rt2: u32, rn: u32, offset: u32) -> u32 {
+        0xA900_0000 | (((offset / 8) & 0x7F) << 15) | (rt2 << 10) | (rn << 5) | rt
+    }
+
+    fn strb(rt: u32, rn: u32, offset: u32) -> u32 {
+        0x3900_0000 | (offset << 10) | (rn << 5) | rt
+    }
+
+    fn stp_w(rt: u32, rt2: u32, rn: u32, offset: u32) -> u32 {
+        0x2900_0000 | (((offset / 4) & 0x7F) << 15) | (rt2 << 10) | (rn << 5) | rt
+    }
+
+    fn movz(rd: u32, imm: u32) -> u32 {
+        0xD280_0000 | (imm << 5) | rd
+    }
+
+    fn movk(rd: u32, imm: u32, shift: u32) -> u32 {
+        0xF280_0000 | ((shift / 16) << 21) | (imm << 5) | rd
+    }
+
+    fn bl(pc: u64, target: u64) -> u32 {
+        0x9400_0000 | ((((target as i64 - pc as i64) >> 2) as u32) & 0x03FF_FFFF)
+    }
+
+    const BLR_X0: u32 = 0xD63F_0000;
+    const RET: u32 = 0xD65F_03C0;
+    const SP: u32 = 31;
+
+    fn pc(i: usize) -> u64 {
+        CODE + (i * 4) as u64
+    }
+
+    /// Reads `code` at `CODE`, with `libfoo` and `foo_init` at `STRINGS`
+    fn read(code: &[u32]) -> Option<PInvokeImport> {
+        let mut strings = vec![0; 0x20];
+        strings[..6].copy_from_slice(b"libfoo");
+        strings[0x10..0x18].copy_from_slice(b"foo_init");
+
+        let code = code
+            .iter()
+            .flat_map(|i| i.to_le_bytes())
+            .collect::<Vec<_>>();
+
+        let image = ElfImage::from_segments(vec![(CODE, &code), (STRINGS, &strings)]);
+        let exported = HashMap::from([(RESOLVE, "foo_init".to_string())]);
+        read_wrapper(&image, &exported, CODE, code.len())
+    }
+
+    fn import(library: &str, entry_point: Option<&str>) -> Option<PInvokeImport> {
+        Some(PInvokeImport {
+            library: library.to_string(),
+            entry_point: entry_point.map(str::to_string),
+            calling_convention: None,
+            char_set: None,
+            is_no_mangle: None,
+        })
+    }
+
+    #[test]
+    fn reads_arguments_by_stack_offset() {
+        // the entry point is built first, only its offset tells it apart
+        let wrapper = read(&[
+            adrp(9, pc(0), STRINGS),
+            add(9, 9, 0x10),
+            str(9, SP, 0x28),
+            adrp(8, pc(3), STRINGS),
+            add(8, 8, 0x0),
+            str(8, SP, 0x18),
+            bl(pc(6), RESOLVE),
+            BLR_X0,
+            RET,
+        ]);
+
+        assert_eq!(wrapper, import("libfoo", Some("foo_init")));
+    }
+
+    #[test]
+    fn reads_arguments_stored_in_pairs() {
+        let wrapper = read(&[
+            adrp(8, pc(0), STRINGS),
+            add(8, 8, 0x0),
+            adrp(10, pc(2), STRINGS),
+            add(10, 10, 0x10),
+            // string and length
+            stp(8, 9, SP, 0x10),
+            stp(10, 11, SP, 0x20),
+            bl(pc(6), RESOLVE),
+            BLR_X0,
+            RET,
+        ]);
+
+        assert_eq!(wrapper, import("libfoo", Some("foo_init")));
+    }
+
+    #[test]
+    fn reads_argument_constants() {
+        let wrapper = read(&[
+            adrp(8, pc(0), STRINGS),
+            add(8, 8, 0x0),
+            adrp(10, pc(2), STRINGS),
+            add(10, 10, 0x10),
+            stp(8, 9, SP, 0x10),
+            stp(10, 11, SP, 0x20),
+            // IL2CPP_CALL_C and CHARSET_NOT_SPECIFIED in one register
+            movz(12, 1),
+            movk(12, 3, 32),
+            str(12, SP, 0x30),
+            // wzr
+            strb(31, SP, 0x3C),
+            bl(pc(10), RESOLVE),
+            BLR_X0,
+            RET,
+        ]);
+
+        assert_eq!(
+            wrapper,
+            Some(PInvokeImport {
+                calling_convention: Some(CallingConvention::C),
+                char_set: Some(CharSet::NotSpecified),
+                is_no_mangle: Some(false),
+                ..import("libfoo", Some("foo_init")).unwrap()
+            })
+        );
+    }
+
+    #[test]
+    fn reads_argument_constants_stored_in_pairs() {
+        let wrapper = read(&[
+            adrp(8, pc(0), STRINGS),
+            add(8, 8, 0x0),
+            str(8, SP, 0x10),
+            adrp(8, pc(3), STRINGS),
+            add(8, 8, 0x10),
+            str(8, SP, 0x20),
+            // IL2CPP_CALL_STDCALL and CHARSET_UNICODE
+            movz(12, 2),
+            stp_w(12, 12, SP, 0x30),
+            // isNoMangle is never stored
+            bl(pc(8), RESOLVE),
+            BLR_X0,
+            RET,
+        ]);
+
+        assert_eq!(
+            wrapper,
+            Some(PInvokeImport {
+                calling_convention: Some(CallingConvention::StdCall),
+                char_set: Some(CharSet::Unicode),
+                ..import("libfoo", Some("foo_init")).unwrap()
+            })
+        );
+    }
+
+    #[test]
+    fn unrecovered_resolve_is_none() {
+        // the strings were loaded, not stored as `PInvokeArguments`
+        let wrapper = read(&[
+            adrp(0, pc(0), STRINGS),
+            add(0, 0, 0x0),
+            adrp(1, pc(2), STRINGS),
+            add(1, 1, 0x10),
+            bl(pc(4), RESOLVE),
+            BLR_X0,
+            RET,
+        ]);
+
+        assert_eq!(wrapper, None);
+    }
+
+    #[test]
+    fn internal_calls_are_direct() {
+        let wrapper = read(&[bl(pc(0), 0x4000), bl(pc(1), RESOLVE), RET]);
+
+        assert_eq!(wrapper, import(INTERNAL_LIBRARY, Some("foo_init")));
+    }
+
+    #[test]
+    fn runtime_symbols() {
+        assert!(is_runtime_symbol("il2cpp_string_new"));
+        assert!(is_runtime_symbol(
+            "_ZN6il2cpp2vm13PlatformInvoke7ResolveERK16PInvokeArguments"
+        ));
+        assert!(is_runtime_symbol("_Z33il2cpp_codegen_marshal_allocatem"));
+        assert!(!is_runtime_symbol("foo_init"));
+        assert!(!is_runtime_symbol("_ZN3foo4initEv"));
+    }
+}
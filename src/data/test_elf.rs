@@ -0,0 +1,167 @@
+//! Shared objects built in memory for the tests of the ELF readers
+
+use object::{
+    Endianness, elf,
+    write::elf::{FileHeader, ProgramHeader, SectionHeader, Sym, Writer},
+};
+
+/// Section of a [`TestElf`], at the same file offset and virtual address
+pub struct TestSection<'a> {
+    pub name: &'static str,
+    pub addr: u64,
+    /// `SHF_*`
+    pub flags: u32,
+    pub data: &'a [u8],
+}
+
+/// Builds AArch64 or, with `elf32`, armv7 shared objects,
+/// mapping every section with a single `PT_LOAD`
+#[derive(Default)]
+pub struct TestElf<'a> {
+    /// In ascending order, after the program headers
+    pub sections: Vec<TestSection<'a>>,
+    /// `(name, address, size)` of functions in `.symtab`
+    pub symbols: Vec<(&'static str, u64, u64)>,
+    /// Section mapped by `PT_GNU_EH_FRAME`
+    pub eh_frame_hdr: Option<&'static str>,
+    /// Leaves out the section headers, and with them `.symtab`
+    pub strip_section_headers: bool,
+    pub elf32: bool,
+}
+
+impl TestElf<'_> {
+    pub fn write(&self) -> Vec<u8> {
+        let mut data = vec![];
+        let mut writer = Writer::new(Endianness::Little, !self.elf32, &mut data);
+        let section_headers = !self.strip_section_headers;
+        let eh_frame_hdr = self.eh_frame_hdr.map(|name| {
+            self.sections
+                .iter()
+                .find(|s| s.name == name)
+                .expect("PT_GNU_EH_FRAME of a missing section")
+        });
+
+        writer.reserve_file_header();
+        writer.reserve_program_headers(1 + eh_frame_hdr.is_some() as u32);
+
+        let mut sections = vec![];
+        let mut symbols = vec![];
+        if section_headers {
+            writer.reserve_null_section_index();
+            for section in &self.sections {
+                let name = writer.add_section_name(section.name.as_bytes());
+                sections.push((name, writer.reserve_section_index()));
+            }
+
+            writer.reserve_null_symbol_index();
+            for (name, addr, _) in &self.symbols {
+                let section = self
+                    .sections
+                    .iter()
+                    .position(|s| (s.addr..s.addr + s.data.len() as u64).contains(addr))
+                    .map(|i| sections[i].1);
+                symbols.push((writer.add_string(name.as_bytes()), section));
+                writer.reserve_symbol_index(section);
+            }
+            writer.reserve_symtab_section_index();
+            writer.reserve_strtab_section_index();
+            writer.reserve_shstrtab_section_index();
+        }
+
+        for section in &self.sections {
+            writer.reserve_until(section.addr as usize);
+            writer.reserve(section.data.len(), 1);
+        }
+        let mapped_len = writer.reserved_len() as u64;
+
+        if section_headers {
+            writer.reserve_symtab();
+            writer.reserve_strtab();
+            writer.reserve_shstrtab();
+            writer.reserve_section_headers();
+        }
+
+        writer
+            .write_file_header(&FileHeader {
+                os_abi: elf::ELFOSABI_NONE,
+                abi_version: 0,
+                e_type: elf::ET_DYN,
+                e_machine: if self.elf32 {
+                    elf::EM_ARM
+                } else {
+                    elf::EM_AARCH64
+                },
+                e_entry: 0,
+                e_flags: 0,
+            })
+            .unwrap();
+
+        writer.write_align_program_headers();
+        writer.write_program_header(&ProgramHeader {
+            p_type: elf::PT_LOAD,
+            p_flags: elf::PF_R | elf::PF_X,
+            p_offset: 0,
+            p_vaddr: 0,
+            p_paddr: 0,
+            p_filesz: mapped_len,
+            p_memsz: mapped_len,
+            p_align: 0x1000,
+        });
+        if let Some(section) = eh_frame_hdr {
+            writer.write_program_header(&ProgramHeader {
+                p_type: elf::PT_GNU_EH_FRAME,
+                p_flags: elf::PF_R,
+                p_offset: section.addr,
+                p_vaddr: section.addr,
+                p_paddr: section.addr,
+                p_filesz: section.data.len() as u64,
+                p_memsz: section.data.len() as u64,
+                p_align: 4,
+            });
+        }
+
+        for section in &self.sections {
+            writer.pad_until(section.addr as usize);
+            writer.write(section.data);
+        }
+
+        if section_headers {
+            writer.write_null_symbol();
+            for ((name, section), (_, addr, size)) in symbols.iter().zip(&self.symbols) {
+                writer.write_symbol(&Sym {
+                    name: Some(*name),
+                    section: *section,
+                    st_info: (elf::STB_GLOBAL << 4) | elf::STT_FUNC,
+                    st_other: elf::STV_DEFAULT,
+                    st_shndx: 0,
+                    st_value: *addr,
+                    st_size: *size,
+                });
+            }
+            writer.write_strtab();
+            writer.write_shstrtab();
+
+            writer.write_null_section_header();
+            for ((name, _), section) in sections.iter().zip(&self.sections) {
+                writer.write_section_header(&SectionHeader {
+                    name: Some(*name),
+                    sh_type: elf::SHT_PROGBITS,
+                    sh_flags: (elf::SHF_ALLOC | section.flags) as u64,
+                    sh_addr: section.addr,
+                    sh_offset: section.addr,
+                    sh_size: section.data.len() as u64,
+                    sh_link: 0,
+                    sh_info: 0,
+                    sh_addralign: 1,
+                    sh_entsize: 0,
+                });
+            }
+            // only the null symbol is local
+            writer.write_symtab_section_header(1);
+            writer.write_strtab_section_header();
+            writer.write_shstrtab_section_header();
+        }
+
+        data
+    }
+}
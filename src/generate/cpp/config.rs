@@ -8,14 +8,18 @@ pub static STATIC_CONFIG: LazyLock<CppGenerationConfig> = LazyLock::new(|| CppGe
         "./codegen/include/cordl_internals/cordl_internals.hpp",
     ),
     use_anonymous_namespace: false,
+    pinvoke_bindings: false,
 });
 
+#[derive(Clone)]
 pub struct CppGenerationConfig {
     pub source_path: PathBuf,
     pub header_path: PathBuf,
     pub dst_internals_path: PathBuf,
     pub dst_header_internals_file: PathBuf,
     pub use_anonymous_namespace: bool,
+    /// Add `_native` bindings calling the function of P/Invoke methods directly
+    pub pinvoke_bindings: bool,
 }
 
 impl CppGenerationConfig {
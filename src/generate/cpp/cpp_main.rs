@@ -12,7 +12,7 @@ use crate::{
     INTERNALS_DIR,
     generate::{
         cpp::{
-            config::STATIC_CONFIG,
+            config::{CppGenerationConfig, STATIC_CONFIG},
             cpp_context_collection::CppContextCollection,
             cpp_members::CppMember,
             handlers::{object, unity, value_type},
@@ -26,9 +26,14 @@ pub fn run_cpp(
     cs_collection: TypeContextCollection,
     metadata: &CordlMetadata,
     format: bool,
+    pinvoke_bindings: bool,
 ) -> color_eyre::Result<()> {
+    let config = CppGenerationConfig {
+        pinvoke_bindings,
+        ..STATIC_CONFIG.clone()
+    };
     let mut cpp_context_collection =
-        CppContextCollection::from_cs_collection(cs_collection, metadata, &STATIC_CONFIG);
+        CppContextCollection::from_cs_collection(cs_collection, metadata, &config);
 
     info!("Registering handlers!");
     // il2cpp_internals::register_il2cpp_types(&mut metadata)?;
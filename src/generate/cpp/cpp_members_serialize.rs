@@ -205,7 +205,11 @@ impl Writable for CppMethodDecl {
     // declaration
     fn write(&self, writer: &mut Writer) -> color_eyre::Result<()> {
         if let Some(brief) = &self.brief {
-            writeln!(writer, "/// @brief {brief}")?;
+            let mut lines = brief.lines();
+            if let Some(first) = lines.next() {
+                writeln!(writer, "/// @brief {first}")?;
+            }
+            lines.try_for_each(|line| writeln!(writer, "/// {line}"))?;
         }
 
         // Param default comments
@@ -4,13 +4,17 @@ use std::{
     sync::Arc,
 };
 
-use brocolib::global_metadata::{FieldIndex, MethodIndex, TypeDefinitionIndex};
+use brocolib::{
+    global_metadata::{FieldIndex, MethodIndex, TypeDefinitionIndex},
+    runtime_metadata::Il2CppTypeEnum,
+};
 use color_eyre::eyre::Context;
 use itertools::Itertools;
 
 use std::io::Write;
 
 use crate::{
+    analysis::signatures::cpp_string,
     data::{
         name_components::NameComponents,
         type_resolver::{ResolvedType, ResolvedTypeData, TypeUsage},
@@ -18,7 +22,8 @@ use crate::{
     generate::{
         cpp::cpp_members::{CppMethodSizeStruct, CppStaticAssert},
         cs_members::{
-            CSMethodFlags, CsConstructor, CsField, CsMethod, CsParam, CsProperty, CsValue,
+            CSMethodFlags, CsConstructor, CsField, CsMethod, CsPInvoke, CsParam, CsProperty,
+            CsValue,
         },
         cs_type::CsType,
         cs_type_tag::CsTypeTag,
@@ -929,16 +934,25 @@ impl CppType {
         let is_final = method.method_flags.contains(CSMethodFlags::FINAL);
         let is_static = method.method_flags.contains(CSMethodFlags::STATIC);
 
+        let compiler_generated = method.compiler_generated.iter().map(|member| {
+            format!(
+                "Compiler generated {}: {}",
+                member.kind,
+                member.full_name(metadata.metadata)
+            )
+        });
         let method_decl = CppMethodDecl {
             body: None,
-            brief: format!(
+            brief: std::iter::once(format!(
                 "Method {m_name}, addr 0x{:x}, size 0x{:x}, virtual {}, abstract: {}, final {}",
                 method.method_data.addrs.unwrap_or(u64::MAX),
                 method.method_data.estimated_size.unwrap_or(usize::MAX),
                 is_virtual,
                 is_abstract,
                 is_final
-            )
+            ))
+            .chain(compiler_generated)
+            .join("\n")
             .into(),
             is_const: false,
             is_constexpr: false,
@@ -1207,12 +1221,95 @@ impl CppType {
                 .push(CppMember::MethodImpl(method_impl).into());
         }
 
+        if config.pinvoke_bindings && !is_generic_method_inst {
+            self.create_pinvoke_binding(method, &method_decl);
+        }
+
         if !is_generic_method_inst {
             self.declarations
                 .push(CppMember::MethodDecl(method_decl).into());
         }
     }
 
+    /// `{method}_native`, calling the `DllImport` function found with dlsym
+    /// instead of the managed wrapper. Only for signatures that need no marshalling
+    fn create_pinvoke_binding(&mut self, method: &CsMethod, method_decl: &CppMethodDecl) {
+        let Some(CsPInvoke {
+            library: Some(library),
+            entry_point: Some(entry_point),
+            ..
+        }) = &method.pinvoke
+        else {
+            return;
+        };
+
+        let is_blittable = |ty: &ResolvedType| {
+            matches!(
+                ty.data,
+                ResolvedTypeData::Ptr(_)
+                    | ResolvedTypeData::Primitive(
+                        Il2CppTypeEnum::I1
+                            | Il2CppTypeEnum::U1
+                            | Il2CppTypeEnum::I2
+                            | Il2CppTypeEnum::U2
+                            | Il2CppTypeEnum::I4
+                            | Il2CppTypeEnum::U4
+                            | Il2CppTypeEnum::I8
+                            | Il2CppTypeEnum::U8
+                            | Il2CppTypeEnum::R4
+                            | Il2CppTypeEnum::R8
+                    )
+            )
+        };
+        let returns_void = matches!(
+            method.return_type.data,
+            ResolvedTypeData::Primitive(Il2CppTypeEnum::Void)
+        );
+        if method.instance
+            || method.template.is_some()
+            || !(returns_void || is_blittable(&method.return_type))
+            || !method.parameters.iter().all(|p| is_blittable(&p.il2cpp_ty))
+        {
+            return;
+        }
+
+        let param_types = CppParam::params_types(&method_decl.parameters).join(", ");
+        let param_names = CppParam::params_names(&method_decl.parameters).join(", ");
+        let native_decl = CppMethodDecl {
+            cpp_name: format!("{}_native", method_decl.cpp_name),
+            brief: Some(format!(
+                "Calls {entry_point} in {library} without marshalling"
+            )),
+            body: None,
+            ..method_decl.clone()
+        };
+
+        let body = [
+            format!(
+                "static auto ___native_function = reinterpret_cast<{} (*)({param_types})>(::cordl_internals::ResolvePInvoke(\"{}\", \"{}\"));",
+                method_decl.return_type,
+                cpp_string(library),
+                cpp_string(entry_point)
+            ),
+            format!("return ___native_function({param_names});"),
+        ];
+        let native_impl = CppMethodImpl {
+            body: body
+                .into_iter()
+                .map(|l| -> Arc<dyn WritableDebug> { Arc::new(CppLine::make(l)) })
+                .collect_vec(),
+            brief: None,
+            declaring_cpp_full_name: self.cpp_name_components.remove_pointer().combine_all(),
+            declaring_type_template: self.cpp_template.clone(),
+            ..native_decl.clone().into()
+        };
+
+        self.implementations
+            .push(CppMember::MethodImpl(native_impl).into());
+        self.declarations
+            .push(CppMember::MethodDecl(native_decl).into());
+    }
+
     pub fn classof_cpp_name(&self) -> String {
         format!(
             "::il2cpp_utils::il2cpp_type_check::il2cpp_no_arg_class<{}>::get",
@@ -2,7 +2,12 @@ use bitflags::bitflags;
 use brocolib::global_metadata::MethodIndex;
 use bytes::Bytes;
 
-use crate::data::type_resolver::ResolvedType;
+use crate::data::{
+    compiler_generated::GeneratedMember,
+    elf_functions::MethodSizeSource,
+    pinvoke::{CallingConvention, CharSet},
+    type_resolver::ResolvedType,
+};
 
 use std::hash::Hash;
 
@@ -62,8 +67,19 @@ pub enum CsMember {}
 #[derive(Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct CsMethodData {
     pub estimated_size: Option<usize>,
+    pub size_source: MethodSizeSource,
     pub addrs: Option<u64>,
     pub slot: Option<u16>,
+
+    pub invoker_index: Option<u32>,
+    pub invoker_addrs: Option<u64>,
+    pub adjustor_thunk_addrs: Option<u64>,
+    pub reverse_pinvoke_wrapper_addrs: Option<u64>,
+
+    /// Registration name of internal calls
+    pub icall_name: Option<String>,
+    /// Native function of the internal call, when libunity.so is given
+    pub icall_addrs: Option<u64>,
 }
 
 #[derive(Clone, Debug)]
@@ -194,6 +210,8 @@ bitflags! {
         const FINAL = 0b00100000;
         const SPECIAL_NAME = 0b01000000;
         const UNSAFE = 0b10000000;
+        const INTERNAL_CALL = 0b1_00000000;
+        const PINVOKE = 0b10_00000000;
     }
 }
 
@@ -215,6 +233,25 @@ pub struct CsMethod {
     /// if this method is a generic instantiation, the types used to instantiate it
     /// are stored here
     pub generic_instatiation: Option<Vec<ResolvedType>>,
+    /// `DllImport` of extern methods
+    pub pinvoke: Option<CsPInvoke>,
+    /// Closures, lambdas and state machines holding code written in this method
+    pub compiler_generated: Vec<GeneratedMember>,
+}
+
+/// What il2cpp keeps of a `DllImport`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CsPInvoke {
+    /// Read from the marshalling wrapper, `__Internal` for plugins linked into libil2cpp.so
+    pub library: Option<String>,
+    pub entry_point: Option<String>,
+    pub calling_convention: Option<CallingConvention>,
+    pub char_set: Option<CharSet>,
+    pub is_no_mangle: Option<bool>,
+    /// HRESULT returns aren't turned into exceptions
+    pub preserve_sig: bool,
+    /// Names of the parameters with a `MarshalAs` attribute
+    pub marshaled_parameters: Vec<String>,
 }
 
 // TODO: Generics
@@ -30,8 +30,8 @@ use crate::{
 
 use super::{
     cs_members::{
-        CSMethodFlags, CsConstructor, CsGenericTemplate, CsMethod, CsMethodData, CsParam,
-        CsParamFlags, CsProperty, CsValue,
+        CSMethodFlags, CsConstructor, CsGenericTemplate, CsMethod, CsMethodData, CsPInvoke,
+        CsParam, CsParamFlags, CsProperty, CsValue,
     },
     cs_type_tag::CsTypeTag,
     metadata::CordlMetadata,
@@ -358,6 +358,32 @@ impl CsType {
         }
     }
 
+    fn make_pinvoke(
+        method: &brocolib::global_metadata::Il2CppMethodDefinition,
+        method_index: MethodIndex,
+        metadata: &CordlMetadata,
+    ) -> CsPInvoke {
+        let import = metadata.pinvoke_imports.get(&method_index);
+
+        CsPInvoke {
+            library: import.map(|i| i.library.clone()),
+            entry_point: import.and_then(|i| i.entry_point.clone()),
+            calling_convention: import.and_then(|i| i.calling_convention),
+            char_set: import.and_then(|i| i.char_set),
+            is_no_mangle: import.and_then(|i| i.is_no_mangle),
+            preserve_sig: method.is_preserve_sig(),
+            marshaled_parameters: method
+                .parameters(metadata.metadata)
+                .iter()
+                .filter(|param| {
+                    metadata.metadata_registration.types[param.type_index as usize]
+                        .is_param_marshaled()
+                })
+                .map(|param| param.name(metadata.metadata).to_string())
+                .collect(),
+        }
+    }
+
     fn make_methods(&mut self, type_resolver: &TypeResolver) {
         let metadata = type_resolver.cordl_metadata;
         let tdi = self.self_tag.get_tdi();
@@ -757,6 +783,16 @@ impl CsType {
         if method.is_special_name() {
             flag = flag.union(CSMethodFlags::SPECIAL_NAME);
         }
+        if method.is_internal_call() {
+            flag = flag.union(CSMethodFlags::INTERNAL_CALL);
+        }
+        if method.is_pinvoke_impl() {
+            flag = flag.union(CSMethodFlags::PINVOKE);
+        }
+
+        let icall_name = method
+            .is_internal_call()
+            .then(|| metadata.icall_name(method_index));
 
         // don't emit method size structs for generic methods
         let is_concrete = !method.is_abstract_method();
@@ -765,7 +801,24 @@ impl CsType {
             estimated_size: is_concrete
                 .then(|| method_calc.map(|c| c.estimated_size))
                 .flatten(),
+            size_source: is_concrete
+                .then(|| method_calc.map(|c| c.size_source))
+                .flatten()
+                .unwrap_or_default(),
             slot: (method.slot != u16::MAX).then_some(method.slot),
+            // invokers exist even for abstract methods
+            invoker_index: method_calc.and_then(|c| c.invoker_index),
+            invoker_addrs: method_calc.and_then(|c| c.invoker_addrs),
+            adjustor_thunk_addrs: is_concrete
+                .then(|| method_calc.and_then(|c| c.adjustor_thunk_addrs))
+                .flatten(),
+            reverse_pinvoke_wrapper_addrs: is_concrete
+                .then(|| method_calc.and_then(|c| c.reverse_pinvoke_wrapper_addrs))
+                .flatten(),
+            icall_name: icall_name.clone(),
+            icall_addrs: icall_name
+                .as_deref()
+                .and_then(|name| metadata.icall_address(name)),
         };
 
         let method_decl = CsMethod {
@@ -793,6 +846,14 @@ impl CsType {
             template: template.clone(),
             method_data,
             generic_instatiation: generic_inst,
+            pinvoke: method
+                .is_pinvoke_impl()
+                .then(|| Self::make_pinvoke(method, method_index, metadata)),
+            compiler_generated: metadata
+                .compiler_generated
+                .get(&method_index)
+                .cloned()
+                .unwrap_or_default(),
         };
 
         // if type is a generic
@@ -0,0 +1,742 @@
+//! Split debug file for libil2cpp.so, with DWARF for every method and object layout
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::Path,
+};
+
+use color_eyre::eyre::{Context, Result};
+use gimli::{
+    DW_ATE_boolean, DW_ATE_float, DW_ATE_signed, DW_ATE_unsigned, DwAte, Register,
+    write::{Address, AttributeValue, DwarfUnit, EndianVec, Expression, Sections, UnitEntryId},
+};
+use itertools::Itertools;
+use log::info;
+use object::{
+    Endianness, Object, ObjectSection, SectionFlags, elf,
+    write::elf::{FileHeader, SectionHeader, Sym, Writer},
+};
+
+use crate::generate::{cs_context_collection::TypeContextCollection, metadata::CordlMetadata};
+
+use super::{
+    disasm_main::{DisasmPrototype, DisasmStruct, DisasmStructField, make_struct},
+    disasm_name_resolver::{DisasmNameResolver, IL2CPP_ARRAY_TYPE, IL2CPP_OBJECT_TYPE},
+};
+
+const POINTER_SIZE: u64 = 8;
+
+struct DwarfFunction {
+    addrs: u64,
+    size: u64,
+    /// `Namespace.Type$$Method`
+    name: String,
+    prototype: DisasmPrototype,
+}
+
+/// A section of the original binary kept in the debug file
+struct KeptSection<'a> {
+    name: &'a [u8],
+    address: u64,
+    size: u64,
+    align: u64,
+    flags: u64,
+    /// only notes keep their contents, everything else is `SHT_NOBITS`
+    data: Option<&'a [u8]>,
+}
+
+/// DWARF types by their C name, as produced by `DisasmNameResolver`
+struct DwarfTypes {
+    dwarf: DwarfUnit,
+    types: HashMap<String, UnitEntryId>,
+}
+
+impl DwarfTypes {
+    fn new() -> Self {
+        let encoding = gimli::Encoding {
+            format: gimli::Format::Dwarf32,
+            version: 4,
+            address_size: POINTER_SIZE as u8,
+        };
+
+        DwarfTypes {
+            dwarf: DwarfUnit::new(encoding),
+            types: HashMap::new(),
+        }
+    }
+
+    fn string(&mut self, s: &str) -> AttributeValue {
+        AttributeValue::StringRef(self.dwarf.strings.add(s))
+    }
+
+    /// None for `void`
+    fn type_id(&mut self, name: &str) -> Option<UnitEntryId> {
+        let name = name.trim();
+        if name == "void" {
+            return None;
+        }
+        if let Some(id) = self.types.get(name) {
+            return Some(*id);
+        }
+
+        let root = self.dwarf.unit.root();
+        let id = if let Some(inner) = name.strip_prefix("const ") {
+            let inner = self.type_id(inner);
+            let id = self.dwarf.unit.add(root, gimli::DW_TAG_const_type);
+            if let Some(inner) = inner {
+                self.dwarf
+                    .unit
+                    .get_mut(id)
+                    .set(gimli::DW_AT_type, AttributeValue::UnitRef(inner));
+            }
+            id
+        } else if let Some(inner) = name.strip_suffix('*') {
+            let inner = self.type_id(inner);
+            let id = self.dwarf.unit.add(root, gimli::DW_TAG_pointer_type);
+            let entry = self.dwarf.unit.get_mut(id);
+            entry.set(gimli::DW_AT_byte_size, AttributeValue::Udata(POINTER_SIZE));
+            if let Some(inner) = inner {
+                entry.set(gimli::DW_AT_type, AttributeValue::UnitRef(inner));
+            }
+            id
+        } else if let Some((encoding, size)) = base_type(name) {
+            let name_value = self.string(name);
+            let id = self.dwarf.unit.add(root, gimli::DW_TAG_base_type);
+            let entry = self.dwarf.unit.get_mut(id);
+            entry.set(gimli::DW_AT_name, name_value);
+            entry.set(gimli::DW_AT_encoding, AttributeValue::Encoding(encoding));
+            entry.set(gimli::DW_AT_byte_size, AttributeValue::Udata(size));
+            id
+        } else {
+            // completed by `define_struct` if the layout is known
+            let name_value = self.string(name);
+            let id = self.dwarf.unit.add(root, gimli::DW_TAG_structure_type);
+            let entry = self.dwarf.unit.get_mut(id);
+            entry.set(gimli::DW_AT_name, name_value);
+            entry.set(gimli::DW_AT_declaration, AttributeValue::Flag(true));
+            id
+        };
+
+        self.types.insert(name.to_string(), id);
+        Some(id)
+    }
+
+    fn define_struct(&mut self, s: &DisasmStruct) {
+        let id = self.type_id(&s.name).unwrap();
+
+        let entry = self.dwarf.unit.get_mut(id);
+        entry.delete(gimli::DW_AT_declaration);
+        entry.set(gimli::DW_AT_byte_size, AttributeValue::Udata(s.size as u64));
+
+        // explicit layout fields simply overlap
+        for field in &s.fields {
+            let ty = self.type_id(&field.ty);
+            let name_value = self.string(&field.name);
+
+            let member = self.dwarf.unit.add(id, gimli::DW_TAG_member);
+            let entry = self.dwarf.unit.get_mut(member);
+            entry.set(gimli::DW_AT_name, name_value);
+            entry.set(
+                gimli::DW_AT_data_member_location,
+                AttributeValue::Udata(field.offset as u64),
+            );
+            if let Some(ty) = ty {
+                entry.set(gimli::DW_AT_type, AttributeValue::UnitRef(ty));
+            }
+        }
+    }
+
+    fn add_function(&mut self, function: &DwarfFunction) {
+        let root = self.dwarf.unit.root();
+        let ret = self.type_id(&function.prototype.ret);
+        let name_value = self.string(&function.name);
+
+        let id = self.dwarf.unit.add(root, gimli::DW_TAG_subprogram);
+        let entry = self.dwarf.unit.get_mut(id);
+        entry.set(gimli::DW_AT_name, name_value);
+        entry.set(gimli::DW_AT_external, AttributeValue::Flag(true));
+        entry.set(gimli::DW_AT_prototyped, AttributeValue::Flag(true));
+        entry.set(
+            gimli::DW_AT_low_pc,
+            AttributeValue::Address(Address::Constant(function.addrs)),
+        );
+        entry.set(gimli::DW_AT_high_pc, AttributeValue::Udata(function.size));
+        if let Some(ret) = ret {
+            entry.set(gimli::DW_AT_type, AttributeValue::UnitRef(ret));
+        }
+
+        let registers = argument_registers(&function.prototype.params);
+        for ((ty, name), register) in function.prototype.params.iter().zip(registers) {
+            let ty = self.type_id(ty);
+            let name_value = self.string(name);
+
+            let param = self.dwarf.unit.add(id, gimli::DW_TAG_formal_parameter);
+            let entry = self.dwarf.unit.get_mut(param);
+            entry.set(gimli::DW_AT_name, name_value);
+            if let Some(ty) = ty {
+                entry.set(gimli::DW_AT_type, AttributeValue::UnitRef(ty));
+            }
+            if let Some(register) = register {
+                let mut location = Expression::new();
+                location.op_reg(register);
+                entry.set(gimli::DW_AT_location, AttributeValue::Exprloc(location));
+            }
+        }
+    }
+}
+
+/// AAPCS64 register each parameter arrives in, only valid on entry.
+/// Structs passed by value take registers depending on their layout,
+/// so parameters from the first one on are left without a location
+fn argument_registers(params: &[(String, String)]) -> Vec<Option<Register>> {
+    // x0-x7 and v0-v7
+    const ARGUMENT_REGISTERS: u16 = 8;
+    const V0: u16 = 64;
+
+    let mut next_x = 0;
+    let mut next_v = 0;
+    params
+        .iter()
+        .map_while(|(ty, _)| {
+            let ty = ty.trim();
+            let (next, base) = match base_type(ty) {
+                Some((encoding, _)) if encoding == DW_ATE_float => (&mut next_v, V0),
+                Some(_) => (&mut next_x, 0),
+                None if ty.ends_with('*') => (&mut next_x, 0),
+                None => return None,
+            };
+            let index = *next;
+            *next += 1;
+
+            // the rest are passed on the stack
+            Some((index < ARGUMENT_REGISTERS).then_some(Register(base + index)))
+        })
+        .chain(std::iter::repeat(None))
+        .take(params.len())
+        .collect()
+}
+
+fn base_type(name: &str) -> Option<(DwAte, u64)> {
+    Some(match name {
+        "bool" => (DW_ATE_boolean, 1),
+        "int8_t" => (DW_ATE_signed, 1),
+        "uint8_t" => (DW_ATE_unsigned, 1),
+        "int16_t" => (DW_ATE_signed, 2),
+        "uint16_t" => (DW_ATE_unsigned, 2),
+        "int32_t" => (DW_ATE_signed, 4),
+        "uint32_t" => (DW_ATE_unsigned, 4),
+        "int64_t" | "intptr_t" => (DW_ATE_signed, 8),
+        "uint64_t" | "uintptr_t" => (DW_ATE_unsigned, 8),
+        "float" => (DW_ATE_float, 4),
+        "double" => (DW_ATE_float, 8),
+        _ => return None,
+    })
+}
+
+pub fn run_dwarf(collection: &TypeContextCollection, metadata: &CordlMetadata) -> Result<()> {
+    let debug_file = make_debug_file(collection, metadata)?;
+
+    let file = Path::new("./libil2cpp.so.debug");
+    println!("Writing debug file {file:?}");
+    fs::write(file, debug_file)?;
+
+    Ok(())
+}
+
+pub fn make_debug_file(
+    collection: &TypeContextCollection,
+    metadata: &CordlMetadata,
+) -> Result<Vec<u8>> {
+    let mut resolver = DisasmNameResolver::new(metadata, collection);
+
+    let types = collection
+        .get()
+        .values()
+        .flat_map(|c| c.get_types().iter())
+        .sorted_by_key(|(tag, _)| **tag)
+        .map(|(_, ty)| ty)
+        .collect_vec();
+
+    let structs = types
+        .iter()
+        .filter_map(|ty| make_struct(&mut resolver, ty))
+        .collect_vec();
+
+    // identical code folding means several methods may share an address, the first one wins
+    let mut functions: Vec<DwarfFunction> = types
+        .iter()
+        .flat_map(|ty| ty.methods.iter().map(move |method| (*ty, method)))
+        .filter_map(|(ty, method)| {
+            let calc = metadata.method_calculations.get(&method.method_index)?;
+            if calc.addrs == 0x0 || calc.estimated_size == usize::MAX {
+                return None;
+            }
+
+            Some(DwarfFunction {
+                addrs: calc.addrs,
+                size: calc.estimated_size as u64,
+                name: format!("{}$${}", ty.cs_name_components.combine_all(), method.name),
+                prototype: DisasmPrototype::new(&mut resolver, ty, method),
+            })
+        })
+        .collect();
+    functions.sort_by_key(|f| f.addrs);
+    functions.dedup_by_key(|f| f.addrs);
+    info!("{} functions, {} structs", functions.len(), structs.len());
+
+    let debug_sections = make_debug_sections(&structs, &resolver.opaque_value_types, &functions)?;
+
+    write_elf(metadata.elf_data, &debug_sections, &functions)
+}
+
+/// DWARF sections describing `functions` and every struct they use
+fn make_debug_sections(
+    structs: &[DisasmStruct],
+    opaque_value_types: &BTreeMap<String, u32>,
+    functions: &[DwarfFunction],
+) -> Result<Vec<(&'static str, Vec<u8>)>> {
+    let mut dwarf_types = DwarfTypes::new();
+    let root = dwarf_types.dwarf.unit.root();
+    let producer = dwarf_types.string("cordl");
+    let name = dwarf_types.string("libil2cpp.so");
+    let entry = dwarf_types.dwarf.unit.get_mut(root);
+    entry.set(gimli::DW_AT_producer, producer);
+    entry.set(gimli::DW_AT_name, name);
+    entry.set(
+        gimli::DW_AT_language,
+        AttributeValue::Language(gimli::DW_LANG_C99),
+    );
+    if let (Some(first), Some(last)) = (functions.first(), functions.last()) {
+        entry.set(
+            gimli::DW_AT_low_pc,
+            AttributeValue::Address(Address::Constant(first.addrs)),
+        );
+        entry.set(
+            gimli::DW_AT_high_pc,
+            AttributeValue::Udata(last.addrs + last.size - first.addrs),
+        );
+    }
+
+    let object_field = |offset: u64, ty: &str, name: &str| DisasmStructField {
+        offset: offset as u32,
+        size: POINTER_SIZE as u32,
+        ty: ty.to_string(),
+        name: name.to_string(),
+    };
+    dwarf_types.define_struct(&DisasmStruct {
+        name: IL2CPP_OBJECT_TYPE.to_string(),
+        size: 2 * POINTER_SIZE as u32,
+        fields: vec![
+            object_field(0, "void*", "klass"),
+            object_field(POINTER_SIZE, "void*", "monitor"),
+        ],
+    });
+    dwarf_types.define_struct(&DisasmStruct {
+        name: IL2CPP_ARRAY_TYPE.to_string(),
+        size: 4 * POINTER_SIZE as u32,
+        fields: vec![
+            object_field(0, "void*", "klass"),
+            object_field(POINTER_SIZE, "void*", "monitor"),
+            object_field(2 * POINTER_SIZE, "void*", "bounds"),
+            object_field(3 * POINTER_SIZE, "uintptr_t", "max_length"),
+        ],
+    });
+    for s in structs {
+        dwarf_types.define_struct(s);
+    }
+    for (name, size) in opaque_value_types {
+        dwarf_types.define_struct(&DisasmStruct {
+            name: name.clone(),
+            size: *size,
+            fields: vec![],
+        });
+    }
+    for function in functions {
+        dwarf_types.add_function(function);
+    }
+
+    let mut sections = Sections::new(EndianVec::new(gimli::LittleEndian));
+    dwarf_types.dwarf.write(&mut sections)?;
+
+    let mut debug_sections = vec![];
+    sections.for_each(|id, data| {
+        if !data.slice().is_empty() {
+            debug_sections.push((id.name(), data.slice().to_vec()));
+        }
+        Ok::<_, gimli::write::Error>(())
+    })?;
+
+    Ok(debug_sections)
+}
+
+/// Debug only ELF like `objcopy --only-keep-debug` makes:
+/// the original sections without contents, the build id, DWARF and a symbol table
+fn write_elf(
+    elf_data: &[u8],
+    debug_sections: &[(&'static str, Vec<u8>)],
+    functions: &[DwarfFunction],
+) -> Result<Vec<u8>> {
+    let original = object::File::parse(elf_data).context("Unable to parse ELF file")?;
+
+    let mut kept = vec![];
+    for section in original.sections() {
+        let SectionFlags::Elf { sh_flags } = section.flags() else {
+            continue;
+        };
+        if sh_flags & u64::from(elf::SHF_ALLOC) == 0 {
+            continue;
+        }
+
+        let name = section.name_bytes()?;
+        // debuggers match the debug file to the binary by build id
+        let data = match name {
+            b".note.gnu.build-id" => Some(section.data()?),
+            _ => None,
+        };
+
+        kept.push(KeptSection {
+            name,
+            address: section.address(),
+            size: section.size(),
+            align: section.align().max(1),
+            flags: sh_flags,
+            data,
+        });
+    }
+
+    let mut buffer = Vec::new();
+    {
+        let mut writer = Writer::new(Endianness::Little, true, &mut buffer);
+        writer.reserve_file_header();
+
+        writer.reserve_null_section_index();
+        let kept_indices = kept
+            .iter()
+            .map(|s| {
+                (
+                    writer.add_section_name(s.name),
+                    writer.reserve_section_index(),
+                )
+            })
+            .collect_vec();
+        let debug_indices = debug_sections
+            .iter()
+            .map(|(name, _)| {
+                (
+                    writer.add_section_name(name.as_bytes()),
+                    writer.reserve_section_index(),
+                )
+            })
+            .collect_vec();
+        writer.reserve_symtab_section_index();
+        writer.reserve_strtab_section_index();
+        writer.reserve_shstrtab_section_index();
+
+        let kept_offsets = kept
+            .iter()
+            .map(|s| match s.data {
+                Some(data) => writer.reserve(data.len(), s.align as usize),
+                None => 0,
+            })
+            .collect_vec();
+        let debug_offsets = debug_sections
+            .iter()
+            .map(|(_, data)| writer.reserve(data.len(), 1))
+            .collect_vec();
+
+        writer.reserve_null_symbol_index();
+        let symbols = functions
+            .iter()
+            .map(|f| {
+                let section = kept
+                    .iter()
+                    .position(|s| {
+                        s.data.is_none() && (s.address..s.address + s.size).contains(&f.addrs)
+                    })
+                    .map(|i| kept_indices[i].1);
+                let name = writer.add_string(f.name.as_bytes());
+                writer.reserve_symbol_index(section);
+                (name, section)
+            })
+            .collect_vec();
+        writer.reserve_symtab();
+        writer.reserve_strtab();
+        writer.reserve_shstrtab();
+        writer.reserve_section_headers();
+
+        writer.write_file_header(&FileHeader {
+            os_abi: elf::ELFOSABI_NONE,
+            abi_version: 0,
+            e_type: elf::ET_DYN,
+            e_machine: elf::EM_AARCH64,
+            e_entry: 0,
+            e_flags: 0,
+        })?;
+
+        for s in &kept {
+            if let Some(data) = s.data {
+                writer.write_align(s.align as usize);
+                writer.write(data);
+            }
+        }
+        for (_, data) in debug_sections {
+            writer.write(data);
+        }
+
+        writer.write_null_symbol();
+        for ((name, section), function) in symbols.iter().zip(functions) {
+            writer.write_symbol(&Sym {
+                name: Some(*name),
+                section: *section,
+                st_info: (elf::STB_GLOBAL << 4) | elf::STT_FUNC,
+                st_other: elf::STV_DEFAULT,
+                st_shndx: match section {
+                    Some(_) => 0,
+                    None => elf::SHN_ABS,
+                },
+                st_value: function.addrs,
+                st_size: function.size,
+            });
+        }
+        writer.write_strtab();
+        writer.write_shstrtab();
+
+        writer.write_null_section_header();
+        for ((s, (name, _)), offset) in kept.iter().zip(&kept_indices).zip(kept_offsets) {
+            writer.write_section_header(&SectionHeader {
+                name: Some(*name),
+                sh_type: match s.data {
+                    Some(_) => elf::SHT_NOTE,
+                    None => elf::SHT_NOBITS,
+                },
+                sh_flags: s.flags,
+                sh_addr: s.address,
+                sh_offset: offset as u64,
+                sh_size: s.size,
+                sh_link: 0,
+                sh_info: 0,
+                sh_addralign: s.align,
+                sh_entsize: 0,
+            });
+        }
+        for (((_, data), (name, _)), offset) in
+            debug_sections.iter().zip(&debug_indices).zip(debug_offsets)
+        {
+            writer.write_section_header(&SectionHeader {
+                name: Some(*name),
+                sh_type: elf::SHT_PROGBITS,
+                sh_flags: 0,
+                sh_addr: 0,
+                sh_offset: offset as u64,
+                sh_size: data.len() as u64,
+                sh_link: 0,
+                sh_info: 0,
+                sh_addralign: 1,
+                sh_entsize: 0,
+            });
+        }
+        // only the null symbol is local
+        writer.write_symtab_section_header(1);
+        writer.write_strtab_section_header();
+        writer.write_shstrtab_section_header();
+    }
+
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use gimli::{EndianSlice, LittleEndian, Operation};
+    use object::{
+        Architecture, BinaryFormat,
+        write::{self, SectionKind},
+    };
+
+    use super::*;
+
+    const BUILD_ID: &[u8] = b"\x04\0\0\0\x08\0\0\0\x03\0\0\0GNU\0cordl_id";
+
+    fn original_elf() -> Vec<u8> {
+        let mut obj =
+            write::Object::new(BinaryFormat::Elf, Architecture::Aarch64, Endianness::Little);
+        let text = obj.add_section(vec![], b".text".to_vec(), SectionKind::Text);
+        obj.append_section_data(text, &[0; 0x100], 4);
+        let build_id = obj.add_section(vec![], b".note.gnu.build-id".to_vec(), SectionKind::Note);
+        obj.section_mut(build_id).flags = SectionFlags::Elf {
+            sh_flags: elf::SHF_ALLOC.into(),
+        };
+        obj.append_section_data(build_id, BUILD_ID, 4);
+
+        obj.write().unwrap()
+    }
+
+    fn params(params: &[(&str, &str)]) -> Vec<(String, String)> {
+        params
+            .iter()
+            .map(|(ty, name)| (ty.to_string(), name.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn assigns_argument_registers() {
+        let registers = argument_registers(&params(&[
+            ("Game_Player_o*", "__this"),
+            ("float", "speed"),
+            ("int32_t", "count"),
+            ("double", "scale"),
+            ("UnityEngine_Vector2_o", "target"),
+            ("bool", "force"),
+            ("const MethodInfo*", "method"),
+        ]));
+        assert_eq!(
+            registers,
+            [
+                Some(Register(0)),
+                Some(Register(64)),
+                Some(Register(1)),
+                Some(Register(65)),
+                None,
+                None,
+                None,
+            ]
+        );
+
+        let registers = argument_registers(&vec![("void*".to_string(), String::new()); 9]);
+        assert_eq!(registers[7], Some(Register(7)));
+        assert_eq!(registers[8], None);
+    }
+
+    #[test]
+    fn writes_readable_debug_file() {
+        let structs = [DisasmStruct {
+            name: "Game_Player_o".to_string(),
+            size: 0x20,
+            fields: vec![
+                DisasmStructField {
+                    offset: 0x10,
+                    size: 0x4,
+                    ty: "int32_t".to_string(),
+                    name: "health".to_string(),
+                },
+                DisasmStructField {
+                    offset: 0x14,
+                    size: 0x8,
+                    ty: "Opaque".to_string(),
+                    name: "position".to_string(),
+                },
+            ],
+        }];
+        let opaque_value_types = BTreeMap::from([("Opaque".to_string(), 0x8)]);
+        let functions = [DwarfFunction {
+            addrs: 0x10,
+            size: 0x20,
+            name: "Game.Player$$Move".to_string(),
+            prototype: DisasmPrototype {
+                ret: "void".to_string(),
+                params: params(&[
+                    ("Game_Player_o*", "__this"),
+                    ("float", "speed"),
+                    ("const MethodInfo*", "method"),
+                ]),
+            },
+        }];
+
+        let debug_sections =
+            make_debug_sections(&structs, &opaque_value_types, &functions).unwrap();
+        let debug_file = write_elf(&original_elf(), &debug_sections, &functions).unwrap();
+
+        let file = object::File::parse(&*debug_file).unwrap();
+        let build_id = file.section_by_name(".note.gnu.build-id").unwrap();
+        assert_eq!(build_id.data().unwrap(), BUILD_ID);
+
+        let dwarf = gimli::Dwarf::load(|id| -> gimli::Result<_> {
+            let data = file
+                .section_by_name(id.name())
+                .map(|s| s.data().unwrap())
+                .unwrap_or_default();
+            Ok(EndianSlice::new(data, LittleEndian))
+        })
+        .unwrap();
+        let header = dwarf.units().next().unwrap().unwrap();
+        let unit = dwarf.unit(header).unwrap();
+
+        let name = |entry: &gimli::DebuggingInformationEntry<_>| {
+            let value = entry.attr_value(gimli::DW_AT_name).unwrap().unwrap();
+            let name = dwarf.attr_string(&unit, value).unwrap();
+            name.to_string_lossy().into_owned()
+        };
+
+        let mut subprograms = 0;
+        let mut members = vec![];
+        let mut tree = unit.entries_tree(None).unwrap();
+        let mut children = tree.root().unwrap().children();
+        while let Some(child) = children.next().unwrap() {
+            let entry = child.entry();
+            match entry.tag() {
+                gimli::DW_TAG_subprogram => {
+                    subprograms += 1;
+                    assert_eq!(name(entry), "Game.Player$$Move");
+                    assert_eq!(
+                        entry.attr_value(gimli::DW_AT_low_pc).unwrap(),
+                        Some(gimli::AttributeValue::Addr(0x10))
+                    );
+                    assert_eq!(
+                        entry.attr_value(gimli::DW_AT_high_pc).unwrap(),
+                        Some(gimli::AttributeValue::Udata(0x20))
+                    );
+
+                    let mut parameters = vec![];
+                    let mut params = child.children();
+                    while let Some(param) = params.next().unwrap() {
+                        let entry = param.entry();
+                        assert_eq!(entry.tag(), gimli::DW_TAG_formal_parameter);
+
+                        let Some(gimli::AttributeValue::Exprloc(location)) =
+                            entry.attr_value(gimli::DW_AT_location).unwrap()
+                        else {
+                            panic!("{} has no location", name(entry));
+                        };
+                        let register = match location.operations(unit.encoding()).next() {
+                            Ok(Some(Operation::Register { register })) => register,
+                            op => panic!("{op:?} isn't a register"),
+                        };
+                        parameters.push((name(entry), register));
+                    }
+                    assert_eq!(
+                        parameters,
+                        [
+                            ("__this".to_string(), Register(0)),
+                            ("speed".to_string(), Register(64)),
+                            ("method".to_string(), Register(1)),
+                        ]
+                    );
+                }
+                gimli::DW_TAG_structure_type if name(entry) == "Game_Player_o" => {
+                    let mut fields = child.children();
+                    while let Some(field) = fields.next().unwrap() {
+                        let entry = field.entry();
+                        let offset = entry.attr_value(gimli::DW_AT_data_member_location);
+                        members.push((name(entry), offset.unwrap()));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        assert_eq!(subprograms, 1);
+        assert_eq!(
+            members,
+            [
+                (
+                    "health".to_string(),
+                    Some(gimli::AttributeValue::Udata(0x10))
+                ),
+                (
+                    "position".to_string(),
+                    Some(gimli::AttributeValue::Udata(0x14))
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,577 @@
+//! Il2CppDumper style il2cpp.h, for disassembler struct import and plain C FFI
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Write as _,
+    fs,
+    path::Path,
+};
+
+use color_eyre::eyre::Result;
+use itertools::Itertools;
+
+use crate::generate::{
+    cs_context_collection::TypeContextCollection, cs_type::CsType, metadata::CordlMetadata,
+    offsets::get_il2cpptype_sa,
+};
+
+use super::{
+    disasm_main::{DisasmStructField, instance_fields},
+    disasm_name_resolver::{
+        DisasmNameResolver, IL2CPP_ARRAY_TYPE, IL2CPP_OBJECT_TYPE, sanitize_identifier,
+    },
+};
+
+/// Runtime structs referenced by the generated ones.
+/// `Il2CppClass` is split around `static_fields` and `rgctx_data` like Il2CppDumper does,
+/// laid out as in metadata v29+ libil2cpp on 64 bit
+const RUNTIME_HEADER: &str = "typedef struct MethodInfo MethodInfo;
+typedef void* Il2CppRGCTXData;
+
+typedef struct Il2CppType {
+    void* data;
+    uint32_t bits;
+} Il2CppType;
+
+typedef struct VirtualInvokeData {
+    void* methodPtr;
+    const MethodInfo* method;
+} VirtualInvokeData;
+
+typedef struct Il2CppClass_1 {
+    void* image;
+    void* gc_desc;
+    const char* name;
+    const char* namespaze;
+    Il2CppType byval_arg;
+    Il2CppType this_arg;
+    void* element_class;
+    void* castClass;
+    void* declaringType;
+    void* parent;
+    void* generic_class;
+    void* typeMetadataHandle;
+    void* interopData;
+    void* klass;
+    void* fields;
+    void* events;
+    void* properties;
+    void* methods;
+    void** nestedTypes;
+    void** implementedInterfaces;
+    void* interfaceOffsets;
+} Il2CppClass_1;
+
+typedef struct Il2CppClass_2 {
+    void** typeHierarchy;
+    void* unity_user_data;
+    uint32_t initializationExceptionGCHandle;
+    uint32_t cctor_started;
+    uint32_t cctor_finished;
+    size_t cctor_thread;
+    void* genericContainerHandle;
+    uint32_t instance_size;
+    uint32_t actualSize;
+    uint32_t element_size;
+    int32_t native_size;
+    uint32_t static_fields_size;
+    uint32_t thread_static_fields_size;
+    int32_t thread_static_fields_offset;
+    uint32_t flags;
+    uint32_t token;
+    uint16_t method_count;
+    uint16_t property_count;
+    uint16_t field_count;
+    uint16_t event_count;
+    uint16_t nested_type_count;
+    uint16_t vtable_count;
+    uint16_t interfaces_count;
+    uint16_t interface_offsets_count;
+    uint8_t typeHierarchyDepth;
+    uint8_t genericRecursionDepth;
+    uint8_t rank;
+    uint8_t minimumAlignment;
+    uint8_t naturalAligment;
+    uint8_t packingSize;
+    uint8_t bitflags1;
+    uint8_t bitflags2;
+} Il2CppClass_2;
+";
+
+/// Field offset table entry of `[ThreadStatic]` fields, `THREAD_STATIC_FIELD_OFFSET` in libil2cpp
+const THREAD_STATIC_FIELD_OFFSET: u32 = u32::MAX;
+
+struct HeaderType {
+    /// C identifier, without suffix
+    name: String,
+    is_value_type: bool,
+    /// offsets relative to the start of `_Fields`
+    fields: Vec<DisasmStructField>,
+    fields_size: u32,
+    static_fields: Vec<DisasmStructField>,
+    static_fields_size: u32,
+    /// Per thread statics, which aren't stored in `Il2CppClass::static_fields`
+    thread_static_fields: Vec<DisasmStructField>,
+    thread_static_fields_size: u32,
+    /// slot names, in slot order
+    vtable: Vec<String>,
+}
+
+pub fn run_header(collection: &TypeContextCollection, metadata: &CordlMetadata) -> Result<()> {
+    let header = make_header(collection, metadata)?;
+
+    let file = Path::new("./cordl_il2cpp.h");
+    println!("Writing C header {file:?}");
+    fs::write(file, header)?;
+
+    Ok(())
+}
+
+pub fn make_header(collection: &TypeContextCollection, metadata: &CordlMetadata) -> Result<String> {
+    let mut resolver = DisasmNameResolver::new(metadata, collection);
+
+    let header_types = collection
+        .get()
+        .values()
+        .flat_map(|c| c.get_types().iter())
+        .sorted_by_key(|(tag, _)| **tag)
+        .filter_map(|(_, ty)| make_header_type(&mut resolver, metadata, ty))
+        .collect_vec();
+
+    write_header(&header_types, &resolver.opaque_value_types)
+}
+
+fn write_header(
+    header_types: &[HeaderType],
+    opaque_value_types: &BTreeMap<String, u32>,
+) -> Result<String> {
+    let mut out = String::new();
+    writeln!(out, "// Generated by cordl")?;
+    writeln!(out, "#pragma once")?;
+    writeln!(out)?;
+    writeln!(out, "#include <stdbool.h>")?;
+    writeln!(out, "#include <stddef.h>")?;
+    writeln!(out, "#include <stdint.h>")?;
+    writeln!(out)?;
+    writeln!(out, "{RUNTIME_HEADER}")?;
+    writeln!(
+        out,
+        "typedef struct {IL2CPP_OBJECT_TYPE} {{ void* klass; void* monitor; }} {IL2CPP_OBJECT_TYPE};"
+    )?;
+    writeln!(
+        out,
+        "typedef struct {IL2CPP_ARRAY_TYPE} {{ {IL2CPP_OBJECT_TYPE} obj; void* bounds; uintptr_t max_length; }} {IL2CPP_ARRAY_TYPE};"
+    )?;
+    writeln!(out)?;
+
+    for ht in header_types {
+        let name = &ht.name;
+        for suffix in [
+            "_Fields",
+            "_o",
+            "_StaticFields",
+            "_ThreadStaticFields",
+            "_VTable",
+            "_c",
+        ] {
+            writeln!(out, "typedef struct {name}{suffix} {name}{suffix};")?;
+        }
+    }
+    writeln!(out)?;
+
+    // generated layouts use explicit padding, so nothing may be added by the compiler
+    writeln!(out, "#pragma pack(push, 1)")?;
+    writeln!(out)?;
+    for (name, size) in opaque_value_types {
+        writeln!(
+            out,
+            "typedef struct {name} {{ uint8_t data[{}]; }} {name};",
+            (*size).max(1)
+        )?;
+    }
+    writeln!(out)?;
+
+    // value types embedded by value must be defined before their users
+    let by_name: HashMap<String, &HeaderType> = header_types
+        .iter()
+        .map(|ht| (format!("{}_o", ht.name), ht))
+        .collect();
+    let mut written = HashSet::new();
+    for ht in header_types {
+        write_object_ordered(&mut out, ht, &by_name, &mut written)?;
+    }
+
+    for ht in header_types {
+        for (suffix, fields, size) in [
+            ("_StaticFields", &ht.static_fields, ht.static_fields_size),
+            (
+                "_ThreadStaticFields",
+                &ht.thread_static_fields,
+                ht.thread_static_fields_size,
+            ),
+        ] {
+            if size == 0 {
+                continue;
+            }
+            writeln!(out, "struct {}{suffix} {{", ht.name)?;
+            write_fields(&mut out, fields, size)?;
+            writeln!(out, "}};")?;
+            writeln!(out)?;
+        }
+    }
+
+    writeln!(out, "#pragma pack(pop)")?;
+    writeln!(out)?;
+
+    for ht in header_types {
+        let name = &ht.name;
+        if !ht.vtable.is_empty() {
+            writeln!(out, "struct {name}_VTable {{")?;
+            for slot in &ht.vtable {
+                writeln!(out, "    VirtualInvokeData {slot};")?;
+            }
+            writeln!(out, "}};")?;
+            writeln!(out)?;
+        }
+
+        writeln!(out, "struct {name}_c {{")?;
+        writeln!(out, "    Il2CppClass_1 _1;")?;
+        writeln!(out, "    {name}_StaticFields* static_fields;")?;
+        writeln!(out, "    Il2CppRGCTXData* rgctx_data;")?;
+        writeln!(out, "    Il2CppClass_2 _2;")?;
+        if !ht.vtable.is_empty() {
+            writeln!(out, "    {name}_VTable vtable;")?;
+        }
+        writeln!(out, "}};")?;
+        writeln!(out)?;
+    }
+
+    Ok(out)
+}
+
+fn make_header_type(
+    resolver: &mut DisasmNameResolver,
+    metadata: &CordlMetadata,
+    ty: &CsType,
+) -> Option<HeaderType> {
+    // uninstantiated generics have no single layout
+    if ty.is_enum_type || ty.generic_template.is_some() {
+        return None;
+    }
+    let name = resolver.type_name(ty.self_tag)?.to_string();
+
+    // `_Fields` excludes the object header
+    let base = match ty.is_value_type {
+        true => 0,
+        false => metadata.object_size() as u32,
+    };
+    let fields = instance_fields(resolver, ty, &mut HashSet::new())
+        .into_iter()
+        .map(|f| DisasmStructField {
+            offset: f.offset.saturating_sub(base),
+            ..f
+        })
+        .collect_vec();
+
+    let fields_end = fields.iter().map(|f| f.offset + f.size).max().unwrap_or(0);
+    let instance_size = ty
+        .size_info
+        .as_ref()
+        .map(|s| s.instance_size.saturating_sub(base))
+        .unwrap_or(0);
+    let mut fields_size = instance_size.max(fields_end);
+    // structs without fields still take a byte
+    if ty.is_value_type {
+        fields_size = fields_size.max(1);
+    }
+
+    let thread_statics = thread_static_fields(metadata, ty);
+    let (static_fields, static_fields_size) =
+        layout_static_fields(resolver, metadata, ty, |name| {
+            !thread_statics.contains(name)
+        });
+    let (thread_static_fields, thread_static_fields_size) =
+        layout_static_fields(resolver, metadata, ty, |name| thread_statics.contains(name));
+
+    Some(HeaderType {
+        name,
+        is_value_type: ty.is_value_type,
+        fields,
+        fields_size,
+        static_fields,
+        static_fields_size,
+        thread_static_fields,
+        thread_static_fields_size,
+        vtable: vtable_slots(resolver, metadata, ty),
+    })
+}
+
+/// Names of the `[ThreadStatic]` fields of `ty`
+fn thread_static_fields<'a>(metadata: &CordlMetadata<'a>, ty: &CsType) -> HashSet<&'a str> {
+    let tdi = ty.self_tag.get_tdi();
+    let td = &metadata.metadata.global_metadata.type_definitions[tdi];
+    let Some(field_offsets) = metadata
+        .metadata_registration
+        .field_offsets
+        .as_ref()
+        .and_then(|offsets| offsets.get(tdi.index() as usize))
+    else {
+        return HashSet::new();
+    };
+
+    td.fields(metadata.metadata)
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| field_offsets.get(*i) == Some(&THREAD_STATIC_FIELD_OFFSET))
+        .map(|(_, f)| f.name(metadata.metadata))
+        .collect()
+}
+
+/// Statics selected by `include` are laid out in declaration order at their natural alignment,
+/// like `Class::LayoutFieldsLocked`
+fn layout_static_fields(
+    resolver: &mut DisasmNameResolver,
+    metadata: &CordlMetadata,
+    ty: &CsType,
+    include: impl Fn(&str) -> bool,
+) -> (Vec<DisasmStructField>, u32) {
+    let generic_inst_types = ty
+        .generic_instantiations_args_types
+        .as_ref()
+        .map(|v| v.iter().map(|t| t.ty).collect_vec());
+
+    let mut field_names = HashSet::new();
+    let mut size = 0;
+    let mut fields = vec![];
+    for field in ty
+        .fields
+        .iter()
+        .filter(|f| !f.instance && !f.is_const && include(&f.name))
+    {
+        let il2cpp_ty = &metadata.metadata_registration.types[field.field_ty.ty];
+        let sa = get_il2cpptype_sa(metadata, il2cpp_ty, generic_inst_types.as_deref());
+        if sa.size == 0 {
+            continue;
+        }
+
+        let offset = size.next_multiple_of(sa.alignment.max(1) as u32);
+        size = offset + sa.size as u32;
+
+        let mut field_name = sanitize_identifier(&field.name);
+        while !field_names.insert(field_name.clone()) {
+            field_name.push('_');
+        }
+
+        fields.push(DisasmStructField {
+            offset,
+            size: sa.size as u32,
+            ty: resolver.resolve_name(&field.field_ty),
+            name: field_name,
+        });
+    }
+
+    (fields, size)
+}
+
+/// Slot names, taken from the most derived method occupying each slot
+fn vtable_slots(
+    resolver: &DisasmNameResolver,
+    metadata: &CordlMetadata,
+    ty: &CsType,
+) -> Vec<String> {
+    let td = &metadata.metadata.global_metadata.type_definitions[ty.self_tag.get_tdi()];
+
+    let mut slots: BTreeMap<u16, &str> = BTreeMap::new();
+    let mut current = Some(ty);
+    while let Some(cs_type) = current {
+        for method in &cs_type.methods {
+            if let Some(slot) = method.method_data.slot {
+                slots.entry(slot).or_insert(&method.name);
+            }
+        }
+        current = resolver.parent(cs_type);
+    }
+
+    (0..td.vtable_count)
+        .map(|slot| match slots.get(&slot) {
+            Some(name) => format!("_{slot}_{}", sanitize_identifier(name)),
+            None => format!("_{slot}_unknown"),
+        })
+        .collect()
+}
+
+fn write_object_ordered<'a>(
+    out: &mut String,
+    ht: &'a HeaderType,
+    by_name: &HashMap<String, &'a HeaderType>,
+    written: &mut HashSet<&'a str>,
+) -> std::fmt::Result {
+    if !written.insert(&ht.name) {
+        return Ok(());
+    }
+    for field in &ht.fields {
+        if let Some(dependency) = by_name.get(&field.ty) {
+            write_object_ordered(out, dependency, by_name, written)?;
+        }
+    }
+
+    let name = &ht.name;
+    let has_fields = ht.fields_size > 0;
+    if has_fields {
+        writeln!(out, "struct {name}_Fields {{")?;
+        write_fields(out, &ht.fields, ht.fields_size)?;
+        writeln!(out, "}};")?;
+        writeln!(out)?;
+    }
+
+    writeln!(out, "struct {name}_o {{")?;
+    if !ht.is_value_type {
+        writeln!(out, "    {name}_c* klass;")?;
+        writeln!(out, "    void* monitor;")?;
+    }
+    if has_fields {
+        writeln!(out, "    {name}_Fields fields;")?;
+    }
+    writeln!(out, "}};")?;
+    writeln!(out)
+}
+
+/// Fields padded to their offsets. Overlapping fields, i.e explicit layout, become a union
+fn write_fields(out: &mut String, fields: &[DisasmStructField], size: u32) -> std::fmt::Result {
+    let mut clusters: Vec<(u32, u32, Vec<&DisasmStructField>)> = vec![];
+    for field in fields.iter().sorted_by_key(|f| f.offset) {
+        let field_end = field.offset + field.size;
+        match clusters.last_mut() {
+            Some((_, end, cluster)) if field.offset < *end => {
+                *end = (*end).max(field_end);
+                cluster.push(field);
+            }
+            _ => clusters.push((field.offset, field_end, vec![field])),
+        }
+    }
+
+    let mut end = 0;
+    for (start, cluster_end, cluster) in clusters {
+        if start > end {
+            writeln!(out, "    uint8_t _pad_0x{end:x}[{}];", start - end)?;
+        }
+
+        match cluster.as_slice() {
+            [field] => writeln!(out, "    {} {};", field.ty, field.name)?,
+            _ => {
+                writeln!(out, "    union {{")?;
+                for field in cluster {
+                    match field.offset - start {
+                        0 => writeln!(out, "        {} {};", field.ty, field.name)?,
+                        pad => writeln!(
+                            out,
+                            "        struct {{ uint8_t _pad_{}[{pad}]; {} {}; }};",
+                            field.name, field.ty, field.name
+                        )?,
+                    }
+                }
+                writeln!(out, "    }};")?;
+            }
+        }
+        end = cluster_end;
+    }
+
+    if size > end {
+        writeln!(out, "    uint8_t _pad_0x{end:x}[{}];", size - end)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process::Command};
+
+    use super::*;
+
+    fn field(offset: u32, size: u32, ty: &str, name: &str) -> DisasmStructField {
+        DisasmStructField {
+            offset,
+            size,
+            ty: ty.to_string(),
+            name: name.to_string(),
+        }
+    }
+
+    /// A class embedding a value type declared after it, with an explicit layout union
+    fn fixture() -> Vec<HeaderType> {
+        vec![
+            HeaderType {
+                name: "Game_Player".to_string(),
+                is_value_type: false,
+                fields: vec![
+                    field(0x0, 0x8, "UnityEngine_Vector2_o", "position"),
+                    field(0x8, 0x8, "Il2CppObject*", "name"),
+                    field(0x10, 0x4, "int32_t", "a"),
+                    field(0x10, 0x8, "int64_t", "b"),
+                    field(0x14, 0x1, "uint8_t", "c"),
+                    field(0x1c, 0xc, "Opaque", "opaque"),
+                ],
+                fields_size: 0x28,
+                static_fields: vec![field(0x0, 0x4, "int32_t", "count")],
+                static_fields_size: 0x4,
+                thread_static_fields: vec![field(0x0, 0x8, "Game_Player_o*", "current")],
+                thread_static_fields_size: 0x8,
+                vtable: vec!["_0_Equals".to_string(), "_1_Finalize".to_string()],
+            },
+            HeaderType {
+                name: "UnityEngine_Vector2".to_string(),
+                is_value_type: true,
+                fields: vec![field(0x0, 0x4, "float", "x"), field(0x4, 0x4, "float", "y")],
+                fields_size: 0x8,
+                static_fields: vec![],
+                static_fields_size: 0,
+                thread_static_fields: vec![],
+                thread_static_fields_size: 0,
+                vtable: vec![],
+            },
+        ]
+    }
+
+    #[test]
+    fn header_compiles() {
+        // skipped where no C compiler is installed
+        if let Err(e) = Command::new("cc").arg("--version").output() {
+            eprintln!("Skipping header_compiles, unable to run cc: {e}");
+            return;
+        }
+
+        let opaque_value_types = BTreeMap::from([("Opaque".to_string(), 0xc)]);
+        let header = write_header(&fixture(), &opaque_value_types).unwrap();
+
+        let dir = env::temp_dir().join(format!("cordl_header_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("cordl_il2cpp.h"), header).unwrap();
+        fs::write(
+            dir.join("main.c"),
+            r#"#include "cordl_il2cpp.h"
+_Static_assert(sizeof(Game_Player_Fields) == 0x28, "fields size");
+_Static_assert(offsetof(Game_Player_Fields, c) == 0x14, "union offset");
+_Static_assert(offsetof(Game_Player_Fields, opaque) == 0x1c, "padding");
+_Static_assert(offsetof(Game_Player_o, fields) == 0x10, "object header");
+_Static_assert(sizeof(UnityEngine_Vector2_o) == 0x8, "value type size");
+_Static_assert(sizeof(Game_Player_ThreadStaticFields) == 0x8, "thread statics");
+_Static_assert(sizeof(Game_Player_VTable) == 0x20, "vtable");
+"#,
+        )
+        .unwrap();
+
+        let output = Command::new("cc")
+            .args(["-std=c11", "-fsyntax-only", "main.c"])
+            .current_dir(&dir)
+            .output()
+            .expect("running cc");
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+}
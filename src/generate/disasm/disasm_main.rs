@@ -0,0 +1,464 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Write as _,
+    io::Write,
+    path::Path,
+};
+
+use color_eyre::eyre::Result;
+use itertools::Itertools;
+use log::info;
+
+use crate::{
+    analysis::xrefs::XrefAnalysis,
+    data::metadata_usage::MetadataUsage,
+    generate::{
+        cs_context_collection::TypeContextCollection, cs_members::CsMethod, cs_type::CsType,
+        metadata::CordlMetadata,
+    },
+};
+
+use super::{
+    disasm_name_resolver::{
+        DisasmNameResolver, IL2CPP_ARRAY_TYPE, IL2CPP_OBJECT_TYPE, sanitize_identifier,
+    },
+    ghidra, ida,
+};
+
+/// Types every declaration relies on, declared here so the scripts don't depend on type libraries
+const PRELUDE: &str = "typedef signed char int8_t;
+typedef unsigned char uint8_t;
+typedef short int16_t;
+typedef unsigned short uint16_t;
+typedef int int32_t;
+typedef unsigned int uint32_t;
+typedef long long int64_t;
+typedef unsigned long long uint64_t;
+typedef int64_t intptr_t;
+typedef uint64_t uintptr_t;
+typedef struct MethodInfo MethodInfo;
+";
+
+pub struct DisasmFunction {
+    pub addrs: u64,
+    /// `Namespace.Type$$Method`
+    pub name: String,
+    /// C prototype without the trailing `;`
+    pub prototype: String,
+    /// every method sharing this body, if there's more than one
+    pub comment: Option<String>,
+}
+
+pub struct DisasmLabel {
+    pub addrs: u64,
+    pub name: String,
+    pub comment: String,
+}
+
+/// Everything the disassembler scripts apply, independent of the disassembler
+pub struct DisasmData {
+    /// C declarations of every struct used by the prototypes
+    pub declarations: String,
+    pub functions: Vec<DisasmFunction>,
+    pub labels: Vec<DisasmLabel>,
+}
+
+/// C signature of a method, as called by il2cpp
+pub struct DisasmPrototype {
+    pub ret: String,
+    /// (type, name), including `this` and the trailing `MethodInfo`
+    pub params: Vec<(String, String)>,
+}
+
+impl DisasmPrototype {
+    pub fn new(resolver: &mut DisasmNameResolver, ty: &CsType, method: &CsMethod) -> Self {
+        let mut params = vec![];
+        if method.instance {
+            params.push((resolver.this_name(ty.self_tag), "__this".to_string()));
+        }
+        let mut param_names = HashSet::new();
+        for param in &method.parameters {
+            let mut param_name = sanitize_identifier(&param.name);
+            while !param_names.insert(param_name.clone())
+                || param_name == "method"
+                || param_name == "__this"
+            {
+                param_name.push('_');
+            }
+
+            params.push((resolver.resolve_name(&param.il2cpp_ty), param_name));
+        }
+        params.push(("const MethodInfo*".to_string(), "method".to_string()));
+
+        DisasmPrototype {
+            ret: resolver.resolve_name(&method.return_type),
+            params,
+        }
+    }
+
+    pub fn to_c(&self, name: &str) -> String {
+        format!(
+            "{} {}({})",
+            self.ret,
+            sanitize_identifier(name),
+            self.params
+                .iter()
+                .map(|(ty, name)| format!("{ty} {name}"))
+                .join(", ")
+        )
+    }
+}
+
+pub(super) struct DisasmStructField {
+    pub offset: u32,
+    pub size: u32,
+    pub ty: String,
+    pub name: String,
+}
+
+pub(super) struct DisasmStruct {
+    pub name: String,
+    pub size: u32,
+    pub fields: Vec<DisasmStructField>,
+}
+
+pub fn run_disasm(collection: &TypeContextCollection, metadata: &CordlMetadata) -> Result<()> {
+    info!("Analyzing metadata usages");
+    let xrefs = XrefAnalysis::analyze(metadata)?;
+
+    let data = make_disasm_data(collection, metadata, &xrefs);
+    info!(
+        "{} functions, {} labels",
+        data.functions.len(),
+        data.labels.len()
+    );
+
+    let ida_file = Path::new("./cordl_ida.py");
+    println!("Writing IDA script {ida_file:?}");
+    ida::write_script(&data, ida_file)?;
+
+    let ghidra_file = Path::new("./cordl_ghidra.py");
+    println!("Writing Ghidra script {ghidra_file:?}");
+    ghidra::write_script(&data, ghidra_file)?;
+
+    Ok(())
+}
+
+pub fn make_disasm_data(
+    collection: &TypeContextCollection,
+    metadata: &CordlMetadata,
+    xrefs: &XrefAnalysis,
+) -> DisasmData {
+    let mut resolver = DisasmNameResolver::new(metadata, collection);
+
+    let types = collection
+        .get()
+        .values()
+        .flat_map(|c| c.get_types().iter())
+        .sorted_by_key(|(tag, _)| **tag)
+        .map(|(_, ty)| ty)
+        .collect_vec();
+
+    let structs = types
+        .iter()
+        .filter_map(|ty| make_struct(&mut resolver, ty))
+        .collect_vec();
+
+    // identical code folding means several methods may share an address
+    let mut functions_by_addrs: BTreeMap<u64, Vec<(String, String)>> = BTreeMap::new();
+    for ty in &types {
+        for method in &ty.methods {
+            let Some(addrs) = method.method_data.addrs.filter(|a| *a != 0x0) else {
+                continue;
+            };
+
+            let name = format!("{}$${}", ty.cs_name_components.combine_all(), method.name);
+
+            let prototype = DisasmPrototype::new(&mut resolver, ty, method).to_c(&name);
+
+            functions_by_addrs
+                .entry(addrs)
+                .or_default()
+                .push((name, prototype));
+        }
+    }
+
+    let functions = functions_by_addrs
+        .into_iter()
+        .map(|(addrs, mut methods)| {
+            let comment =
+                (methods.len() > 1).then(|| methods.iter().map(|(name, _)| name).join("\n"));
+            let (name, prototype) = methods.swap_remove(0);
+
+            DisasmFunction {
+                addrs,
+                name,
+                prototype,
+                comment,
+            }
+        })
+        .collect_vec();
+
+    let labels = xrefs
+        .usage_slots
+        .iter()
+        .map(|(addrs, usage)| {
+            let usage_name = XrefAnalysis::usage_name(usage, metadata);
+            let name = match usage {
+                MetadataUsage::TypeInfo(_) => format!("{usage_name}_TypeInfo"),
+                MetadataUsage::Il2CppType(_) => format!("{usage_name}_var"),
+                MetadataUsage::MethodDef(_) | MetadataUsage::MethodRef(_) => {
+                    format!("Method${usage_name}")
+                }
+                MetadataUsage::FieldInfo(_) => format!("Field${usage_name}"),
+                MetadataUsage::FieldRva(_) => format!("FieldRva${usage_name}"),
+                MetadataUsage::StringLiteral(i) => format!("StringLiteral_{i}"),
+            };
+
+            DisasmLabel {
+                addrs: *addrs,
+                name,
+                comment: usage_name,
+            }
+        })
+        .collect_vec();
+
+    DisasmData {
+        declarations: make_declarations(&resolver, &types, &structs),
+        functions,
+        labels,
+    }
+}
+
+/// Object layout with every instance field, including inherited ones, at its computed offset
+pub(super) fn make_struct(resolver: &mut DisasmNameResolver, ty: &CsType) -> Option<DisasmStruct> {
+    // uninstantiated generics have no single layout
+    if ty.is_enum_type || ty.generic_template.is_some() {
+        return None;
+    }
+    let name = format!("{}_o", resolver.type_name(ty.self_tag)?);
+
+    let mut fields = vec![];
+    if !ty.is_value_type {
+        fields.push(DisasmStructField {
+            offset: 0,
+            size: 8,
+            ty: "void*".to_string(),
+            name: "klass".to_string(),
+        });
+        fields.push(DisasmStructField {
+            offset: 8,
+            size: 8,
+            ty: "void*".to_string(),
+            name: "monitor".to_string(),
+        });
+    }
+    let mut field_names: HashSet<String> = fields.iter().map(|f| f.name.clone()).collect();
+    fields.extend(instance_fields(resolver, ty, &mut field_names));
+
+    let fields_end = fields.iter().map(|f| f.offset + f.size).max().unwrap_or(0);
+    let size = ty
+        .size_info
+        .as_ref()
+        .map(|s| s.instance_size)
+        .unwrap_or(0)
+        .max(fields_end);
+
+    Some(DisasmStruct { name, size, fields })
+}
+
+/// Instance fields of a type and its parents, with offsets as stored in `CsField`.
+/// Names are made unique against `field_names`
+pub(super) fn instance_fields(
+    resolver: &mut DisasmNameResolver,
+    ty: &CsType,
+    field_names: &mut HashSet<String>,
+) -> Vec<DisasmStructField> {
+    let mut hierarchy = vec![ty];
+    if !ty.is_value_type {
+        while let Some(parent) = resolver.parent(hierarchy.last().unwrap()) {
+            hierarchy.push(parent);
+        }
+    }
+
+    let mut fields = vec![];
+    for cs_type in hierarchy.into_iter().rev() {
+        for field in cs_type.fields.iter().filter(|f| f.instance && !f.is_const) {
+            let (Some(offset), size) = (field.offset, field.size as u32) else {
+                continue;
+            };
+            if size == 0 {
+                continue;
+            }
+
+            // shadowed fields keep their own slot
+            let mut field_name = sanitize_identifier(&field.name);
+            while !field_names.insert(field_name.clone()) {
+                field_name.push('_');
+            }
+
+            fields.push(DisasmStructField {
+                offset,
+                size,
+                ty: resolver.resolve_name(&field.field_ty),
+                name: field_name,
+            });
+        }
+    }
+
+    fields
+}
+
+fn write_struct(out: &mut String, s: &DisasmStruct) -> std::fmt::Result {
+    writeln!(out, "struct {} {{", s.name)?;
+
+    let mut end = 0;
+    for field in s.fields.iter().sorted_by_key(|f| f.offset) {
+        // explicit layout unions can overlap, keep the first
+        if field.offset < end {
+            writeln!(
+                out,
+                "    // {} {} at 0x{:x}",
+                field.ty, field.name, field.offset
+            )?;
+            continue;
+        }
+        if field.offset > end {
+            writeln!(out, "    uint8_t _pad_0x{end:x}[{}];", field.offset - end)?;
+        }
+
+        writeln!(out, "    {} {};", field.ty, field.name)?;
+        end = field.offset + field.size;
+    }
+    if s.size > end {
+        writeln!(out, "    uint8_t _pad_0x{end:x}[{}];", s.size - end)?;
+    }
+
+    writeln!(out, "}};")
+}
+
+fn make_declarations(
+    resolver: &DisasmNameResolver,
+    types: &[&CsType],
+    structs: &[DisasmStruct],
+) -> String {
+    let mut out = PRELUDE.to_string();
+
+    writeln!(
+        out,
+        "typedef struct {IL2CPP_OBJECT_TYPE} {{ void* klass; void* monitor; }} {IL2CPP_OBJECT_TYPE};"
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "typedef struct {IL2CPP_ARRAY_TYPE} {{ {IL2CPP_OBJECT_TYPE} obj; void* bounds; uintptr_t max_length; }} {IL2CPP_ARRAY_TYPE};"
+    )
+    .unwrap();
+
+    // forward declare everything so pointers resolve regardless of order
+    for name in types
+        .iter()
+        .filter(|ty| !ty.is_enum_type)
+        .filter_map(|ty| resolver.type_name(ty.self_tag))
+    {
+        writeln!(out, "typedef struct {name}_o {name}_o;").unwrap();
+    }
+    for (name, size) in &resolver.opaque_value_types {
+        writeln!(
+            out,
+            "typedef struct {name} {{ uint8_t data[{}]; }} {name};",
+            (*size).max(1)
+        )
+        .unwrap();
+    }
+
+    // value types embedded by value must be defined before their users
+    let by_name: HashMap<&str, &DisasmStruct> =
+        structs.iter().map(|s| (s.name.as_str(), s)).collect();
+    let mut written: HashSet<&str> = HashSet::new();
+
+    fn write_ordered<'a>(
+        out: &mut String,
+        s: &'a DisasmStruct,
+        by_name: &HashMap<&str, &'a DisasmStruct>,
+        written: &mut HashSet<&'a str>,
+    ) {
+        if !written.insert(&s.name) {
+            return;
+        }
+        for field in &s.fields {
+            if let Some(dependency) = by_name.get(field.ty.as_str()) {
+                write_ordered(out, dependency, by_name, written);
+            }
+        }
+        write_struct(out, s).unwrap();
+    }
+
+    for s in structs {
+        write_ordered(&mut out, s, &by_name, &mut written);
+    }
+
+    out
+}
+
+/// Python string literal, escaping anything outside printable ASCII.
+/// Unicode literal since Ghidra runs Jython 2.7, where `\U` only escapes inside `u"..."`
+pub(super) fn python_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 3);
+    out.push_str("u\"");
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            ' '..='~' => out.push(c),
+            _ => write!(out, "\\U{:08x}", c as u32).unwrap(),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// The data shared by both scripts as Python literals
+pub(super) fn write_python_data(writer: &mut impl Write, data: &DisasmData) -> Result<()> {
+    writeln!(
+        writer,
+        "DECLARATIONS = {}",
+        python_string(&data.declarations)
+    )?;
+    writeln!(writer)?;
+
+    writeln!(writer, "# (address, name, prototype, comment)")?;
+    writeln!(writer, "FUNCTIONS = [")?;
+    for function in &data.functions {
+        writeln!(
+            writer,
+            "    (0x{:x}, {}, {}, {}),",
+            function.addrs,
+            python_string(&function.name),
+            python_string(&function.prototype),
+            function
+                .comment
+                .as_deref()
+                .map_or("None".to_string(), python_string)
+        )?;
+    }
+    writeln!(writer, "]")?;
+    writeln!(writer)?;
+
+    writeln!(writer, "# (address, name, comment)")?;
+    writeln!(writer, "LABELS = [")?;
+    for label in &data.labels {
+        writeln!(
+            writer,
+            "    (0x{:x}, {}, {}),",
+            label.addrs,
+            python_string(&label.name),
+            python_string(&label.comment)
+        )?;
+    }
+    writeln!(writer, "]")?;
+    writeln!(writer)?;
+
+    Ok(())
+}
@@ -0,0 +1,219 @@
+use std::collections::{BTreeMap, HashMap};
+
+use brocolib::runtime_metadata::{Il2CppTypeEnum, TypeData};
+use itertools::Itertools;
+
+use crate::{
+    data::type_resolver::{ResolvedType, ResolvedTypeData},
+    generate::{
+        cs_context_collection::TypeContextCollection, cs_type::CsType, cs_type_tag::CsTypeTag,
+        metadata::CordlMetadata, offsets::get_sizeof_type,
+        type_extensions::TypeDefinitionExtensions,
+    },
+};
+
+pub const IL2CPP_OBJECT_TYPE: &str = "Il2CppObject";
+pub const IL2CPP_ARRAY_TYPE: &str = "Il2CppArray";
+
+const C_KEYWORDS: &[&str] = &[
+    "auto", "bool", "break", "case", "char", "const", "continue", "default", "do", "double",
+    "else", "enum", "extern", "float", "for", "goto", "if", "inline", "int", "long", "register",
+    "restrict", "return", "short", "signed", "sizeof", "static", "struct", "switch", "typedef",
+    "union", "unsigned", "void", "volatile", "while",
+];
+
+/// Turns any C# name into a valid C identifier
+pub fn sanitize_identifier(name: &str) -> String {
+    let mut ident: String = name
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c,
+            false => '_',
+        })
+        .collect();
+
+    if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    if C_KEYWORDS.contains(&ident.as_str()) {
+        ident.push('_');
+    }
+
+    ident
+}
+
+/// Resolves types to plain C, named like Il2CppDumper's il2cpp.h:
+/// reference types are `Namespace_Type_o*`, value types `Namespace_Type_o` by value
+/// and enums their backing type
+pub struct DisasmNameResolver<'a, 'b> {
+    pub cordl_metadata: &'a CordlMetadata<'b>,
+    pub collection: &'a TypeContextCollection,
+
+    /// unique C identifier of every type, without the `_o` suffix
+    names: HashMap<CsTypeTag, String>,
+    /// value types used by value that have no generated layout, name -> size
+    pub opaque_value_types: BTreeMap<String, u32>,
+}
+
+impl<'a, 'b> DisasmNameResolver<'a, 'b> {
+    pub fn new(
+        cordl_metadata: &'a CordlMetadata<'b>,
+        collection: &'a TypeContextCollection,
+    ) -> Self {
+        let mut used: HashMap<String, usize> = HashMap::new();
+        let names = collection
+            .get()
+            .values()
+            .flat_map(|c| c.get_types().iter())
+            .sorted_by_key(|(tag, _)| **tag)
+            .map(|(tag, ty)| {
+                let name = sanitize_identifier(&ty.cs_name_components.combine_all());
+                let count = used.entry(name.clone()).or_default();
+                *count += 1;
+
+                match *count {
+                    1 => (*tag, name),
+                    n => (*tag, format!("{name}_{n}")),
+                }
+            })
+            .collect();
+
+        DisasmNameResolver {
+            cordl_metadata,
+            collection,
+            names,
+            opaque_value_types: Default::default(),
+        }
+    }
+
+    /// C identifier of a type, without the `_o` suffix
+    pub fn type_name(&self, tag: CsTypeTag) -> Option<&str> {
+        self.names.get(&tag).map(|s| s.as_str())
+    }
+
+    /// Tag of the type a resolved type points to, following generic instantiations
+    pub fn resolve_tag(&self, ty: &ResolvedType) -> Option<CsTypeTag> {
+        let metadata = self.cordl_metadata;
+        match metadata.metadata_registration.types[ty.ty].data {
+            TypeData::TypeDefinitionIndex(tdi) => Some(tdi.into()),
+            TypeData::GenericClassIndex(generic_class) => Some(
+                CsTypeTag::from_generic_class_index(generic_class, metadata.metadata),
+            ),
+            _ => None,
+        }
+    }
+
+    pub fn resolve_name(&mut self, ty: &ResolvedType) -> String {
+        match &ty.data {
+            ResolvedTypeData::Primitive(il2cpp_type_enum) => self.primitive_name(*il2cpp_type_enum),
+            ResolvedTypeData::Type(_) | ResolvedTypeData::GenericInst(_, _) => {
+                match self.resolve_tag(ty) {
+                    Some(tag) => self.tag_name(tag),
+                    None => format!("{IL2CPP_OBJECT_TYPE}*"),
+                }
+            }
+            ResolvedTypeData::Blacklisted(tag) => self.tag_name(*tag),
+            ResolvedTypeData::Array(_) => format!("{IL2CPP_ARRAY_TYPE}*"),
+            // shared generic code treats these as objects
+            ResolvedTypeData::GenericArg(_, _) | ResolvedTypeData::GenericMethodArg(_, _, _) => {
+                format!("{IL2CPP_OBJECT_TYPE}*")
+            }
+            ResolvedTypeData::Ptr(inner)
+            | ResolvedTypeData::ByRef(inner)
+            | ResolvedTypeData::ByRefConst(inner) => format!("{}*", self.resolve_name(inner)),
+        }
+    }
+
+    /// Type of `this` in instance methods, value types are passed unboxed by pointer
+    pub fn this_name(&mut self, tag: CsTypeTag) -> String {
+        let name = self.tag_name(tag);
+        match name.ends_with('*') {
+            true => name,
+            false => format!("{name}*"),
+        }
+    }
+
+    fn primitive_name(&self, il2cpp_type_enum: Il2CppTypeEnum) -> String {
+        match il2cpp_type_enum {
+            Il2CppTypeEnum::Void => "void".to_string(),
+            Il2CppTypeEnum::Boolean => "bool".to_string(),
+            Il2CppTypeEnum::Char => "uint16_t".to_string(),
+            Il2CppTypeEnum::I1 => "int8_t".to_string(),
+            Il2CppTypeEnum::U1 => "uint8_t".to_string(),
+            Il2CppTypeEnum::I2 => "int16_t".to_string(),
+            Il2CppTypeEnum::U2 => "uint16_t".to_string(),
+            Il2CppTypeEnum::I4 => "int32_t".to_string(),
+            Il2CppTypeEnum::U4 => "uint32_t".to_string(),
+            Il2CppTypeEnum::I8 => "int64_t".to_string(),
+            Il2CppTypeEnum::U8 => "uint64_t".to_string(),
+            Il2CppTypeEnum::R4 => "float".to_string(),
+            Il2CppTypeEnum::R8 => "double".to_string(),
+            Il2CppTypeEnum::I => "intptr_t".to_string(),
+            Il2CppTypeEnum::U => "uintptr_t".to_string(),
+            Il2CppTypeEnum::String => match self.type_name(self.cordl_metadata.string_tdi.into()) {
+                Some(name) => format!("{name}_o*"),
+                None => format!("{IL2CPP_OBJECT_TYPE}*"),
+            },
+            _ => format!("{IL2CPP_OBJECT_TYPE}*"),
+        }
+    }
+
+    fn tag_name(&mut self, tag: CsTypeTag) -> String {
+        let Some(cs_type) = self.collection.get_cs_type(tag) else {
+            return self.missing_tag_name(tag);
+        };
+
+        if cs_type.is_enum_type
+            && let Some(backing_type) = cs_type.enum_backing_type
+        {
+            return self.primitive_name(backing_type);
+        }
+
+        let name = self.type_name(tag).unwrap();
+        match cs_type.is_value_type || cs_type.is_enum_type {
+            true => format!("{name}_o"),
+            false => format!("{name}_o*"),
+        }
+    }
+
+    /// Types cordl didn't generate, e.g blacklisted or uninstantiated generics.
+    /// Value types get an opaque struct of the right size so calls still line up
+    fn missing_tag_name(&mut self, tag: CsTypeTag) -> String {
+        let metadata = self.cordl_metadata;
+        let td = &metadata.metadata.global_metadata.type_definitions[tag.get_tdi()];
+
+        if !td.is_value_type() && !td.is_enum_type() {
+            return format!("{IL2CPP_OBJECT_TYPE}*");
+        }
+
+        let size = get_sizeof_type(
+            tag.get_tdi(),
+            tag.get_generic_inst(metadata.metadata)
+                .map(|g| g.types.as_slice()),
+            metadata,
+        );
+        let name = match tag {
+            CsTypeTag::TypeDefinitionIndex(tdi) => format!(
+                "{}_{}",
+                sanitize_identifier(&td.full_name(metadata.metadata, true)),
+                tdi.index()
+            ),
+            CsTypeTag::GenericInstantiation(inst) => format!(
+                "{}_{}_{}",
+                sanitize_identifier(&td.full_name(metadata.metadata, true)),
+                inst.tdi.index(),
+                inst.inst
+            ),
+        };
+
+        let name = format!("{name}_o");
+        self.opaque_value_types.insert(name.clone(), size);
+        name
+    }
+
+    /// Parent of a type, if cordl generated it
+    pub fn parent(&self, cs_type: &CsType) -> Option<&'a CsType> {
+        let parent_tag = self.resolve_tag(cs_type.parent.as_ref()?)?;
+        self.collection.get_cs_type(parent_tag)
+    }
+}
@@ -0,0 +1,82 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use color_eyre::eyre::Result;
+
+use super::disasm_main::{DisasmData, write_python_data};
+
+const SCRIPT: &str = r#"
+import re
+
+from ghidra.app.cmd.function import ApplyFunctionSignatureCmd
+from ghidra.app.util.cparser.C import CParser
+from ghidra.program.model.symbol import SourceType
+
+# Ghidra rejects spaces in symbols, substitute like IDA's SN_NOCHECK does for generic names
+INVALID_SYMBOL_CHARS = re.compile(r"[\s<>,]")
+
+def symbol_name(name):
+    return INVALID_SYMBOL_CHARS.sub("_", name)
+
+dtm = currentProgram.getDataTypeManager()
+
+print("cordl: parsing declarations")
+parser = CParser(dtm, True, None)
+parser.parse(DECLARATIONS)
+
+BASE = currentProgram.getImageBase()
+listing = currentProgram.getListing()
+prototype_parser = CParser(dtm, False, None)
+
+monitor.initialize(len(FUNCTIONS) + len(LABELS))
+
+print("cordl: applying %d functions" % len(FUNCTIONS))
+for addr, name, prototype, comment in FUNCTIONS:
+    monitor.checkCanceled()
+    monitor.incrementProgress(1)
+    address = BASE.add(addr)
+    name = symbol_name(name)
+    try:
+        function = getFunctionAt(address)
+        if function is None:
+            function = createFunction(address, name)
+        if function is None:
+            continue
+        function.setName(name, SourceType.USER_DEFINED)
+        if comment is not None:
+            function.setComment(comment)
+
+        signature = prototype_parser.parse(prototype + ";")
+        ApplyFunctionSignatureCmd(address, signature, SourceType.USER_DEFINED).applyTo(currentProgram)
+    except Exception as e:
+        print("cordl: could not apply %s: %s" % (name, e))
+
+print("cordl: applying %d labels" % len(LABELS))
+for addr, name, comment in LABELS:
+    monitor.checkCanceled()
+    monitor.incrementProgress(1)
+    address = BASE.add(addr)
+    name = symbol_name(name)
+    try:
+        createLabel(address, name, True, SourceType.USER_DEFINED)
+        listing.setComment(address, listing.EOL_COMMENT, comment)
+    except Exception as e:
+        print("cordl: could not label %s: %s" % (name, e))
+
+print("cordl: done")
+"#;
+
+/// Ghidra Jython script, run from the Script Manager
+pub fn write_script(data: &DisasmData, file: &Path) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(file)?);
+
+    writeln!(writer, "# Generated by cordl")?;
+    writeln!(writer, "# @category cordl")?;
+    write_python_data(&mut writer, data)?;
+    writer.write_all(SCRIPT.as_bytes())?;
+
+    Ok(())
+}
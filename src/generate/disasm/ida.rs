@@ -0,0 +1,54 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use color_eyre::eyre::Result;
+
+use super::disasm_main::{DisasmData, write_python_data};
+
+const SCRIPT: &str = r#"
+import ida_name
+import idaapi
+import idc
+
+FLAGS = ida_name.SN_NOWARN | ida_name.SN_NOCHECK | ida_name.SN_FORCE
+
+print("cordl: parsing declarations")
+errors = idc.parse_decls(DECLARATIONS, idc.PT_SILENT)
+if errors:
+    print("cordl: %d declaration errors" % errors)
+
+BASE = idaapi.get_imagebase()
+
+print("cordl: applying %d functions" % len(FUNCTIONS))
+for addr, name, prototype, comment in FUNCTIONS:
+    ea = BASE + addr
+    idc.create_insn(ea)
+    idaapi.add_func(ea)
+    idc.set_name(ea, name, FLAGS)
+    if idc.SetType(ea, prototype + ";") is None:
+        print("cordl: could not apply %s" % prototype)
+    if comment is not None:
+        idc.set_func_cmt(ea, comment, 1)
+
+print("cordl: applying %d labels" % len(LABELS))
+for addr, name, comment in LABELS:
+    ea = BASE + addr
+    idc.set_name(ea, name, FLAGS)
+    idc.set_cmt(ea, comment, 1)
+
+print("cordl: done")
+"#;
+
+/// IDAPython script, run with File > Script file
+pub fn write_script(data: &DisasmData, file: &Path) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(file)?);
+
+    writeln!(writer, "# Generated by cordl")?;
+    write_python_data(&mut writer, data)?;
+    writer.write_all(SCRIPT.as_bytes())?;
+
+    Ok(())
+}
@@ -0,0 +1,6 @@
+pub mod disasm_dwarf;
+pub mod disasm_header;
+pub mod disasm_main;
+pub mod disasm_name_resolver;
+mod ghidra;
+mod ida;
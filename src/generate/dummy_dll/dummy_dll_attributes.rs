@@ -0,0 +1,354 @@
+//! `Il2CppDummyDll.dll`, the attributes annotating members with their native addresses and offsets,
+//! and the values of the game's own attributes
+
+use crate::{
+    data::custom_attributes::{AttributeValue, ElementType},
+    generate::type_extensions::{
+        FIELD_ATTRIBUTE_PUBLIC, METHOD_ATTRIBUTE_HIDE_BY_SIG, METHOD_ATTRIBUTE_PUBLIC,
+        METHOD_ATTRIBUTE_RT_SPECIAL_NAME, METHOD_ATTRIBUTE_SPECIAL_NAME,
+        TYPE_ATTRIBUTE_BEFORE_FIELD_INIT, TYPE_ATTRIBUTE_PUBLIC, TYPE_ATTRIBUTE_SEALED,
+    },
+};
+
+use super::{
+    dummy_dll_metadata::{
+        Cell, CodedIndex, FIELD, METHOD_DEF, MetadataBuilder, PARAM, TYPE_DEF, Token,
+        write_compressed,
+    },
+    dummy_dll_pe::STUB_BODY_RVA,
+};
+
+pub const ATTRIBUTES_ASSEMBLY: &str = "Il2CppDummyDll";
+pub const ATTRIBUTES_NAMESPACE: &str = "Il2CppDummyDll";
+
+pub const ADDRESS_ATTRIBUTE: &str = "AddressAttribute";
+pub const FIELD_OFFSET_ATTRIBUTE: &str = "FieldOffsetAttribute";
+
+/// Attribute types and their string fields, same as Il2CppDumper so existing tooling keeps working
+const ATTRIBUTES: &[(&str, &[&str])] = &[
+    (ADDRESS_ATTRIBUTE, &["RVA", "Offset", "VA", "Slot"]),
+    (FIELD_OFFSET_ATTRIBUTE, &["Offset"]),
+];
+
+const SERIALIZATION_TYPE_SZARRAY: u8 = 0x1D;
+const SERIALIZATION_TYPE_TYPE: u8 = 0x50;
+const SERIALIZATION_TYPE_TAGGED_OBJECT: u8 = 0x51;
+const SERIALIZATION_TYPE_FIELD: u8 = 0x53;
+const SERIALIZATION_TYPE_PROPERTY: u8 = 0x54;
+const SERIALIZATION_TYPE_ENUM: u8 = 0x55;
+const NULL_SER_STRING: u8 = 0xFF;
+
+/// Field or property set by one of the game's attributes
+pub struct NamedValue<'a> {
+    pub property: bool,
+    pub name: &'a str,
+    /// Declared as `object`
+    pub boxed: bool,
+    pub value: &'a AttributeValue,
+}
+
+/// `instance void .ctor()`
+pub const DEFAULT_CTOR_SIGNATURE: [u8; 3] = [0x20, 0x00, 0x01];
+
+const CTOR_FLAGS: u16 = METHOD_ATTRIBUTE_PUBLIC
+    | METHOD_ATTRIBUTE_HIDE_BY_SIG
+    | METHOD_ATTRIBUTE_SPECIAL_NAME
+    | METHOD_ATTRIBUTE_RT_SPECIAL_NAME;
+
+/// Metadata of the attributes assembly, `mscorlib` is the assembly defining `System.Attribute`
+pub fn make_attributes_metadata(mscorlib: &str) -> Vec<u8> {
+    let mut builder = MetadataBuilder::default();
+    builder.add_assembly(ATTRIBUTES_ASSEMBLY);
+
+    let mscorlib = builder.add_assembly_ref(mscorlib);
+    let attribute = builder.add_type_ref(mscorlib, "System", "Attribute");
+
+    let module_name = builder.string("<Module>");
+    builder.add_row(
+        TYPE_DEF,
+        vec![
+            Cell::U32(0),
+            Cell::String(module_name),
+            Cell::String(0),
+            Cell::Coded(CodedIndex::TypeDefOrRef, Token::NULL),
+            Cell::Index(FIELD, 1),
+            Cell::Index(METHOD_DEF, 1),
+        ],
+    );
+
+    let namespace = builder.string(ATTRIBUTES_NAMESPACE);
+    let string_signature = builder.blob(&[0x06, 0x0E]);
+    let ctor_name = builder.string(".ctor");
+    let ctor_signature = builder.blob(&DEFAULT_CTOR_SIGNATURE);
+
+    for (name, fields) in ATTRIBUTES {
+        let name = builder.string(name);
+        let field_list = builder.next_row(FIELD);
+        let method_list = builder.next_row(METHOD_DEF);
+        builder.add_row(
+            TYPE_DEF,
+            vec![
+                Cell::U32(
+                    TYPE_ATTRIBUTE_PUBLIC
+                        | TYPE_ATTRIBUTE_SEALED
+                        | TYPE_ATTRIBUTE_BEFORE_FIELD_INIT,
+                ),
+                Cell::String(name),
+                Cell::String(namespace),
+                Cell::Coded(CodedIndex::TypeDefOrRef, attribute),
+                Cell::Index(FIELD, field_list),
+                Cell::Index(METHOD_DEF, method_list),
+            ],
+        );
+
+        for field in *fields {
+            let field = builder.string(field);
+            builder.add_row(
+                FIELD,
+                vec![
+                    Cell::U16(FIELD_ATTRIBUTE_PUBLIC),
+                    Cell::String(field),
+                    Cell::Blob(string_signature),
+                ],
+            );
+        }
+
+        let param_list = builder.next_row(PARAM);
+        builder.add_row(
+            METHOD_DEF,
+            vec![
+                Cell::U32(STUB_BODY_RVA),
+                Cell::U16(0),
+                Cell::U16(CTOR_FLAGS),
+                Cell::String(ctor_name),
+                Cell::Blob(ctor_signature),
+                Cell::Index(PARAM, param_list),
+            ],
+        );
+    }
+
+    builder.finish()
+}
+
+/// Custom attribute value setting string fields, with no constructor arguments (II.23.3)
+pub fn named_fields_blob(fields: &[(&str, String)]) -> Vec<u8> {
+    let mut blob = vec![0x01, 0x00];
+    blob.extend_from_slice(&(fields.len() as u16).to_le_bytes());
+
+    for (name, value) in fields {
+        // FIELD, of type string
+        blob.extend_from_slice(&[0x53, 0x0E]);
+        write_ser_string(&mut blob, name);
+        write_ser_string(&mut blob, value);
+    }
+
+    blob
+}
+
+fn write_ser_string(blob: &mut Vec<u8>, s: &str) {
+    write_compressed(blob, s.len() as u32);
+    blob.extend_from_slice(s.as_bytes());
+}
+
+/// Custom attribute value of one of the game's attributes (II.23.3). Arguments are paired with
+/// whether their parameter is an `object`, `type_name` gives the assembly qualified name of a type
+pub fn attribute_blob(
+    arguments: &[(bool, &AttributeValue)],
+    named: &[NamedValue],
+    type_name: &impl Fn(usize) -> String,
+) -> Vec<u8> {
+    let mut blob = vec![0x01, 0x00];
+    for (boxed, value) in arguments {
+        write_fixed_arg(&mut blob, value, *boxed, type_name);
+    }
+
+    blob.extend_from_slice(&(named.len() as u16).to_le_bytes());
+    for named in named {
+        blob.push(match named.property {
+            true => SERIALIZATION_TYPE_PROPERTY,
+            false => SERIALIZATION_TYPE_FIELD,
+        });
+        match named.boxed {
+            true => blob.push(SERIALIZATION_TYPE_TAGGED_OBJECT),
+            false => write_value_type(&mut blob, named.value, type_name),
+        }
+        write_ser_string(&mut blob, named.name);
+        write_fixed_arg(&mut blob, named.value, named.boxed, type_name);
+    }
+
+    blob
+}
+
+/// Boxed values start with their type
+fn write_fixed_arg(
+    blob: &mut Vec<u8>,
+    value: &AttributeValue,
+    boxed: bool,
+    type_name: &impl Fn(usize) -> String,
+) {
+    if boxed {
+        write_value_type(blob, value, type_name);
+    }
+
+    match value {
+        AttributeValue::Null | AttributeValue::String(None) | AttributeValue::Type(None) => {
+            blob.push(NULL_SER_STRING)
+        }
+        AttributeValue::Bool(b) => blob.push(*b as u8),
+        AttributeValue::Char(c) => blob.extend_from_slice(&c.to_le_bytes()),
+        AttributeValue::I8(x) => blob.extend_from_slice(&x.to_le_bytes()),
+        AttributeValue::U8(x) => blob.extend_from_slice(&x.to_le_bytes()),
+        AttributeValue::I16(x) => blob.extend_from_slice(&x.to_le_bytes()),
+        AttributeValue::U16(x) => blob.extend_from_slice(&x.to_le_bytes()),
+        AttributeValue::I32(x) => blob.extend_from_slice(&x.to_le_bytes()),
+        AttributeValue::U32(x) => blob.extend_from_slice(&x.to_le_bytes()),
+        AttributeValue::I64(x) => blob.extend_from_slice(&x.to_le_bytes()),
+        AttributeValue::U64(x) => blob.extend_from_slice(&x.to_le_bytes()),
+        AttributeValue::F32(x) => blob.extend_from_slice(&x.to_le_bytes()),
+        AttributeValue::F64(x) => blob.extend_from_slice(&x.to_le_bytes()),
+        AttributeValue::String(Some(s)) => write_ser_string(blob, s),
+        AttributeValue::Type(Some(type_index)) => write_ser_string(blob, &type_name(*type_index)),
+        AttributeValue::Enum(_, value) => write_fixed_arg(blob, value, false, type_name),
+        AttributeValue::Array(_, None) => blob.extend_from_slice(&u32::MAX.to_le_bytes()),
+        AttributeValue::Array(element, Some(values)) => {
+            blob.extend_from_slice(&(values.len() as u32).to_le_bytes());
+            for value in values {
+                write_fixed_arg(blob, value, *element == ElementType::Object, type_name);
+            }
+        }
+    }
+}
+
+/// FieldOrPropType of a value, null objects are written as null strings
+fn write_value_type(
+    blob: &mut Vec<u8>,
+    value: &AttributeValue,
+    type_name: &impl Fn(usize) -> String,
+) {
+    let element = match value {
+        AttributeValue::Null | AttributeValue::String(_) => ElementType::String,
+        AttributeValue::Bool(_) => ElementType::Bool,
+        AttributeValue::Char(_) => ElementType::Char,
+        AttributeValue::I8(_) => ElementType::I8,
+        AttributeValue::U8(_) => ElementType::U8,
+        AttributeValue::I16(_) => ElementType::I16,
+        AttributeValue::U16(_) => ElementType::U16,
+        AttributeValue::I32(_) => ElementType::I32,
+        AttributeValue::U32(_) => ElementType::U32,
+        AttributeValue::I64(_) => ElementType::I64,
+        AttributeValue::U64(_) => ElementType::U64,
+        AttributeValue::F32(_) => ElementType::F32,
+        AttributeValue::F64(_) => ElementType::F64,
+        AttributeValue::Type(_) => ElementType::Type,
+        AttributeValue::Enum(type_index, _) => ElementType::Enum(*type_index),
+        AttributeValue::Array(element, _) => {
+            blob.push(SERIALIZATION_TYPE_SZARRAY);
+            *element
+        }
+    };
+
+    write_element_type(blob, element, type_name);
+}
+
+fn write_element_type(
+    blob: &mut Vec<u8>,
+    element: ElementType,
+    type_name: &impl Fn(usize) -> String,
+) {
+    blob.push(match element {
+        ElementType::Bool => 0x02,
+        ElementType::Char => 0x03,
+        ElementType::I8 => 0x04,
+        ElementType::U8 => 0x05,
+        ElementType::I16 => 0x06,
+        ElementType::U16 => 0x07,
+        ElementType::I32 => 0x08,
+        ElementType::U32 => 0x09,
+        ElementType::I64 => 0x0A,
+        ElementType::U64 => 0x0B,
+        ElementType::F32 => 0x0C,
+        ElementType::F64 => 0x0D,
+        ElementType::String => 0x0E,
+        ElementType::Type => SERIALIZATION_TYPE_TYPE,
+        ElementType::Object => SERIALIZATION_TYPE_TAGGED_OBJECT,
+        ElementType::Enum(type_index) => {
+            blob.push(SERIALIZATION_TYPE_ENUM);
+            write_ser_string(blob, &type_name(type_index));
+            return;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_name(type_index: usize) -> String {
+        format!("T{type_index}, A")
+    }
+
+    #[test]
+    fn writes_arguments_by_parameter_type() {
+        let enum_value = AttributeValue::Enum(1, Box::new(AttributeValue::I32(2)));
+        let boxed_type = AttributeValue::Type(Some(3));
+        let objects = AttributeValue::Array(
+            ElementType::Object,
+            Some(vec![AttributeValue::U8(4), AttributeValue::Null]),
+        );
+
+        let blob = attribute_blob(
+            &[(false, &enum_value), (true, &boxed_type), (false, &objects)],
+            &[],
+            &type_name,
+        );
+
+        assert_eq!(
+            blob,
+            [
+                &[0x01, 0x00][..],
+                // enums are written as their underlying value
+                &[0x02, 0x00, 0x00, 0x00],
+                &[0x50, 0x05, b'T', b'3', b',', b' ', b'A'],
+                &[0x02, 0x00, 0x00, 0x00, 0x05, 0x04, 0x0E, 0xFF],
+                &[0x00, 0x00],
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn writes_named_members_with_their_type() {
+        let values = AttributeValue::Array(ElementType::Enum(1), Some(vec![]));
+        let flag = AttributeValue::Bool(true);
+
+        let blob = attribute_blob(
+            &[],
+            &[
+                NamedValue {
+                    property: false,
+                    name: "F",
+                    boxed: false,
+                    value: &values,
+                },
+                NamedValue {
+                    property: true,
+                    name: "P",
+                    boxed: true,
+                    value: &flag,
+                },
+            ],
+            &type_name,
+        );
+
+        assert_eq!(
+            blob,
+            [
+                &[0x01, 0x00, 0x02, 0x00][..],
+                &[0x53, 0x1D, 0x55, 0x05, b'T', b'1', b',', b' ', b'A'],
+                &[0x01, b'F', 0x00, 0x00, 0x00, 0x00],
+                &[0x54, 0x51, 0x01, b'P', 0x02, 0x01],
+            ]
+            .concat()
+        );
+    }
+}
@@ -0,0 +1,1142 @@
+use std::{collections::HashMap, fs, ops::Range, path::Path};
+
+use brocolib::{
+    global_metadata::{
+        Il2CppGenericParameter, Il2CppMethodDefinition, Il2CppTypeDefinition, MethodIndex,
+        TypeDefinitionIndex,
+    },
+    runtime_metadata::{Il2CppType, Il2CppTypeEnum, TypeData},
+};
+use color_eyre::eyre::Result;
+use itertools::Itertools;
+use log::{info, warn};
+
+use crate::{
+    data::{
+        custom_attributes::{
+            ASSEMBLY_TOKEN, CustomAttribute, EVENT_TOKEN, FIELD_DEF_TOKEN, METHOD_DEF_TOKEN,
+            NamedArgument, PARAM_DEF_TOKEN, PROPERTY_TOKEN, TYPE_DEF_TOKEN,
+        },
+        pinvoke::{CallingConvention, CharSet},
+    },
+    generate::{
+        cs_context_collection::TypeContextCollection,
+        cs_members::{CsPInvoke, CsValue},
+        cs_type::CsType,
+        dumper::dumper_main::FileOffsets,
+        metadata::{CordlMetadata, Il2cppFullName},
+        type_extensions::{
+            METHOD_ATTRIBUTE_ABSTRACT, METHOD_ATTRIBUTE_PINVOKE_IMPL, METHOD_ATTRIBUTE_STATIC,
+            METHOD_IMPL_ATTRIBUTE_CODE_TYPE_MASK, METHOD_IMPL_ATTRIBUTE_INTERNAL_CALL,
+            TYPE_ATTRIBUTE_EXPLICIT_LAYOUT, TypeDefinitionExtensions,
+        },
+    },
+};
+
+use super::{
+    dummy_dll_attributes::{
+        ADDRESS_ATTRIBUTE, ATTRIBUTES_ASSEMBLY, ATTRIBUTES_NAMESPACE, DEFAULT_CTOR_SIGNATURE,
+        FIELD_OFFSET_ATTRIBUTE, NamedValue, attribute_blob, make_attributes_metadata,
+        named_fields_blob,
+    },
+    dummy_dll_metadata::{
+        ASSEMBLY, CLASS_LAYOUT, CONSTANT, Cell, CodedIndex, EVENT, EVENT_MAP, FIELD, FIELD_LAYOUT,
+        INTERFACE_IMPL, METHOD_DEF, METHOD_SEMANTICS, MetadataBuilder, NESTED_CLASS, PARAM,
+        PROPERTY, PROPERTY_MAP, TYPE_DEF, TYPE_SPEC, Token, write_compressed,
+    },
+    dummy_dll_pe::{STUB_BODY_RVA, write_pe},
+};
+
+const COMPILER_GENERATED_ATTRIBUTE: &str = "CompilerGeneratedAttribute";
+
+const SIG_HAS_THIS: u8 = 0x20;
+const SIG_GENERIC: u8 = 0x10;
+const SIG_FIELD: u8 = 0x06;
+const SIG_PROPERTY: u8 = 0x08;
+
+const ELEMENT_TYPE_BYREF: u8 = 0x10;
+const ELEMENT_TYPE_CLASS: u8 = 0x12;
+const ELEMENT_TYPE_GENERICINST: u8 = 0x15;
+const ELEMENT_TYPE_OBJECT: u8 = 0x1C;
+
+const SEMANTICS_SETTER: u16 = 0x1;
+const SEMANTICS_GETTER: u16 = 0x2;
+const SEMANTICS_ADD_ON: u16 = 0x8;
+const SEMANTICS_REMOVE_ON: u16 = 0x10;
+const SEMANTICS_FIRE: u16 = 0x20;
+
+/// `PInvokeAttributes` of ImplMap rows (II.23.1.8)
+const PINVOKE_NO_MANGLE: u16 = 0x1;
+const PINVOKE_CHAR_SET_ANSI: u16 = 0x2;
+const PINVOKE_CHAR_SET_UNICODE: u16 = 0x4;
+const PINVOKE_CALL_CONV_WINAPI: u16 = 0x100;
+const PINVOKE_CALL_CONV_CDECL: u16 = 0x200;
+const PINVOKE_CALL_CONV_STDCALL: u16 = 0x300;
+const PINVOKE_CALL_CONV_THISCALL: u16 = 0x400;
+const PINVOKE_CALL_CONV_FASTCALL: u16 = 0x500;
+
+/// Type definition ranges and assembly names, indexed like `global_metadata.images`
+struct Images {
+    ranges: Vec<Range<u32>>,
+    names: Vec<String>,
+}
+
+impl Images {
+    fn new(metadata: &CordlMetadata) -> Self {
+        let md = metadata.metadata;
+        let (ranges, names): (Vec<_>, Vec<_>) = md
+            .global_metadata
+            .images
+            .as_vec()
+            .iter()
+            .map(|image| {
+                let start = image.type_start.index();
+                let name = image.name(md);
+                (
+                    start..start + image.type_count,
+                    name.strip_suffix(".dll").unwrap_or(name).to_string(),
+                )
+            })
+            .unzip();
+
+        Images { ranges, names }
+    }
+
+    fn image_of(&self, tdi: TypeDefinitionIndex) -> Option<usize> {
+        self.ranges.iter().position(|r| r.contains(&tdi.index()))
+    }
+}
+
+/// One reference assembly per image in `./DummyDll`, plus `Il2CppDummyDll.dll`
+/// defining the attributes that carry method addresses and field offsets.
+/// The game's own attributes are only reconstructed from metadata v29 on
+pub fn run_dummy_dll(collection: &TypeContextCollection, metadata: &CordlMetadata) -> Result<()> {
+    let out_dir = Path::new("./DummyDll");
+    println!("Writing {out_dir:?}");
+    fs::create_dir_all(out_dir)?;
+
+    let images = Images::new(metadata);
+    let offsets = FileOffsets::new(metadata.elf_data)?;
+
+    let mscorlib = metadata
+        .name_to_tdi
+        .get(&Il2cppFullName("System", "Attribute"))
+        .and_then(|tdi| images.image_of(*tdi))
+        .map_or("mscorlib", |i| images.names[i].as_str());
+    fs::write(
+        out_dir.join(format!("{ATTRIBUTES_ASSEMBLY}.dll")),
+        write_pe(&make_attributes_metadata(mscorlib))?,
+    )?;
+
+    for image_index in 0..images.names.len() {
+        let name = &images.names[image_index];
+        info!("Writing assembly {name}");
+
+        let emitter = AssemblyEmitter::new(metadata, collection, &images, &offsets, image_index);
+        fs::write(
+            out_dir.join(format!("{name}.dll")),
+            write_pe(&emitter.emit())?,
+        )?;
+    }
+
+    Ok(())
+}
+
+struct AssemblyEmitter<'a, 'b> {
+    metadata: &'a CordlMetadata<'b>,
+    collection: &'a TypeContextCollection,
+    images: &'a Images,
+    offsets: &'a FileOffsets,
+    image_index: usize,
+    /// Images normally start with `<Module>`, which must be the first row
+    needs_module_type: bool,
+
+    builder: MetadataBuilder,
+    assembly_refs: HashMap<String, Token>,
+    module_refs: HashMap<String, Token>,
+    type_refs: HashMap<TypeDefinitionIndex, Token>,
+    type_specs: HashMap<Vec<u8>, Token>,
+    attribute_ctors: HashMap<&'static str, Option<Token>>,
+    /// Constructors of the game's attributes
+    constructors: HashMap<MethodIndex, Token>,
+    /// `MethodDef` row of the first method of each type, types are emitted in order
+    method_rows: Vec<u32>,
+}
+
+impl<'a, 'b> AssemblyEmitter<'a, 'b> {
+    fn new(
+        metadata: &'a CordlMetadata<'b>,
+        collection: &'a TypeContextCollection,
+        images: &'a Images,
+        offsets: &'a FileOffsets,
+        image_index: usize,
+    ) -> Self {
+        let md = metadata.metadata;
+        let first_type = images.ranges[image_index].start;
+        let needs_module_type = images.ranges[image_index].is_empty()
+            || md.global_metadata.type_definitions[TypeDefinitionIndex::new(first_type)].name(md)
+                != "<Module>";
+
+        let mut next_row = 1;
+        let method_rows = images.ranges[image_index]
+            .clone()
+            .map(|tdi| {
+                let row = next_row;
+                next_row += md.global_metadata.type_definitions[TypeDefinitionIndex::new(tdi)]
+                    .method_count as u32;
+                row
+            })
+            .collect();
+
+        AssemblyEmitter {
+            metadata,
+            collection,
+            images,
+            offsets,
+            image_index,
+            needs_module_type,
+            builder: MetadataBuilder::default(),
+            assembly_refs: HashMap::new(),
+            module_refs: HashMap::new(),
+            type_refs: HashMap::new(),
+            type_specs: HashMap::new(),
+            attribute_ctors: HashMap::new(),
+            constructors: HashMap::new(),
+            method_rows,
+        }
+    }
+
+    fn emit(mut self) -> Vec<u8> {
+        self.builder
+            .add_assembly(&self.images.names[self.image_index]);
+        self.add_game_attributes(Token::new(ASSEMBLY, 1), ASSEMBLY_TOKEN);
+
+        if self.needs_module_type {
+            let name = self.builder.string("<Module>");
+            self.builder.add_row(
+                TYPE_DEF,
+                vec![
+                    Cell::U32(0),
+                    Cell::String(name),
+                    Cell::String(0),
+                    Cell::Coded(CodedIndex::TypeDefOrRef, Token::NULL),
+                    Cell::Index(FIELD, 1),
+                    Cell::Index(METHOD_DEF, 1),
+                ],
+            );
+        }
+
+        for tdi in self.images.ranges[self.image_index].clone() {
+            self.emit_type(TypeDefinitionIndex::new(tdi));
+        }
+
+        self.builder.finish()
+    }
+
+    fn emit_type(&mut self, tdi: TypeDefinitionIndex) {
+        let metadata = self.metadata;
+        let md = metadata.metadata;
+        let td = &md.global_metadata.type_definitions[tdi];
+        let collection = self.collection;
+        let cs_type = collection.get_cs_type(tdi.into());
+        let declaring = metadata.child_to_parent_map.get(&tdi);
+
+        let extends = match td.parent_index {
+            u32::MAX => Token::NULL,
+            parent => self.type_def_or_ref_of(parent as usize),
+        };
+        let name = self.builder.string(td.name(md));
+        // nested types are scoped by their declaring type instead
+        let namespace = match declaring {
+            Some(_) => 0,
+            None => self.builder.string(td.namespace(md)),
+        };
+        let field_list = self.builder.next_row(FIELD);
+        let method_list = self.builder.next_row(METHOD_DEF);
+        let type_token = self.builder.add_row(
+            TYPE_DEF,
+            vec![
+                Cell::U32(td.flags),
+                Cell::String(name),
+                Cell::String(namespace),
+                Cell::Coded(CodedIndex::TypeDefOrRef, extends),
+                Cell::Index(FIELD, field_list),
+                Cell::Index(METHOD_DEF, method_list),
+            ],
+        );
+        debug_assert_eq!(type_token, self.type_def_or_ref(tdi));
+
+        if let Some(declaring) = declaring {
+            let enclosing = self.type_def_or_ref(declaring.tdi);
+            self.builder.add_row(
+                NESTED_CLASS,
+                vec![
+                    Cell::Index(TYPE_DEF, type_token.row),
+                    Cell::Index(TYPE_DEF, enclosing.row),
+                ],
+            );
+        }
+
+        for &interface in td.interfaces(md) {
+            let interface = self.type_def_or_ref_of(interface as usize);
+            self.builder.add_row(
+                INTERFACE_IMPL,
+                vec![
+                    Cell::Index(TYPE_DEF, type_token.row),
+                    Cell::Coded(CodedIndex::TypeDefOrRef, interface),
+                ],
+            );
+        }
+
+        if td.generic_container_index.is_valid() {
+            let generic_params = td.generic_container(md).generic_parameters(md);
+            self.emit_generic_params(type_token, generic_params);
+        }
+
+        // the game's attributes already mark most generated types
+        let game_token = TYPE_DEF_TOKEN | td.token.rid() as u32;
+        let marked = metadata
+            .custom_attributes
+            .get(self.image_index, game_token)
+            .iter()
+            .any(|a| self.attribute_name(a) == COMPILER_GENERATED_ATTRIBUTE);
+        if cs_type.is_some_and(|t| t.is_compiler_generated) && !marked {
+            self.add_attribute(type_token, COMPILER_GENERATED_ATTRIBUTE, &[]);
+        }
+        self.add_game_attributes(type_token, game_token);
+
+        self.emit_fields(td, cs_type);
+        self.emit_methods(td, cs_type);
+        self.emit_properties(td, type_token, method_list);
+        self.emit_events(td, type_token, method_list);
+
+        if td.flags & TYPE_ATTRIBUTE_EXPLICIT_LAYOUT != 0
+            && let Some(size_info) = cs_type.and_then(|t| t.size_info.as_ref())
+        {
+            let header = self.object_header(td);
+            self.builder.add_row(
+                CLASS_LAYOUT,
+                vec![
+                    Cell::U16(cs_type.and_then(|t| t.packing).unwrap_or_default() as u16),
+                    Cell::U32(size_info.instance_size.saturating_sub(header)),
+                    Cell::Index(TYPE_DEF, type_token.row),
+                ],
+            );
+        }
+    }
+
+    fn emit_fields(&mut self, td: &Il2CppTypeDefinition, cs_type: Option<&CsType>) {
+        let metadata = self.metadata;
+        let md = metadata.metadata;
+        let explicit_layout = td.flags & TYPE_ATTRIBUTE_EXPLICIT_LAYOUT != 0;
+        let header = self.object_header(td);
+
+        for (i, field) in td.fields(md).iter().enumerate() {
+            let field_type = &metadata.metadata_registration.types[field.type_index as usize];
+            // fields of the model line up with the definition's
+            let cs_field = cs_type.and_then(|t| t.fields.get(i));
+
+            let name = self.builder.string(field.name(md));
+            let mut signature = vec![SIG_FIELD];
+            self.encode_type(&mut signature, field.type_index as usize);
+            let signature = self.builder.blob(&signature);
+            let field_token = self.builder.add_row(
+                FIELD,
+                vec![
+                    Cell::U16(field_type.attrs),
+                    Cell::String(name),
+                    Cell::Blob(signature),
+                ],
+            );
+            self.add_game_attributes(field_token, FIELD_DEF_TOKEN | field.token.rid() as u32);
+
+            if let Some(value) = cs_field.and_then(|f| f.value.as_ref()) {
+                self.add_constant(field_token, value);
+            }
+
+            let Some(offset) = cs_field.and_then(|f| f.offset) else {
+                continue;
+            };
+            if explicit_layout {
+                self.builder.add_row(
+                    FIELD_LAYOUT,
+                    vec![
+                        Cell::U32(offset.saturating_sub(header)),
+                        Cell::Index(FIELD, field_token.row),
+                    ],
+                );
+            }
+            self.add_attribute(
+                field_token,
+                FIELD_OFFSET_ATTRIBUTE,
+                &[("Offset", format!("0x{offset:X}"))],
+            );
+        }
+    }
+
+    fn emit_methods(&mut self, td: &Il2CppTypeDefinition, cs_type: Option<&CsType>) {
+        let metadata = self.metadata;
+        let md = metadata.metadata;
+
+        for (i, method) in td.methods(md).iter().enumerate() {
+            let method_index = MethodIndex::new(td.method_start.index() + i as u32);
+            // the model skips static constructors
+            let cs_method = cs_type.and_then(|t| {
+                t.methods
+                    .iter()
+                    .find(|m| m.method_index == method_index && m.generic_instatiation.is_none())
+            });
+
+            // ImplMap rows need the library, without it the method gets a body instead
+            let pinvoke = cs_method
+                .and_then(|m| m.pinvoke.as_ref())
+                .filter(|p| p.library.is_some());
+            let flags = match pinvoke {
+                Some(_) => method.flags,
+                None => method.flags & !METHOD_ATTRIBUTE_PINVOKE_IMPL,
+            };
+            let has_body = flags & (METHOD_ATTRIBUTE_ABSTRACT | METHOD_ATTRIBUTE_PINVOKE_IMPL) == 0
+                && method.iflags
+                    & (METHOD_IMPL_ATTRIBUTE_CODE_TYPE_MASK | METHOD_IMPL_ATTRIBUTE_INTERNAL_CALL)
+                    == 0;
+
+            let name = self.builder.string(method.name(md));
+            let signature = self.method_signature(method);
+            let signature = self.builder.blob(&signature);
+            let param_list = self.builder.next_row(PARAM);
+            let method_token = self.builder.add_row(
+                METHOD_DEF,
+                vec![
+                    Cell::U32(if has_body { STUB_BODY_RVA } else { 0 }),
+                    Cell::U16(method.iflags),
+                    Cell::U16(flags),
+                    Cell::String(name),
+                    Cell::Blob(signature),
+                    Cell::Index(PARAM, param_list),
+                ],
+            );
+            if let Some(pinvoke) = pinvoke {
+                self.emit_impl_map(method_token, pinvoke, method.name(md));
+            }
+            self.add_game_attributes(method_token, METHOD_DEF_TOKEN | method.token.rid() as u32);
+
+            for (pi, param) in method.parameters(md).iter().enumerate() {
+                let param_type = &metadata.metadata_registration.types[param.type_index as usize];
+                let name = self.builder.string(param.name(md));
+                let param_token = self.builder.add_row(
+                    PARAM,
+                    vec![
+                        Cell::U16(param_type.attrs),
+                        Cell::U16(pi as u16 + 1),
+                        Cell::String(name),
+                    ],
+                );
+                self.add_game_attributes(param_token, PARAM_DEF_TOKEN | param.token.rid() as u32);
+
+                let def_value = cs_method
+                    .and_then(|m| m.parameters.get(pi))
+                    .and_then(|p| p.def_value.as_ref());
+                if let Some(value) = def_value {
+                    self.add_constant(param_token, value);
+                }
+            }
+
+            if method.generic_container_index.is_valid()
+                && let Some(container) = method.generic_container(md)
+            {
+                self.emit_generic_params(method_token, container.generic_parameters(md));
+            }
+
+            let addrs = metadata
+                .method_calculations
+                .get(&method_index)
+                .map(|c| c.addrs)
+                .filter(|a| *a != 0x0);
+            if let Some(addrs) = addrs {
+                let offset = self.offsets.file_offset(addrs).unwrap_or(addrs);
+                let mut fields = vec![
+                    ("RVA", format!("0x{addrs:X}")),
+                    ("Offset", format!("0x{offset:X}")),
+                    ("VA", format!("0x{addrs:X}")),
+                ];
+                if method.slot != u16::MAX {
+                    fields.push(("Slot", method.slot.to_string()));
+                }
+                self.add_attribute(method_token, ADDRESS_ATTRIBUTE, &fields);
+            }
+        }
+    }
+
+    /// `method_list` is the row of the type's first method
+    fn emit_properties(&mut self, td: &Il2CppTypeDefinition, type_token: Token, method_list: u32) {
+        let md = self.metadata.metadata;
+        if td.property_count == 0 {
+            return;
+        }
+
+        let property_list = self.builder.next_row(PROPERTY);
+        self.builder.add_row(
+            PROPERTY_MAP,
+            vec![
+                Cell::Index(TYPE_DEF, type_token.row),
+                Cell::Index(PROPERTY, property_list),
+            ],
+        );
+
+        let method_row = |method_index: MethodIndex| {
+            method_list + method_index.index() - td.method_start.index()
+        };
+
+        for prop in td.properties(md) {
+            let getter = (prop.get != u32::MAX).then(|| prop.get_method_index(td));
+            let setter = (prop.set != u32::MAX).then(|| prop.set_method_index(td));
+            let Some(accessor) = getter.or(setter) else {
+                continue;
+            };
+            let accessor = &md.global_metadata.methods[accessor];
+
+            // indexers take the same parameters as their getter, the setter adds the value
+            let params = accessor.parameters(md);
+            let (ty, index_params) = match getter {
+                Some(_) => (accessor.return_type as usize, params),
+                None => match params.split_last() {
+                    Some((value, index_params)) => (value.type_index as usize, index_params),
+                    None => continue,
+                },
+            };
+
+            let mut signature = vec![match accessor.flags & METHOD_ATTRIBUTE_STATIC {
+                0 => SIG_PROPERTY | SIG_HAS_THIS,
+                _ => SIG_PROPERTY,
+            }];
+            write_compressed(&mut signature, index_params.len() as u32);
+            self.encode_type(&mut signature, ty);
+            for param in index_params {
+                self.encode_type(&mut signature, param.type_index as usize);
+            }
+
+            let name = self.builder.string(prop.name(md));
+            let signature = self.builder.blob(&signature);
+            let property_token = self.builder.add_row(
+                PROPERTY,
+                vec![
+                    Cell::U16(prop.attrs as u16),
+                    Cell::String(name),
+                    Cell::Blob(signature),
+                ],
+            );
+            self.add_game_attributes(property_token, PROPERTY_TOKEN | prop.token.rid() as u32);
+
+            let accessors = [(SEMANTICS_GETTER, getter), (SEMANTICS_SETTER, setter)];
+            for (semantics, method) in accessors {
+                let Some(method) = method else {
+                    continue;
+                };
+                self.builder.add_row(
+                    METHOD_SEMANTICS,
+                    vec![
+                        Cell::U16(semantics),
+                        Cell::Index(METHOD_DEF, method_row(method)),
+                        Cell::Coded(CodedIndex::HasSemantics, property_token),
+                    ],
+                );
+            }
+        }
+    }
+
+    fn emit_events(&mut self, td: &Il2CppTypeDefinition, type_token: Token, method_list: u32) {
+        let metadata = self.metadata;
+        let md = metadata.metadata;
+        if td.event_count == 0 {
+            return;
+        }
+
+        let event_list = self.builder.next_row(EVENT);
+        self.builder.add_row(
+            EVENT_MAP,
+            vec![
+                Cell::Index(TYPE_DEF, type_token.row),
+                Cell::Index(EVENT, event_list),
+            ],
+        );
+
+        for event in td.events(md) {
+            let event_type = &metadata.metadata_registration.types[event.type_index as usize];
+            let delegate = self.type_def_or_ref_of(event.type_index as usize);
+            let name = self.builder.string(event.name(md));
+            let event_token = self.builder.add_row(
+                EVENT,
+                vec![
+                    Cell::U16(event_type.attrs),
+                    Cell::String(name),
+                    Cell::Coded(CodedIndex::TypeDefOrRef, delegate),
+                ],
+            );
+            self.add_game_attributes(event_token, EVENT_TOKEN | event.token.rid() as u32);
+
+            // accessors are relative to the declaring type's methods
+            let accessors = [
+                (SEMANTICS_ADD_ON, event.add),
+                (SEMANTICS_REMOVE_ON, event.remove),
+                (SEMANTICS_FIRE, event.raise),
+            ];
+            for (semantics, method) in accessors {
+                if method == u32::MAX {
+                    continue;
+                }
+                self.builder.add_row(
+                    METHOD_SEMANTICS,
+                    vec![
+                        Cell::U16(semantics),
+                        Cell::Index(METHOD_DEF, method_list + method),
+                        Cell::Coded(CodedIndex::HasSemantics, event_token),
+                    ],
+                );
+            }
+        }
+    }
+
+    fn emit_generic_params(&mut self, owner: Token, generic_params: &[Il2CppGenericParameter]) {
+        let md = self.metadata.metadata;
+
+        for param in generic_params {
+            let constraints = param
+                .constraints(md)
+                .iter()
+                .map(|constraint| self.type_def_or_ref_of(*constraint as usize))
+                .collect_vec();
+
+            self.builder.add_generic_param(
+                owner,
+                param.num,
+                param.flags,
+                param.name(md),
+                constraints,
+            );
+        }
+    }
+
+    /// Boxed object header, which il2cpp includes in the offsets of reference types
+    fn object_header(&self, td: &Il2CppTypeDefinition) -> u32 {
+        match td.is_value_type() || td.is_enum_type() {
+            true => 0,
+            false => self.metadata.object_size() as u32,
+        }
+    }
+
+    fn add_constant(&mut self, parent: Token, value: &CsValue) {
+        let Some((element_type, data)) = constant_value(value) else {
+            return;
+        };
+
+        let data = self.builder.blob(&data);
+        self.builder.add_row(
+            CONSTANT,
+            vec![
+                Cell::U16(element_type as u16),
+                Cell::Coded(CodedIndex::HasConstant, parent),
+                Cell::Blob(data),
+            ],
+        );
+    }
+
+    fn add_attribute(&mut self, parent: Token, attribute: &'static str, fields: &[(&str, String)]) {
+        let Some(constructor) = self.attribute_ctor(attribute) else {
+            return;
+        };
+
+        let value = named_fields_blob(fields);
+        self.builder
+            .add_custom_attribute(parent, constructor, &value);
+    }
+
+    /// Attributes the game applied to the member with `token` in its image
+    fn add_game_attributes(&mut self, parent: Token, token: u32) {
+        let metadata = self.metadata;
+
+        for attribute in metadata.custom_attributes.get(self.image_index, token) {
+            let Some(value) = self.game_attribute_blob(attribute) else {
+                warn!(
+                    "Arguments of {} don't match its constructor, skipping it",
+                    self.attribute_name(attribute)
+                );
+                continue;
+            };
+            let constructor = self.constructor(attribute.constructor);
+            self.builder
+                .add_custom_attribute(parent, constructor, &value);
+        }
+    }
+
+    fn attribute_name(&self, attribute: &CustomAttribute) -> &'b str {
+        let md = self.metadata.metadata;
+        let gm = &md.global_metadata;
+
+        gm.type_definitions[gm.methods[attribute.constructor].declaring_type].name(md)
+    }
+
+    /// None if the arguments don't match the constructor's parameters
+    fn game_attribute_blob<'c>(&self, attribute: &'c CustomAttribute) -> Option<Vec<u8>> {
+        let metadata = self.metadata;
+        let md = metadata.metadata;
+        let gm = &md.global_metadata;
+        let types = &metadata.metadata_registration.types;
+        let is_object = |type_index: usize| matches!(types[type_index].ty, Il2CppTypeEnum::Object);
+
+        let constructor = &gm.methods[attribute.constructor];
+        let params = constructor.parameters(md);
+        if params.len() != attribute.arguments.len() {
+            return None;
+        }
+        let arguments = params
+            .iter()
+            .zip(&attribute.arguments)
+            .map(|(param, value)| (is_object(param.type_index as usize), value))
+            .collect_vec();
+
+        let named_value = |argument: &'c NamedArgument, property: bool| {
+            let tdi = argument
+                .declaring_type
+                .unwrap_or(constructor.declaring_type);
+            let td = &gm.type_definitions[tdi];
+            let index = argument.index as usize;
+
+            let (name, type_index) = match property {
+                false => {
+                    let field = td.fields(md).get(index)?;
+                    (field.name(md), field.type_index as usize)
+                }
+                true => {
+                    let prop = td.properties(md).get(index)?;
+                    let type_index = match prop.get != u32::MAX {
+                        true => gm.methods[prop.get_method_index(td)].return_type as usize,
+                        false => {
+                            let setter = &gm.methods[prop.set_method_index(td)];
+                            setter.parameters(md).last()?.type_index as usize
+                        }
+                    };
+                    (prop.name(md), type_index)
+                }
+            };
+
+            Some(NamedValue {
+                property,
+                name,
+                boxed: is_object(type_index),
+                value: &argument.value,
+            })
+        };
+        let named = attribute
+            .fields
+            .iter()
+            .map(|f| named_value(f, false))
+            .chain(attribute.properties.iter().map(|p| named_value(p, true)))
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(attribute_blob(&arguments, &named, &|type_index| {
+            self.serialized_type_name(type_index)
+        }))
+    }
+
+    /// `MethodDef` of constructors in this image, `MemberRef` otherwise
+    fn constructor(&mut self, method_index: MethodIndex) -> Token {
+        if let Some(constructor) = self.constructors.get(&method_index) {
+            return *constructor;
+        }
+
+        let md = self.metadata.metadata;
+        let method = &md.global_metadata.methods[method_index];
+        let tdi = method.declaring_type;
+        let constructor = match self.images.image_of(tdi) == Some(self.image_index) {
+            true => {
+                let td = &md.global_metadata.type_definitions[tdi];
+                let type_row = tdi.index() - self.images.ranges[self.image_index].start;
+                let first_method_row = self.method_rows[type_row as usize];
+                Token::new(
+                    METHOD_DEF,
+                    first_method_row + method_index.index() - td.method_start.index(),
+                )
+            }
+            false => {
+                let parent = self.type_def_or_ref(tdi);
+                let signature = self.method_signature(method);
+                self.builder.add_member_ref(parent, ".ctor", &signature)
+            }
+        };
+
+        self.constructors.insert(method_index, constructor);
+        constructor
+    }
+
+    /// Assembly qualified name of types in attribute values (II.23.3)
+    fn serialized_type_name(&self, type_index: usize) -> String {
+        match self.reflection_name(type_index) {
+            Some((name, assembly)) => format!("{name}, {assembly}"),
+            None => {
+                let metadata = self.metadata;
+                metadata.metadata_registration.types[type_index].full_name(metadata.metadata)
+            }
+        }
+    }
+
+    /// `Namespace.Type+Nested` and its assembly, generic arguments are assembly qualified
+    fn reflection_name(&self, type_index: usize) -> Option<(String, &'a str)> {
+        let metadata = self.metadata;
+        let md = metadata.metadata;
+        let mr = metadata.metadata_registration;
+        let ty = &mr.types[type_index];
+
+        match (ty.ty, ty.data) {
+            (Il2CppTypeEnum::Szarray, TypeData::TypeIndex(element)) => {
+                let (name, assembly) = self.reflection_name(element)?;
+                Some((format!("{name}[]"), assembly))
+            }
+            (Il2CppTypeEnum::Genericinst, TypeData::GenericClassIndex(generic_class)) => {
+                let generic_class = &mr.generic_classes[generic_class];
+                let (name, assembly) = self.reflection_name(generic_class.type_index)?;
+                let args = generic_class
+                    .context
+                    .class_inst_idx
+                    .map(|i| mr.generic_insts[i].types.as_slice())
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|arg| format!("[{}]", self.serialized_type_name(*arg)))
+                    .join(",");
+                Some((format!("{name}[{args}]"), assembly))
+            }
+            (_, TypeData::TypeDefinitionIndex(tdi)) => {
+                let td = &md.global_metadata.type_definitions[tdi];
+                let images = self.images;
+                let assembly = images.names[images.image_of(tdi)?].as_str();
+                Some((td.full_name(md, true).replace('/', "+"), assembly))
+            }
+            _ => None,
+        }
+    }
+
+    /// `MemberRef` to the default constructor, None if the game stripped the attribute
+    fn attribute_ctor(&mut self, attribute: &'static str) -> Option<Token> {
+        if let Some(constructor) = self.attribute_ctors.get(attribute) {
+            return *constructor;
+        }
+
+        let metadata = self.metadata;
+        let attribute_type = match attribute {
+            COMPILER_GENERATED_ATTRIBUTE => metadata
+                .name_to_tdi
+                .get(&Il2cppFullName(
+                    "System.Runtime.CompilerServices",
+                    attribute,
+                ))
+                .map(|tdi| self.type_def_or_ref(*tdi)),
+            _ => {
+                let scope = self.assembly_ref(ATTRIBUTES_ASSEMBLY);
+                Some(
+                    self.builder
+                        .add_type_ref(scope, ATTRIBUTES_NAMESPACE, attribute),
+                )
+            }
+        };
+        let constructor = attribute_type.map(|attribute_type| {
+            self.builder
+                .add_member_ref(attribute_type, ".ctor", &DEFAULT_CTOR_SIGNATURE)
+        });
+
+        self.attribute_ctors.insert(attribute, constructor);
+        constructor
+    }
+
+    fn emit_impl_map(&mut self, method_token: Token, pinvoke: &CsPInvoke, method_name: &str) {
+        let Some(library) = &pinvoke.library else {
+            return;
+        };
+        let scope = match self.module_refs.get(library) {
+            Some(module_ref) => *module_ref,
+            None => {
+                let module_ref = self.builder.add_module_ref(library);
+                self.module_refs.insert(library.clone(), module_ref);
+                module_ref
+            }
+        };
+
+        self.builder.add_impl_map(
+            method_token,
+            pinvoke_flags(pinvoke),
+            pinvoke.entry_point.as_deref().unwrap_or(method_name),
+            scope,
+        );
+    }
+
+    fn assembly_ref(&mut self, name: &str) -> Token {
+        if let Some(assembly_ref) = self.assembly_refs.get(name) {
+            return *assembly_ref;
+        }
+
+        let assembly_ref = self.builder.add_assembly_ref(name);
+        self.assembly_refs.insert(name.to_string(), assembly_ref);
+        assembly_ref
+    }
+
+    /// `TypeDef` of this image or a `TypeRef` into another
+    fn type_def_or_ref(&mut self, tdi: TypeDefinitionIndex) -> Token {
+        let image = self.images.image_of(tdi).unwrap_or(self.image_index);
+        if image == self.image_index {
+            let row = tdi.index() - self.images.ranges[image].start + 1;
+            return Token::new(TYPE_DEF, row + self.needs_module_type as u32);
+        }
+
+        if let Some(type_ref) = self.type_refs.get(&tdi) {
+            return *type_ref;
+        }
+
+        let metadata = self.metadata;
+        let md = metadata.metadata;
+        let td = &md.global_metadata.type_definitions[tdi];
+        let (scope, namespace) = match metadata.child_to_parent_map.get(&tdi) {
+            Some(declaring) => (self.type_def_or_ref(declaring.tdi), ""),
+            None => {
+                let images = self.images;
+                (self.assembly_ref(&images.names[image]), td.namespace(md))
+            }
+        };
+
+        let type_ref = self.builder.add_type_ref(scope, namespace, td.name(md));
+        self.type_refs.insert(tdi, type_ref);
+        type_ref
+    }
+
+    /// `TypeDefOrRef` of `metadata_registration.types[type_index]`,
+    /// anything but a plain class or value type gets a `TypeSpec`
+    fn type_def_or_ref_of(&mut self, type_index: usize) -> Token {
+        let metadata = self.metadata;
+        let ty = &metadata.metadata_registration.types[type_index];
+        if !ty.byref
+            && matches!(ty.ty, Il2CppTypeEnum::Class | Il2CppTypeEnum::Valuetype)
+            && let TypeData::TypeDefinitionIndex(tdi) = ty.data
+        {
+            return self.type_def_or_ref(tdi);
+        }
+
+        let mut signature = vec![];
+        self.encode_type(&mut signature, type_index);
+        if let Some(type_spec) = self.type_specs.get(&signature) {
+            return *type_spec;
+        }
+
+        let blob = self.builder.blob(&signature);
+        let type_spec = self.builder.add_row(TYPE_SPEC, vec![Cell::Blob(blob)]);
+        self.type_specs.insert(signature, type_spec);
+        type_spec
+    }
+
+    /// MethodDefSig (II.23.2.1)
+    fn method_signature(&mut self, method: &Il2CppMethodDefinition) -> Vec<u8> {
+        let md = self.metadata.metadata;
+
+        let generic_count = match method.generic_container_index.is_valid() {
+            true => method
+                .generic_container(md)
+                .map_or(0, |c| c.generic_parameters(md).len()),
+            false => 0,
+        };
+
+        let mut flags = 0;
+        if method.flags & METHOD_ATTRIBUTE_STATIC == 0 {
+            flags |= SIG_HAS_THIS;
+        }
+        if generic_count > 0 {
+            flags |= SIG_GENERIC;
+        }
+
+        let mut signature = vec![flags];
+        if generic_count > 0 {
+            write_compressed(&mut signature, generic_count as u32);
+        }
+
+        let params = method.parameters(md);
+        write_compressed(&mut signature, params.len() as u32);
+        self.encode_type(&mut signature, method.return_type as usize);
+        for param in params {
+            self.encode_type(&mut signature, param.type_index as usize);
+        }
+
+        signature
+    }
+
+    /// Type in a signature (II.23.2.12)
+    fn encode_type(&mut self, signature: &mut Vec<u8>, type_index: usize) {
+        let metadata = self.metadata;
+        let ty = &metadata.metadata_registration.types[type_index];
+        if ty.byref {
+            signature.push(ELEMENT_TYPE_BYREF);
+        }
+
+        self.encode_type_data(signature, ty);
+    }
+
+    fn encode_type_data(&mut self, signature: &mut Vec<u8>, ty: &Il2CppType) {
+        let metadata = self.metadata;
+        let mr = metadata.metadata_registration;
+
+        match (ty.ty, ty.data) {
+            (
+                Il2CppTypeEnum::Class | Il2CppTypeEnum::Valuetype,
+                TypeData::TypeDefinitionIndex(tdi),
+            ) => {
+                signature.push(element_type(ty.ty));
+                let token = self.type_def_or_ref(tdi);
+                write_compressed(signature, CodedIndex::TypeDefOrRef.encode(token));
+            }
+            (Il2CppTypeEnum::Szarray | Il2CppTypeEnum::Ptr, TypeData::TypeIndex(element)) => {
+                signature.push(element_type(ty.ty));
+                self.encode_type(signature, element);
+            }
+            (
+                Il2CppTypeEnum::Var | Il2CppTypeEnum::Mvar,
+                TypeData::GenericParameterIndex(index),
+            ) => {
+                signature.push(element_type(ty.ty));
+                let generic_param = &metadata.metadata.global_metadata.generic_parameters[index];
+                write_compressed(signature, generic_param.num as u32);
+            }
+            (Il2CppTypeEnum::Genericinst, TypeData::GenericClassIndex(generic_class)) => {
+                let generic_class = &mr.generic_classes[generic_class];
+                let args = generic_class
+                    .context
+                    .class_inst_idx
+                    .map(|i| mr.generic_insts[i].types.as_slice())
+                    .unwrap_or_default();
+
+                signature.push(ELEMENT_TYPE_GENERICINST);
+                self.encode_type_data(signature, &mr.types[generic_class.type_index]);
+                write_compressed(signature, args.len() as u32);
+                for arg in args {
+                    self.encode_type(signature, *arg);
+                }
+            }
+            // the type resolver doesn't support multidimensional arrays either
+            (Il2CppTypeEnum::Array, _) => signature.push(ELEMENT_TYPE_OBJECT),
+            (Il2CppTypeEnum::Class | Il2CppTypeEnum::Valuetype, _)
+            | (Il2CppTypeEnum::Szarray | Il2CppTypeEnum::Ptr, _)
+            | (Il2CppTypeEnum::Var | Il2CppTypeEnum::Mvar, _)
+            | (Il2CppTypeEnum::Genericinst, _) => {
+                warn!("Unexpected type data {ty:?}, writing object instead");
+                signature.push(ELEMENT_TYPE_OBJECT);
+            }
+            (primitive, _) => signature.push(element_type(primitive)),
+        }
+    }
+}
+
+/// ELEMENT_TYPE_* (II.23.1.16), il2cpp keeps the same values
+fn element_type(ty: Il2CppTypeEnum) -> u8 {
+    match ty {
+        Il2CppTypeEnum::Void => 0x01,
+        Il2CppTypeEnum::Boolean => 0x02,
+        Il2CppTypeEnum::Char => 0x03,
+        Il2CppTypeEnum::I1 => 0x04,
+        Il2CppTypeEnum::U1 => 0x05,
+        Il2CppTypeEnum::I2 => 0x06,
+        Il2CppTypeEnum::U2 => 0x07,
+        Il2CppTypeEnum::I4 => 0x08,
+        Il2CppTypeEnum::U4 => 0x09,
+        Il2CppTypeEnum::I8 => 0x0A,
+        Il2CppTypeEnum::U8 => 0x0B,
+        Il2CppTypeEnum::R4 => 0x0C,
+        Il2CppTypeEnum::R8 => 0x0D,
+        Il2CppTypeEnum::String => 0x0E,
+        Il2CppTypeEnum::Ptr => 0x0F,
+        Il2CppTypeEnum::Valuetype => 0x11,
+        Il2CppTypeEnum::Class => ELEMENT_TYPE_CLASS,
+        Il2CppTypeEnum::Var => 0x13,
+        Il2CppTypeEnum::Genericinst => ELEMENT_TYPE_GENERICINST,
+        Il2CppTypeEnum::Typedbyref => 0x16,
+        Il2CppTypeEnum::I => 0x18,
+        Il2CppTypeEnum::U => 0x19,
+        Il2CppTypeEnum::Szarray => 0x1D,
+        Il2CppTypeEnum::Mvar => 0x1E,
+        _ => ELEMENT_TYPE_OBJECT,
+    }
+}
+
+/// Element type and little endian value of a `Constant` row,
+/// references can only be null (II.22.9)
+fn constant_value(value: &CsValue) -> Option<(u8, Vec<u8>)> {
+    let utf16 = |s: &str| {
+        unescape(s)
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect_vec()
+    };
+
+    Some(match value {
+        CsValue::String(s) => (element_type(Il2CppTypeEnum::String), utf16(s)),
+        CsValue::Char(c) => (element_type(Il2CppTypeEnum::Char), utf16(c)),
+        CsValue::Bool(b) => (element_type(Il2CppTypeEnum::Boolean), vec![*b as u8]),
+        CsValue::U8(x) => (element_type(Il2CppTypeEnum::U1), x.to_le_bytes().to_vec()),
+        CsValue::U16(x) => (element_type(Il2CppTypeEnum::U2), x.to_le_bytes().to_vec()),
+        CsValue::U32(x) => (element_type(Il2CppTypeEnum::U4), x.to_le_bytes().to_vec()),
+        CsValue::U64(x) => (element_type(Il2CppTypeEnum::U8), x.to_le_bytes().to_vec()),
+        CsValue::I8(x) => (element_type(Il2CppTypeEnum::I1), x.to_le_bytes().to_vec()),
+        CsValue::I16(x) => (element_type(Il2CppTypeEnum::I2), x.to_le_bytes().to_vec()),
+        CsValue::I32(x) => (element_type(Il2CppTypeEnum::I4), x.to_le_bytes().to_vec()),
+        CsValue::I64(x) => (element_type(Il2CppTypeEnum::I8), x.to_le_bytes().to_vec()),
+        CsValue::F32(x) => (element_type(Il2CppTypeEnum::R4), x.to_le_bytes().to_vec()),
+        CsValue::F64(x) => (element_type(Il2CppTypeEnum::R8), x.to_le_bytes().to_vec()),
+        CsValue::Null => (ELEMENT_TYPE_CLASS, vec![0; 4]),
+        CsValue::Object(_) | CsValue::ValueType(_) => return None,
+    })
+}
+
+/// Reverses `str::escape_default`, which the model applies to string and char constants
+fn unescape(s: &str) -> String {
+    let mut unescaped = String::with_capacity(s.len());
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('r') => unescaped.push('\r'),
+            Some('n') => unescaped.push('\n'),
+            // \u{XXXX}
+            Some('u') => {
+                let hex: String = chars.by_ref().skip(1).take_while(|c| *c != '}').collect();
+                if let Some(c) = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                    unescaped.push(c);
+                }
+            }
+            Some(c) => unescaped.push(c),
+            None => {}
+        }
+    }
+
+    unescaped
+}
+
+/// `PInvokeAttributes` of an ImplMap row, conventions the wrapper didn't record are `WinApi`
+fn pinvoke_flags(pinvoke: &CsPInvoke) -> u16 {
+    let call_conv = match pinvoke.calling_convention {
+        Some(CallingConvention::C) => PINVOKE_CALL_CONV_CDECL,
+        Some(CallingConvention::StdCall) => PINVOKE_CALL_CONV_STDCALL,
+        Some(CallingConvention::ThisCall) => PINVOKE_CALL_CONV_THISCALL,
+        Some(CallingConvention::FastCall) => PINVOKE_CALL_CONV_FASTCALL,
+        Some(CallingConvention::Default) | None => PINVOKE_CALL_CONV_WINAPI,
+    };
+    // UTF-8 strings come from `MarshalAs`, there is no char set for them
+    let char_set = match pinvoke.char_set {
+        Some(CharSet::Ansi) => PINVOKE_CHAR_SET_ANSI,
+        Some(CharSet::Unicode) => PINVOKE_CHAR_SET_UNICODE,
+        Some(CharSet::Utf8 | CharSet::NotSpecified) | None => 0,
+    };
+    let no_mangle = match pinvoke.is_no_mangle {
+        Some(true) => PINVOKE_NO_MANGLE,
+        _ => 0,
+    };
+
+    call_conv | char_set | no_mangle
+}
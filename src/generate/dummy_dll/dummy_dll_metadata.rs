@@ -0,0 +1,566 @@
+//! ECMA-335 metadata root, heaps and the `#~` table stream (Partition II, 24)
+
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+};
+
+use itertools::Itertools;
+
+pub type TableId = u8;
+
+pub const MODULE: TableId = 0x00;
+pub const TYPE_REF: TableId = 0x01;
+pub const TYPE_DEF: TableId = 0x02;
+pub const FIELD: TableId = 0x04;
+pub const METHOD_DEF: TableId = 0x06;
+pub const PARAM: TableId = 0x08;
+pub const INTERFACE_IMPL: TableId = 0x09;
+pub const MEMBER_REF: TableId = 0x0A;
+pub const CONSTANT: TableId = 0x0B;
+pub const CUSTOM_ATTRIBUTE: TableId = 0x0C;
+pub const DECL_SECURITY: TableId = 0x0E;
+pub const CLASS_LAYOUT: TableId = 0x0F;
+pub const FIELD_LAYOUT: TableId = 0x10;
+pub const STAND_ALONE_SIG: TableId = 0x11;
+pub const EVENT_MAP: TableId = 0x12;
+pub const EVENT: TableId = 0x14;
+pub const PROPERTY_MAP: TableId = 0x15;
+pub const PROPERTY: TableId = 0x17;
+pub const METHOD_SEMANTICS: TableId = 0x18;
+pub const MODULE_REF: TableId = 0x1A;
+pub const TYPE_SPEC: TableId = 0x1B;
+pub const IMPL_MAP: TableId = 0x1C;
+pub const ASSEMBLY: TableId = 0x20;
+pub const ASSEMBLY_REF: TableId = 0x23;
+pub const FILE: TableId = 0x26;
+pub const EXPORTED_TYPE: TableId = 0x27;
+pub const MANIFEST_RESOURCE: TableId = 0x28;
+pub const NESTED_CLASS: TableId = 0x29;
+pub const GENERIC_PARAM: TableId = 0x2A;
+pub const METHOD_SPEC: TableId = 0x2B;
+pub const GENERIC_PARAM_CONSTRAINT: TableId = 0x2C;
+
+const TABLE_COUNT: usize = 0x2D;
+/// Tables that must be sorted by their key column, the mask compilers emit
+const SORTED_TABLES: u64 = 0x0000_1600_3301_FA00;
+
+/// Unused slot of a coded index
+const NONE: TableId = 0xFF;
+
+/// Coded index kinds, the tag is the table's position in the list
+#[derive(Clone, Copy, Debug)]
+pub enum CodedIndex {
+    TypeDefOrRef,
+    HasConstant,
+    HasCustomAttribute,
+    HasSemantics,
+    MemberRefParent,
+    MemberForwarded,
+    ResolutionScope,
+    CustomAttributeType,
+    TypeOrMethodDef,
+}
+
+impl CodedIndex {
+    fn tables(self) -> &'static [TableId] {
+        match self {
+            CodedIndex::TypeDefOrRef => &[TYPE_DEF, TYPE_REF, TYPE_SPEC],
+            CodedIndex::HasConstant => &[FIELD, PARAM, PROPERTY],
+            CodedIndex::HasCustomAttribute => &[
+                METHOD_DEF,
+                FIELD,
+                TYPE_REF,
+                TYPE_DEF,
+                PARAM,
+                INTERFACE_IMPL,
+                MEMBER_REF,
+                MODULE,
+                DECL_SECURITY,
+                PROPERTY,
+                EVENT,
+                STAND_ALONE_SIG,
+                MODULE_REF,
+                TYPE_SPEC,
+                ASSEMBLY,
+                ASSEMBLY_REF,
+                FILE,
+                EXPORTED_TYPE,
+                MANIFEST_RESOURCE,
+                GENERIC_PARAM,
+                GENERIC_PARAM_CONSTRAINT,
+                METHOD_SPEC,
+            ],
+            CodedIndex::HasSemantics => &[EVENT, PROPERTY],
+            CodedIndex::MemberRefParent => &[TYPE_DEF, TYPE_REF, MODULE_REF, METHOD_DEF, TYPE_SPEC],
+            CodedIndex::MemberForwarded => &[FIELD, METHOD_DEF],
+            CodedIndex::ResolutionScope => &[MODULE, MODULE_REF, ASSEMBLY_REF, TYPE_REF],
+            CodedIndex::CustomAttributeType => &[NONE, NONE, METHOD_DEF, MEMBER_REF, NONE],
+            CodedIndex::TypeOrMethodDef => &[TYPE_DEF, METHOD_DEF],
+        }
+    }
+
+    fn tag_bits(self) -> u32 {
+        (self.tables().len() as u32)
+            .next_power_of_two()
+            .trailing_zeros()
+    }
+
+    /// Encoded value as stored in a table or, for `TypeDefOrRef`, a signature
+    pub fn encode(self, token: Token) -> u32 {
+        if token.is_null() {
+            return 0;
+        }
+
+        let tag = self
+            .tables()
+            .iter()
+            .position(|t| *t == token.table)
+            .unwrap_or_else(|| panic!("Table {:#x} is not part of {self:?}", token.table));
+
+        (token.row << self.tag_bits()) | tag as u32
+    }
+}
+
+/// Row of a table, rows are 1-based and 0 is null
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Token {
+    pub table: TableId,
+    pub row: u32,
+}
+
+impl Token {
+    pub const NULL: Token = Token {
+        table: NONE,
+        row: 0,
+    };
+
+    pub fn new(table: TableId, row: u32) -> Self {
+        Token { table, row }
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.row == 0
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Cell {
+    U16(u16),
+    U32(u32),
+    String(u32),
+    Guid(u32),
+    Blob(u32),
+    /// Simple index into a table, may point one past the end for member lists
+    Index(TableId, u32),
+    Coded(CodedIndex, Token),
+}
+
+struct GenericParam {
+    number: u16,
+    flags: u16,
+    owner: Token,
+    name: u32,
+    constraints: Vec<Token>,
+}
+
+pub struct MetadataBuilder {
+    strings: Vec<u8>,
+    string_indices: HashMap<String, u32>,
+    blobs: Vec<u8>,
+    blob_indices: HashMap<Vec<u8>, u32>,
+    guids: Vec<[u8; 16]>,
+
+    tables: Vec<Vec<Vec<Cell>>>,
+    /// Sorted by owner before they get rows, constraints refer to those rows
+    generic_params: Vec<GenericParam>,
+}
+
+impl Default for MetadataBuilder {
+    fn default() -> Self {
+        MetadataBuilder {
+            strings: vec![0],
+            string_indices: HashMap::new(),
+            blobs: vec![0],
+            blob_indices: HashMap::new(),
+            guids: vec![],
+            tables: vec![vec![]; TABLE_COUNT],
+            generic_params: vec![],
+        }
+    }
+}
+
+impl MetadataBuilder {
+    pub fn string(&mut self, s: &str) -> u32 {
+        if s.is_empty() {
+            return 0;
+        }
+        if let Some(index) = self.string_indices.get(s) {
+            return *index;
+        }
+
+        let index = self.strings.len() as u32;
+        self.strings.extend_from_slice(s.as_bytes());
+        self.strings.push(0);
+        self.string_indices.insert(s.to_string(), index);
+        index
+    }
+
+    pub fn blob(&mut self, data: &[u8]) -> u32 {
+        if data.is_empty() {
+            return 0;
+        }
+        if let Some(index) = self.blob_indices.get(data) {
+            return *index;
+        }
+
+        let index = self.blobs.len() as u32;
+        write_compressed(&mut self.blobs, data.len() as u32);
+        self.blobs.extend_from_slice(data);
+        self.blob_indices.insert(data.to_vec(), index);
+        index
+    }
+
+    pub fn guid(&mut self, guid: [u8; 16]) -> u32 {
+        self.guids.push(guid);
+        self.guids.len() as u32
+    }
+
+    pub fn add_row(&mut self, table: TableId, row: Vec<Cell>) -> Token {
+        let rows = &mut self.tables[table as usize];
+        rows.push(row);
+        Token::new(table, rows.len() as u32)
+    }
+
+    /// Row the next `add_row` to `table` will return, for member list columns
+    pub fn next_row(&self, table: TableId) -> u32 {
+        self.tables[table as usize].len() as u32 + 1
+    }
+
+    /// Module and assembly rows, version 0.0.0.0 without a public key
+    pub fn add_assembly(&mut self, name: &str) {
+        let mut hasher = DefaultHasher::new();
+        name.hash(&mut hasher);
+        let hash = hasher.finish().to_le_bytes();
+        // deterministic so rebuilding the same game gives identical files
+        let mvid = self.guid([hash, hash.map(|b| !b)].concat().try_into().unwrap());
+
+        let module_name = self.string(&format!("{name}.dll"));
+        self.add_row(
+            MODULE,
+            vec![
+                Cell::U16(0),
+                Cell::String(module_name),
+                Cell::Guid(mvid),
+                Cell::Guid(0),
+                Cell::Guid(0),
+            ],
+        );
+
+        let name = self.string(name);
+        self.add_row(
+            ASSEMBLY,
+            vec![
+                // SHA1
+                Cell::U32(0x8004),
+                Cell::U16(0),
+                Cell::U16(0),
+                Cell::U16(0),
+                Cell::U16(0),
+                Cell::U32(0),
+                Cell::Blob(0),
+                Cell::String(name),
+                Cell::String(0),
+            ],
+        );
+    }
+
+    pub fn add_assembly_ref(&mut self, name: &str) -> Token {
+        let name = self.string(name);
+        self.add_row(
+            ASSEMBLY_REF,
+            vec![
+                Cell::U16(0),
+                Cell::U16(0),
+                Cell::U16(0),
+                Cell::U16(0),
+                Cell::U32(0),
+                Cell::Blob(0),
+                Cell::String(name),
+                Cell::String(0),
+                Cell::Blob(0),
+            ],
+        )
+    }
+
+    pub fn add_type_ref(&mut self, scope: Token, namespace: &str, name: &str) -> Token {
+        let name = self.string(name);
+        let namespace = self.string(namespace);
+        self.add_row(
+            TYPE_REF,
+            vec![
+                Cell::Coded(CodedIndex::ResolutionScope, scope),
+                Cell::String(name),
+                Cell::String(namespace),
+            ],
+        )
+    }
+
+    pub fn add_member_ref(&mut self, parent: Token, name: &str, signature: &[u8]) -> Token {
+        let name = self.string(name);
+        let signature = self.blob(signature);
+        self.add_row(
+            MEMBER_REF,
+            vec![
+                Cell::Coded(CodedIndex::MemberRefParent, parent),
+                Cell::String(name),
+                Cell::Blob(signature),
+            ],
+        )
+    }
+
+    pub fn add_module_ref(&mut self, name: &str) -> Token {
+        let name = self.string(name);
+        self.add_row(MODULE_REF, vec![Cell::String(name)])
+    }
+
+    pub fn add_impl_map(&mut self, method: Token, flags: u16, import_name: &str, scope: Token) {
+        let import_name = self.string(import_name);
+        self.add_row(
+            IMPL_MAP,
+            vec![
+                Cell::U16(flags),
+                Cell::Coded(CodedIndex::MemberForwarded, method),
+                Cell::String(import_name),
+                Cell::Index(MODULE_REF, scope.row),
+            ],
+        );
+    }
+
+    pub fn add_custom_attribute(&mut self, parent: Token, constructor: Token, value: &[u8]) {
+        let value = self.blob(value);
+        self.add_row(
+            CUSTOM_ATTRIBUTE,
+            vec![
+                Cell::Coded(CodedIndex::HasCustomAttribute, parent),
+                Cell::Coded(CodedIndex::CustomAttributeType, constructor),
+                Cell::Blob(value),
+            ],
+        );
+    }
+
+    pub fn add_generic_param(
+        &mut self,
+        owner: Token,
+        number: u16,
+        flags: u16,
+        name: &str,
+        constraints: Vec<Token>,
+    ) {
+        let name = self.string(name);
+        self.generic_params.push(GenericParam {
+            number,
+            flags,
+            owner,
+            name,
+            constraints,
+        });
+    }
+
+    /// Metadata root with the `#~`, `#Strings`, `#US`, `#GUID` and `#Blob` streams
+    pub fn finish(mut self) -> Vec<u8> {
+        self.flush_generic_params();
+
+        for table in 0..TABLE_COUNT {
+            if SORTED_TABLES & (1 << table) == 0 {
+                continue;
+            }
+            let Some(key) = sort_key(table as TableId) else {
+                continue;
+            };
+
+            // stable, rows with equal keys keep their order
+            self.tables[table]
+                .sort_by_key(|row| key.iter().map(|i| cell_value(&row[*i])).collect_vec());
+        }
+
+        let mut tables = self.table_stream();
+        let mut guids = self.guids.concat();
+        let mut strings = self.strings;
+        let mut blobs = self.blobs;
+        // nothing loads string literals, so the user string heap stays empty
+        let mut user_strings = vec![0];
+
+        for heap in [
+            &mut tables,
+            &mut strings,
+            &mut user_strings,
+            &mut guids,
+            &mut blobs,
+        ] {
+            pad4(heap);
+        }
+
+        let streams: [(&str, &[u8]); 5] = [
+            ("#~", &tables),
+            ("#Strings", &strings),
+            ("#US", &user_strings),
+            ("#GUID", &guids),
+            ("#Blob", &blobs),
+        ];
+
+        let version = b"v4.0.30319\0\0";
+        let header_len = 16
+            + version.len()
+            + 4
+            + streams
+                .iter()
+                .map(|(name, _)| 8 + (name.len() + 1).next_multiple_of(4))
+                .sum::<usize>();
+
+        let mut root = Vec::new();
+        root.extend_from_slice(&0x424A_5342u32.to_le_bytes());
+        root.extend_from_slice(&1u16.to_le_bytes());
+        root.extend_from_slice(&1u16.to_le_bytes());
+        root.extend_from_slice(&0u32.to_le_bytes());
+        root.extend_from_slice(&(version.len() as u32).to_le_bytes());
+        root.extend_from_slice(version);
+        root.extend_from_slice(&0u16.to_le_bytes());
+        root.extend_from_slice(&(streams.len() as u16).to_le_bytes());
+
+        let mut offset = header_len;
+        for (name, data) in &streams {
+            root.extend_from_slice(&(offset as u32).to_le_bytes());
+            root.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            root.extend_from_slice(name.as_bytes());
+            root.push(0);
+            pad4(&mut root);
+            offset += data.len();
+        }
+        for (_, data) in &streams {
+            root.extend_from_slice(data);
+        }
+
+        root
+    }
+
+    fn flush_generic_params(&mut self) {
+        let mut generic_params = std::mem::take(&mut self.generic_params);
+        generic_params.sort_by_key(|p| (CodedIndex::TypeOrMethodDef.encode(p.owner), p.number));
+
+        for param in generic_params {
+            let row = self.add_row(
+                GENERIC_PARAM,
+                vec![
+                    Cell::U16(param.number),
+                    Cell::U16(param.flags),
+                    Cell::Coded(CodedIndex::TypeOrMethodDef, param.owner),
+                    Cell::String(param.name),
+                ],
+            );
+
+            for constraint in param.constraints {
+                self.add_row(
+                    GENERIC_PARAM_CONSTRAINT,
+                    vec![
+                        Cell::Index(GENERIC_PARAM, row.row),
+                        Cell::Coded(CodedIndex::TypeDefOrRef, constraint),
+                    ],
+                );
+            }
+        }
+    }
+
+    fn table_stream(&self) -> Vec<u8> {
+        let wide_strings = self.strings.len() >= 0x10000;
+        let wide_guids = self.guids.len() >= 0x10000;
+        let wide_blobs = self.blobs.len() >= 0x10000;
+        let row_count = |table: TableId| match table {
+            NONE => 0,
+            _ => self.tables[table as usize].len(),
+        };
+
+        let mut stream = Vec::new();
+        stream.extend_from_slice(&0u32.to_le_bytes());
+        stream.extend_from_slice(&[2, 0]);
+        stream.push(wide_strings as u8 | (wide_guids as u8) << 1 | (wide_blobs as u8) << 2);
+        stream.push(1);
+
+        let valid = (0..TABLE_COUNT)
+            .filter(|t| !self.tables[*t].is_empty())
+            .fold(0u64, |mask, t| mask | 1 << t);
+        stream.extend_from_slice(&valid.to_le_bytes());
+        stream.extend_from_slice(&SORTED_TABLES.to_le_bytes());
+        for rows in self.tables.iter().filter(|rows| !rows.is_empty()) {
+            stream.extend_from_slice(&(rows.len() as u32).to_le_bytes());
+        }
+
+        let write_index = |stream: &mut Vec<u8>, value: u32, wide: bool| match wide {
+            true => stream.extend_from_slice(&value.to_le_bytes()),
+            false => stream.extend_from_slice(&(value as u16).to_le_bytes()),
+        };
+
+        for row in self.tables.iter().flatten() {
+            for cell in row {
+                match cell {
+                    Cell::U16(value) => stream.extend_from_slice(&value.to_le_bytes()),
+                    Cell::U32(value) => stream.extend_from_slice(&value.to_le_bytes()),
+                    Cell::String(index) => write_index(&mut stream, *index, wide_strings),
+                    Cell::Guid(index) => write_index(&mut stream, *index, wide_guids),
+                    Cell::Blob(index) => write_index(&mut stream, *index, wide_blobs),
+                    Cell::Index(table, row) => {
+                        write_index(&mut stream, *row, row_count(*table) >= 0x10000)
+                    }
+                    Cell::Coded(kind, token) => {
+                        let max_rows = kind.tables().iter().map(|t| row_count(*t)).max();
+                        let wide = max_rows.unwrap_or_default() >= 1 << (16 - kind.tag_bits());
+                        write_index(&mut stream, kind.encode(*token), wide)
+                    }
+                }
+            }
+        }
+
+        stream
+    }
+}
+
+/// Key columns of the sorted tables we write
+fn sort_key(table: TableId) -> Option<&'static [usize]> {
+    Some(match table {
+        INTERFACE_IMPL => &[0, 1],
+        CONSTANT => &[1],
+        CUSTOM_ATTRIBUTE => &[0],
+        CLASS_LAYOUT => &[2],
+        FIELD_LAYOUT => &[1],
+        METHOD_SEMANTICS => &[2],
+        IMPL_MAP => &[1],
+        NESTED_CLASS => &[0],
+        GENERIC_PARAM_CONSTRAINT => &[0],
+        _ => return None,
+    })
+}
+
+fn cell_value(cell: &Cell) -> u32 {
+    match cell {
+        Cell::U16(value) => *value as u32,
+        Cell::U32(value)
+        | Cell::String(value)
+        | Cell::Guid(value)
+        | Cell::Blob(value)
+        | Cell::Index(_, value) => *value,
+        Cell::Coded(kind, token) => kind.encode(*token),
+    }
+}
+
+fn pad4(data: &mut Vec<u8>) {
+    data.resize(data.len().next_multiple_of(4), 0);
+}
+
+/// Compressed unsigned integer of signatures and blob lengths (II.23.2)
+pub fn write_compressed(data: &mut Vec<u8>, value: u32) {
+    match value {
+        0..0x80 => data.push(value as u8),
+        0x80..0x4000 => data.extend_from_slice(&(0x8000 | value as u16).to_be_bytes()),
+        _ => data.extend_from_slice(&(0xC000_0000 | value).to_be_bytes()),
+    }
+}
@@ -0,0 +1,314 @@
+//! PE32 image holding the CLI header, method bodies and metadata in a single `.text` section
+
+use color_eyre::eyre::{Result, ensure};
+use object::{
+    pe,
+    write::pe::{NtHeaders, Writer},
+};
+
+/// `.text` is the first section, so it starts at the section alignment
+pub const TEXT_RVA: u32 = 0x2000;
+const FILE_ALIGNMENT: u32 = 0x200;
+const IMAGE_BASE: u64 = 0x1000_0000;
+
+/// IMAGE_COR20_HEADER
+const CLI_HEADER_SIZE: u32 = 72;
+const COMIMAGE_FLAGS_ILONLY: u32 = 0x1;
+
+/// Tiny header for a 2 byte body, `ldnull; throw`
+const STUB_BODY: [u8; 3] = [(2 << 2) | 0x2, 0x14, 0x7A];
+/// Every method with a body shares the same `throw null` stub
+pub const STUB_BODY_RVA: u32 = TEXT_RVA + CLI_HEADER_SIZE;
+
+/// Wraps a metadata root into a DLL. Without imports or an entry point
+/// it can't be executed, only loaded for its metadata
+pub fn write_pe(metadata: &[u8]) -> Result<Vec<u8>> {
+    let metadata_offset = (CLI_HEADER_SIZE as usize + STUB_BODY.len()).next_multiple_of(4);
+
+    let mut text = Vec::with_capacity(metadata_offset + metadata.len());
+    text.extend_from_slice(&CLI_HEADER_SIZE.to_le_bytes());
+    // runtime version 2.5
+    text.extend_from_slice(&2u16.to_le_bytes());
+    text.extend_from_slice(&5u16.to_le_bytes());
+    text.extend_from_slice(&(TEXT_RVA + metadata_offset as u32).to_le_bytes());
+    text.extend_from_slice(&(metadata.len() as u32).to_le_bytes());
+    text.extend_from_slice(&COMIMAGE_FLAGS_ILONLY.to_le_bytes());
+    // entry point token, then the resources, strong name, code manager table,
+    // vtable fixups, export address table jumps and managed native header directories
+    text.resize(CLI_HEADER_SIZE as usize, 0);
+    text.extend_from_slice(&STUB_BODY);
+    text.resize(metadata_offset, 0);
+    text.extend_from_slice(metadata);
+
+    let mut buffer = Vec::new();
+    let mut writer = Writer::new(false, TEXT_RVA, FILE_ALIGNMENT, &mut buffer);
+    writer.reserve_dos_header_and_stub();
+    writer.reserve_nt_headers(pe::IMAGE_NUMBEROF_DIRECTORY_ENTRIES);
+    writer.reserve_section_headers(1);
+    let text_range = writer.reserve_text_section(text.len() as u32);
+    ensure!(
+        text_range.virtual_address == TEXT_RVA,
+        "Unexpected .text address {:#x}",
+        text_range.virtual_address
+    );
+    writer.set_data_directory(
+        pe::IMAGE_DIRECTORY_ENTRY_COM_DESCRIPTOR,
+        TEXT_RVA,
+        CLI_HEADER_SIZE,
+    );
+
+    writer.write_dos_header_and_stub()?;
+    writer.write_nt_headers(NtHeaders {
+        machine: pe::IMAGE_FILE_MACHINE_I386,
+        time_date_stamp: 0,
+        characteristics: pe::IMAGE_FILE_EXECUTABLE_IMAGE
+            | pe::IMAGE_FILE_32BIT_MACHINE
+            | pe::IMAGE_FILE_DLL,
+        major_linker_version: 8,
+        minor_linker_version: 0,
+        address_of_entry_point: 0,
+        image_base: IMAGE_BASE,
+        major_operating_system_version: 4,
+        minor_operating_system_version: 0,
+        major_image_version: 0,
+        minor_image_version: 0,
+        major_subsystem_version: 4,
+        minor_subsystem_version: 0,
+        subsystem: pe::IMAGE_SUBSYSTEM_WINDOWS_CUI,
+        dll_characteristics: pe::IMAGE_DLLCHARACTERISTICS_DYNAMIC_BASE
+            | pe::IMAGE_DLLCHARACTERISTICS_NX_COMPAT
+            | pe::IMAGE_DLLCHARACTERISTICS_NO_SEH,
+        size_of_stack_reserve: 0x10_0000,
+        size_of_stack_commit: 0x1000,
+        size_of_heap_reserve: 0x10_0000,
+        size_of_heap_commit: 0x1000,
+    });
+    writer.write_section_headers();
+    writer.write_section(text_range.file_offset, &text);
+
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use object::{pe::ImageNtHeaders32, read::pe::PeFile};
+
+    use crate::generate::dummy_dll::dummy_dll_metadata::{
+        ASSEMBLY, ASSEMBLY_REF, CUSTOM_ATTRIBUTE, Cell, CodedIndex, FIELD, IMPL_MAP, MEMBER_REF,
+        METHOD_DEF, MODULE, MODULE_REF, MetadataBuilder, PARAM, TYPE_DEF, TYPE_REF, TableId, Token,
+    };
+
+    use super::*;
+
+    /// Size of the rows of the tables below, with 2 byte heap and table indices
+    const ROW_SIZES: [(TableId, usize); 10] = [
+        (MODULE, 10),
+        (TYPE_REF, 6),
+        (TYPE_DEF, 14),
+        (METHOD_DEF, 14),
+        (MEMBER_REF, 6),
+        (CUSTOM_ATTRIBUTE, 6),
+        (MODULE_REF, 2),
+        (IMPL_MAP, 8),
+        (ASSEMBLY, 22),
+        (ASSEMBLY_REF, 20),
+    ];
+
+    fn u16_at(data: &[u8], offset: usize) -> u16 {
+        u16::from_le_bytes(data[offset..offset + 2].try_into().unwrap())
+    }
+
+    fn u32_at(data: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    /// CLI header and the metadata root it points to
+    fn read_cli(image: &[u8]) -> (&[u8], &[u8]) {
+        let file = PeFile::<ImageNtHeaders32>::parse(image).unwrap();
+        let sections = file.section_table();
+        let cli_header = file
+            .data_directory(pe::IMAGE_DIRECTORY_ENTRY_COM_DESCRIPTOR)
+            .unwrap()
+            .data(image, &sections)
+            .unwrap();
+
+        let metadata = sections.pe_data_at(image, u32_at(cli_header, 8)).unwrap();
+        (cli_header, &metadata[..u32_at(cli_header, 12) as usize])
+    }
+
+    /// Contents of a stream of the metadata root
+    fn stream<'a>(metadata: &'a [u8], name: &str) -> &'a [u8] {
+        let version_len = u32_at(metadata, 12) as usize;
+        let stream_count = u16_at(metadata, 16 + version_len + 2);
+
+        let mut offset = 16 + version_len + 4;
+        for _ in 0..stream_count {
+            let start = u32_at(metadata, offset) as usize;
+            let size = u32_at(metadata, offset + 4) as usize;
+            let stream_name = metadata[offset + 8..].split(|b| *b == 0).next().unwrap();
+            if stream_name == name.as_bytes() {
+                return &metadata[start..start + size];
+            }
+            offset += 8 + (stream_name.len() + 1).next_multiple_of(4);
+        }
+
+        panic!("No {name} stream");
+    }
+
+    /// Rows of every present table, by table
+    fn read_tables(tables: &[u8]) -> Vec<(TableId, Vec<&[u8]>)> {
+        let valid = u64::from_le_bytes(tables[8..16].try_into().unwrap());
+        let present = (0..64)
+            .filter(|t| valid & (1 << t) != 0)
+            .collect::<Vec<_>>();
+
+        let mut offset = 24 + 4 * present.len();
+        let mut rows = vec![];
+        for (i, table) in present.into_iter().enumerate() {
+            let row_count = u32_at(tables, 24 + 4 * i) as usize;
+            let (_, row_size) = ROW_SIZES
+                .iter()
+                .find(|(t, _)| *t == table)
+                .unwrap_or_else(|| panic!("Unexpected table {table:#x}"));
+
+            rows.push((
+                table,
+                tables[offset..offset + row_count * row_size]
+                    .chunks(*row_size)
+                    .collect(),
+            ));
+            offset += row_count * row_size;
+        }
+        assert_eq!(offset.next_multiple_of(4), tables.len());
+
+        rows
+    }
+
+    fn add_type(builder: &mut MetadataBuilder, name: &str, extends: Token) -> Token {
+        let name = builder.string(name);
+        let method_list = builder.next_row(METHOD_DEF);
+        builder.add_row(
+            TYPE_DEF,
+            vec![
+                Cell::U32(0),
+                Cell::String(name),
+                Cell::String(0),
+                Cell::Coded(CodedIndex::TypeDefOrRef, extends),
+                Cell::Index(FIELD, 1),
+                Cell::Index(METHOD_DEF, method_list),
+            ],
+        )
+    }
+
+    fn add_method(builder: &mut MetadataBuilder, name: &str, flags: u16, rva: u32) -> Token {
+        let name = builder.string(name);
+        let signature = builder.blob(&[0x00, 0x00, 0x01]);
+        builder.add_row(
+            METHOD_DEF,
+            vec![
+                Cell::U32(rva),
+                Cell::U16(0),
+                Cell::U16(flags),
+                Cell::String(name),
+                Cell::Blob(signature),
+                Cell::Index(PARAM, 1),
+            ],
+        )
+    }
+
+    #[test]
+    fn writes_readable_assemblies() {
+        let mut builder = MetadataBuilder::default();
+        builder.add_assembly("Test");
+        let corlib = builder.add_assembly_ref("mscorlib");
+        let object = builder.add_type_ref(corlib, "System", "Object");
+        let attribute = builder.add_type_ref(corlib, "System", "ObsoleteAttribute");
+        let constructor = builder.add_member_ref(attribute, ".ctor", &[0x20, 0x00, 0x01]);
+
+        add_type(&mut builder, "<Module>", Token::NULL);
+        let first = add_type(&mut builder, "First", object);
+        let update = add_method(&mut builder, "Update", 0x0006, STUB_BODY_RVA);
+        let second = add_type(&mut builder, "Second", object);
+        let native = add_method(&mut builder, "Native", 0x2016, 0);
+        let library = builder.add_module_ref("libnative");
+        builder.add_impl_map(native, 0x0200, "native_entry", library);
+
+        // added out of order, rows are sorted by parent
+        for parent in [second, native, first, update] {
+            builder.add_custom_attribute(parent, constructor, &[0x01, 0x00, 0x00, 0x00]);
+        }
+
+        let image = write_pe(&builder.finish()).unwrap();
+        let (cli_header, metadata) = read_cli(&image);
+        assert_eq!(u32_at(cli_header, 0), CLI_HEADER_SIZE);
+        assert_eq!(u16_at(cli_header, 4), 2);
+        assert_eq!(u16_at(cli_header, 6), 5);
+        assert_eq!(u32_at(cli_header, 16), COMIMAGE_FLAGS_ILONLY);
+        assert_eq!(u32_at(metadata, 0), 0x424A_5342);
+
+        let file = PeFile::<ImageNtHeaders32>::parse(&*image).unwrap();
+        let stub = file
+            .section_table()
+            .pe_data_at(&*image, STUB_BODY_RVA)
+            .unwrap();
+        assert_eq!(stub[..STUB_BODY.len()], STUB_BODY);
+
+        let tables = stream(metadata, "#~");
+        // heap sizes
+        assert_eq!(tables[6], 0);
+        let tables = read_tables(tables);
+        assert_eq!(
+            tables
+                .iter()
+                .map(|(table, rows)| (*table, rows.len()))
+                .collect::<Vec<_>>(),
+            [
+                (MODULE, 1),
+                (TYPE_REF, 2),
+                (TYPE_DEF, 3),
+                (METHOD_DEF, 2),
+                (MEMBER_REF, 1),
+                (CUSTOM_ATTRIBUTE, 4),
+                (MODULE_REF, 1),
+                (IMPL_MAP, 1),
+                (ASSEMBLY, 1),
+                (ASSEMBLY_REF, 1),
+            ]
+        );
+
+        let rows = |table| &tables.iter().find(|(t, _)| *t == table).unwrap().1;
+        let parents = rows(CUSTOM_ATTRIBUTE)
+            .iter()
+            .map(|row| u16_at(row, 0) as u32)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            parents,
+            [update, native, first, second].map(|t| CodedIndex::HasCustomAttribute.encode(t))
+        );
+
+        let impl_map = rows(IMPL_MAP)[0];
+        assert_eq!(u16_at(impl_map, 0), 0x0200);
+        assert_eq!(
+            u16_at(impl_map, 2) as u32,
+            CodedIndex::MemberForwarded.encode(native)
+        );
+        assert_eq!(u16_at(impl_map, 6), 1);
+    }
+
+    #[test]
+    fn widens_large_heaps() {
+        let mut builder = MetadataBuilder::default();
+        builder.add_assembly("Test");
+        builder.string(&"a".repeat(0x10000));
+
+        let image = write_pe(&builder.finish()).unwrap();
+        let (_, metadata) = read_cli(&image);
+        let tables = stream(metadata, "#~");
+        // only the string heap is wide
+        assert_eq!(tables[6], 0x1);
+
+        // the module name, assembly name and culture take 4 bytes each
+        let rows_len: usize = 24 + 2 * 4 + (10 + 2) + (22 + 4);
+        assert_eq!(tables.len(), rows_len.next_multiple_of(4));
+    }
+}
@@ -0,0 +1,4 @@
+pub mod dummy_dll_attributes;
+pub mod dummy_dll_main;
+pub mod dummy_dll_metadata;
+pub mod dummy_dll_pe;
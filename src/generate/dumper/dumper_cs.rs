@@ -0,0 +1,668 @@
+//! Il2CppDumper style `dump.cs`
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use brocolib::global_metadata::{MethodIndex, TypeDefinitionIndex};
+use color_eyre::eyre::Result;
+use itertools::Itertools;
+
+use crate::{
+    data::custom_attributes::{
+        AttributeValue, CustomAttribute, ElementType, FIELD_DEF_TOKEN, METHOD_DEF_TOKEN,
+        NamedArgument, PROPERTY_TOKEN, TYPE_DEF_TOKEN,
+    },
+    generate::{
+        cs_context_collection::TypeContextCollection,
+        cs_members::{CsField, CsMethod, CsParamFlags, CsProperty, CsValue},
+        cs_type::CsType,
+        cs_type_tag::CsTypeTag,
+        metadata::CordlMetadata,
+        type_extensions::{
+            FIELD_ATTRIBUTE_FIELD_ACCESS_MASK, FIELD_ATTRIBUTE_INIT_ONLY, FIELD_ATTRIBUTE_LITERAL,
+            FIELD_ATTRIBUTE_NOT_SERIALIZED, FIELD_ATTRIBUTE_STATIC, METHOD_ATTRIBUTE_ABSTRACT,
+            METHOD_ATTRIBUTE_FINAL, METHOD_ATTRIBUTE_MEMBER_ACCESS_MASK, METHOD_ATTRIBUTE_NEW_SLOT,
+            METHOD_ATTRIBUTE_PINVOKE_IMPL, METHOD_ATTRIBUTE_STATIC, METHOD_ATTRIBUTE_VIRTUAL,
+            TYPE_ATTRIBUTE_ABSTRACT, TYPE_ATTRIBUTE_SEALED, TYPE_ATTRIBUTE_SERIALIZABLE,
+            TYPE_ATTRIBUTE_VISIBILITY_MASK,
+        },
+    },
+};
+
+use super::{dumper_main::FileOffsets, dumper_name_resolver::DumperNameResolver};
+
+/// Parents Il2CppDumper leaves out of the base list
+const IMPLICIT_PARENTS: &[&str] = &["System.Object", "System.ValueType", "System.Enum"];
+
+struct DumpContext<'a, 'b> {
+    metadata: &'a CordlMetadata<'b>,
+    resolver: DumperNameResolver<'a, 'b>,
+    offsets: FileOffsets,
+    /// definition -> address -> every instantiation compiled to it
+    generic_instances: HashMap<MethodIndex, BTreeMap<u64, Vec<String>>>,
+}
+
+pub fn write_dump_cs(
+    collection: &TypeContextCollection,
+    metadata: &CordlMetadata,
+    file: &Path,
+) -> Result<()> {
+    let resolver = DumperNameResolver {
+        cordl_metadata: metadata,
+        collection,
+    };
+    let context = DumpContext {
+        metadata,
+        generic_instances: generic_instances(collection, &resolver),
+        resolver,
+        offsets: FileOffsets::new(metadata.elf_data)?,
+    };
+
+    let md = metadata.metadata;
+    let images = md.global_metadata.images.as_vec();
+    let mut writer = BufWriter::new(File::create(file)?);
+
+    for (i, image) in images.iter().enumerate() {
+        writeln!(
+            writer,
+            "// Image {i}: {} - {}",
+            image.name(md),
+            image.type_start.index()
+        )?;
+    }
+    writeln!(writer)?;
+
+    for (image_index, image) in images.iter().enumerate() {
+        let start = image.type_start.index();
+        for tdi in (start..start + image.type_count).map(TypeDefinitionIndex::new) {
+            let Some(ty) = collection.get_cs_type(CsTypeTag::TypeDefinitionIndex(tdi)) else {
+                continue;
+            };
+
+            write_type(&mut writer, &context, image_index, tdi, ty)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Instantiations of generic types and methods, which cordl makes separately,
+/// grouped under their definition
+fn generic_instances(
+    collection: &TypeContextCollection,
+    resolver: &DumperNameResolver,
+) -> HashMap<MethodIndex, BTreeMap<u64, Vec<String>>> {
+    let mut instances: HashMap<MethodIndex, BTreeMap<u64, Vec<String>>> = HashMap::new();
+
+    for ty in collection
+        .get()
+        .values()
+        .flat_map(|c| c.get_types().values())
+    {
+        let generic_type = matches!(ty.self_tag, CsTypeTag::GenericInstantiation(_));
+
+        for method in &ty.methods {
+            if !generic_type && method.generic_instatiation.is_none() {
+                continue;
+            }
+            let Some(addrs) = method.method_data.addrs.filter(|a| *a != 0x0) else {
+                continue;
+            };
+
+            let method_generics = method
+                .generic_instatiation
+                .as_ref()
+                .map(|args| {
+                    format!(
+                        "<{}>",
+                        args.iter().map(|a| resolver.resolve_name(a)).join(", ")
+                    )
+                })
+                .unwrap_or_default();
+            let name = format!(
+                "{}.{}{method_generics}",
+                resolver.tag_name(ty.self_tag, true),
+                method.name
+            );
+
+            instances
+                .entry(method.method_index)
+                .or_default()
+                .entry(addrs)
+                .or_default()
+                .push(name);
+        }
+    }
+
+    instances
+}
+
+fn write_type(
+    writer: &mut impl Write,
+    context: &DumpContext,
+    image_index: usize,
+    tdi: TypeDefinitionIndex,
+    ty: &CsType,
+) -> Result<()> {
+    let metadata = context.metadata;
+    let md = metadata.metadata;
+    let td = &md.global_metadata.type_definitions[tdi];
+    let attributes = |token| metadata.custom_attributes.get(image_index, token);
+
+    writeln!(writer, "// Namespace: {}", ty.namespace())?;
+    write_attributes(
+        writer,
+        context,
+        "",
+        attributes(TYPE_DEF_TOKEN | td.token.rid() as u32),
+    )?;
+    if td.flags & TYPE_ATTRIBUTE_SERIALIZABLE != 0 {
+        writeln!(writer, "[Serializable]")?;
+    }
+
+    let visibility = match td.flags & TYPE_ATTRIBUTE_VISIBILITY_MASK {
+        1 | 2 => "public ",
+        3 => "private ",
+        4 => "protected ",
+        6 => "private protected ",
+        7 => "protected internal ",
+        _ => "internal ",
+    };
+    let is_abstract = td.flags & TYPE_ATTRIBUTE_ABSTRACT != 0;
+    let is_sealed = td.flags & TYPE_ATTRIBUTE_SEALED != 0;
+    let modifier = match (is_abstract, is_sealed) {
+        (true, true) => "static ",
+        (true, false) if !ty.is_interface => "abstract ",
+        (false, true) if !ty.is_value_type && !ty.is_enum_type => "sealed ",
+        _ => "",
+    };
+    let kind = if ty.is_enum_type {
+        "enum"
+    } else if ty.is_value_type {
+        "struct"
+    } else if ty.is_interface {
+        "interface"
+    } else {
+        "class"
+    };
+
+    let bases = ty
+        .parent
+        .iter()
+        .filter(|p| {
+            let parent_name = metadata.metadata_registration.types[p.ty].full_name(md);
+            !IMPLICIT_PARENTS.contains(&parent_name.as_str())
+        })
+        .chain(&ty.interfaces)
+        .map(|t| context.resolver.resolve_name(t))
+        .collect_vec();
+
+    write!(
+        writer,
+        "{visibility}{modifier}{kind} {}",
+        context.resolver.tag_name(ty.self_tag, true)
+    )?;
+    if !bases.is_empty() {
+        write!(writer, " : {}", bases.join(", "))?;
+    }
+    writeln!(writer, " // TypeDefIndex: {}", tdi.index())?;
+    writeln!(writer, "{{")?;
+
+    if !ty.fields.is_empty() {
+        writeln!(writer, "\t// Fields")?;
+        // fields of the model line up with the definition's
+        for (field, field_def) in ty.fields.iter().zip(td.fields(md)) {
+            let token = FIELD_DEF_TOKEN | field_def.token.rid() as u32;
+            write_attributes(writer, context, "\t", attributes(token))?;
+            write_field(writer, context, field)?;
+        }
+    }
+
+    if !ty.properties.is_empty() {
+        writeln!(writer)?;
+        writeln!(writer, "\t// Properties")?;
+        for property in &ty.properties {
+            let property_def = td
+                .properties(md)
+                .iter()
+                .find(|p| p.name(md) == property.name);
+            if let Some(property_def) = property_def {
+                let token = PROPERTY_TOKEN | property_def.token.rid() as u32;
+                write_attributes(writer, context, "\t", attributes(token))?;
+            }
+            write_property(writer, context, property)?;
+        }
+    }
+
+    let methods = ty
+        .methods
+        .iter()
+        .filter(|m| m.generic_instatiation.is_none())
+        .collect_vec();
+    if !methods.is_empty() {
+        writeln!(writer)?;
+        writeln!(writer, "\t// Methods")?;
+        for method in methods {
+            writeln!(writer)?;
+            let method_def = &md.global_metadata.methods[method.method_index];
+            let token = METHOD_DEF_TOKEN | method_def.token.rid() as u32;
+            write_attributes(writer, context, "\t", attributes(token))?;
+            write_method(writer, context, method)?;
+        }
+    }
+
+    writeln!(writer, "}}")?;
+    writeln!(writer)?;
+
+    Ok(())
+}
+
+fn format_value(value: &CsValue) -> Option<String> {
+    Some(match value {
+        CsValue::String(s) => format!("\"{s}\""),
+        CsValue::Char(c) => format!("'{c}'"),
+        CsValue::Bool(b) => b.to_string(),
+        CsValue::U8(x) => x.to_string(),
+        CsValue::U16(x) => x.to_string(),
+        CsValue::U32(x) => x.to_string(),
+        CsValue::U64(x) => x.to_string(),
+        CsValue::I8(x) => x.to_string(),
+        CsValue::I16(x) => x.to_string(),
+        CsValue::I32(x) => x.to_string(),
+        CsValue::I64(x) => x.to_string(),
+        CsValue::F32(x) => format!("{x:?}"),
+        CsValue::F64(x) => format!("{x:?}"),
+        CsValue::Null => "null".to_string(),
+        CsValue::Object(_) | CsValue::ValueType(_) => return None,
+    })
+}
+
+/// `[Name(arguments, Member = value)]` lines, `Attribute` is left out of the name like in C#
+fn write_attributes(
+    writer: &mut impl Write,
+    context: &DumpContext,
+    indent: &str,
+    attributes: &[CustomAttribute],
+) -> Result<()> {
+    let metadata = context.metadata;
+    let md = metadata.metadata;
+    let gm = &md.global_metadata;
+
+    for attribute in attributes {
+        let attribute_tdi = gm.methods[attribute.constructor].declaring_type;
+        let name = gm.type_definitions[attribute_tdi].name(md);
+        let name = name.strip_suffix("Attribute").unwrap_or(name);
+
+        let named = |argument: &NamedArgument, property: bool| {
+            let td = &gm.type_definitions[argument.declaring_type.unwrap_or(attribute_tdi)];
+            let index = argument.index as usize;
+            let name = match property {
+                true => td.properties(md).get(index)?.name(md),
+                false => td.fields(md).get(index)?.name(md),
+            };
+            Some(format!(
+                "{name} = {}",
+                format_attribute_value(metadata, &argument.value)
+            ))
+        };
+        let arguments = attribute
+            .arguments
+            .iter()
+            .map(|value| format_attribute_value(metadata, value))
+            .chain(attribute.fields.iter().filter_map(|f| named(f, false)))
+            .chain(attribute.properties.iter().filter_map(|p| named(p, true)))
+            .join(", ");
+
+        match arguments.is_empty() {
+            true => writeln!(writer, "{indent}[{name}]")?,
+            false => writeln!(writer, "{indent}[{name}({arguments})]")?,
+        }
+    }
+
+    Ok(())
+}
+
+/// C# expression of an attribute argument
+fn format_attribute_value(metadata: &CordlMetadata, value: &AttributeValue) -> String {
+    let md = metadata.metadata;
+    let type_name =
+        |type_index: usize| metadata.metadata_registration.types[type_index].full_name(md);
+
+    match value {
+        AttributeValue::Null
+        | AttributeValue::String(None)
+        | AttributeValue::Type(None)
+        | AttributeValue::Array(_, None) => "null".to_string(),
+        AttributeValue::Bool(b) => b.to_string(),
+        AttributeValue::Char(c) => {
+            let c = String::from_utf16_lossy(&[*c]);
+            format!("'{}'", csharp_escape(&c, '\''))
+        }
+        AttributeValue::I8(x) => x.to_string(),
+        AttributeValue::U8(x) => x.to_string(),
+        AttributeValue::I16(x) => x.to_string(),
+        AttributeValue::U16(x) => x.to_string(),
+        AttributeValue::I32(x) => x.to_string(),
+        AttributeValue::U32(x) => x.to_string(),
+        AttributeValue::I64(x) => x.to_string(),
+        AttributeValue::U64(x) => x.to_string(),
+        AttributeValue::F32(x) => format!("{x:?}f"),
+        AttributeValue::F64(x) => format!("{x:?}"),
+        AttributeValue::String(Some(s)) => format!("\"{}\"", csharp_escape(s, '"')),
+        AttributeValue::Type(Some(type_index)) => format!("typeof({})", type_name(*type_index)),
+        AttributeValue::Enum(type_index, value) => {
+            // `(Type)-1` would parse as a subtraction
+            let value = format_attribute_value(metadata, value);
+            match value.starts_with('-') {
+                true => format!("({})({value})", type_name(*type_index)),
+                false => format!("({}){value}", type_name(*type_index)),
+            }
+        }
+        AttributeValue::Array(element, Some(values)) => {
+            let element = match element {
+                ElementType::Bool => "bool".to_string(),
+                ElementType::Char => "char".to_string(),
+                ElementType::I8 => "sbyte".to_string(),
+                ElementType::U8 => "byte".to_string(),
+                ElementType::I16 => "short".to_string(),
+                ElementType::U16 => "ushort".to_string(),
+                ElementType::I32 => "int".to_string(),
+                ElementType::U32 => "uint".to_string(),
+                ElementType::I64 => "long".to_string(),
+                ElementType::U64 => "ulong".to_string(),
+                ElementType::F32 => "float".to_string(),
+                ElementType::F64 => "double".to_string(),
+                ElementType::String => "string".to_string(),
+                ElementType::Type => "System.Type".to_string(),
+                ElementType::Object => "object".to_string(),
+                ElementType::Enum(type_index) => type_name(*type_index),
+            };
+            let values = values
+                .iter()
+                .map(|value| format_attribute_value(metadata, value))
+                .join(", ");
+            format!("new {element}[] {{ {values} }}")
+        }
+    }
+}
+
+/// Escapes for a C# literal quoted with `quote`, anything printable is kept as is
+fn csharp_escape(s: &str, quote: char) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            '\0' => escaped.push_str("\\0"),
+            c if c == quote => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04X}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+fn write_field(writer: &mut impl Write, context: &DumpContext, field: &CsField) -> Result<()> {
+    let attrs = context.metadata.metadata_registration.types[field.field_ty.ty].attrs;
+
+    if attrs & FIELD_ATTRIBUTE_NOT_SERIALIZED != 0 {
+        writeln!(writer, "\t[NonSerialized]")?;
+    }
+
+    let access = match attrs & FIELD_ATTRIBUTE_FIELD_ACCESS_MASK {
+        1 => "private ",
+        2 => "private protected ",
+        3 => "internal ",
+        4 => "protected ",
+        5 => "protected internal ",
+        6 => "public ",
+        _ => "",
+    };
+    let modifiers = if attrs & FIELD_ATTRIBUTE_LITERAL != 0 {
+        "const "
+    } else {
+        match (
+            attrs & FIELD_ATTRIBUTE_STATIC != 0,
+            attrs & FIELD_ATTRIBUTE_INIT_ONLY != 0,
+        ) {
+            (true, true) => "static readonly ",
+            (true, false) => "static ",
+            (false, true) => "readonly ",
+            (false, false) => "",
+        }
+    };
+
+    write!(
+        writer,
+        "\t{access}{modifiers}{} {}",
+        context.resolver.resolve_name(&field.field_ty),
+        field.name
+    )?;
+    if let Some(value) = field.value.as_ref().and_then(format_value) {
+        write!(writer, " = {value}")?;
+    }
+    match field.offset {
+        Some(offset) => writeln!(writer, "; // 0x{offset:X}")?,
+        None => writeln!(writer, ";")?,
+    }
+
+    Ok(())
+}
+
+fn method_modifiers(flags: u16) -> String {
+    let access = match flags & METHOD_ATTRIBUTE_MEMBER_ACCESS_MASK {
+        1 => "private ",
+        2 => "private protected ",
+        3 => "internal ",
+        4 => "protected ",
+        5 => "protected internal ",
+        6 => "public ",
+        _ => "",
+    };
+
+    let mut modifiers = access.to_string();
+    if flags & METHOD_ATTRIBUTE_STATIC != 0 {
+        modifiers.push_str("static ");
+    }
+    if flags & METHOD_ATTRIBUTE_ABSTRACT != 0 {
+        modifiers.push_str("abstract ");
+        if flags & METHOD_ATTRIBUTE_NEW_SLOT == 0 {
+            modifiers.push_str("override ");
+        }
+    } else if flags & METHOD_ATTRIBUTE_VIRTUAL != 0 {
+        match (
+            flags & METHOD_ATTRIBUTE_FINAL != 0,
+            flags & METHOD_ATTRIBUTE_NEW_SLOT != 0,
+        ) {
+            (true, false) => modifiers.push_str("sealed override "),
+            (true, true) => {}
+            (false, false) => modifiers.push_str("override "),
+            (false, true) => modifiers.push_str("virtual "),
+        }
+    }
+    if flags & METHOD_ATTRIBUTE_PINVOKE_IMPL != 0 {
+        modifiers.push_str("extern ");
+    }
+
+    modifiers
+}
+
+fn write_property(
+    writer: &mut impl Write,
+    context: &DumpContext,
+    property: &CsProperty,
+) -> Result<()> {
+    let methods = &context.metadata.metadata.global_metadata.methods;
+    let flags = property
+        .getter
+        .as_ref()
+        .or(property.setter.as_ref())
+        .map(|(method_index, _)| methods[*method_index].flags)
+        .unwrap_or_default();
+
+    let accessors = [
+        property.getter.as_ref().map(|_| "get;"),
+        property.setter.as_ref().map(|_| "set;"),
+    ]
+    .into_iter()
+    .flatten()
+    .join(" ");
+
+    writeln!(
+        writer,
+        "\t{}{} {} {{ {accessors} }}",
+        method_modifiers(flags),
+        context.resolver.resolve_name(&property.prop_ty),
+        property.name
+    )?;
+
+    Ok(())
+}
+
+fn write_address(writer: &mut impl Write, offsets: &FileOffsets, addrs: u64) -> Result<()> {
+    let offset = offsets.file_offset(addrs).unwrap_or(addrs);
+    write!(
+        writer,
+        "RVA: 0x{addrs:X} Offset: 0x{offset:X} VA: 0x{addrs:X}"
+    )?;
+
+    Ok(())
+}
+
+fn write_method(writer: &mut impl Write, context: &DumpContext, method: &CsMethod) -> Result<()> {
+    let method_def = &context.metadata.metadata.global_metadata.methods[method.method_index];
+
+    write!(writer, "\t// ")?;
+    match method.method_data.addrs.filter(|a| *a != 0x0) {
+        Some(addrs) => write_address(writer, &context.offsets, addrs)?,
+        None => write!(writer, "RVA: -1 Offset: -1")?,
+    }
+    if let Some(slot) = method.method_data.slot {
+        write!(writer, " Slot: {slot}")?;
+    }
+    writeln!(writer)?;
+
+    let generics = method
+        .template
+        .as_ref()
+        .map(|t| format!("<{}>", t.just_names().join(", ")))
+        .unwrap_or_default();
+    let params = method
+        .parameters
+        .iter()
+        .map(|p| {
+            let modifier = if p.modifiers.contains(CsParamFlags::OUT) {
+                "out "
+            } else if p.modifiers.contains(CsParamFlags::IN) {
+                "in "
+            } else if p.modifiers.contains(CsParamFlags::REF) {
+                "ref "
+            } else {
+                ""
+            };
+
+            format!(
+                "{modifier}{} {}",
+                context.resolver.resolve_name(&p.il2cpp_ty),
+                p.name
+            )
+        })
+        .join(", ");
+
+    writeln!(
+        writer,
+        "\t{}{} {}{generics}({params}) {{ }}",
+        method_modifiers(method_def.flags),
+        context.resolver.resolve_name(&method.return_type),
+        method.name
+    )?;
+
+    if let Some(instances) = context.generic_instances.get(&method.method_index) {
+        writeln!(writer, "\t/* GenericInstMethod :")?;
+        for (addrs, names) in instances {
+            writeln!(writer, "\t|")?;
+            write!(writer, "\t|-")?;
+            write_address(writer, &context.offsets, *addrs)?;
+            writeln!(writer)?;
+            for name in names {
+                writeln!(writer, "\t|-{name}")?;
+            }
+        }
+        writeln!(writer, "\t*/")?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_file_offsets_of_addresses() {
+        // a data segment loaded 0x1000 past its file offset
+        let offsets = FileOffsets {
+            segments: vec![(0x0, 0x8000, 0x0), (0x9000, 0x1000, 0x8000)],
+        };
+        let address = |addrs| {
+            let mut line = vec![];
+            write_address(&mut line, &offsets, addrs).unwrap();
+            String::from_utf8(line).unwrap()
+        };
+
+        assert_eq!(address(0x1A2C), "RVA: 0x1A2C Offset: 0x1A2C VA: 0x1A2C");
+        assert_eq!(address(0x9010), "RVA: 0x9010 Offset: 0x8010 VA: 0x9010");
+        // outside every segment
+        assert_eq!(address(0x8800), "RVA: 0x8800 Offset: 0x8800 VA: 0x8800");
+    }
+
+    #[test]
+    fn escapes_csharp_literals() {
+        assert_eq!(csharp_escape("Play", '"'), "Play");
+        assert_eq!(csharp_escape("say \"hi\"", '"'), "say \\\"hi\\\"");
+        assert_eq!(csharp_escape("it's", '\''), "it\\'s");
+        assert_eq!(csharp_escape("it's", '"'), "it's");
+        assert_eq!(csharp_escape("a\\b\n\0", '"'), "a\\\\b\\n\\0");
+        assert_eq!(csharp_escape("\u{1B}é", '"'), "\\u001Bé");
+    }
+
+    #[test]
+    fn writes_method_modifiers() {
+        assert_eq!(method_modifiers(0x0006), "public ");
+        assert_eq!(
+            method_modifiers(0x0006 | METHOD_ATTRIBUTE_STATIC),
+            "public static "
+        );
+        assert_eq!(
+            method_modifiers(0x0004 | METHOD_ATTRIBUTE_VIRTUAL | METHOD_ATTRIBUTE_NEW_SLOT),
+            "protected virtual "
+        );
+        assert_eq!(
+            method_modifiers(0x0006 | METHOD_ATTRIBUTE_VIRTUAL | METHOD_ATTRIBUTE_FINAL),
+            "public sealed override "
+        );
+        assert_eq!(
+            method_modifiers(
+                0x0006
+                    | METHOD_ATTRIBUTE_VIRTUAL
+                    | METHOD_ATTRIBUTE_ABSTRACT
+                    | METHOD_ATTRIBUTE_NEW_SLOT
+            ),
+            "public abstract "
+        );
+        assert_eq!(
+            method_modifiers(0x0001 | METHOD_ATTRIBUTE_STATIC | METHOD_ATTRIBUTE_PINVOKE_IMPL),
+            "private static extern "
+        );
+    }
+}
@@ -0,0 +1,53 @@
+use std::path::Path;
+
+use color_eyre::eyre::{Context, Result};
+use object::{Object, ObjectSegment};
+
+use crate::generate::{cs_context_collection::TypeContextCollection, metadata::CordlMetadata};
+
+use super::{dumper_cs, dumper_script};
+
+/// Maps virtual addresses to file offsets through the loaded segments
+pub struct FileOffsets {
+    /// (virtual address, size, file offset)
+    pub(super) segments: Vec<(u64, u64, u64)>,
+}
+
+impl FileOffsets {
+    pub fn new(elf_data: &[u8]) -> Result<Self> {
+        let file = object::File::parse(elf_data).context("Unable to parse ELF file")?;
+
+        let segments = file
+            .segments()
+            .map(|s| {
+                let (offset, size) = s.file_range();
+                (s.address(), size, offset)
+            })
+            .collect();
+
+        Ok(FileOffsets { segments })
+    }
+
+    pub fn file_offset(&self, addr: u64) -> Option<u64> {
+        self.segments.iter().find_map(|(start, size, offset)| {
+            let delta = addr.checked_sub(*start)?;
+            (delta < *size).then_some(offset + delta)
+        })
+    }
+}
+
+pub fn run_dumper(
+    collection: &TypeContextCollection,
+    metadata: &CordlMetadata,
+    format: bool,
+) -> Result<()> {
+    let dump_cs = Path::new("./dump.cs");
+    println!("Writing {dump_cs:?}");
+    dumper_cs::write_dump_cs(collection, metadata, dump_cs)?;
+
+    let script_json = Path::new("./script.json");
+    println!("Writing {script_json:?}");
+    dumper_script::write_script_json(collection, metadata, script_json, format)?;
+
+    Ok(())
+}
@@ -0,0 +1,104 @@
+use brocolib::runtime_metadata::Il2CppTypeEnum;
+use itertools::Itertools;
+
+use crate::{
+    data::type_resolver::{ResolvedType, ResolvedTypeData},
+    generate::{
+        cs_context_collection::TypeContextCollection, cs_type_tag::CsTypeTag,
+        metadata::CordlMetadata,
+    },
+};
+
+/// Resolves types to C# source names, without namespaces like Il2CppDumper
+pub struct DumperNameResolver<'a, 'b> {
+    pub cordl_metadata: &'a CordlMetadata<'b>,
+    pub collection: &'a TypeContextCollection,
+}
+
+impl DumperNameResolver<'_, '_> {
+    pub fn resolve_name(&self, ty: &ResolvedType) -> String {
+        let metadata = self.cordl_metadata;
+        match &ty.data {
+            ResolvedTypeData::Primitive(il2cpp_type_enum) => {
+                primitive_name(*il2cpp_type_enum).to_string()
+            }
+            ResolvedTypeData::Type(tag) | ResolvedTypeData::Blacklisted(tag) => {
+                self.tag_name(*tag, true)
+            }
+            ResolvedTypeData::GenericInst(generic_type, args) => {
+                let name = match &generic_type.data {
+                    ResolvedTypeData::Type(tag) | ResolvedTypeData::Blacklisted(tag) => {
+                        self.tag_name(*tag, false)
+                    }
+                    _ => self.resolve_name(generic_type),
+                };
+                let args = args
+                    .iter()
+                    .map(|(arg, _)| self.resolve_name(arg))
+                    .join(", ");
+
+                format!("{name}<{args}>")
+            }
+            ResolvedTypeData::GenericArg(gen_param_idx, _)
+            | ResolvedTypeData::GenericMethodArg(_, gen_param_idx, _) => {
+                let generic_param =
+                    &metadata.metadata.global_metadata.generic_parameters[*gen_param_idx];
+
+                generic_param.name(metadata.metadata).to_string()
+            }
+            ResolvedTypeData::Array(element) => format!("{}[]", self.resolve_name(element)),
+            ResolvedTypeData::Ptr(pointee) => format!("{}*", self.resolve_name(pointee)),
+            // `ref`/`out`/`in` are written by the parameter
+            ResolvedTypeData::ByRef(inner) | ResolvedTypeData::ByRefConst(inner) => {
+                self.resolve_name(inner)
+            }
+        }
+    }
+
+    /// `Outer.Inner<T>`
+    pub fn tag_name(&self, tag: CsTypeTag, include_generics: bool) -> String {
+        let metadata = self.cordl_metadata;
+
+        let Some(cs_type) = self.collection.get_cs_type(tag) else {
+            let td = &metadata.metadata.global_metadata.type_definitions[tag.get_tdi()];
+            return td.name(metadata.metadata).to_string();
+        };
+
+        let components = &cs_type.cs_name_components;
+        let mut name = components
+            .declaring_types
+            .iter()
+            .flatten()
+            .chain([&components.name])
+            .join(".");
+
+        if include_generics && let Some(generics) = &components.generics {
+            name = format!("{name}<{}>", generics.join(", "));
+        }
+
+        name
+    }
+}
+
+fn primitive_name(il2cpp_type_enum: Il2CppTypeEnum) -> &'static str {
+    match il2cpp_type_enum {
+        Il2CppTypeEnum::Void => "void",
+        Il2CppTypeEnum::Boolean => "bool",
+        Il2CppTypeEnum::Char => "char",
+        Il2CppTypeEnum::I1 => "sbyte",
+        Il2CppTypeEnum::U1 => "byte",
+        Il2CppTypeEnum::I2 => "short",
+        Il2CppTypeEnum::U2 => "ushort",
+        Il2CppTypeEnum::I4 => "int",
+        Il2CppTypeEnum::U4 => "uint",
+        Il2CppTypeEnum::I8 => "long",
+        Il2CppTypeEnum::U8 => "ulong",
+        Il2CppTypeEnum::R4 => "float",
+        Il2CppTypeEnum::R8 => "double",
+        Il2CppTypeEnum::String => "string",
+        Il2CppTypeEnum::I => "IntPtr",
+        Il2CppTypeEnum::U => "UIntPtr",
+        Il2CppTypeEnum::Typedbyref => "TypedReference",
+        _ => "object",
+    }
+}
@@ -0,0 +1,208 @@
+//! Il2CppDumper style `script.json`, consumed by its disassembler scripts together with il2cpp.h
+
+use std::{collections::BTreeSet, fs::File, io::BufWriter, path::Path};
+
+use brocolib::{global_metadata::MethodIndex, runtime_metadata::TypeData};
+use color_eyre::eyre::Result;
+use itertools::Itertools;
+use log::info;
+use serde::Serialize;
+
+use crate::{
+    analysis::xrefs::XrefAnalysis,
+    data::metadata_usage::MetadataUsage,
+    generate::{
+        cs_context_collection::TypeContextCollection,
+        cs_type_tag::CsTypeTag,
+        disasm::{disasm_main::DisasmPrototype, disasm_name_resolver::DisasmNameResolver},
+        metadata::CordlMetadata,
+    },
+};
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct ScriptJson {
+    script_method: Vec<ScriptMethod>,
+    script_string: Vec<ScriptString>,
+    script_metadata: Vec<ScriptMetadata>,
+    script_metadata_method: Vec<ScriptMetadataMethod>,
+    addresses: Vec<u64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct ScriptMethod {
+    address: u64,
+    name: String,
+    /// C prototype using il2cpp.h names
+    signature: String,
+    type_signature: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct ScriptString {
+    address: u64,
+    value: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct ScriptMetadata {
+    address: u64,
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signature: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct ScriptMetadataMethod {
+    address: u64,
+    name: String,
+    method_address: u64,
+}
+
+/// One character per parameter, as Il2CppDumper encodes them
+fn type_signature(prototype: &DisasmPrototype) -> String {
+    [&prototype.ret]
+        .into_iter()
+        .chain(prototype.params.iter().map(|(ty, _)| ty))
+        .map(|ty| match ty.as_str() {
+            "void" => 'v',
+            "int64_t" | "uint64_t" => 'j',
+            "float" => 'f',
+            "double" => 'd',
+            _ => 'i',
+        })
+        .collect()
+}
+
+pub fn write_script_json(
+    collection: &TypeContextCollection,
+    metadata: &CordlMetadata,
+    file: &Path,
+    format: bool,
+) -> Result<()> {
+    let mut resolver = DisasmNameResolver::new(metadata, collection);
+
+    let mut script_method = collection
+        .get()
+        .values()
+        .flat_map(|c| c.get_types().values())
+        .flat_map(|ty| ty.methods.iter().map(move |method| (ty, method)))
+        .filter_map(|(ty, method)| {
+            let addrs = method.method_data.addrs.filter(|a| *a != 0x0)?;
+            let name = format!("{}$${}", ty.cs_name_components.combine_all(), method.name);
+            let prototype = DisasmPrototype::new(&mut resolver, ty, method);
+
+            Some(ScriptMethod {
+                address: addrs,
+                signature: format!("{};", prototype.to_c(&name)),
+                type_signature: type_signature(&prototype),
+                name,
+            })
+        })
+        .collect_vec();
+    script_method.sort_by(|a, b| a.address.cmp(&b.address).then(a.name.cmp(&b.name)));
+
+    info!("Analyzing metadata usages");
+    let xrefs = XrefAnalysis::analyze(metadata)?;
+
+    let mut script_string = vec![];
+    let mut script_metadata = vec![];
+    let mut script_metadata_method = vec![];
+    for (addrs, usage) in &xrefs.usage_slots {
+        let usage_name = XrefAnalysis::usage_name(usage, metadata);
+
+        match *usage {
+            MetadataUsage::StringLiteral(i) => script_string.push(ScriptString {
+                address: *addrs,
+                value: metadata.string_literal(i as usize).unwrap_or_default(),
+            }),
+            MetadataUsage::TypeInfo(i) => {
+                let tag = match metadata.metadata_registration.types[i as usize].data {
+                    TypeData::TypeDefinitionIndex(tdi) => Some(tdi.into()),
+                    TypeData::GenericClassIndex(generic_class) => Some(
+                        CsTypeTag::from_generic_class_index(generic_class, metadata.metadata),
+                    ),
+                    _ => None,
+                };
+
+                script_metadata.push(ScriptMetadata {
+                    address: *addrs,
+                    name: format!("{usage_name}_TypeInfo"),
+                    signature: tag
+                        .and_then(|tag| resolver.type_name(tag))
+                        .map(|name| format!("{name}_c*")),
+                });
+            }
+            MetadataUsage::Il2CppType(_) => script_metadata.push(ScriptMetadata {
+                address: *addrs,
+                name: format!("{usage_name}_var"),
+                signature: None,
+            }),
+            MetadataUsage::FieldInfo(_) | MetadataUsage::FieldRva(_) => {
+                script_metadata.push(ScriptMetadata {
+                    address: *addrs,
+                    name: format!("Field${usage_name}"),
+                    signature: None,
+                })
+            }
+            MetadataUsage::MethodDef(i) => script_metadata_method.push(ScriptMetadataMethod {
+                address: *addrs,
+                name: format!("Method${usage_name}"),
+                method_address: metadata
+                    .method_calculations
+                    .get(&MethodIndex::new(i))
+                    .map(|calc| calc.addrs)
+                    .unwrap_or_default(),
+            }),
+            // generic instances have no single definition address
+            MetadataUsage::MethodRef(_) => script_metadata_method.push(ScriptMetadataMethod {
+                address: *addrs,
+                name: format!("Method${usage_name}"),
+                method_address: 0,
+            }),
+        }
+    }
+
+    let addresses: BTreeSet<u64> = metadata
+        .method_calculations
+        .values()
+        .flat_map(|calc| {
+            [
+                Some(calc.addrs),
+                calc.invoker_addrs,
+                calc.adjustor_thunk_addrs,
+                calc.reverse_pinvoke_wrapper_addrs,
+            ]
+        })
+        .flatten()
+        .chain(script_method.iter().map(|m| m.address))
+        .chain(
+            metadata
+                .code_registration
+                .unresolved_virtual_call_pointers
+                .iter()
+                .copied(),
+        )
+        .filter(|a| *a != 0x0)
+        .collect();
+
+    let script = ScriptJson {
+        script_method,
+        script_string,
+        script_metadata,
+        script_metadata_method,
+        addresses: addresses.into_iter().collect(),
+    };
+
+    let mut buf_writer = BufWriter::new(File::create(file)?);
+    match format {
+        true => serde_json::to_writer_pretty(&mut buf_writer, &script)?,
+        false => serde_json::to_writer(&mut buf_writer, &script)?,
+    };
+
+    Ok(())
+}
@@ -0,0 +1,4 @@
+pub mod dumper_cs;
+pub mod dumper_main;
+pub mod dumper_name_resolver;
+pub mod dumper_script;
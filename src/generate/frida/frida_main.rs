@@ -0,0 +1,330 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use color_eyre::eyre::{Context, Result};
+use itertools::Itertools;
+
+use crate::generate::{
+    cs_context_collection::TypeContextCollection, cs_type::CsType, metadata::CordlMetadata,
+};
+
+use super::frida_name_resolver::{FridaNameResolver, sanitize_identifier};
+
+/// Shared by every namespace module, rebases addresses and wraps `NativeFunction` and `Interceptor`
+const RUNTIME: &str = r#"// Generated by cordl
+
+export const base: NativePointer = Process.getModuleByName("libil2cpp.so").base;
+
+/** A compiled method, `rva` is relative to the base of libil2cpp.so */
+export class Method {
+    readonly address: NativePointer;
+    #native?: NativeFunction<any, any>;
+
+    constructor(
+        readonly name: string,
+        readonly rva: number,
+        readonly returnType: NativeFunctionReturnType,
+        readonly argTypes: NativeFunctionArgumentType[],
+        readonly argNames: string[],
+    ) {
+        this.address = base.add(rva);
+    }
+
+    /** Instance methods take `this` first, every method takes a trailing `MethodInfo*` */
+    get native(): NativeFunction<any, any> {
+        return (this.#native ??= new NativeFunction(this.address, this.returnType, this.argTypes));
+    }
+
+    attach(callbacks: InvocationListenerCallbacks): InvocationListener {
+        return Interceptor.attach(this.address, callbacks);
+    }
+
+    replace(replacement: NativePointerValue): void {
+        Interceptor.replace(this.address, replacement);
+    }
+
+    /** Logs every call with its named arguments and return value */
+    trace(): InvocationListener {
+        const method = this;
+        return this.attach({
+            onEnter(args) {
+                const values = method.argNames.map((name, i) => `${name}=${args[i]}`);
+                console.log(`${method.name}(${values.join(", ")})`);
+            },
+            onLeave(retval) {
+                if (method.returnType !== "void") {
+                    console.log(`${method.name} -> ${retval}`);
+                }
+            },
+        });
+    }
+}
+
+interface FieldTypes {
+    bool: boolean;
+    int8: number;
+    uint8: number;
+    int16: number;
+    uint16: number;
+    int32: number;
+    uint32: number;
+    int64: Int64;
+    uint64: UInt64;
+    float: number;
+    double: number;
+    pointer: NativePointer;
+    /** Value types are embedded, this is their address */
+    struct: NativePointer;
+}
+
+export type FieldKind = keyof FieldTypes;
+
+/** Instance field, offsets of value types are relative to their unboxed data */
+export class Field<K extends FieldKind> {
+    constructor(
+        readonly offset: number,
+        readonly kind: K,
+    ) {}
+
+    address(instance: NativePointer): NativePointer {
+        return instance.add(this.offset);
+    }
+
+    get(instance: NativePointer): FieldTypes[K] {
+        const p = this.address(instance);
+        const value = (() => {
+            switch (this.kind) {
+                case "bool": return p.readU8() !== 0;
+                case "int8": return p.readS8();
+                case "uint8": return p.readU8();
+                case "int16": return p.readS16();
+                case "uint16": return p.readU16();
+                case "int32": return p.readS32();
+                case "uint32": return p.readU32();
+                case "int64": return p.readS64();
+                case "uint64": return p.readU64();
+                case "float": return p.readFloat();
+                case "double": return p.readDouble();
+                case "pointer": return p.readPointer();
+                default: return p;
+            }
+        })();
+        return value as FieldTypes[K];
+    }
+
+    set(instance: NativePointer, value: FieldTypes[K]): void {
+        const p = this.address(instance);
+        const v = value as any;
+        switch (this.kind) {
+            case "bool": p.writeU8(v ? 1 : 0); break;
+            case "int8": p.writeS8(v); break;
+            case "uint8": p.writeU8(v); break;
+            case "int16": p.writeS16(v); break;
+            case "uint16": p.writeU16(v); break;
+            case "int32": p.writeS32(v); break;
+            case "uint32": p.writeU32(v); break;
+            case "int64": p.writeS64(v); break;
+            case "uint64": p.writeU64(v); break;
+            case "float": p.writeFloat(v); break;
+            case "double": p.writeDouble(v); break;
+            case "pointer": p.writePointer(v); break;
+            default: throw new Error("value type fields are written through their address");
+        }
+    }
+}
+"#;
+
+pub fn run_frida(collection: &TypeContextCollection, metadata: &CordlMetadata) -> Result<()> {
+    let frida_folder = Path::new("./frida");
+    println!("Writing {frida_folder:?}");
+
+    if frida_folder.exists() {
+        fs::remove_dir_all(frida_folder)?;
+    }
+    fs::create_dir_all(frida_folder)?;
+
+    fs::write(frida_folder.join("cordl.ts"), RUNTIME)?;
+
+    let resolver = FridaNameResolver {
+        cordl_metadata: metadata,
+        collection,
+    };
+
+    let namespaces: BTreeMap<String, Vec<&CsType>> = collection
+        .get()
+        .values()
+        .flat_map(|c| c.get_types().iter())
+        .sorted_by_key(|(tag, _)| **tag)
+        .map(|(_, ty)| ty)
+        .filter(|ty| has_bindings(ty))
+        .into_group_map_by(|ty| ty.namespace())
+        .into_iter()
+        .collect();
+
+    for (namespace, types) in namespaces {
+        let module_name = match namespace.is_empty() {
+            true => "GlobalNamespace".to_string(),
+            false => namespace.clone(),
+        };
+        let path = frida_folder.join(format!("{module_name}.ts"));
+
+        write_namespace(&resolver, &namespace, &types, &path)
+            .with_context(|| format!("Writing {path:?}"))?;
+    }
+
+    Ok(())
+}
+
+/// Types without compiled methods or instance fields have nothing to bind
+fn has_bindings(ty: &CsType) -> bool {
+    ty.methods
+        .iter()
+        .any(|m| m.method_data.addrs.is_some_and(|a| a != 0x0))
+        || ty.fields.iter().any(|f| f.instance && f.offset.is_some())
+}
+
+fn write_namespace(
+    resolver: &FridaNameResolver,
+    namespace: &str,
+    types: &[&CsType],
+    path: &Path,
+) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    writeln!(writer, "// Generated by cordl, namespace {namespace}")?;
+    writeln!(writer, "import * as cordl from \"./cordl.js\";")?;
+
+    let mut type_names = UniqueNames::default();
+
+    for ty in types {
+        let components = &ty.cs_name_components;
+        let local_name = components
+            .declaring_types
+            .iter()
+            .flatten()
+            .chain(std::iter::once(&components.name))
+            .join("_");
+        let generics = components
+            .generics
+            .as_ref()
+            .map(|g| format!("_{}", g.join("_")))
+            .unwrap_or_default();
+        let ident = type_names.get(sanitize_identifier(&format!("{local_name}{generics}")));
+        let full_name = components.combine_all();
+
+        writeln!(writer)?;
+        writeln!(writer, "/** {full_name} */")?;
+        writeln!(writer, "export namespace {ident} {{")?;
+
+        write_methods(&mut writer, resolver, ty, &full_name)?;
+        write_fields(&mut writer, resolver, ty)?;
+
+        writeln!(writer, "}}")?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+fn write_methods(
+    writer: &mut impl Write,
+    resolver: &FridaNameResolver,
+    ty: &CsType,
+    full_name: &str,
+) -> Result<()> {
+    let mut method_names = UniqueNames::default();
+
+    writeln!(writer, "    export const methods = {{")?;
+    for method in &ty.methods {
+        let Some(addrs) = method.method_data.addrs.filter(|a| *a != 0x0) else {
+            continue;
+        };
+
+        let mut arg_types = vec![];
+        let mut arg_names = vec![];
+        if method.instance {
+            arg_types.push("\"pointer\"".to_string());
+            arg_names.push("this".to_string());
+        }
+        for param in &method.parameters {
+            arg_types.push(resolver.native_type(&param.il2cpp_ty));
+            arg_names.push(param.name.clone());
+        }
+        arg_types.push("\"pointer\"".to_string());
+        arg_names.push("method".to_string());
+
+        writeln!(
+            writer,
+            "        {}: new cordl.Method({:?}, {addrs:#x}, {}, [{}], [{}]),",
+            method_names.get(sanitize_identifier(&method.name)),
+            format!("{full_name}::{}", method.name),
+            resolver.native_type(&method.return_type),
+            arg_types.join(", "),
+            arg_names.iter().map(|n| format!("{n:?}")).join(", "),
+        )?;
+    }
+    writeln!(writer, "    }};")?;
+
+    Ok(())
+}
+
+fn write_fields(writer: &mut impl Write, resolver: &FridaNameResolver, ty: &CsType) -> Result<()> {
+    let mut field_names = UniqueNames::default();
+
+    writeln!(writer, "    export const fields = {{")?;
+    for field in ty.fields.iter().filter(|f| f.instance) {
+        let Some(offset) = field.offset else {
+            continue;
+        };
+
+        writeln!(
+            writer,
+            "        {}: new cordl.Field({offset:#x}, \"{}\"),",
+            field_names.get(sanitize_identifier(&field.name)),
+            resolver.field_kind(&field.field_ty),
+        )?;
+    }
+    writeln!(writer, "    }};")?;
+
+    Ok(())
+}
+
+/// Suffixes repeated names, overloads become `name`, `name_1`, `name_2`...
+#[derive(Default)]
+struct UniqueNames(HashSet<String>);
+
+impl UniqueNames {
+    fn get(&mut self, name: String) -> String {
+        let unique = (0..)
+            .map(|i| match i {
+                0 => name.clone(),
+                i => format!("{name}_{i}"),
+            })
+            .find(|n| !self.0.contains(n))
+            .unwrap();
+
+        self.0.insert(unique.clone());
+        unique
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suffixes_repeated_names() {
+        let mut names = UniqueNames::default();
+
+        assert_eq!(names.get("Add".to_string()), "Add");
+        assert_eq!(names.get("Add".to_string()), "Add_1");
+        assert_eq!(names.get("Remove".to_string()), "Remove");
+        assert_eq!(names.get("Add".to_string()), "Add_2");
+        // a name that's already taken by a suffixed overload
+        assert_eq!(names.get("Add_1".to_string()), "Add_1_1");
+    }
+}
@@ -0,0 +1,217 @@
+use brocolib::runtime_metadata::{Il2CppTypeEnum, TypeData};
+use itertools::Itertools;
+
+use crate::{
+    data::type_resolver::{ResolvedType, ResolvedTypeData},
+    generate::{
+        cs_context_collection::TypeContextCollection, cs_type::CsType, cs_type_tag::CsTypeTag,
+        metadata::CordlMetadata,
+    },
+};
+
+/// Value types nested deeper than this are passed as a pointer
+const MAX_STRUCT_DEPTH: usize = 16;
+
+/// Resolves types to Frida's `NativeFunction` types and field accessor kinds
+pub struct FridaNameResolver<'a, 'b> {
+    pub cordl_metadata: &'a CordlMetadata<'b>,
+    pub collection: &'a TypeContextCollection,
+}
+
+impl<'a> FridaNameResolver<'a, '_> {
+    /// TypeScript literal of an argument or return type,
+    /// value types are passed by value as nested arrays of their fields
+    pub fn native_type(&self, ty: &ResolvedType) -> String {
+        self.native_type_depth(ty, 0)
+    }
+
+    fn native_type_depth(&self, ty: &ResolvedType, depth: usize) -> String {
+        match &ty.data {
+            ResolvedTypeData::Primitive(il2cpp_type_enum) => {
+                format!("\"{}\"", primitive_type(*il2cpp_type_enum))
+            }
+            ResolvedTypeData::Type(_) | ResolvedTypeData::GenericInst(_, _) => {
+                match self.resolve_cs_type(ty) {
+                    Some(cs_type) if cs_type.is_enum_type => format!(
+                        "\"{}\"",
+                        primitive_type(cs_type.enum_backing_type.unwrap_or(Il2CppTypeEnum::I4))
+                    ),
+                    Some(cs_type) if cs_type.is_value_type && depth < MAX_STRUCT_DEPTH => {
+                        let fields = cs_type
+                            .fields
+                            .iter()
+                            .filter(|f| f.instance)
+                            .map(|f| self.native_type_depth(&f.field_ty, depth + 1))
+                            .collect_vec();
+
+                        match fields.is_empty() {
+                            // empty structs still take a byte
+                            true => "[\"uint8\"]".to_string(),
+                            false => format!("[{}]", fields.join(", ")),
+                        }
+                    }
+                    _ => "\"pointer\"".to_string(),
+                }
+            }
+            _ => "\"pointer\"".to_string(),
+        }
+    }
+
+    /// `FieldKind` of the runtime's `Field` accessor
+    pub fn field_kind(&self, ty: &ResolvedType) -> &'static str {
+        match &ty.data {
+            ResolvedTypeData::Primitive(il2cpp_type_enum) => primitive_type(*il2cpp_type_enum),
+            ResolvedTypeData::Type(_) | ResolvedTypeData::GenericInst(_, _) => {
+                match self.resolve_cs_type(ty) {
+                    Some(cs_type) if cs_type.is_enum_type => {
+                        primitive_type(cs_type.enum_backing_type.unwrap_or(Il2CppTypeEnum::I4))
+                    }
+                    Some(cs_type) if cs_type.is_value_type => "struct",
+                    _ => "pointer",
+                }
+            }
+            _ => "pointer",
+        }
+    }
+
+    fn resolve_cs_type(&self, ty: &ResolvedType) -> Option<&'a CsType> {
+        let metadata = self.cordl_metadata;
+        let tag = match metadata.metadata_registration.types[ty.ty].data {
+            TypeData::TypeDefinitionIndex(tdi) => tdi.into(),
+            TypeData::GenericClassIndex(generic_class) => {
+                CsTypeTag::from_generic_class_index(generic_class, metadata.metadata)
+            }
+            _ => return None,
+        };
+
+        self.collection.get_cs_type(tag)
+    }
+}
+
+fn primitive_type(il2cpp_type_enum: Il2CppTypeEnum) -> &'static str {
+    match il2cpp_type_enum {
+        Il2CppTypeEnum::Void => "void",
+        Il2CppTypeEnum::Boolean => "bool",
+        Il2CppTypeEnum::Char => "uint16",
+        Il2CppTypeEnum::I1 => "int8",
+        Il2CppTypeEnum::U1 => "uint8",
+        Il2CppTypeEnum::I2 => "int16",
+        Il2CppTypeEnum::U2 => "uint16",
+        Il2CppTypeEnum::I4 => "int32",
+        Il2CppTypeEnum::U4 => "uint32",
+        Il2CppTypeEnum::I8 => "int64",
+        Il2CppTypeEnum::U8 => "uint64",
+        Il2CppTypeEnum::R4 => "float",
+        Il2CppTypeEnum::R8 => "double",
+        _ => "pointer",
+    }
+}
+
+/// Reserved words of TypeScript, plus the name the runtime module is imported as
+const TS_KEYWORDS: &[&str] = &[
+    "any",
+    "as",
+    "boolean",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "constructor",
+    "continue",
+    "cordl",
+    "debugger",
+    "declare",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "from",
+    "function",
+    "get",
+    "if",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "interface",
+    "let",
+    "module",
+    "namespace",
+    "new",
+    "null",
+    "number",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "require",
+    "return",
+    "set",
+    "static",
+    "string",
+    "super",
+    "switch",
+    "symbol",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "type",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+/// Turns any C# name into a valid TypeScript identifier
+pub fn sanitize_identifier(name: &str) -> String {
+    let mut ident: String = name
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() || c == '$' {
+            true => c,
+            false => '_',
+        })
+        .collect();
+
+    if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    if TS_KEYWORDS.contains(&ident.as_str()) {
+        ident.push('_');
+    }
+
+    ident
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitizes_identifiers() {
+        assert_eq!(sanitize_identifier("Update"), "Update");
+        assert_eq!(sanitize_identifier("get_$Item"), "get_$Item");
+        assert_eq!(sanitize_identifier(".ctor"), "_ctor");
+        assert_eq!(sanitize_identifier("<Start>b__0"), "_Start_b__0");
+        assert_eq!(sanitize_identifier("List`1"), "List_1");
+        assert_eq!(sanitize_identifier("1stPlace"), "_1stPlace");
+        assert_eq!(sanitize_identifier(""), "_");
+        assert_eq!(sanitize_identifier("Método"), "M_todo");
+    }
+
+    #[test]
+    fn avoids_keywords() {
+        assert_eq!(sanitize_identifier("delete"), "delete_");
+        assert_eq!(sanitize_identifier("this"), "this_");
+        assert_eq!(sanitize_identifier("Delete"), "Delete");
+    }
+}
@@ -0,0 +1,2 @@
+pub mod frida_main;
+pub mod frida_name_resolver;
@@ -0,0 +1,269 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use brocolib::global_metadata::TypeDefinitionIndex;
+use color_eyre::eyre::{Context, Result};
+use itertools::Itertools;
+
+use crate::generate::{
+    cs_context_collection::TypeContextCollection, cs_type::CsType, cs_type_tag::CsTypeTag,
+    metadata::CordlMetadata,
+};
+
+use super::{
+    html_name_resolver::{HtmlNameResolver, escape, page_name},
+    html_type::type_page,
+};
+
+const STYLE: &str = r#"body { font-family: sans-serif; margin: 0; color: #222; }
+header { position: sticky; top: 0; background: #2d3748; padding: 0.5em 1em; }
+header > a { color: white; font-weight: bold; margin-right: 1em; text-decoration: none; }
+#search { width: 30em; }
+#search-results { position: absolute; background: white; margin: 0; padding: 0; list-style: none;
+    max-height: 70vh; overflow-y: auto; box-shadow: 0 2px 6px #0004; }
+#search-results a { display: block; padding: 0.2em 0.5em; }
+main { padding: 0 1em 2em; }
+a { color: #2b6cb0; text-decoration: none; }
+a:hover { text-decoration: underline; }
+table { border-collapse: collapse; }
+td, th { border-bottom: 1px solid #ddd; padding: 0.2em 0.6em; text-align: left; vertical-align: top; }
+.address { font-family: monospace; color: #718096; }
+.kind, .namespace { color: #718096; }
+"#;
+
+const SEARCH: &str = r#"// Filters CORDL_INDEX by full name, CORDL_ROOT is the path to the site root
+document.addEventListener("DOMContentLoaded", () => {
+    const input = document.getElementById("search");
+    const results = document.getElementById("search-results");
+
+    input.addEventListener("input", () => {
+        const query = input.value.trim().toLowerCase();
+        results.replaceChildren();
+        if (query.length < 2) {
+            return;
+        }
+
+        const matches = CORDL_INDEX.filter(([fullName]) => fullName.toLowerCase().includes(query))
+            .sort((a, b) => rank(a, query) - rank(b, query) || a[0].length - b[0].length)
+            .slice(0, 50);
+
+        for (const [fullName, page] of matches) {
+            const link = document.createElement("a");
+            link.href = CORDL_ROOT + page;
+            link.textContent = fullName;
+            const item = document.createElement("li");
+            item.append(link);
+            results.append(item);
+        }
+    });
+});
+
+// exact and prefix matches of the type name first
+function rank([fullName], query) {
+    const name = fullName.slice(fullName.lastIndexOf(".") + 1).toLowerCase();
+    if (name === query) return 0;
+    if (name.startsWith(query)) return 1;
+    return 2;
+}
+"#;
+
+pub fn run_html(collection: &TypeContextCollection, metadata: &CordlMetadata) -> Result<()> {
+    let site = Path::new("./html");
+    println!("Writing {site:?}");
+
+    if site.exists() {
+        fs::remove_dir_all(site)?;
+    }
+    fs::create_dir_all(site.join("types"))?;
+    fs::create_dir_all(site.join("namespaces"))?;
+
+    fs::write(site.join("style.css"), STYLE)?;
+    fs::write(site.join("search.js"), SEARCH)?;
+
+    let resolver = HtmlNameResolver {
+        cordl_metadata: metadata,
+        collection,
+    };
+
+    // generic instantiations link to the page of their definition
+    let types = collection
+        .get()
+        .values()
+        .flat_map(|c| c.get_types().values())
+        .filter(|ty| matches!(ty.self_tag, CsTypeTag::TypeDefinitionIndex(_)))
+        .sorted_by_cached_key(|ty| ty.cs_name_components.combine_all())
+        .collect_vec();
+
+    write_search_index(&types, &site.join("search-index.js"))?;
+
+    let namespaces: BTreeMap<String, Vec<&CsType>> = types
+        .iter()
+        .copied()
+        .into_group_map_by(|ty| ty.namespace())
+        .into_iter()
+        .collect();
+
+    let mut index = String::new();
+    writeln!(index, "<h1>Namespaces</h1>\n<ul>")?;
+    for (namespace, namespace_types) in &namespaces {
+        writeln!(
+            index,
+            "<li><a href=\"namespaces/{}\">{}</a> ({})</li>",
+            namespace_page_name(namespace),
+            escape(namespace_title(namespace)),
+            namespace_types.len()
+        )?;
+    }
+    writeln!(index, "</ul>")?;
+    fs::write(site.join("index.html"), page("cordl", "", &index))?;
+
+    for (namespace, namespace_types) in &namespaces {
+        let mut body = String::new();
+        writeln!(
+            body,
+            "<h1>{}</h1>\n<ul>",
+            escape(namespace_title(namespace))
+        )?;
+        for ty in namespace_types {
+            let CsTypeTag::TypeDefinitionIndex(tdi) = ty.self_tag else {
+                continue;
+            };
+            writeln!(
+                body,
+                "<li><a href=\"../types/{}\">{}</a></li>",
+                page_name(tdi),
+                escape(&ty.cs_name_components.combine_all())
+            )?;
+        }
+        writeln!(body, "</ul>")?;
+
+        fs::write(
+            site.join("namespaces").join(namespace_page_name(namespace)),
+            page(namespace_title(namespace), "../", &body),
+        )?;
+    }
+
+    for ty in &types {
+        let CsTypeTag::TypeDefinitionIndex(tdi) = ty.self_tag else {
+            continue;
+        };
+        let full_name = ty.cs_name_components.combine_all();
+        let body = type_page(&resolver, ty).with_context(|| format!("Rendering {full_name}"))?;
+
+        let path: PathBuf = site.join("types").join(page_name(tdi));
+        fs::write(path, page(&full_name, "../", &body))?;
+    }
+
+    Ok(())
+}
+
+/// `root` is the relative path from the page to the site root
+fn page(title: &str, root: &str, body: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<link rel="stylesheet" href="{root}style.css">
+<script>const CORDL_ROOT = "{root}";</script>
+<script src="{root}search-index.js" defer></script>
+<script src="{root}search.js" defer></script>
+</head>
+<body>
+<header><a href="{root}index.html">cordl</a><input id="search" type="search" placeholder="Search types" autocomplete="off"><ul id="search-results"></ul></header>
+<main>
+{body}</main>
+</body>
+</html>
+"#,
+        title = escape(title)
+    )
+}
+
+/// `[full name, page]` of every type, loaded as a script so the site works from `file://`
+fn write_search_index(types: &[&CsType], file: &Path) -> Result<()> {
+    let entries = types
+        .iter()
+        .filter_map(|ty| match ty.self_tag {
+            CsTypeTag::TypeDefinitionIndex(tdi) => Some((ty.cs_name_components.combine_all(), tdi)),
+            _ => None,
+        })
+        .collect_vec();
+
+    fs::write(file, search_index(&entries)?)?;
+    Ok(())
+}
+
+fn search_index(entries: &[(String, TypeDefinitionIndex)]) -> Result<String> {
+    let mut index = String::from("const CORDL_INDEX = [\n");
+    for (full_name, tdi) in entries {
+        writeln!(index, "[{full_name:?}, \"types/{}\"],", page_name(*tdi))?;
+    }
+    index.push_str("];\n");
+
+    Ok(index)
+}
+
+fn namespace_title(namespace: &str) -> &str {
+    match namespace.is_empty() {
+        true => "GlobalNamespace",
+        false => namespace,
+    }
+}
+
+fn namespace_page_name(namespace: &str) -> String {
+    let name: String = namespace_title(namespace)
+        .chars()
+        .map(
+            |c| match c.is_ascii_alphanumeric() || c == '.' || c == '_' {
+                true => c,
+                false => '_',
+            },
+        )
+        .collect();
+
+    format!("{name}.html")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_search_index() {
+        let index = search_index(&[
+            ("Game.Player".to_string(), TypeDefinitionIndex::new(3)),
+            (
+                "System.Collections.Generic.List<T>".to_string(),
+                TypeDefinitionIndex::new(10),
+            ),
+            (
+                "Game.\"Quoted\\Name\"".to_string(),
+                TypeDefinitionIndex::new(11),
+            ),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            index,
+            r#"const CORDL_INDEX = [
+["Game.Player", "types/3.html"],
+["System.Collections.Generic.List<T>", "types/10.html"],
+["Game.\"Quoted\\Name\"", "types/11.html"],
+];
+"#
+        );
+    }
+
+    #[test]
+    fn names_namespace_pages() {
+        assert_eq!(namespace_page_name(""), "GlobalNamespace.html");
+        assert_eq!(namespace_page_name("Game.UI_Menu"), "Game.UI_Menu.html");
+        assert_eq!(namespace_page_name("Game/<Private>"), "Game__Private_.html");
+    }
+}
@@ -0,0 +1,159 @@
+use brocolib::{
+    global_metadata::TypeDefinitionIndex,
+    runtime_metadata::{Il2CppTypeEnum, TypeData},
+};
+use itertools::Itertools;
+
+use crate::{
+    data::type_resolver::{ResolvedType, ResolvedTypeData},
+    generate::{
+        cs_context_collection::TypeContextCollection, cs_type::CsType, cs_type_tag::CsTypeTag,
+        metadata::CordlMetadata,
+    },
+};
+
+/// Renders type references as HTML, linking every type that has a page
+pub struct HtmlNameResolver<'a, 'b> {
+    pub cordl_metadata: &'a CordlMetadata<'b>,
+    pub collection: &'a TypeContextCollection,
+}
+
+impl<'a> HtmlNameResolver<'a, '_> {
+    pub fn resolve_html(&self, ty: &ResolvedType) -> String {
+        let metadata = self.cordl_metadata;
+        match &ty.data {
+            ResolvedTypeData::Array(inner) => format!("{}[]", self.resolve_html(inner)),
+            ResolvedTypeData::GenericInst(inner, args) => {
+                let base = match &inner.data {
+                    ResolvedTypeData::Type(tag) => self.tag_link(*tag, false),
+                    _ => self.resolve_html(inner),
+                };
+                let args = args
+                    .iter()
+                    .map(|(arg, _)| self.resolve_html(arg))
+                    .join(", ");
+
+                format!("{base}&lt;{args}&gt;")
+            }
+            ResolvedTypeData::GenericArg(gen_param_idx, _)
+            | ResolvedTypeData::GenericMethodArg(_, gen_param_idx, _) => {
+                let generic_param =
+                    &metadata.metadata.global_metadata.generic_parameters[*gen_param_idx];
+
+                escape(generic_param.name(metadata.metadata))
+            }
+            ResolvedTypeData::Ptr(inner) => format!("{}*", self.resolve_html(inner)),
+            ResolvedTypeData::Type(tag) => self.tag_link(*tag, true),
+            ResolvedTypeData::Primitive(il2cpp_type_enum) => {
+                primitive_name(*il2cpp_type_enum).to_string()
+            }
+            ResolvedTypeData::Blacklisted(tag) => {
+                let td = &metadata.metadata.global_metadata.type_definitions[tag.get_tdi()];
+
+                escape(&td.full_name(metadata.metadata, true))
+            }
+            ResolvedTypeData::ByRef(inner) => format!("ref {}", self.resolve_html(inner)),
+            ResolvedTypeData::ByRefConst(inner) => format!("in {}", self.resolve_html(inner)),
+        }
+    }
+
+    /// Link to the page of the type definition, titled with the full name
+    pub fn tag_link(&self, tag: CsTypeTag, with_generics: bool) -> String {
+        let Some(ty) = self.collection.get_cs_type(tag) else {
+            return "?".to_string();
+        };
+
+        let name = escape(&short_name(ty, with_generics));
+        match self.collection.get_cs_type(tag.get_tdi().into()) {
+            Some(_) => format!(
+                r#"<a href="{}" title="{}">{name}</a>"#,
+                page_name(tag.get_tdi()),
+                escape(&ty.cs_name_components.combine_all())
+            ),
+            None => name,
+        }
+    }
+
+    /// The type definition or generic instantiation named by `ty`
+    pub fn cs_type(&self, ty: &ResolvedType) -> Option<&'a CsType> {
+        let metadata = self.cordl_metadata;
+        let tag = match metadata.metadata_registration.types[ty.ty].data {
+            TypeData::TypeDefinitionIndex(tdi) => tdi.into(),
+            TypeData::GenericClassIndex(generic_class) => {
+                CsTypeTag::from_generic_class_index(generic_class, metadata.metadata)
+            }
+            _ => return None,
+        };
+
+        self.collection.get_cs_type(tag)
+    }
+}
+
+/// `Outer.Inner<T>`, without the namespace
+pub fn short_name(ty: &CsType, with_generics: bool) -> String {
+    let components = &ty.cs_name_components;
+    let name = components
+        .declaring_types
+        .iter()
+        .flatten()
+        .chain(std::iter::once(&components.name))
+        .join(".");
+
+    match &components.generics {
+        Some(generics) if with_generics => format!("{name}<{}>", generics.join(", ")),
+        _ => name,
+    }
+}
+
+/// Type pages are all in `types/`, named by type definition index
+pub fn page_name(tdi: TypeDefinitionIndex) -> String {
+    format!("{}.html", tdi.index())
+}
+
+pub fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn primitive_name(il2cpp_type_enum: Il2CppTypeEnum) -> &'static str {
+    match il2cpp_type_enum {
+        Il2CppTypeEnum::Void => "void",
+        Il2CppTypeEnum::Boolean => "bool",
+        Il2CppTypeEnum::Char => "char",
+        Il2CppTypeEnum::I1 => "sbyte",
+        Il2CppTypeEnum::U1 => "byte",
+        Il2CppTypeEnum::I2 => "short",
+        Il2CppTypeEnum::U2 => "ushort",
+        Il2CppTypeEnum::I4 => "int",
+        Il2CppTypeEnum::U4 => "uint",
+        Il2CppTypeEnum::I8 => "long",
+        Il2CppTypeEnum::U8 => "ulong",
+        Il2CppTypeEnum::R4 => "float",
+        Il2CppTypeEnum::R8 => "double",
+        Il2CppTypeEnum::String => "string",
+        Il2CppTypeEnum::Object => "object",
+        Il2CppTypeEnum::I => "nint",
+        Il2CppTypeEnum::U => "nuint",
+        Il2CppTypeEnum::Typedbyref => "TypedReference",
+        _ => "?",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_html() {
+        assert_eq!(escape("Player"), "Player");
+        assert_eq!(
+            escape("List<Dictionary<K, V>>"),
+            "List&lt;Dictionary&lt;K, V&gt;&gt;"
+        );
+        assert_eq!(escape("a && \"b\""), "a &amp;&amp; &quot;b&quot;");
+        // already escaped text is escaped again
+        assert_eq!(escape("&lt;"), "&amp;lt;");
+    }
+}
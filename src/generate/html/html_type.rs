@@ -0,0 +1,304 @@
+use std::fmt::Write;
+
+use color_eyre::eyre::Result;
+use itertools::Itertools;
+
+use crate::{
+    data::type_resolver::ResolvedTypeData,
+    generate::{
+        cs_members::{CSMethodFlags, CsField, CsMethod, CsParam, CsParamFlags, CsValue},
+        cs_type::CsType,
+    },
+};
+
+use super::html_name_resolver::{HtmlNameResolver, escape, short_name};
+
+/// Guards against cycles in broken metadata
+const MAX_INHERITANCE_DEPTH: usize = 64;
+
+/// Body of the page of a type definition
+pub fn type_page(resolver: &HtmlNameResolver, ty: &CsType) -> Result<String> {
+    let mut html = String::new();
+
+    let kind = if ty.is_interface {
+        "interface"
+    } else if ty.is_enum_type {
+        "enum"
+    } else if ty.is_value_type {
+        "struct"
+    } else {
+        "class"
+    };
+    let namespace = ty.namespace();
+
+    writeln!(
+        html,
+        "<h1><span class=\"kind\">{kind}</span> {}</h1>",
+        escape(&short_name(ty, true))
+    )?;
+    writeln!(
+        html,
+        "<p class=\"namespace\">Namespace: {}</p>",
+        escape(match namespace.is_empty() {
+            true => "GlobalNamespace",
+            false => &namespace,
+        })
+    )?;
+    if let Some(declaring_ty) = ty.declaring_ty {
+        writeln!(
+            html,
+            "<p>Declared in {}</p>",
+            resolver.tag_link(declaring_ty, true)
+        )?;
+    }
+    if let Some(size_info) = &ty.size_info {
+        writeln!(
+            html,
+            "<p>Instance size: 0x{:X}</p>",
+            size_info.instance_size
+        )?;
+    }
+
+    write_inheritance(&mut html, resolver, ty)?;
+
+    if !ty.interfaces.is_empty() {
+        writeln!(html, "<h2>Interfaces</h2>\n<ul>")?;
+        for interface in &ty.interfaces {
+            writeln!(html, "<li>{}</li>", resolver.resolve_html(interface))?;
+        }
+        writeln!(html, "</ul>")?;
+    }
+
+    if !ty.nested_types.is_empty() {
+        writeln!(html, "<h2>Nested types</h2>\n<ul>")?;
+        for nested in ty
+            .nested_types
+            .iter()
+            .filter_map(|tag| resolver.collection.get_cs_type(*tag))
+            .sorted_by(|a, b| a.name().cmp(b.name()))
+        {
+            writeln!(
+                html,
+                "<li>{}</li>",
+                resolver.tag_link(nested.self_tag, true)
+            )?;
+        }
+        writeln!(html, "</ul>")?;
+    }
+
+    if !ty.fields.is_empty() {
+        writeln!(
+            html,
+            "<h2>Fields</h2>\n<table>\n<tr><th>Offset</th><th>Field</th></tr>"
+        )?;
+        for field in &ty.fields {
+            writeln!(
+                html,
+                "<tr><td class=\"address\">{}</td><td><code>{}</code></td></tr>",
+                field.offset.map(|o| format!("0x{o:X}")).unwrap_or_default(),
+                field_signature(resolver, field)
+            )?;
+        }
+        writeln!(html, "</table>")?;
+    }
+
+    if !ty.properties.is_empty() {
+        writeln!(html, "<h2>Properties</h2>\n<table>")?;
+        for property in &ty.properties {
+            let accessors = [
+                property.getter.as_ref().map(|_| "get;"),
+                property.setter.as_ref().map(|_| "set;"),
+            ]
+            .into_iter()
+            .flatten()
+            .join(" ");
+
+            writeln!(
+                html,
+                "<tr><td><code>{}{} {} {{ {accessors} }}</code></td></tr>",
+                match property.instance {
+                    true => "",
+                    false => "static ",
+                },
+                resolver.resolve_html(&property.prop_ty),
+                escape(&property.name)
+            )?;
+        }
+        writeln!(html, "</table>")?;
+    }
+
+    if !ty.methods.is_empty() {
+        writeln!(
+            html,
+            "<h2>Methods</h2>\n<table>\n<tr><th>Address</th><th>Slot</th><th>Method</th></tr>"
+        )?;
+        for method in &ty.methods {
+            writeln!(
+                html,
+                "<tr><td class=\"address\">{}</td><td>{}</td><td><code>{}</code></td></tr>",
+                method
+                    .method_data
+                    .addrs
+                    .filter(|a| *a != 0x0)
+                    .map(|a| format!("0x{a:X}"))
+                    .unwrap_or_default(),
+                method
+                    .method_data
+                    .slot
+                    .map(|s| s.to_string())
+                    .unwrap_or_default(),
+                method_signature(resolver, method)
+            )?;
+        }
+        writeln!(html, "</table>")?;
+    }
+
+    Ok(html)
+}
+
+/// `System.Object → Base → ThisType`
+fn write_inheritance(html: &mut String, resolver: &HtmlNameResolver, ty: &CsType) -> Result<()> {
+    let mut chain = vec![];
+    let mut parent = ty.parent.as_ref();
+    while let Some(parent_ty) = parent
+        && chain.len() < MAX_INHERITANCE_DEPTH
+    {
+        chain.push(resolver.resolve_html(parent_ty));
+        parent = resolver
+            .cs_type(parent_ty)
+            .and_then(|parent_cs| parent_cs.parent.as_ref());
+    }
+
+    if chain.is_empty() {
+        return Ok(());
+    }
+
+    chain.reverse();
+    chain.push(escape(&short_name(ty, true)));
+    writeln!(html, "<h2>Inheritance</h2>")?;
+    writeln!(
+        html,
+        "<p class=\"inheritance\">{}</p>",
+        chain.join(" &rarr; ")
+    )?;
+
+    Ok(())
+}
+
+fn field_signature(resolver: &HtmlNameResolver, field: &CsField) -> String {
+    let modifiers = if field.is_const {
+        "const "
+    } else {
+        match (field.instance, field.readonly) {
+            (true, true) => "readonly ",
+            (true, false) => "",
+            (false, true) => "static readonly ",
+            (false, false) => "static ",
+        }
+    };
+    let value = field
+        .value
+        .as_ref()
+        .map(|v| format!(" = {}", escape(&value_text(v))))
+        .unwrap_or_default();
+
+    format!(
+        "{modifiers}{} {}{value}",
+        resolver.resolve_html(&field.field_ty),
+        escape(&field.name)
+    )
+}
+
+fn method_signature(resolver: &HtmlNameResolver, method: &CsMethod) -> String {
+    let modifiers = [
+        (CSMethodFlags::STATIC, "static "),
+        (CSMethodFlags::ABSTRACT, "abstract "),
+        (CSMethodFlags::OVERRIDE, "override "),
+        (CSMethodFlags::VIRTUAL, "virtual "),
+        (CSMethodFlags::FINAL, "sealed "),
+    ]
+    .into_iter()
+    .filter(|(flag, _)| method.method_flags.contains(flag.clone()))
+    // overrides are virtual too
+    .filter(|(flag, _)| {
+        *flag != CSMethodFlags::VIRTUAL || !method.method_flags.contains(CSMethodFlags::OVERRIDE)
+    })
+    .map(|(_, modifier)| modifier)
+    .collect::<String>();
+
+    let generics = match (&method.generic_instatiation, &method.template) {
+        (Some(args), _) => format!(
+            "&lt;{}&gt;",
+            args.iter().map(|a| resolver.resolve_html(a)).join(", ")
+        ),
+        (None, Some(template)) if !template.names.is_empty() => format!(
+            "&lt;{}&gt;",
+            template.just_names().map(|n| escape(n)).join(", ")
+        ),
+        _ => String::new(),
+    };
+    let parameters = method
+        .parameters
+        .iter()
+        .map(|p| parameter_signature(resolver, p))
+        .join(", ");
+
+    format!(
+        "{modifiers}{} <b>{}</b>{generics}({parameters})",
+        resolver.resolve_html(&method.return_type),
+        escape(&method.name)
+    )
+}
+
+fn parameter_signature(resolver: &HtmlNameResolver, param: &CsParam) -> String {
+    let modifier = if param.modifiers.contains(CsParamFlags::OUT) {
+        "out "
+    } else if param.modifiers.contains(CsParamFlags::IN) {
+        "in "
+    } else if param.modifiers.contains(CsParamFlags::REF) {
+        "ref "
+    } else {
+        ""
+    };
+    // the modifier replaces the by ref wrapper
+    let ty = match &param.il2cpp_ty.data {
+        ResolvedTypeData::ByRef(inner) | ResolvedTypeData::ByRefConst(inner)
+            if !modifier.is_empty() =>
+        {
+            inner
+        }
+        _ => &param.il2cpp_ty,
+    };
+    let default = param
+        .def_value
+        .as_ref()
+        .map(|v| format!(" = {}", escape(&value_text(v))))
+        .unwrap_or_default();
+
+    format!(
+        "{modifier}{} {}{default}",
+        resolver.resolve_html(ty),
+        escape(&param.name)
+    )
+}
+
+/// C# literal of a constant, strings and chars are already escaped
+fn value_text(value: &CsValue) -> String {
+    match value {
+        CsValue::String(s) => format!("\"{s}\""),
+        CsValue::Char(c) => format!("'{c}'"),
+        CsValue::Bool(b) => b.to_string(),
+        CsValue::U8(v) => v.to_string(),
+        CsValue::U16(v) => v.to_string(),
+        CsValue::U32(v) => v.to_string(),
+        CsValue::U64(v) => v.to_string(),
+        CsValue::I8(v) => v.to_string(),
+        CsValue::I16(v) => v.to_string(),
+        CsValue::I32(v) => v.to_string(),
+        CsValue::I64(v) => v.to_string(),
+        CsValue::F32(v) => format!("{v}f"),
+        CsValue::F64(v) => v.to_string(),
+        CsValue::Object(_) | CsValue::ValueType(_) => "...".to_string(),
+        CsValue::Null => "null".to_string(),
+    }
+}
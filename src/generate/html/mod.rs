@@ -0,0 +1,3 @@
+pub mod html_main;
+pub mod html_name_resolver;
+pub mod html_type;
@@ -1,18 +1,28 @@
 use brocolib::{global_metadata::TypeDefinitionIndex, runtime_metadata::Il2CppTypeEnum};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    data::type_resolver::{ResolvedType, ResolvedTypeData},
-    generate::cs_type_tag::CsTypeTag,
+    data::{
+        compiler_generated::GeneratedKind,
+        pinvoke::{CallingConvention, CharSet},
+        type_resolver::{ResolvedType, ResolvedTypeData},
+    },
+    generate::{
+        cs_members::{CSMethodFlags, CsValue},
+        cs_type_tag::CsTypeTag,
+    },
 };
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, PartialOrd, Ord, Eq, Hash)]
+#[derive(
+    Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq, PartialOrd, Ord, Eq, Hash,
+)]
 pub enum JsonTypeTag {
     TypeDefinition(u32),
     GenericInstantiation { type_definition: u32, inst: usize },
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
 pub enum JsonResolvedTypeData {
     Array(Box<JsonResolvedTypeData>),
     GenericInst(Box<JsonResolvedTypeData>, Vec<(JsonResolvedTypeData, bool)>),
@@ -54,11 +64,175 @@ impl From<ResolvedType> for JsonResolvedTypeData {
     }
 }
 
+/// Constant of a field, enum value or default parameter value
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub enum JsonValue {
+    String(String),
+    Char(String),
+    Bool(bool),
+
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+
+    /// Finite floats only, JSON has no NaN or infinity
+    F32(f32),
+    F64(f64),
+    NaN,
+    Infinity,
+    NegativeInfinity,
+
+    Object(Vec<u8>),
+    ValueType(Vec<u8>),
+    Null,
+}
+
+impl From<CsValue> for JsonValue {
+    fn from(value: CsValue) -> Self {
+        match value {
+            CsValue::String(s) => JsonValue::String(s),
+            CsValue::Char(c) => JsonValue::Char(c),
+            CsValue::Bool(b) => JsonValue::Bool(b),
+            CsValue::U8(v) => JsonValue::U8(v),
+            CsValue::U16(v) => JsonValue::U16(v),
+            CsValue::U32(v) => JsonValue::U32(v),
+            CsValue::U64(v) => JsonValue::U64(v),
+            CsValue::I8(v) => JsonValue::I8(v),
+            CsValue::I16(v) => JsonValue::I16(v),
+            CsValue::I32(v) => JsonValue::I32(v),
+            CsValue::I64(v) => JsonValue::I64(v),
+            CsValue::F32(v) if v.is_finite() => JsonValue::F32(v),
+            CsValue::F64(v) if v.is_finite() => JsonValue::F64(v),
+            CsValue::F32(v) => JsonValue::non_finite(v.into()),
+            CsValue::F64(v) => JsonValue::non_finite(v),
+            CsValue::Object(bytes) => JsonValue::Object(bytes.to_vec()),
+            CsValue::ValueType(bytes) => JsonValue::ValueType(bytes.to_vec()),
+            CsValue::Null => JsonValue::Null,
+        }
+    }
+}
+
+impl JsonValue {
+    fn non_finite(v: f64) -> Self {
+        if v.is_nan() {
+            JsonValue::NaN
+        } else if v.is_sign_positive() {
+            JsonValue::Infinity
+        } else {
+            JsonValue::NegativeInfinity
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+pub enum JsonMethodFlag {
+    Static,
+    Virtual,
+    Operator,
+    Abstract,
+    Override,
+    Final,
+    SpecialName,
+    Unsafe,
+    InternalCall,
+    PInvoke,
+}
+
+impl JsonMethodFlag {
+    pub fn from_flags(flags: &CSMethodFlags) -> Vec<Self> {
+        [
+            (CSMethodFlags::STATIC, JsonMethodFlag::Static),
+            (CSMethodFlags::VIRTUAL, JsonMethodFlag::Virtual),
+            (CSMethodFlags::OPERATOR, JsonMethodFlag::Operator),
+            (CSMethodFlags::ABSTRACT, JsonMethodFlag::Abstract),
+            (CSMethodFlags::OVERRIDE, JsonMethodFlag::Override),
+            (CSMethodFlags::FINAL, JsonMethodFlag::Final),
+            (CSMethodFlags::SPECIAL_NAME, JsonMethodFlag::SpecialName),
+            (CSMethodFlags::UNSAFE, JsonMethodFlag::Unsafe),
+            (CSMethodFlags::INTERNAL_CALL, JsonMethodFlag::InternalCall),
+            (CSMethodFlags::PINVOKE, JsonMethodFlag::PInvoke),
+        ]
+        .into_iter()
+        .filter(|(flag, _)| flags.contains(flag.clone()))
+        .map(|(_, json_flag)| json_flag)
+        .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonGeneratedKind {
+    AsyncStateMachine,
+    Iterator,
+    Closure,
+    Lambda,
+    LocalFunction,
+}
+
+impl From<GeneratedKind> for JsonGeneratedKind {
+    fn from(value: GeneratedKind) -> Self {
+        match value {
+            GeneratedKind::AsyncStateMachine => JsonGeneratedKind::AsyncStateMachine,
+            GeneratedKind::Iterator => JsonGeneratedKind::Iterator,
+            GeneratedKind::Closure => JsonGeneratedKind::Closure,
+            GeneratedKind::Lambda => JsonGeneratedKind::Lambda,
+            GeneratedKind::LocalFunction => JsonGeneratedKind::LocalFunction,
+        }
+    }
+}
+
+/// `Il2CppCallConvention` of a P/Invoke method
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonCallingConvention {
+    Default,
+    C,
+    StdCall,
+    ThisCall,
+    FastCall,
+}
+
+impl From<CallingConvention> for JsonCallingConvention {
+    fn from(value: CallingConvention) -> Self {
+        match value {
+            CallingConvention::Default => JsonCallingConvention::Default,
+            CallingConvention::C => JsonCallingConvention::C,
+            CallingConvention::StdCall => JsonCallingConvention::StdCall,
+            CallingConvention::ThisCall => JsonCallingConvention::ThisCall,
+            CallingConvention::FastCall => JsonCallingConvention::FastCall,
+        }
+    }
+}
+
+/// `Il2CppCharSet` of a P/Invoke method
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonCharSet {
+    Ansi,
+    Utf8,
+    Unicode,
+    NotSpecified,
+}
+
+impl From<CharSet> for JsonCharSet {
+    fn from(value: CharSet) -> Self {
+        match value {
+            CharSet::Ansi => JsonCharSet::Ansi,
+            CharSet::Utf8 => JsonCharSet::Utf8,
+            CharSet::Unicode => JsonCharSet::Unicode,
+            CharSet::NotSpecified => JsonCharSet::NotSpecified,
+        }
+    }
+}
+
 /// Corresponds to element type signatures.
 /// See ECMA-335, II.23.1.16
 ///
 /// Defined at `il2cpp-blob.h:6`
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize, JsonSchema)]
 pub enum JsonTypeEnum {
     /// End of list
     End,
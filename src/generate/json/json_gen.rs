@@ -2,39 +2,71 @@ use std::collections::HashMap;
 
 use itertools::Itertools;
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::generate::{
-    cs_context_collection::TypeContextCollection,
-    cs_members::{
-        CsField, CsGenericTemplate, CsGenericTemplateType, CsMethod, CsParam, CsParamFlags,
-        CsProperty,
+use crate::{
+    analysis::string_literals::StringLiteral,
+    data::{custom_attributes::METHOD_DEF_TOKEN, elf_functions::MethodSizeSource},
+    generate::{
+        cs_context_collection::TypeContextCollection,
+        cs_members::{
+            CsField, CsGenericTemplate, CsGenericTemplateType, CsMethod, CsParam, CsParamFlags,
+            CsProperty,
+        },
+        cs_type::CsType,
+        metadata::CordlMetadata,
+        type_extensions::TypeDefinitionExtensions,
     },
-    cs_type::CsType,
-    metadata::CordlMetadata,
-    type_extensions::TypeDefinitionExtensions,
 };
 
 use super::{
-    json_data::{JsonResolvedTypeData, JsonTypeTag},
+    json_data::{
+        JsonCallingConvention, JsonCharSet, JsonGeneratedKind, JsonMethodFlag,
+        JsonResolvedTypeData, JsonTypeEnum, JsonTypeTag, JsonValue,
+    },
     json_name_resolver::JsonNameResolver,
 };
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct JsonTable {
+    /// `JSON_FORMAT_VERSION` of the writer, 0 for dumps written before versioning
+    #[serde(default)]
+    pub format_version: u32,
+
     /// associated with types by their index in types_table
     pub types: HashMap<usize, JsonType>,
     pub types_table: Vec<JsonTypeTag>,
+
+    /// `code_registration.unresolved_virtual_call_pointers`,
+    /// stubs used when calling a virtual method with no compiled implementation
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unresolved_virtual_call_pointers: Vec<u64>,
+
+    /// Every C# string literal, only written with `--string-literals`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub string_literals: Vec<StringLiteral>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A file of the multi-file output, holding a single type
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct JsonTypeFile {
+    /// `JSON_FORMAT_VERSION` of the writer, 0 for dumps written before versioning
+    #[serde(default)]
+    pub format_version: u32,
+
+    #[serde(flatten)]
+    pub ty: JsonType,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub enum JsonFieldRef {
     In,
     Out,
     Ref,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct JsonType {
     pub full_name: String,
     pub name: String,
@@ -46,6 +78,13 @@ pub struct JsonType {
     pub children: Vec<JsonType>,
     pub tag: JsonTypeTag,
     pub parent: Option<JsonResolvedTypeData>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub interfaces: Vec<JsonResolvedTypeData>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub declaring_tag: Option<JsonTypeTag>,
+    /// Tags of `children`, including compiler generated types left out of it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nested_tags: Vec<JsonTypeTag>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub template: Option<JsonTemplate>,
 
@@ -55,9 +94,17 @@ pub struct JsonType {
 
     pub size: u32,
     pub packing: Option<u8>,
+
+    #[serde(default)]
+    pub is_interface: bool,
+    #[serde(default)]
+    pub is_compiler_generated: bool,
+    /// Backing type of enums, their values are the constants in `fields`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enum_backing_type: Option<JsonTypeEnum>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct JsonField {
     pub name: String,
     pub ty_name: String,
@@ -66,8 +113,12 @@ pub struct JsonField {
     pub is_const: bool,
     pub readonly: bool,
     pub offset: Option<u32>,
+    #[serde(default)]
+    pub size: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<JsonValue>,
 }
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct JsonProperty {
     pub name: String,
     pub ty_name: String,
@@ -80,7 +131,7 @@ pub struct JsonProperty {
     pub setter: Option<(u32, String)>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub enum JsonGenericArgumentType {
     AnyType,
     ReferenceType,
@@ -88,28 +139,89 @@ pub enum JsonGenericArgumentType {
 
 type JsonTemplate = Vec<(JsonGenericArgumentType, String)>;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct JsonMethod {
     pub name: String,
     pub ret: String,
     pub ret_ty_tag: JsonResolvedTypeData,
     pub parameters: Vec<JsonParam>,
     pub instance: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub flags: Vec<JsonMethodFlag>,
+    #[serde(default)]
+    pub method_index: u32,
+    /// Metadata token of the method definition
+    #[serde(default)]
+    pub token: u32,
     pub method_info: JsonMethodInfo,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub template: Option<JsonTemplate>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub generic_instatiation: Option<Vec<JsonResolvedTypeData>>,
+    /// `DllImport` of extern methods
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pinvoke: Option<JsonPInvoke>,
+    /// Closures, lambdas and state machines holding code written in this method,
+    /// compiler generated types aren't in `children`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub compiler_generated: Vec<JsonCompilerGenerated>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct JsonPInvoke {
+    /// `__Internal` for plugins linked into libil2cpp.so
+    pub library: Option<String>,
+    pub entry_point: Option<String>,
+    /// Read from the marshalling wrapper, unknown for `__Internal`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub calling_convention: Option<JsonCallingConvention>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub char_set: Option<JsonCharSet>,
+    /// `ExactSpelling`, no `A` or `W` suffixed entry points are tried
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_no_mangle: Option<bool>,
+    pub preserve_sig: bool,
+    /// Parameters with a `MarshalAs` attribute
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub marshaled_parameters: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct JsonCompilerGenerated {
+    pub kind: JsonGeneratedKind,
+    /// `Namespace.Type/<Method>d__3` or `Namespace.Type/<>c::<Method>b__3_0`
+    pub name: String,
+    /// The generated type, or the type declaring the generated method
+    pub tag: JsonTypeTag,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub method_index: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct JsonMethodInfo {
     pub estimated_size: Option<usize>,
+    #[serde(default)]
+    pub size_source: MethodSizeSource,
     pub addrs: Option<u64>,
     pub slot: Option<u16>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invoker_index: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invoker_addrs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub adjustor_thunk_addrs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reverse_pinvoke_wrapper_addrs: Option<u64>,
+    /// Registration name of internal calls
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icall_name: Option<String>,
+    /// Native function of the internal call in libunity.so
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icall_addrs: Option<u64>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct JsonParam {
     pub name: String,
     pub ty: String,
@@ -117,6 +229,73 @@ pub struct JsonParam {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub ref_mode: Option<JsonFieldRef>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub def_value: Option<JsonValue>,
+}
+
+/// `Namespace.Type::name` when the declaring type is given
+fn qualified_name(declaring_type: Option<&str>, name: &str) -> String {
+    match declaring_type {
+        Some(declaring_type) => format!("{declaring_type}::{name}"),
+        None => name.to_string(),
+    }
+}
+
+impl JsonField {
+    /// C# like declaration, e.g `static readonly int Namespace.Type::name`
+    pub fn signature(&self, declaring_type: Option<&str>) -> String {
+        let modifiers = match (self.instance, self.is_const, self.readonly) {
+            (_, true, _) => "const ",
+            (false, _, true) => "static readonly ",
+            (false, _, false) => "static ",
+            (true, _, true) => "readonly ",
+            (true, _, false) => "",
+        };
+
+        format!(
+            "{modifiers}{} {}",
+            self.ty_name,
+            qualified_name(declaring_type, &self.name)
+        )
+    }
+}
+
+impl JsonProperty {
+    /// C# like declaration, e.g `int Namespace.Type::Name { get; set; }`
+    pub fn signature(&self, declaring_type: Option<&str>) -> String {
+        let modifiers = if self.instance { "" } else { "static " };
+        let accessors = [
+            self.getter.as_ref().map(|_| "get;"),
+            self.setter.as_ref().map(|_| "set;"),
+        ]
+        .into_iter()
+        .flatten()
+        .join(" ");
+
+        format!(
+            "{modifiers}{} {} {{ {accessors} }}",
+            self.ty_name,
+            qualified_name(declaring_type, &self.name)
+        )
+    }
+}
+
+impl JsonMethod {
+    /// C# like declaration, e.g `static void Namespace.Type::Method(int a)`
+    pub fn signature(&self, declaring_type: Option<&str>) -> String {
+        let modifiers = if self.instance { "" } else { "static " };
+        let params = self
+            .parameters
+            .iter()
+            .map(|p| format!("{} {}", p.ty, p.name))
+            .join(", ");
+
+        format!(
+            "{modifiers}{} {}({params})",
+            self.ret,
+            qualified_name(declaring_type, &self.name)
+        )
+    }
 }
 
 fn make_field(field: &CsField, name_resolver: &JsonNameResolver) -> JsonField {
@@ -132,6 +311,8 @@ fn make_field(field: &CsField, name_resolver: &JsonNameResolver) -> JsonField {
         instance: field.instance,
         is_const: field.is_const,
         readonly: field.readonly,
+        size: field.size,
+        value: field.value.clone().map(JsonValue::from),
     }
 }
 fn make_property(property: &CsProperty, name_resolver: &JsonNameResolver) -> JsonProperty {
@@ -176,6 +357,7 @@ fn make_param(param: &CsParam, name_resolver: &JsonNameResolver) -> JsonParam {
         ty: ty_name,
         ty_tag: param_type,
         ref_mode,
+        def_value: param.def_value.clone().map(JsonValue::from),
     }
 }
 
@@ -197,7 +379,11 @@ fn make_template(template: &CsGenericTemplate) -> JsonTemplate {
         .collect_vec();
 }
 
-fn make_method(method: &CsMethod, name_resolver: &JsonNameResolver) -> JsonMethod {
+fn make_method(
+    method: &CsMethod,
+    metadata: &CordlMetadata,
+    name_resolver: &JsonNameResolver,
+) -> JsonMethod {
     let ret_ty_name = name_resolver
         .resolve_name(&method.return_type)
         .combine_all();
@@ -212,7 +398,14 @@ fn make_method(method: &CsMethod, name_resolver: &JsonNameResolver) -> JsonMetho
     let json_method_info = JsonMethodInfo {
         addrs: method.method_data.addrs,
         estimated_size: method.method_data.estimated_size,
+        size_source: method.method_data.size_source,
         slot: method.method_data.slot,
+        invoker_index: method.method_data.invoker_index,
+        invoker_addrs: method.method_data.invoker_addrs,
+        adjustor_thunk_addrs: method.method_data.adjustor_thunk_addrs,
+        reverse_pinvoke_wrapper_addrs: method.method_data.reverse_pinvoke_wrapper_addrs,
+        icall_name: method.method_data.icall_name.clone(),
+        icall_addrs: method.method_data.icall_addrs,
     };
 
     let generic_instatiation = method
@@ -220,15 +413,39 @@ fn make_method(method: &CsMethod, name_resolver: &JsonNameResolver) -> JsonMetho
         .as_ref()
         .map(|inst_types| inst_types.iter().map(|ty| ty.clone().into()).collect_vec());
 
+    let method_def = &metadata.metadata.global_metadata.methods[method.method_index];
+
     JsonMethod {
         name: method.name.to_string(),
         parameters: params,
         instance: method.instance,
+        flags: JsonMethodFlag::from_flags(&method.method_flags),
+        method_index: method.method_index.index(),
+        token: METHOD_DEF_TOKEN | method_def.token.rid() as u32,
         ret: ret_ty_name,
         ret_ty_tag: ret_ty,
         method_info: json_method_info,
         template: method.template.as_ref().map(make_template),
         generic_instatiation,
+        pinvoke: method.pinvoke.as_ref().map(|pinvoke| JsonPInvoke {
+            library: pinvoke.library.clone(),
+            entry_point: pinvoke.entry_point.clone(),
+            calling_convention: pinvoke.calling_convention.map(Into::into),
+            char_set: pinvoke.char_set.map(Into::into),
+            is_no_mangle: pinvoke.is_no_mangle,
+            preserve_sig: pinvoke.preserve_sig,
+            marshaled_parameters: pinvoke.marshaled_parameters.clone(),
+        }),
+        compiler_generated: method
+            .compiler_generated
+            .iter()
+            .map(|member| JsonCompilerGenerated {
+                kind: member.kind.into(),
+                name: member.full_name(metadata.metadata),
+                tag: member.tdi.into(),
+                method_index: member.method.map(|m| m.index()),
+            })
+            .collect_vec(),
     }
 }
 
@@ -258,7 +475,7 @@ pub fn make_type(
     let methods = td
         .methods
         .iter()
-        .map(|f| make_method(f, &name_resolver))
+        .map(|f| make_method(f, metadata, &name_resolver))
         .sorted_by(|a, b| a.name.cmp(&b.name))
         .collect_vec();
 
@@ -273,6 +490,14 @@ pub fn make_type(
         .sorted_by(|a, b| a.name.cmp(&b.name))
         .collect_vec();
 
+    let interfaces = td.interfaces.iter().map(|i| i.clone().into()).collect_vec();
+    let nested_tags = td
+        .nested_types
+        .iter()
+        .map(|tag| JsonTypeTag::from(*tag))
+        .sorted()
+        .collect_vec();
+
     let namespace = td.namespace().to_string();
     let name = td.name().to_string();
 
@@ -300,6 +525,15 @@ pub fn make_type(
         size,
         tag: td.self_tag.into(),
         parent,
+        interfaces,
+        declaring_tag: td.declaring_ty.map(JsonTypeTag::from),
+        nested_tags,
         generic_instatiation,
+        is_interface: td.is_interface,
+        is_compiler_generated: td.is_compiler_generated,
+        enum_backing_type: td
+            .enum_backing_type
+            .filter(|_| td.is_enum_type)
+            .map(JsonTypeEnum::from),
     }
 }
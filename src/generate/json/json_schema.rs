@@ -0,0 +1,104 @@
+//! JSON Schemas of the output, published next to it as `cordl.schema.json`
+//! for `cordl.json` and `cordl.type.schema.json` for the files of the multi-file output
+
+use std::{fs, path::Path};
+
+use color_eyre::eyre::Result;
+use schemars::{JsonSchema, Schema, schema_for};
+
+use super::json_gen::{JsonTable, JsonTypeFile};
+
+/// Written to [`JsonTable::format_version`] and [`JsonTypeFile::format_version`].
+///
+/// Bump it once per release that changes either schema, then run the snapshot tests with
+/// `UPDATE_SCHEMA=1` to add `schema/cordl.schema.v<version>.json` and
+/// `schema/cordl.type.schema.v<version>.json`. The snapshots of every released version
+/// are kept and never overwritten, so consumers can diff them and the tests fail
+/// on any schema change until the version is bumped
+pub const JSON_FORMAT_VERSION: u32 = 2;
+
+/// Schema of the single file output
+pub fn make_schema() -> Schema {
+    versioned_schema::<JsonTable>("cordl.schema")
+}
+
+/// Schema of every file of the multi-file output
+pub fn make_type_schema() -> Schema {
+    versioned_schema::<JsonTypeFile>("cordl.type.schema")
+}
+
+fn versioned_schema<T: JsonSchema>(name: &str) -> Schema {
+    let mut schema = schema_for!(T);
+    schema.insert(
+        "$id".to_string(),
+        format!("{name}.v{JSON_FORMAT_VERSION}.json").into(),
+    );
+
+    schema
+}
+
+/// Pretty printed, as stored in the snapshots
+pub fn schema_string(schema: &Schema) -> Result<String> {
+    Ok(serde_json::to_string_pretty(schema)? + "\n")
+}
+
+pub fn write_schema(file: &Path, schema: &Schema) -> Result<()> {
+    fs::write(file, schema_string(schema)?)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf};
+
+    use super::*;
+
+    fn snapshot_path(name: &str, version: u32) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("schema")
+            .join(format!("{name}.v{version}.json"))
+    }
+
+    fn check_snapshot(name: &str, schema: &Schema) {
+        let snapshot = snapshot_path(name, JSON_FORMAT_VERSION);
+        let schema = schema_string(schema).unwrap();
+
+        if !snapshot.exists() {
+            assert!(
+                env::var_os("UPDATE_SCHEMA").is_some_and(|v| v == "1"),
+                "{snapshot:?} is missing, run the test with UPDATE_SCHEMA=1 to write it"
+            );
+            fs::write(&snapshot, schema).unwrap();
+            return;
+        }
+
+        let existing = fs::read_to_string(&snapshot).unwrap();
+        assert!(
+            existing == schema,
+            "The JSON schema no longer matches {snapshot:?}, bump JSON_FORMAT_VERSION \
+            and rerun the test with UPDATE_SCHEMA=1 to write the snapshot of the new version"
+        );
+    }
+
+    #[test]
+    fn schema_matches_snapshot() {
+        check_snapshot("cordl.schema", &make_schema());
+    }
+
+    #[test]
+    fn type_schema_matches_snapshot() {
+        check_snapshot("cordl.type.schema", &make_type_schema());
+    }
+
+    #[test]
+    fn released_versions_keep_their_snapshots() {
+        for version in 1..=JSON_FORMAT_VERSION {
+            assert!(snapshot_path("cordl.schema", version).exists());
+        }
+        // the multi-file output is versioned from 2 on
+        for version in 2..=JSON_FORMAT_VERSION {
+            assert!(snapshot_path("cordl.type.schema", version).exists());
+        }
+    }
+}
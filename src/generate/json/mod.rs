@@ -6,7 +6,10 @@ use std::{
 };
 
 use itertools::Itertools;
-use json_gen::{JsonTable, JsonType, make_type};
+use json_gen::{JsonTable, JsonType, JsonTypeFile, make_type};
+use json_schema::JSON_FORMAT_VERSION;
+
+use crate::analysis::string_literals::StringLiteral;
 
 use super::{
     cs_context_collection::TypeContextCollection,
@@ -15,9 +18,10 @@ use super::{
     type_extensions::{TypeDefinitionExtensions, TypeDefinitionIndexExtensions},
 };
 
-mod json_data;
-mod json_gen;
-mod json_name_resolver;
+pub mod json_data;
+pub mod json_gen;
+pub mod json_name_resolver;
+pub mod json_schema;
 
 type Result<T> = std::result::Result<T, color_eyre::eyre::Report>;
 
@@ -26,6 +30,7 @@ pub fn make_json(
     collection: &TypeContextCollection,
     file: &Path,
     format: bool,
+    string_literals: Vec<StringLiteral>,
 ) -> Result<()> {
     // we could use a map here but sorting
     // wouldn't be guaranteed
@@ -42,22 +47,32 @@ pub fn make_json(
         .collect();
 
     let table = JsonTable {
+        format_version: JSON_FORMAT_VERSION,
         types_table: json_objects
             .iter()
             .sorted_by(|a, b| a.0.cmp(b.0))
             .map(|t| t.1.tag.clone())
             .collect(),
         types: json_objects,
+        unresolved_virtual_call_pointers: metadata
+            .code_registration
+            .unresolved_virtual_call_pointers
+            .clone(),
+        string_literals,
     };
 
-    let file = File::create(file)?;
-    let mut buf_writer = BufWriter::new(file);
+    let mut buf_writer = BufWriter::new(File::create(file)?);
 
     match format {
         true => serde_json::to_writer_pretty(&mut buf_writer, &table)?,
         false => serde_json::to_writer(&mut buf_writer, &table)?,
     };
 
+    json_schema::write_schema(
+        &file.with_file_name("cordl.schema.json"),
+        &json_schema::make_schema(),
+    )?;
+
     Ok(())
 }
 
@@ -92,11 +107,21 @@ pub fn make_json_folder(
             let file = File::create(file)?;
             let mut buf_writer = BufWriter::new(file);
 
-            serde_json::to_writer_pretty(&mut buf_writer, &t)?;
+            let type_file = JsonTypeFile {
+                format_version: JSON_FORMAT_VERSION,
+                ty: t,
+            };
+
+            serde_json::to_writer_pretty(&mut buf_writer, &type_file)?;
 
             Ok(())
         })?;
 
+    json_schema::write_schema(
+        &folder.join("cordl.type.schema.json"),
+        &json_schema::make_type_schema(),
+    )?;
+
     Ok(())
 }
 
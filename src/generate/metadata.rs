@@ -1,13 +1,35 @@
 use std::collections::{HashMap, HashSet};
 
-use brocolib::global_metadata::{Il2CppTypeDefinition, MethodIndex, TypeDefinitionIndex};
+use brocolib::{
+    global_metadata::{Il2CppTypeDefinition, MethodIndex, TypeDefinitionIndex},
+    runtime_metadata::TypeData,
+};
 use itertools::Itertools;
+use log::warn;
 
-use super::cs_type::CsType;
+use crate::data::{
+    compiler_generated::GeneratedMember,
+    custom_attributes::CustomAttributes,
+    elf_functions::{FunctionExtents, MethodSizeSource},
+    pinvoke::PInvokeImport,
+};
+
+use super::{cs_type::CsType, type_extensions::TypeDefinitionExtensions};
 
 pub struct MethodCalculations {
     pub estimated_size: usize,
+    pub size_source: MethodSizeSource,
     pub addrs: u64,
+
+    /// Index into `code_registration.invoker_pointers`
+    pub invoker_index: Option<u32>,
+    /// Address of the `runtime_invoke` invoker function
+    pub invoker_addrs: Option<u64>,
+    /// Adjustor thunk for instance methods of value types,
+    /// takes a boxed `this` and unboxes it before calling the method
+    pub adjustor_thunk_addrs: Option<u64>,
+    /// Native callable wrapper for methods marked `MonoPInvokeCallback`
+    pub reverse_pinvoke_wrapper_addrs: Option<u64>,
 }
 
 #[repr(u8)]
@@ -41,9 +63,21 @@ pub struct CordlMetadata<'a> {
     pub metadata: &'a brocolib::Metadata<'a, 'a>,
     pub metadata_registration: &'a brocolib::runtime_metadata::Il2CppMetadataRegistration,
     pub code_registration: &'a brocolib::runtime_metadata::Il2CppCodeRegistration<'a>,
+    /// Raw libil2cpp.so
+    pub elf_data: &'a [u8],
 
     // Method index in metadata
     pub method_calculations: HashMap<MethodIndex, MethodCalculations>,
+    /// Every known function start, sorted, to find the function containing an address
+    pub function_starts: Vec<u64>,
+    /// Native function of every internal call registered by libunity.so, by registration name
+    pub icall_addresses: HashMap<String, u64>,
+    /// Library and entry point of every P/Invoke method with a marshalling wrapper
+    pub pinvoke_imports: HashMap<MethodIndex, PInvokeImport>,
+    /// Attributes of the game's types and members, empty before metadata v29
+    pub custom_attributes: CustomAttributes,
+    /// Closures, lambdas and state machines generated for each method
+    pub compiler_generated: HashMap<MethodIndex, Vec<GeneratedMember>>,
     pub parent_to_child_map: HashMap<TypeDefinitionIndex, Vec<TypeDefinitionPair<'a>>>,
     pub child_to_parent_map: HashMap<TypeDefinitionIndex, TypeDefinitionPair<'a>>,
 
@@ -68,6 +102,94 @@ impl<'a> CordlMetadata<'a> {
         (self.pointer_size as u8) * 2
     }
 
+    /// `Namespace.Type::Method`
+    pub fn method_full_name(&self, method_index: MethodIndex) -> String {
+        let method = &self.metadata.global_metadata.methods[method_index];
+        let td = &self.metadata.global_metadata.type_definitions[method.declaring_type];
+
+        format!(
+            "{}::{}",
+            td.full_name(self.metadata, true),
+            method.name(self.metadata)
+        )
+    }
+
+    /// `Namespace.Type::Method(System.Int32, System.String)`
+    /// Unique across overloads
+    pub fn method_full_signature(&self, method_index: MethodIndex) -> String {
+        let method = &self.metadata.global_metadata.methods[method_index];
+        let params = method
+            .parameters(self.metadata)
+            .iter()
+            .map(|p| {
+                self.metadata_registration.types[p.type_index as usize].full_name(self.metadata)
+            })
+            .join(", ");
+
+        format!("{}({params})", self.method_full_name(method_index))
+    }
+
+    /// Name an internal call is registered under,
+    /// `UnityEngine.Transform::get_position_Injected(UnityEngine.Vector3&)`
+    pub fn icall_name(&self, method_index: MethodIndex) -> String {
+        let gm = &self.metadata.global_metadata;
+        let method = &gm.methods[method_index];
+        let type_name = |td: &Il2CppTypeDefinition| {
+            td.get_name_components(self.metadata)
+                .remove_generics()
+                .combine_all()
+        };
+
+        let params = method
+            .parameters(self.metadata)
+            .iter()
+            .map(|p| {
+                let ty = &self.metadata_registration.types[p.type_index as usize];
+                let name = match ty.data {
+                    TypeData::TypeDefinitionIndex(tdi) => type_name(&gm.type_definitions[tdi]),
+                    _ => ty
+                        .full_name(self.metadata)
+                        .trim_end_matches('&')
+                        .to_string(),
+                };
+
+                match ty.byref {
+                    true => format!("{name}&"),
+                    false => name,
+                }
+            })
+            .join(",");
+
+        format!(
+            "{}::{}({params})",
+            type_name(&gm.type_definitions[method.declaring_type]),
+            method.name(self.metadata)
+        )
+    }
+
+    /// Looks the icall up with its signature first, then by name only like il2cpp does
+    pub fn icall_address(&self, icall_name: &str) -> Option<u64> {
+        let without_signature = icall_name.split_once('(').map(|(name, _)| name);
+
+        self.icall_addresses
+            .get(icall_name)
+            .or_else(|| self.icall_addresses.get(without_signature?))
+            .copied()
+    }
+
+    /// Contents of `global_metadata.string_literals[index]`
+    pub fn string_literal(&self, index: usize) -> Option<String> {
+        let gm = &self.metadata.global_metadata;
+        let literal = gm.string_literals.as_vec().get(index)?;
+
+        let start = literal.data_index as usize;
+        let data = gm
+            .string_literal_data
+            .get(start..start + literal.length as usize)?;
+
+        Some(String::from_utf8_lossy(data).into_owned())
+    }
+
     pub fn parse(&mut self) {
         let gm = &self.metadata.global_metadata;
         self.parse_name_tdi(gm);
@@ -133,21 +255,40 @@ impl<'a> CordlMetadata<'a> {
     }
 
     fn parse_method_size(&mut self, gm: &brocolib::global_metadata::GlobalMetadata) {
+        let function_extents = FunctionExtents::parse(self.elf_data).unwrap_or_else(|e| {
+            warn!("Unable to read function extents from ELF, falling back to estimates: {e:?}");
+            FunctionExtents::default()
+        });
+
         // sorted by address
-        // method index -> address
-        let method_addresses_sorted: Vec<u64> = self
+        // every known function start, used to estimate sizes when the binary doesn't say
+        let function_starts_sorted: Vec<u64> = self
             .code_registration
             .code_gen_modules
             .iter()
-            .flat_map(|m| &m.method_pointers)
-            .copied()
+            .flat_map(|m| {
+                m.method_pointers
+                    .iter()
+                    .copied()
+                    .chain(m.adjustor_thunks.iter().map(|pair| pair.adjustor_thunk))
+            })
+            .chain(
+                self.code_registration
+                    .generic_method_pointers
+                    .iter()
+                    .copied(),
+            )
+            .chain(self.code_registration.invoker_pointers.iter().copied())
+            .chain(
+                self.code_registration
+                    .reverse_pinvoke_wrappers
+                    .iter()
+                    .copied(),
+            )
+            .chain(function_extents.function_starts())
+            .filter(|addr| *addr != 0x0)
             .sorted()
-            .collect();
-        // address -> method index in sorted list
-        let method_addresses_sorted_map: HashMap<u64, usize> = method_addresses_sorted
-            .iter()
-            .enumerate()
-            .map(|(index, m_ptr)| (*m_ptr, index))
+            .dedup()
             .collect();
 
         self.method_calculations = self
@@ -157,6 +298,30 @@ impl<'a> CordlMetadata<'a> {
             .code_gen_modules
             .iter()
             .flat_map(|cgm| {
+                // method pointer index -> adjustor thunk
+                let adjustor_thunks: HashMap<usize, u64> = cgm
+                    .adjustor_thunks
+                    .iter()
+                    // a rid of 0 is a null token, which has no method pointer
+                    .filter_map(|pair| {
+                        let index = pair.token.rid().checked_sub(1)?;
+                        Some((index as usize, pair.adjustor_thunk))
+                    })
+                    .collect();
+                // method pointer index -> reverse pinvoke wrapper
+                let reverse_pinvoke_wrappers: HashMap<usize, u64> = cgm
+                    .reverse_pinvoke_wrapper_indices
+                    .iter()
+                    .filter_map(|tuple| {
+                        let wrapper = self
+                            .code_registration
+                            .reverse_pinvoke_wrappers
+                            .get(tuple.index as usize)?;
+                        let index = tuple.token.rid().checked_sub(1)?;
+                        Some((index as usize, *wrapper))
+                    })
+                    .collect();
+
                 let img = gm
                     .images
                     .as_vec()
@@ -179,25 +344,38 @@ impl<'a> CordlMetadata<'a> {
                             let method_pointer =
                                 *cgm.method_pointers.get(method_pointer_index).unwrap();
 
-                            let sorted_address_index =
-                                *method_addresses_sorted_map.get(&method_pointer).unwrap();
-                            let next_method_pointer = method_addresses_sorted
-                                .get(sorted_address_index + 1)
-                                .cloned()
-                                .unwrap_or(0);
-
-                            let estimated_size =
-                                if method_pointer == 0x0 || next_method_pointer == 0x0 {
-                                    usize::MAX
-                                } else {
-                                    method_pointer.abs_diff(next_method_pointer) as usize
-                                };
+                            let (estimated_size, size_source) = Self::get_method_size(
+                                method_pointer,
+                                &function_extents,
+                                &function_starts_sorted,
+                            );
+
+                            // u32::MAX means the method has no invoker (e.g generic definitions)
+                            let invoker_index = cgm
+                                .invoker_indices
+                                .get(method_pointer_index)
+                                .copied()
+                                .filter(|i| *i != u32::MAX);
+                            let invoker_addrs = invoker_index
+                                .and_then(|i| {
+                                    self.code_registration.invoker_pointers.get(i as usize)
+                                })
+                                .copied();
 
                             (
                                 method_index,
                                 MethodCalculations {
                                     estimated_size,
+                                    size_source,
                                     addrs: method_pointer,
+                                    invoker_index,
+                                    invoker_addrs,
+                                    adjustor_thunk_addrs: adjustor_thunks
+                                        .get(&method_pointer_index)
+                                        .copied(),
+                                    reverse_pinvoke_wrapper_addrs: reverse_pinvoke_wrappers
+                                        .get(&method_pointer_index)
+                                        .copied(),
                                 },
                             )
                         })
@@ -206,6 +384,40 @@ impl<'a> CordlMetadata<'a> {
                 method_calculations
             })
             .collect();
+        self.function_starts = function_starts_sorted;
+    }
+
+    /// Size of the method at `method_pointer`
+    /// Prefers the ELF symbols and unwind info,
+    /// otherwise the distance to the next function or the end of `.text`
+    fn get_method_size(
+        method_pointer: u64,
+        function_extents: &FunctionExtents,
+        function_starts_sorted: &[u64],
+    ) -> (usize, MethodSizeSource) {
+        if method_pointer == 0x0 {
+            return (usize::MAX, MethodSizeSource::Unknown);
+        }
+
+        if let Some((size, source)) = function_extents.get_size(method_pointer) {
+            return (size as usize, source);
+        }
+
+        let next_index = function_starts_sorted.partition_point(|addr| *addr <= method_pointer);
+        let next_function = function_starts_sorted.get(next_index).copied().or_else(|| {
+            function_extents
+                .text_range
+                .map(|(_, end)| end)
+                .filter(|end| *end > method_pointer)
+        });
+
+        match next_function {
+            Some(next) => (
+                (next - method_pointer) as usize,
+                MethodSizeSource::NextFunction,
+            ),
+            None => (usize::MAX, MethodSizeSource::Unknown),
+        }
     }
 
     fn parse_name_tdi(&mut self, gm: &brocolib::global_metadata::GlobalMetadata) {
@@ -10,7 +10,19 @@ pub mod writer;
 
 #[cfg(feature = "cpp")]
 pub mod cpp;
+#[cfg(feature = "disasm")]
+pub mod disasm;
+#[cfg(feature = "dummy_dll")]
+pub mod dummy_dll;
+#[cfg(feature = "dumper")]
+pub mod dumper;
+#[cfg(feature = "frida")]
+pub mod frida;
+#[cfg(feature = "html")]
+pub mod html;
 #[cfg(feature = "json")]
 pub mod json;
 #[cfg(feature = "rust")]
 pub mod rust;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
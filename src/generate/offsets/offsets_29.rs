@@ -799,7 +799,7 @@ fn align_to(size: usize, alignment: usize) -> usize {
 pub struct SizeAndAlignment {
     pub size: usize,
     actual_size: usize,
-    alignment: u8,
+    pub alignment: u8,
     natural_alignment: u8,
     packing: Option<u8>,
 }
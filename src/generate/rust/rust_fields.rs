@@ -235,6 +235,7 @@ pub fn handle_static_fields(
             generics: Default::default(),
 
             feature: None,
+            docs: Default::default(),
 
             return_type: Some(get_return_type.to_type_token()),
             params: vec![],
@@ -254,6 +255,7 @@ pub fn handle_static_fields(
             is_self: false,
 
             feature: None,
+            docs: Default::default(),
 
             return_type: None,
             params: vec![RustParam {
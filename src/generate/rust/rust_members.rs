@@ -107,6 +107,8 @@ pub struct RustFunction {
     pub where_clause: Option<syn::WhereClause>,
 
     pub feature: Option<RustFeature>,
+    /// `///` lines above the function
+    pub docs: Vec<String>,
 
     pub is_self: bool,
     pub is_ref: bool,
@@ -175,17 +177,20 @@ impl RustFunction {
             None => parse_quote! {},
         };
         let where_clause = &self.where_clause;
+        let docs = &self.docs;
 
         let visibility = self.visibility.to_token_stream();
         let mut tokens = match self_param {
             Some(self_param) => {
                 quote! {
+                    #(#[doc = #docs])*
                     #feature
                     #visibility fn #name #generics (#self_param, #(#params),*) #return_type #where_clause
                 }
             }
             None => {
                 quote! {
+                    #(#[doc = #docs])*
                     #feature
                     #visibility fn #name #generics (#(#params),*) #return_type #where_clause
                 }
@@ -252,6 +252,7 @@ impl RustType {
                     is_self: false,
                     where_clause: None,
                     feature: None,
+                    docs: Default::default(),
                     params: vec![RustParam {
                         name: format_ident!("object_param"),
                         param_type: parse_quote!(*mut quest_hook::libil2cpp::Il2CppObject),
@@ -592,6 +593,7 @@ impl RustType {
                 where_clause: Some(where_clause),
 
                 feature: None,
+                docs: Default::default(),
 
                 return_type: Some(parse_quote!(
                     quest_hook::libil2cpp::Result<quest_hook::libil2cpp::Gc<Self>>
@@ -766,6 +768,17 @@ impl RustType {
                     where_clause: Some(where_clause),
 
                     feature: None,
+                    docs: m
+                        .compiler_generated
+                        .iter()
+                        .map(|member| {
+                            format!(
+                                " Compiler generated {}: `{}`",
+                                member.kind,
+                                member.full_name(name_resolver.cordl_metadata.metadata)
+                            )
+                        })
+                        .collect(),
 
                     return_type: Some(m_result_ty),
                     visibility: (Visibility::Public),
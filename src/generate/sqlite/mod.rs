@@ -0,0 +1,2 @@
+pub mod sqlite_main;
+pub mod sqlite_schema;
@@ -0,0 +1,396 @@
+use std::{fs, path::Path};
+
+use brocolib::runtime_metadata::TypeData;
+use color_eyre::eyre::{Context, Result};
+use itertools::Itertools;
+use rusqlite::{Connection, Transaction, params};
+
+use crate::{
+    data::{custom_attributes::METHOD_DEF_TOKEN, type_resolver::ResolvedType},
+    generate::{
+        cs_context_collection::TypeContextCollection,
+        cs_members::{CsMethod, CsMethodData, CsParamFlags},
+        cs_type::CsType,
+        cs_type_tag::CsTypeTag,
+        json::{
+            json_data::{
+                JsonCallingConvention, JsonCharSet, JsonGeneratedKind, JsonMethodFlag, JsonTypeTag,
+                JsonValue,
+            },
+            json_name_resolver::JsonNameResolver,
+        },
+        metadata::CordlMetadata,
+    },
+};
+
+use super::sqlite_schema::{INDICES, SCHEMA};
+
+pub fn run_sqlite(collection: &TypeContextCollection, metadata: &CordlMetadata) -> Result<()> {
+    let database = Path::new("./cordl.db");
+    println!("Writing {database:?}");
+
+    if database.exists() {
+        fs::remove_file(database)?;
+    }
+
+    let mut connection = Connection::open(database).context("Unable to create database")?;
+    connection.execute_batch("PRAGMA journal_mode = OFF; PRAGMA synchronous = OFF;")?;
+    create_schema(&connection)?;
+
+    let transaction = connection.transaction()?;
+    let writer = DatabaseWriter {
+        transaction: &transaction,
+        name_resolver: JsonNameResolver {
+            cordl_metadata: metadata,
+            collection,
+        },
+        metadata,
+    };
+
+    for ty in collection
+        .get()
+        .values()
+        .flat_map(|c| c.get_types().iter())
+        .sorted_by_key(|(tag, _)| **tag)
+        .map(|(_, ty)| ty)
+    {
+        writer
+            .insert_type(ty)
+            .with_context(|| format!("Inserting {}", ty.cs_name_components.combine_all()))?;
+    }
+    transaction.commit()?;
+
+    connection.execute_batch(INDICES)?;
+
+    Ok(())
+}
+
+fn create_schema(connection: &Connection) -> Result<()> {
+    // bundled SQLite enforces foreign keys by default, but types are inserted in tag order
+    // and can reference blacklisted types that are never inserted
+    connection.execute_batch("PRAGMA foreign_keys = OFF;")?;
+    connection.execute_batch(SCHEMA)?;
+
+    Ok(())
+}
+
+struct DatabaseWriter<'a, 'b> {
+    transaction: &'a Transaction<'a>,
+    name_resolver: JsonNameResolver<'a, 'b>,
+    metadata: &'a CordlMetadata<'b>,
+}
+
+impl DatabaseWriter<'_, '_> {
+    fn insert_type(&self, ty: &CsType) -> Result<()> {
+        let tag = tag_key(ty.self_tag);
+        let (type_definition, inst) = match ty.self_tag {
+            CsTypeTag::TypeDefinitionIndex(tdi) => (tdi.index(), None),
+            CsTypeTag::GenericInstantiation(generic_inst) => {
+                (generic_inst.tdi.index(), Some(generic_inst.inst))
+            }
+        };
+
+        self.transaction
+            .prepare_cached(
+                "INSERT INTO types VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+            )?
+            .execute(params![
+                tag,
+                type_definition,
+                inst,
+                ty.cs_name_components.combine_all(),
+                ty.namespace(),
+                ty.name(),
+                ty.declaring_ty.map(tag_key),
+                ty.parent.as_ref().map(|p| self.type_name(p)),
+                ty.parent.as_ref().and_then(|p| self.type_tag(p)),
+                ty.is_value_type,
+                ty.is_enum_type,
+                ty.is_interface,
+                ty.is_compiler_generated,
+                ty.size_info.as_ref().map(|s| s.instance_size),
+                ty.packing,
+            ])?;
+
+        let generic_arguments = match (&ty.generic_instantiations_args_types, &ty.generic_template)
+        {
+            (Some(args), _) => args
+                .iter()
+                .map(|a| (self.type_name(a), self.type_tag(a)))
+                .collect_vec(),
+            (None, Some(template)) => template
+                .names
+                .iter()
+                .map(|(_, name)| (name.clone(), None))
+                .collect_vec(),
+            (None, None) => vec![],
+        };
+        for (position, (name, arg_tag)) in generic_arguments.into_iter().enumerate() {
+            self.transaction
+                .prepare_cached("INSERT INTO generic_arguments VALUES (?1, ?2, ?3, ?4)")?
+                .execute(params![tag, position, name, arg_tag])?;
+        }
+
+        for interface in &ty.interfaces {
+            self.transaction
+                .prepare_cached("INSERT INTO interfaces VALUES (?1, ?2, ?3)")?
+                .execute(params![
+                    tag,
+                    self.type_name(interface),
+                    self.type_tag(interface)
+                ])?;
+        }
+
+        for field in &ty.fields {
+            self.transaction
+                .prepare_cached(
+                    "INSERT INTO fields (type_tag, name, type_name, field_type_tag, is_instance, is_const, is_readonly, offset, value)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                )?
+                .execute(params![
+                    tag,
+                    field.name,
+                    self.type_name(&field.field_ty),
+                    self.type_tag(&field.field_ty),
+                    field.instance,
+                    field.is_const,
+                    field.readonly,
+                    field.offset,
+                    field.value.clone().map(|v| json_key(&JsonValue::from(v))),
+                ])?;
+        }
+
+        for method in &ty.methods {
+            self.insert_method(&tag, method)?;
+        }
+
+        for property in &ty.properties {
+            self.transaction
+                .prepare_cached(
+                    "INSERT INTO properties (type_tag, name, type_name, property_type_tag, is_instance, is_indexable, getter_method_index, setter_method_index)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                )?
+                .execute(params![
+                    tag,
+                    property.name,
+                    self.type_name(&property.prop_ty),
+                    self.type_tag(&property.prop_ty),
+                    property.instance,
+                    property.indexable,
+                    property.getter.as_ref().map(|(i, _)| i.index()),
+                    property.setter.as_ref().map(|(i, _)| i.index()),
+                ])?;
+        }
+
+        Ok(())
+    }
+
+    fn insert_method(&self, tag: &str, method: &CsMethod) -> Result<()> {
+        let method_def = &self.metadata.metadata.global_metadata.methods[method.method_index];
+        let method_id = insert_method_row(
+            self.transaction,
+            &MethodRow {
+                tag,
+                method_index: method.method_index.index(),
+                token: METHOD_DEF_TOKEN | method_def.token.rid() as u32,
+                name: &method.name,
+                return_type_name: self.type_name(&method.return_type),
+                return_type_tag: self.type_tag(&method.return_type),
+                instance: method.instance,
+                method_data: &method.method_data,
+            },
+        )?;
+
+        for (position, arg) in method.generic_instatiation.iter().flatten().enumerate() {
+            self.transaction
+                .prepare_cached("INSERT INTO method_generic_arguments VALUES (?1, ?2, ?3, ?4)")?
+                .execute(params![
+                    method_id,
+                    position,
+                    self.type_name(arg),
+                    self.type_tag(arg)
+                ])?;
+        }
+
+        for (position, param) in method.parameters.iter().enumerate() {
+            let ref_mode = if param.modifiers.contains(CsParamFlags::IN) {
+                Some("In")
+            } else if param.modifiers.contains(CsParamFlags::OUT) {
+                Some("Out")
+            } else if param.modifiers.contains(CsParamFlags::REF) {
+                Some("Ref")
+            } else {
+                None
+            };
+
+            self.transaction
+                .prepare_cached("INSERT INTO parameters VALUES (?1, ?2, ?3, ?4, ?5, ?6)")?
+                .execute(params![
+                    method_id,
+                    position,
+                    param.name,
+                    self.type_name(&param.il2cpp_ty),
+                    self.type_tag(&param.il2cpp_ty),
+                    ref_mode,
+                ])?;
+        }
+
+        for flag in JsonMethodFlag::from_flags(&method.method_flags) {
+            self.transaction
+                .prepare_cached("INSERT INTO method_flags VALUES (?1, ?2)")?
+                .execute(params![method_id, format!("{flag:?}")])?;
+        }
+
+        if let Some(pinvoke) = &method.pinvoke {
+            self.transaction
+                .prepare_cached("INSERT INTO pinvokes VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)")?
+                .execute(params![
+                    method_id,
+                    pinvoke.library,
+                    pinvoke.entry_point,
+                    pinvoke
+                        .calling_convention
+                        .map(|c| format!("{:?}", JsonCallingConvention::from(c))),
+                    pinvoke
+                        .char_set
+                        .map(|c| format!("{:?}", JsonCharSet::from(c))),
+                    pinvoke.is_no_mangle,
+                    pinvoke.preserve_sig,
+                ])?;
+        }
+
+        for member in &method.compiler_generated {
+            self.transaction
+                .prepare_cached("INSERT INTO compiler_generated VALUES (?1, ?2, ?3, ?4, ?5)")?
+                .execute(params![
+                    method_id,
+                    format!("{:?}", JsonGeneratedKind::from(member.kind)),
+                    member.full_name(self.metadata.metadata),
+                    tag_key(member.tdi.into()),
+                    member.method.map(|m| m.index()),
+                ])?;
+        }
+
+        Ok(())
+    }
+
+    /// Same as `ty_name` in cordl.json
+    fn type_name(&self, ty: &ResolvedType) -> String {
+        self.name_resolver.resolve_name(ty).combine_all()
+    }
+
+    /// Key of the type definition or generic instantiation, if `ty` is one
+    fn type_tag(&self, ty: &ResolvedType) -> Option<String> {
+        let metadata = self.metadata;
+        let tag = match metadata.metadata_registration.types[ty.ty].data {
+            TypeData::TypeDefinitionIndex(tdi) => tdi.into(),
+            TypeData::GenericClassIndex(generic_class) => {
+                CsTypeTag::from_generic_class_index(generic_class, metadata.metadata)
+            }
+            _ => return None,
+        };
+
+        Some(tag_key(tag))
+    }
+}
+
+/// Columns of a `methods` row
+struct MethodRow<'a> {
+    tag: &'a str,
+    method_index: u32,
+    token: u32,
+    name: &'a str,
+    return_type_name: String,
+    return_type_tag: Option<String>,
+    instance: bool,
+    method_data: &'a CsMethodData,
+}
+
+/// Inserts into `methods`, returning the row id
+fn insert_method_row(transaction: &Transaction, row: &MethodRow) -> Result<i64> {
+    let method_data = row.method_data;
+    transaction
+        .prepare_cached(
+            "INSERT INTO methods (type_tag, method_index, token, name, return_type_name, return_type_tag, is_instance,
+                address, estimated_size, size_source, slot, invoker_index, invoker_address,
+                adjustor_thunk_address, reverse_pinvoke_wrapper_address, icall_name, icall_address)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)",
+        )?
+        .execute(params![
+            row.tag,
+            row.method_index,
+            row.token,
+            row.name,
+            row.return_type_name,
+            row.return_type_tag,
+            row.instance,
+            method_data.addrs.map(|a| a as i64),
+            // usize::MAX marks an unknown size and doesn't fit in an INTEGER
+            method_data
+                .estimated_size
+                .filter(|s| *s != usize::MAX)
+                .map(|s| s as i64),
+            format!("{:?}", method_data.size_source),
+            method_data.slot,
+            method_data.invoker_index,
+            method_data.invoker_addrs.map(|a| a as i64),
+            method_data.adjustor_thunk_addrs.map(|a| a as i64),
+            method_data.reverse_pinvoke_wrapper_addrs.map(|a| a as i64),
+            method_data.icall_name,
+            method_data.icall_addrs.map(|a| a as i64),
+        ])?;
+
+    Ok(transaction.last_insert_rowid())
+}
+
+fn tag_key(tag: CsTypeTag) -> String {
+    json_key(&JsonTypeTag::from(tag))
+}
+
+/// Same as in cordl.json
+fn json_key(value: &impl serde::Serialize) -> String {
+    serde_json::to_string(value).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_method_size_is_null() {
+        let mut connection = Connection::open_in_memory().unwrap();
+        create_schema(&connection).unwrap();
+        let transaction = connection.transaction().unwrap();
+
+        for estimated_size in [Some(usize::MAX), Some(0x40), None] {
+            let method_data = CsMethodData {
+                estimated_size,
+                addrs: Some(u64::MAX),
+                ..Default::default()
+            };
+            insert_method_row(
+                &transaction,
+                &MethodRow {
+                    tag: "tag",
+                    method_index: 0,
+                    token: METHOD_DEF_TOKEN | 1,
+                    name: "Method",
+                    return_type_name: "System.Void".to_string(),
+                    return_type_tag: None,
+                    instance: true,
+                    method_data: &method_data,
+                },
+            )
+            .unwrap();
+        }
+
+        let sizes: Vec<Option<i64>> = transaction
+            .prepare("SELECT estimated_size FROM methods ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(sizes, [None, Some(0x40), None]);
+    }
+}
@@ -0,0 +1,153 @@
+//! Tables of the generated database. Types are keyed on their `JsonTypeTag`
+//! serialized as in `cordl.json`, e.g. `{"TypeDefinition":12}`.
+//! Constants are stored the same way as their `JsonValue`, e.g. `{"I32":5}`
+
+pub const SCHEMA: &str = r#"
+CREATE TABLE types (
+    tag TEXT PRIMARY KEY,
+    type_definition INTEGER NOT NULL,
+    -- index of the generic instantiation, NULL for type definitions
+    inst INTEGER,
+    full_name TEXT NOT NULL,
+    namespace TEXT NOT NULL,
+    name TEXT NOT NULL,
+    declaring_tag TEXT REFERENCES types(tag),
+    parent_name TEXT,
+    parent_tag TEXT REFERENCES types(tag),
+    is_value_type INTEGER NOT NULL,
+    is_enum INTEGER NOT NULL,
+    is_interface INTEGER NOT NULL,
+    is_compiler_generated INTEGER NOT NULL,
+    size INTEGER,
+    packing INTEGER
+);
+
+-- generic parameters of type definitions, and arguments of generic instantiations
+CREATE TABLE generic_arguments (
+    type_tag TEXT NOT NULL REFERENCES types(tag),
+    position INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    arg_tag TEXT REFERENCES types(tag),
+    PRIMARY KEY (type_tag, position)
+);
+
+CREATE TABLE interfaces (
+    type_tag TEXT NOT NULL REFERENCES types(tag),
+    interface_name TEXT NOT NULL,
+    interface_tag TEXT REFERENCES types(tag)
+);
+
+CREATE TABLE fields (
+    id INTEGER PRIMARY KEY,
+    type_tag TEXT NOT NULL REFERENCES types(tag),
+    name TEXT NOT NULL,
+    type_name TEXT NOT NULL,
+    field_type_tag TEXT REFERENCES types(tag),
+    is_instance INTEGER NOT NULL,
+    is_const INTEGER NOT NULL,
+    is_readonly INTEGER NOT NULL,
+    offset INTEGER,
+    value TEXT
+);
+
+CREATE TABLE methods (
+    id INTEGER PRIMARY KEY,
+    type_tag TEXT NOT NULL REFERENCES types(tag),
+    method_index INTEGER NOT NULL,
+    token INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    return_type_name TEXT NOT NULL,
+    return_type_tag TEXT REFERENCES types(tag),
+    is_instance INTEGER NOT NULL,
+    address INTEGER,
+    estimated_size INTEGER,
+    size_source TEXT NOT NULL,
+    slot INTEGER,
+    invoker_index INTEGER,
+    invoker_address INTEGER,
+    adjustor_thunk_address INTEGER,
+    reverse_pinvoke_wrapper_address INTEGER,
+    -- registration name and native function in libunity.so of internal calls
+    icall_name TEXT,
+    icall_address INTEGER
+);
+
+-- `JsonMethodFlag`s, e.g. Virtual or InternalCall
+CREATE TABLE method_flags (
+    method_id INTEGER NOT NULL REFERENCES methods(id),
+    flag TEXT NOT NULL,
+    PRIMARY KEY (method_id, flag)
+);
+
+-- `DllImport` of extern methods
+CREATE TABLE pinvokes (
+    method_id INTEGER PRIMARY KEY REFERENCES methods(id),
+    -- __Internal for plugins linked into libil2cpp.so
+    library TEXT,
+    entry_point TEXT,
+    calling_convention TEXT,
+    char_set TEXT,
+    is_no_mangle INTEGER,
+    preserve_sig INTEGER NOT NULL
+);
+
+-- closures, lambdas and state machines holding code written in a method
+CREATE TABLE compiler_generated (
+    method_id INTEGER NOT NULL REFERENCES methods(id),
+    kind TEXT NOT NULL,
+    name TEXT NOT NULL,
+    -- the generated type, or the type declaring the generated method
+    type_tag TEXT NOT NULL REFERENCES types(tag),
+    -- set for lambdas and local functions
+    method_index INTEGER
+);
+
+-- type arguments of generic method instantiations
+CREATE TABLE method_generic_arguments (
+    method_id INTEGER NOT NULL REFERENCES methods(id),
+    position INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    arg_tag TEXT REFERENCES types(tag),
+    PRIMARY KEY (method_id, position)
+);
+
+CREATE TABLE parameters (
+    method_id INTEGER NOT NULL REFERENCES methods(id),
+    position INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    type_name TEXT NOT NULL,
+    type_tag TEXT REFERENCES types(tag),
+    -- In, Out or Ref
+    ref_mode TEXT,
+    PRIMARY KEY (method_id, position)
+);
+
+CREATE TABLE properties (
+    id INTEGER PRIMARY KEY,
+    type_tag TEXT NOT NULL REFERENCES types(tag),
+    name TEXT NOT NULL,
+    type_name TEXT NOT NULL,
+    property_type_tag TEXT REFERENCES types(tag),
+    is_instance INTEGER NOT NULL,
+    is_indexable INTEGER NOT NULL,
+    getter_method_index INTEGER,
+    setter_method_index INTEGER
+);
+"#;
+
+/// Created after the rows are inserted, so inserting doesn't maintain them
+pub const INDICES: &str = r#"
+CREATE INDEX types_full_name ON types(full_name);
+CREATE INDEX types_parent_tag ON types(parent_tag);
+CREATE INDEX interfaces_type_tag ON interfaces(type_tag);
+CREATE INDEX interfaces_interface_tag ON interfaces(interface_tag);
+CREATE INDEX fields_type_tag ON fields(type_tag);
+CREATE INDEX methods_type_tag ON methods(type_tag);
+CREATE INDEX methods_return_type_name ON methods(return_type_name);
+CREATE INDEX methods_address ON methods(address);
+CREATE INDEX methods_token ON methods(token);
+CREATE INDEX method_flags_flag ON method_flags(flag);
+CREATE INDEX compiler_generated_method_id ON compiler_generated(method_id);
+CREATE INDEX parameters_type_name ON parameters(type_name);
+CREATE INDEX properties_type_tag ON properties(type_tag);
+"#;
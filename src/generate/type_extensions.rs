@@ -12,16 +12,26 @@ use crate::{data::name_components::NameComponents, generate::cs_type_tag::CsType
 pub const PARAM_ATTRIBUTE_IN: u16 = 0x0001;
 pub const PARAM_ATTRIBUTE_OUT: u16 = 0x0002;
 pub const PARAM_ATTRIBUTE_OPTIONAL: u16 = 0x0010;
+pub const PARAM_ATTRIBUTE_HAS_FIELD_MARSHAL: u16 = 0x2000;
 
 pub const TYPE_ATTRIBUTE_INTERFACE: u32 = 0x00000020;
 pub const TYPE_ATTRIBUTE_NESTED_PUBLIC: u32 = 0x00000002;
 pub const TYPE_ATTRIBUTE_EXPLICIT_LAYOUT: u32 = 0x00000010;
 pub const TYPE_ATTRIBUTE_SPECIAL_NAME: u32 = 0x00000400;
+pub const TYPE_ATTRIBUTE_VISIBILITY_MASK: u32 = 0x00000007;
+pub const TYPE_ATTRIBUTE_ABSTRACT: u32 = 0x00000080;
+pub const TYPE_ATTRIBUTE_SEALED: u32 = 0x00000100;
+pub const TYPE_ATTRIBUTE_SERIALIZABLE: u32 = 0x00002000;
+pub const TYPE_ATTRIBUTE_PUBLIC: u32 = 0x00000001;
+pub const TYPE_ATTRIBUTE_BEFORE_FIELD_INIT: u32 = 0x00100000;
 
 pub const FIELD_ATTRIBUTE_PUBLIC: u16 = 0x0006;
 pub const FIELD_ATTRIBUTE_PRIVATE: u16 = 0x0001;
 pub const FIELD_ATTRIBUTE_STATIC: u16 = 0x0010;
 pub const FIELD_ATTRIBUTE_LITERAL: u16 = 0x0040;
+pub const FIELD_ATTRIBUTE_FIELD_ACCESS_MASK: u16 = 0x0007;
+pub const FIELD_ATTRIBUTE_INIT_ONLY: u16 = 0x0020;
+pub const FIELD_ATTRIBUTE_NOT_SERIALIZED: u16 = 0x0080;
 
 pub const METHOD_ATTRIBUTE_PUBLIC: u16 = 0x0006;
 pub const METHOD_ATTRIBUTE_STATIC: u16 = 0x0010;
@@ -30,6 +40,14 @@ pub const METHOD_ATTRIBUTE_VIRTUAL: u16 = 0x0040;
 pub const METHOD_ATTRIBUTE_HIDE_BY_SIG: u16 = 0x0080;
 pub const METHOD_ATTRIBUTE_ABSTRACT: u16 = 0x0400;
 pub const METHOD_ATTRIBUTE_SPECIAL_NAME: u16 = 0x0800;
+pub const METHOD_ATTRIBUTE_MEMBER_ACCESS_MASK: u16 = 0x0007;
+pub const METHOD_ATTRIBUTE_NEW_SLOT: u16 = 0x0100;
+pub const METHOD_ATTRIBUTE_PINVOKE_IMPL: u16 = 0x2000;
+pub const METHOD_ATTRIBUTE_RT_SPECIAL_NAME: u16 = 0x1000;
+
+pub const METHOD_IMPL_ATTRIBUTE_CODE_TYPE_MASK: u16 = 0x0003;
+pub const METHOD_IMPL_ATTRIBUTE_INTERNAL_CALL: u16 = 0x1000;
+pub const METHOD_IMPL_ATTRIBUTE_PRESERVE_SIG: u16 = 0x0080;
 
 pub trait MethodDefintionExtensions {
     fn is_public_method(&self) -> bool;
@@ -39,6 +57,9 @@ pub trait MethodDefintionExtensions {
     fn is_hidden_sig(&self) -> bool;
     fn is_special_name(&self) -> bool;
     fn is_final_method(&self) -> bool;
+    fn is_internal_call(&self) -> bool;
+    fn is_pinvoke_impl(&self) -> bool;
+    fn is_preserve_sig(&self) -> bool;
 }
 
 impl MethodDefintionExtensions for Il2CppMethodDefinition {
@@ -69,12 +90,27 @@ impl MethodDefintionExtensions for Il2CppMethodDefinition {
     fn is_final_method(&self) -> bool {
         (self.flags & METHOD_ATTRIBUTE_FINAL) != 0
     }
+
+    /// Implemented natively by the engine, `[MethodImpl(MethodImplOptions.InternalCall)]`
+    fn is_internal_call(&self) -> bool {
+        (self.iflags & METHOD_IMPL_ATTRIBUTE_INTERNAL_CALL) != 0
+    }
+
+    /// `extern` with `[DllImport]`
+    fn is_pinvoke_impl(&self) -> bool {
+        (self.flags & METHOD_ATTRIBUTE_PINVOKE_IMPL) != 0
+    }
+
+    fn is_preserve_sig(&self) -> bool {
+        (self.iflags & METHOD_IMPL_ATTRIBUTE_PRESERVE_SIG) != 0
+    }
 }
 
 pub trait ParameterDefinitionExtensions {
     fn is_param_optional(&self) -> bool;
     fn is_param_in(&self) -> bool;
     fn is_param_out(&self) -> bool;
+    fn is_param_marshaled(&self) -> bool;
 }
 
 impl ParameterDefinitionExtensions for Il2CppType {
@@ -89,6 +125,11 @@ impl ParameterDefinitionExtensions for Il2CppType {
     fn is_param_out(&self) -> bool {
         (self.attrs & PARAM_ATTRIBUTE_OUT) != 0
     }
+
+    /// Has a `[MarshalAs]` attribute
+    fn is_param_marshaled(&self) -> bool {
+        (self.attrs & PARAM_ATTRIBUTE_HAS_FIELD_MARSHAL) != 0
+    }
 }
 
 pub trait TypeExtentions {
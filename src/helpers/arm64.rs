@@ -0,0 +1,588 @@
+//! Minimal AArch64 decoder for the instructions cordl cares about:
+//! branches, the ADRP/ADD/LDR sequences used to reach globals and moves of constants.
+//! Everything else decodes to [`Instruction::Other`].
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    /// `BL target`
+    Bl {
+        target: u64,
+    },
+    /// `BLR rn`
+    Blr {
+        rn: u8,
+    },
+    /// `B target`
+    B {
+        target: u64,
+    },
+    /// `ADRP rd, page`
+    Adrp {
+        rd: u8,
+        page: u64,
+    },
+    /// `ADR rd, addr`
+    Adr {
+        rd: u8,
+        addr: u64,
+    },
+    /// `ADD rd, rn, #imm`
+    AddImm {
+        rd: u8,
+        rn: u8,
+        imm: u64,
+    },
+    /// `LDR rt, addr` PC relative literal load
+    LdrLiteral {
+        rt: u8,
+        addr: u64,
+    },
+    /// `LDR/STR rt, [rn, #offset]` with an unsigned offset, accessing `size` bytes
+    LoadStore {
+        rt: u8,
+        rn: u8,
+        offset: u64,
+        size: u8,
+        load: bool,
+    },
+    /// `LDP/STP rt, rt2, [rn, #offset]` of 32 or 64 bit registers with a signed offset,
+    /// accessing `size` bytes per register
+    LoadStorePair {
+        rt: u8,
+        rt2: u8,
+        rn: u8,
+        offset: i64,
+        size: u8,
+        load: bool,
+    },
+    /// `MOVZ/MOVN rd, #imm` or `ORR rd, zr, #imm`, with the immediate already expanded
+    MovImm {
+        rd: u8,
+        value: u64,
+    },
+    /// `MOVK rd, #imm`, keeping the bits of `rd` set in `keep`
+    MovK {
+        rd: u8,
+        imm: u64,
+        keep: u64,
+    },
+    Ret,
+    /// Any other instruction writing the general register `rd`
+    Write {
+        rd: u8,
+    },
+    Other,
+}
+
+pub const INSTRUCTION_SIZE: usize = 4;
+
+fn sign_extend(value: u64, bits: u32) -> i64 {
+    let shift = 64 - bits;
+    ((value << shift) as i64) >> shift
+}
+
+/// `DecodeBitMasks` of logical immediates, `None` for reserved encodings
+fn decode_bit_mask(n: u32, immr: u32, imms: u32, width: u32) -> Option<u64> {
+    let combined = (n << 6) | (!imms & 0x3F);
+    if combined == 0 {
+        return None;
+    }
+
+    let len = 31 - combined.leading_zeros();
+    let esize = 1u32 << len;
+    let levels = esize - 1;
+    let (s, r) = (imms & levels, immr & levels);
+    if len < 1 || s == levels {
+        return None;
+    }
+
+    let emask = match esize {
+        64 => u64::MAX,
+        _ => (1u64 << esize) - 1,
+    };
+    let welem = (1u64 << (s + 1)) - 1;
+    let element = match r {
+        0 => welem,
+        _ => ((welem >> r) | (welem << (esize - r))) & emask,
+    };
+
+    Some(
+        (0..width)
+            .step_by(esize as usize)
+            .fold(0, |value, i| value | (element << i)),
+    )
+}
+
+pub fn decode(insn: u32, pc: u64) -> Instruction {
+    let rd = (insn & 0x1F) as u8;
+    let rn = ((insn >> 5) & 0x1F) as u8;
+
+    // BL imm26
+    if insn & 0xFC00_0000 == 0x9400_0000 {
+        let imm = sign_extend((insn & 0x03FF_FFFF) as u64, 26) << 2;
+        return Instruction::Bl {
+            target: pc.wrapping_add_signed(imm),
+        };
+    }
+    // B imm26
+    if insn & 0xFC00_0000 == 0x1400_0000 {
+        let imm = sign_extend((insn & 0x03FF_FFFF) as u64, 26) << 2;
+        return Instruction::B {
+            target: pc.wrapping_add_signed(imm),
+        };
+    }
+    // ADR/ADRP immlo:immhi
+    if insn & 0x1F00_0000 == 0x1000_0000 {
+        let immlo = ((insn >> 29) & 0x3) as u64;
+        let immhi = ((insn >> 5) & 0x7_FFFF) as u64;
+        let imm = sign_extend((immhi << 2) | immlo, 21);
+
+        return match insn & 0x8000_0000 != 0 {
+            true => Instruction::Adrp {
+                rd,
+                page: (pc & !0xFFF).wrapping_add_signed(imm << 12),
+            },
+            false => Instruction::Adr {
+                rd,
+                addr: pc.wrapping_add_signed(imm),
+            },
+        };
+    }
+    // ADD (immediate), 32 and 64 bit, not setting flags
+    if insn & 0x7F80_0000 == 0x1100_0000 {
+        let imm12 = ((insn >> 10) & 0xFFF) as u64;
+        let shift = if (insn >> 22) & 1 != 0 { 12 } else { 0 };
+        return Instruction::AddImm {
+            rd,
+            rn,
+            imm: imm12 << shift,
+        };
+    }
+    // MOVN/MOVZ/MOVK
+    if insn & 0x1F80_0000 == 0x1280_0000 {
+        let wide = insn >> 31 != 0;
+        let shift = ((insn >> 21) & 0x3) * 16;
+        let imm = (((insn >> 5) & 0xFFFF) as u64) << shift;
+        let width_mask = if wide { u64::MAX } else { 0xFFFF_FFFF };
+
+        if wide || shift < 32 {
+            match (insn >> 29) & 0x3 {
+                0b00 => {
+                    return Instruction::MovImm {
+                        rd,
+                        value: !imm & width_mask,
+                    };
+                }
+                0b10 => return Instruction::MovImm { rd, value: imm },
+                0b11 => {
+                    return Instruction::MovK {
+                        rd,
+                        imm,
+                        keep: !(0xFFFF << shift) & width_mask,
+                    };
+                }
+                _ => {}
+            }
+        }
+    }
+    // ORR (immediate) from the zero register, the MOV alias of bitmask immediates
+    if insn & 0x7F80_0000 == 0x3200_0000 && rn == 31 {
+        let width = if insn >> 31 != 0 { 64 } else { 32 };
+        let n = (insn >> 22) & 1;
+        let immr = (insn >> 16) & 0x3F;
+        let imms = (insn >> 10) & 0x3F;
+
+        if let Some(value) = decode_bit_mask(n, immr, imms, width) {
+            return Instruction::MovImm { rd, value };
+        }
+    }
+    // LDR (literal), including LDRSW, PRFM and SIMD registers
+    if insn & 0x3B00_0000 == 0x1800_0000 {
+        let imm = sign_extend(((insn >> 5) & 0x7_FFFF) as u64, 19) << 2;
+        return Instruction::LdrLiteral {
+            rt: rd,
+            addr: pc.wrapping_add_signed(imm),
+        };
+    }
+    // LDR/STR (immediate, unsigned offset), integer registers
+    if insn & 0x3F00_0000 == 0x3900_0000 {
+        let size = insn >> 30;
+        let opc = (insn >> 22) & 0x3;
+        let imm12 = ((insn >> 10) & 0xFFF) as u64;
+
+        // opc 0b10/0b11 are sign extending loads or prefetches
+        if opc <= 1 {
+            return Instruction::LoadStore {
+                rt: rd,
+                rn,
+                offset: imm12 << size,
+                size: 1 << size,
+                load: opc == 1,
+            };
+        }
+    }
+    // LDP/STP (signed offset), 32 and 64 bit integer registers
+    if insn & 0x7F80_0000 == 0x2900_0000 {
+        let imm7 = ((insn >> 15) & 0x7F) as u64;
+        let size = 4 << (insn >> 31);
+        return Instruction::LoadStorePair {
+            rt: rd,
+            rt2: ((insn >> 10) & 0x1F) as u8,
+            rn,
+            offset: sign_extend(imm7, 7) * size as i64,
+            size,
+            load: (insn >> 22) & 1 != 0,
+        };
+    }
+    if insn & 0xFFFF_FC1F == 0xD63F_0000 {
+        return Instruction::Blr { rn };
+    }
+    if insn == 0xD65F_03C0 {
+        return Instruction::Ret;
+    }
+    // the rest of the data processing groups, e.g. MOV/ORR, MOVZ/MOVK and SUB
+    if insn & 0x1C00_0000 == 0x1000_0000 || insn & 0x0E00_0000 == 0x0A00_0000 {
+        return Instruction::Write { rd };
+    }
+    // the rest of the integer loads, e.g. LDUR, LDRSW and pre/post indexed LDR
+    if insn & 0x3C00_0000 == 0x3800_0000 && (insn >> 22) & 0x3 != 0 {
+        return Instruction::Write { rd };
+    }
+
+    Instruction::Other
+}
+
+/// Addresses held in registers, as built by ADRP/ADR/ADD sequences,
+/// and constants moved into them
+#[derive(Clone, Debug, Default)]
+pub struct RegisterTracker {
+    registers: [Option<u64>; 32],
+}
+
+impl RegisterTracker {
+    /// Value held by `register`, never known for SP/XZR
+    pub fn get(&self, register: u8) -> Option<u64> {
+        self.registers.get(register as usize).copied().flatten()
+    }
+
+    /// Updates the registers written by `instruction`
+    pub fn step(&mut self, instruction: Instruction) {
+        match instruction {
+            Instruction::Adrp { rd, page } => self.set(rd, Some(page)),
+            Instruction::Adr { rd, addr } => self.set(rd, Some(addr)),
+            Instruction::AddImm { rd, rn, imm } => {
+                self.set(rd, self.get(rn).map(|base| base + imm))
+            }
+            Instruction::MovImm { rd, value } => self.set(rd, Some(value)),
+            Instruction::MovK { rd, imm, keep } => {
+                self.set(rd, self.get(rd).map(|value| (value & keep) | imm))
+            }
+            Instruction::LoadStore { rt, load: true, .. } => self.set(rt, None),
+            // literal loads into SIMD registers clear a general one too, which is only conservative
+            Instruction::LdrLiteral { rt: rd, .. } | Instruction::Write { rd } => {
+                self.set(rd, None)
+            }
+            Instruction::LoadStorePair {
+                rt,
+                rt2,
+                load: true,
+                ..
+            } => {
+                self.set(rt, None);
+                self.set(rt2, None);
+            }
+            // caller saved registers are clobbered
+            Instruction::Bl { .. } | Instruction::Blr { .. } | Instruction::Ret => {
+                self.registers = [None; 32]
+            }
+            _ => {}
+        }
+    }
+
+    fn set(&mut self, register: u8, value: Option<u64>) {
+        if register != 31 {
+            self.registers[register as usize] = value;
+        }
+    }
+}
+
+/// Decodes every instruction in `code`, which starts at address `base`
+pub fn disassemble(code: &[u8], base: u64) -> impl Iterator<Item = (u64, u32, Instruction)> + '_ {
+    code.chunks_exact(INSTRUCTION_SIZE)
+        .enumerate()
+        .map(move |(i, bytes)| {
+            let pc = base + (i * INSTRUCTION_SIZE) as u64;
+            let insn = u32::from_le_bytes(bytes.try_into().unwrap());
+            (pc, insn, decode(insn, pc))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PC: u64 = 0x1234;
+
+    #[test]
+    fn decodes_branches() {
+        assert_eq!(decode(0x9400_0002, PC), Instruction::Bl { target: PC + 8 });
+        assert_eq!(decode(0x97FF_FFFF, PC), Instruction::Bl { target: PC - 4 });
+        assert_eq!(decode(0x1400_0001, PC), Instruction::B { target: PC + 4 });
+        assert_eq!(decode(0xD63F_0100, PC), Instruction::Blr { rn: 8 });
+        assert_eq!(decode(0xD65F_03C0, PC), Instruction::Ret);
+        // NOP
+        assert_eq!(decode(0xD503_201F, PC), Instruction::Other);
+    }
+
+    #[test]
+    fn decodes_address_arithmetic() {
+        assert_eq!(
+            decode(0xD000_0000, PC),
+            Instruction::Adrp {
+                rd: 0,
+                page: 0x3000
+            }
+        );
+        assert_eq!(
+            decode(0x1000_0041, PC),
+            Instruction::Adr {
+                rd: 1,
+                addr: PC + 8
+            }
+        );
+        assert_eq!(
+            decode(0x9100_4000, PC),
+            Instruction::AddImm {
+                rd: 0,
+                rn: 0,
+                imm: 0x10
+            }
+        );
+        assert_eq!(
+            decode(0x9140_0441, PC),
+            Instruction::AddImm {
+                rd: 1,
+                rn: 2,
+                imm: 0x1000
+            }
+        );
+        assert_eq!(
+            decode(0x5800_0043, PC),
+            Instruction::LdrLiteral {
+                rt: 3,
+                addr: PC + 8
+            }
+        );
+    }
+
+    #[test]
+    fn decodes_loads_and_stores() {
+        assert_eq!(
+            decode(0xF940_0401, PC),
+            Instruction::LoadStore {
+                rt: 1,
+                rn: 0,
+                offset: 8,
+                size: 8,
+                load: true
+            }
+        );
+        assert_eq!(
+            decode(0xF900_0BE1, PC),
+            Instruction::LoadStore {
+                rt: 1,
+                rn: 31,
+                offset: 16,
+                size: 8,
+                load: false
+            }
+        );
+        // STRB
+        assert_eq!(
+            decode(0x3900_F3FF, PC),
+            Instruction::LoadStore {
+                rt: 31,
+                rn: 31,
+                offset: 0x3C,
+                size: 1,
+                load: false
+            }
+        );
+        // LDRSW
+        assert_eq!(decode(0xB980_0000, PC), Instruction::Write { rd: 0 });
+        // LDUR
+        assert_eq!(decode(0xF85F_83A1, PC), Instruction::Write { rd: 1 });
+
+        assert_eq!(
+            decode(0xA93F_7BFD, PC),
+            Instruction::LoadStorePair {
+                rt: 29,
+                rt2: 30,
+                rn: 31,
+                offset: -16,
+                size: 8,
+                load: false
+            }
+        );
+        assert_eq!(
+            decode(0xA941_7BFD, PC),
+            Instruction::LoadStorePair {
+                rt: 29,
+                rt2: 30,
+                rn: 31,
+                offset: 16,
+                size: 8,
+                load: true
+            }
+        );
+        // 32 bit STP
+        assert_eq!(
+            decode(0x2904_27E8, PC),
+            Instruction::LoadStorePair {
+                rt: 8,
+                rt2: 9,
+                rn: 31,
+                offset: 32,
+                size: 4,
+                load: false
+            }
+        );
+    }
+
+    #[test]
+    fn decodes_constants() {
+        // movz x1, #1
+        assert_eq!(
+            decode(0xD280_0021, PC),
+            Instruction::MovImm { rd: 1, value: 1 }
+        );
+        // movz w2, #0x12, lsl #16
+        assert_eq!(
+            decode(0x52A0_0242, PC),
+            Instruction::MovImm {
+                rd: 2,
+                value: 0x12_0000
+            }
+        );
+        // movn w3, #0
+        assert_eq!(
+            decode(0x1280_0003, PC),
+            Instruction::MovImm {
+                rd: 3,
+                value: 0xFFFF_FFFF
+            }
+        );
+        // movk x8, #3, lsl #32
+        assert_eq!(
+            decode(0xF2C0_0068, PC),
+            Instruction::MovK {
+                rd: 8,
+                imm: 0x3_0000_0000,
+                keep: 0xFFFF_0000_FFFF_FFFF
+            }
+        );
+        // mov x8, #0x100000001
+        assert_eq!(
+            decode(0xB200_03E8, PC),
+            Instruction::MovImm {
+                rd: 8,
+                value: 0x1_0000_0001
+            }
+        );
+        // mov w0, #3
+        assert_eq!(
+            decode(0x3200_07E0, PC),
+            Instruction::MovImm { rd: 0, value: 3 }
+        );
+        // orr x0, x1, #1 isn't a move
+        assert_eq!(decode(0xB240_0020, PC), Instruction::Write { rd: 0 });
+    }
+
+    #[test]
+    fn tracks_addresses() {
+        let mut registers = RegisterTracker::default();
+        registers.step(decode(0xD000_0000, PC));
+        registers.step(decode(0x9100_4000, PC));
+        registers.step(decode(0x1000_0041, PC));
+        assert_eq!(registers.get(0), Some(0x3010));
+        assert_eq!(registers.get(1), Some(PC + 8));
+
+        // ADRP into SP
+        registers.step(Instruction::Adrp {
+            rd: 31,
+            page: 0x3000,
+        });
+        assert_eq!(registers.get(31), None);
+
+        registers.step(decode(0xF940_0401, PC));
+        assert_eq!(registers.get(1), None);
+        assert_eq!(registers.get(0), Some(0x3010));
+
+        // stores leave the registers alone
+        registers.step(decode(0xF900_0BE0, PC));
+        assert_eq!(registers.get(0), Some(0x3010));
+
+        registers.step(Instruction::Adrp { rd: 29, page: 0 });
+        registers.step(Instruction::Adrp { rd: 30, page: 0 });
+        registers.step(decode(0xA941_7BFD, PC));
+        assert_eq!(registers.get(29), None);
+        assert_eq!(registers.get(30), None);
+
+        registers.step(decode(0xD63F_0100, PC));
+        assert_eq!(registers.get(0), None);
+    }
+
+    #[test]
+    fn overwritten_registers_are_unknown() {
+        let mut registers = RegisterTracker::default();
+        for rd in 0..4 {
+            registers.step(Instruction::Adrp { rd, page: 0x3000 });
+        }
+
+        // mov x0, x1
+        registers.step(decode(0xAA01_03E0, PC));
+        assert_eq!(registers.get(0), None);
+        assert_eq!(registers.get(1), Some(0x3000));
+        // sub x1, x1, #1
+        registers.step(decode(0xD100_0421, PC));
+        assert_eq!(registers.get(1), None);
+        // ldrsw x2, [x2]
+        registers.step(decode(0xB980_0042, PC));
+        assert_eq!(registers.get(2), None);
+        // ldr x3, PC + 8
+        registers.step(decode(0x5800_0043, PC));
+        assert_eq!(registers.get(3), None);
+    }
+
+    #[test]
+    fn tracks_constants() {
+        let mut registers = RegisterTracker::default();
+        // movz x8, #1; movk x8, #3, lsl #32
+        registers.step(decode(0xD280_0028, PC));
+        registers.step(decode(0xF2C0_0068, PC));
+        assert_eq!(registers.get(8), Some(0x3_0000_0001));
+
+        // movk of an unknown register
+        registers.step(decode(0xF2C0_0069, PC));
+        assert_eq!(registers.get(9), None);
+    }
+
+    #[test]
+    fn disassembles_whole_instructions() {
+        let code = [0x9400_0002u32, 0xD65F_03C0]
+            .iter()
+            .flat_map(|insn| insn.to_le_bytes())
+            // trailing bytes that don't make an instruction
+            .chain([0xC0, 0x03])
+            .collect::<Vec<_>>();
+
+        let instructions = disassemble(&code, 0x1000).collect::<Vec<_>>();
+        assert_eq!(
+            instructions,
+            [
+                (0x1000, 0x9400_0002, Instruction::Bl { target: 0x1008 }),
+                (0x1004, 0xD65F_03C0, Instruction::Ret),
+            ]
+        );
+    }
+}
@@ -1,2 +1,3 @@
+pub mod arm64;
 pub mod cursor;
 pub mod sorting;
@@ -11,10 +11,13 @@ extern crate brocolib_il2cpp_v31 as brocolib;
 #[cfg(feature = "il2cpp_v29")]
 extern crate brocolib_il2cpp_v29 as brocolib;
 
-use brocolib::{global_metadata::TypeDefinitionIndex, runtime_metadata::TypeData};
+use brocolib::{
+    global_metadata::{MethodIndex, TypeDefinitionIndex},
+    runtime_metadata::TypeData,
+};
 use byteorder::LittleEndian;
 use color_eyre::eyre::Context;
-use generate::metadata::CordlMetadata;
+use generate::{metadata::CordlMetadata, type_extensions::MethodDefintionExtensions};
 use itertools::Itertools;
 extern crate pretty_env_logger;
 
@@ -22,6 +25,7 @@ use include_dir::{Dir, include_dir};
 use log::{info, trace, warn};
 
 use std::{
+    collections::HashSet,
     fs,
     path::{Path, PathBuf},
     time,
@@ -30,6 +34,7 @@ use std::{
 use clap::{Parser, Subcommand};
 
 use crate::generate::{cs_context_collection::TypeContextCollection, cs_type_tag::CsTypeTag};
+mod analysis;
 mod data;
 mod generate;
 // mod handlers;
@@ -45,6 +50,30 @@ enum TargetLang {
     MultiJSON,
     #[cfg(feature = "rust")]
     Rust,
+    /// IDA and Ghidra scripts naming and typing every method
+    #[cfg(feature = "disasm")]
+    Disasm,
+    /// Il2CppDumper style il2cpp.h with every object, class and static field layout
+    #[cfg(feature = "disasm")]
+    CHeader,
+    /// libil2cpp.so.debug with DWARF for every method and object layout
+    #[cfg(feature = "disasm")]
+    Dwarf,
+    /// Il2CppDumper compatible dump.cs and script.json
+    #[cfg(feature = "dumper")]
+    Dumper,
+    /// Reference assemblies of every image, like Il2CppDumper's DummyDll
+    #[cfg(feature = "dummy_dll")]
+    DummyDll,
+    /// Frida TypeScript bindings with method addresses and field accessors
+    #[cfg(feature = "frida")]
+    Frida,
+    /// SQLite database of every type and member, for querying with SQL
+    #[cfg(feature = "sqlite")]
+    Sqlite,
+    /// Static HTML API reference with a search index
+    #[cfg(feature = "html")]
+    Html,
 }
 
 #[derive(Parser)]
@@ -52,11 +81,11 @@ enum TargetLang {
 struct Cli {
     /// The global-metadata.dat file to use
     #[clap(short, long, value_parser, value_name = "FILE")]
-    metadata: PathBuf,
+    metadata: Option<PathBuf>,
 
     /// The libil2cpp.so file to use
     #[clap(short, long, value_parser, value_name = "FILE")]
-    libil2cpp: PathBuf,
+    libil2cpp: Option<PathBuf>,
 
     /// Whether to format
     #[clap(short, long)]
@@ -65,19 +94,181 @@ struct Cli {
     #[clap(short, long)]
     remove_verbose_comments: bool,
 
+    /// Not needed when running a subcommand
     #[clap(value_parser)]
-    target: TargetLang,
+    target: Option<TargetLang>,
 
     /// Whether to generate generic method specializations
     #[clap(short, long)]
     gen_generic_methods_specializations: bool,
 
+    /// Disassemble method bodies and write the call graph and metadata usages to cordl_xrefs.json
+    #[cfg(feature = "json")]
+    #[clap(long)]
+    xrefs: bool,
+
+    /// Add every string literal to cordl.json, with their usage slots when --xrefs is given
+    #[cfg(feature = "json")]
+    #[clap(long)]
+    string_literals: bool,
+
+    /// Give P/Invoke methods a C++ `_native` overload that calls the function found with dlsym,
+    /// skipping the managed wrapper
+    #[cfg(feature = "cpp")]
+    #[clap(long)]
+    pinvoke_bindings: bool,
+
+    /// The libunity.so file to find the native functions of internal calls in
+    #[clap(long, value_parser, value_name = "FILE")]
+    libunity: Option<PathBuf>,
+
     #[clap(subcommand)]
     command: Option<Commands>,
 }
 
 #[derive(Subcommand)]
-enum Commands {}
+enum Commands {
+    /// Write a unique byte pattern for every method, with relocated bytes wildcarded
+    Signatures {
+        #[clap(long, value_enum, default_value = "cpp")]
+        output: SignatureFormat,
+    },
+
+    /// Write the inheritance, interface and usage graph of types to cordl_graph.dot and cordl_graph.graphml
+    Graph {
+        /// Only types in these namespaces and their sub namespaces
+        #[clap(long)]
+        namespace: Vec<String>,
+
+        /// Only these types, by full name, and every type deriving from or implementing them
+        #[clap(long)]
+        root: Vec<String>,
+
+        /// Kinds of edges to include
+        #[clap(
+            long,
+            value_enum,
+            value_delimiter = ',',
+            default_value = "inherits,implements,field,uses"
+        )]
+        edges: Vec<analysis::graph::EdgeKind>,
+
+        /// Only keep types that are part of a cycle, e.g. to find include cycles
+        #[clap(long)]
+        cycles: bool,
+    },
+
+    /// Print the method at libil2cpp.so addresses,
+    /// or append it to the frames of a crash log read from stdin
+    Symbolize {
+        /// Addresses relative to the start of libil2cpp.so, in hex
+        addresses: Vec<String>,
+    },
+
+    /// Print counts, header section sizes, the largest methods
+    /// and code size per namespace and assembly
+    Stats {
+        /// Length of every list
+        #[clap(long, default_value = "20")]
+        top: usize,
+
+        /// Print the stats as JSON
+        #[cfg(feature = "json")]
+        #[clap(long)]
+        json: bool,
+    },
+
+    /// Print the registration name of every internal call,
+    /// with its native function when --libunity is given
+    Icalls,
+
+    /// Write every string literal with its usage slots and users to cordl_strings.csv or .json
+    Strings {
+        #[clap(long, value_enum, default_value = "csv")]
+        output: StringsFormat,
+    },
+
+    /// Find types, fields, properties and methods by name and print their signatures
+    #[cfg(feature = "json")]
+    Search {
+        /// Regex matched against the whole name or full name,
+        /// e.g. `Player.*` or `System\.String::Concat`
+        pattern: String,
+
+        /// Treat patterns as globs, where `*` is any text and `?` any character
+        #[clap(long)]
+        glob: bool,
+
+        #[clap(short, long)]
+        ignore_case: bool,
+
+        /// Kinds of results, every kind by default
+        #[clap(long, value_enum, value_delimiter = ',')]
+        kind: Vec<analysis::search::SearchKind>,
+
+        /// Return type of methods, or type of fields and properties
+        #[clap(long)]
+        returns: Option<String>,
+
+        /// Type of any parameter of methods
+        #[clap(long)]
+        param_type: Option<String>,
+
+        /// Only static members
+        #[clap(long = "static")]
+        only_static: bool,
+
+        /// Only types in these namespaces and their sub namespaces, and their members
+        #[clap(long)]
+        namespace: Vec<String>,
+
+        /// Only types with an instance size of at least this many bytes, and their members
+        #[clap(long)]
+        min_size: Option<u32>,
+
+        /// Print the results as JSON
+        #[clap(long)]
+        json: bool,
+    },
+
+    /// Pair methods of two builds and write an old to new address map to cordl_match.json
+    #[cfg(feature = "json")]
+    Match {
+        /// The global-metadata.dat and libil2cpp.so of the old build
+        #[clap(long, num_args = 2, value_names = ["METADATA", "LIBIL2CPP"], required = true)]
+        old: Vec<PathBuf>,
+
+        /// The global-metadata.dat and libil2cpp.so of the new build
+        #[clap(long, num_args = 2, value_names = ["METADATA", "LIBIL2CPP"], required = true)]
+        new: Vec<PathBuf>,
+    },
+
+    /// Compare the types of two builds and write cordl_diff.md and cordl_diff.json
+    #[cfg(feature = "json")]
+    Diff {
+        /// The global-metadata.dat and libil2cpp.so of the old build
+        #[clap(long, num_args = 2, value_names = ["METADATA", "LIBIL2CPP"], required = true)]
+        old: Vec<PathBuf>,
+
+        /// The global-metadata.dat and libil2cpp.so of the new build
+        #[clap(long, num_args = 2, value_names = ["METADATA", "LIBIL2CPP"], required = true)]
+        new: Vec<PathBuf>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+enum SignatureFormat {
+    Cpp,
+    #[cfg(feature = "json")]
+    Json,
+}
+
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+enum StringsFormat {
+    Csv,
+    #[cfg(feature = "json")]
+    Json,
+}
 
 static INTERNALS_DIR: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/cordl_internals");
 
@@ -98,61 +289,341 @@ fn main() -> color_eyre::Result<()> {
         "Running on {}",
         Path::new("./").canonicalize().unwrap().display()
     );
-    let global_metadata_data = fs::read(&cli.metadata)
-        .with_context(|| format!("il2cpp metadata not found {}", cli.metadata.display()))?;
-    let elf_data = fs::read(&cli.libil2cpp).with_context(|| {
-        format!(
-            "libil2cpp.so shared object not found {}",
-            cli.metadata.display()
-        )
-    })?;
+    // comparing builds loads its own inputs
+    #[cfg(feature = "json")]
+    match &cli.command {
+        Some(Commands::Match { old, new }) => return run_match(old, new, &cli),
+        Some(Commands::Diff { old, new }) => return run_diff(old, new, &cli),
+        _ => {}
+    }
+
+    let (Some(metadata_path), Some(libil2cpp_path)) = (&cli.metadata, &cli.libil2cpp) else {
+        color_eyre::eyre::bail!("--metadata and --libil2cpp are required");
+    };
+    let (global_metadata_data, elf_data) = read_inputs(metadata_path, libil2cpp_path)?;
     let il2cpp_metadata = brocolib::Metadata::parse(&global_metadata_data, &elf_data)?;
+    let mut metadata = make_cordl_metadata(&il2cpp_metadata, &global_metadata_data, &elf_data);
+    if let Some(libunity) = &cli.libunity {
+        resolve_icalls(&mut metadata, libunity)?;
+    }
 
-    let get_tdi = |full_name: &str| {
-        let tdi = il2cpp_metadata
-            .global_metadata
-            .type_definitions
-            .as_vec()
-            .iter()
-            .position(|t| t.full_name(&il2cpp_metadata, false) == full_name)
-            .unwrap_or_else(|| panic!("Unable to find TDI for {full_name}"));
+    #[cfg(feature = "json")]
+    let xrefs_analysis = match cli.xrefs {
+        true => {
+            use analysis::xrefs;
 
-        TypeDefinitionIndex::new(tdi as u32)
+            let t = time::Instant::now();
+            info!("Analyzing cross references");
+            let analysis = xrefs::XrefAnalysis::analyze(&metadata)?;
+            info!("Finished in {}ms", t.elapsed().as_millis());
+
+            let xrefs_file = Path::new("./cordl_xrefs.json");
+            println!("Writing xrefs file {xrefs_file:?}");
+            xrefs::json::make_json(&analysis, &metadata, xrefs_file, cli.format)?;
+            Some(analysis)
+        }
+        false => None,
     };
 
-    let unity_object_tdi_idx = get_tdi("UnityEngine.Object");
-    let object_tdi_idx = get_tdi("System.Object");
-    let str_tdi_idx = get_tdi("System.String");
+    // subcommands only need the parsed metadata
+    if let Some(command) = cli.command {
+        match command {
+            Commands::Signatures { output } => {
+                use analysis::signatures;
 
-    let mut metadata = CordlMetadata {
-        metadata: &il2cpp_metadata,
-        code_registration: &il2cpp_metadata.runtime_metadata.code_registration,
-        metadata_registration: &il2cpp_metadata.runtime_metadata.metadata_registration,
-        method_calculations: Default::default(),
-        parent_to_child_map: Default::default(),
-        child_to_parent_map: Default::default(),
+                let t = time::Instant::now();
+                info!("Making method signatures");
+                let signatures = signatures::make_signatures(&metadata)?;
+                info!("Finished in {}ms", t.elapsed().as_millis());
 
-        unity_object_tdi: unity_object_tdi_idx,
-        object_tdi: object_tdi_idx,
-        string_tdi: str_tdi_idx,
+                match output {
+                    SignatureFormat::Cpp => {
+                        let file = Path::new("./cordl_signatures.hpp");
+                        println!("Writing signatures file {file:?}");
+                        signatures::write_cpp(&signatures, file)?;
+                    }
+                    #[cfg(feature = "json")]
+                    SignatureFormat::Json => {
+                        let file = Path::new("./cordl_signatures.json");
+                        println!("Writing signatures file {file:?}");
+                        signatures::write_json(&signatures, file, cli.format)?;
+                    }
+                }
+            }
+            Commands::Graph {
+                namespace,
+                root,
+                edges,
+                cycles,
+            } => {
+                use analysis::graph;
 
-        name_to_tdi: Default::default(),
-        blacklisted_types: Default::default(),
-        pointer_size: generate::metadata::PointerSize::Bytes8,
-        // For most il2cpp versions
-        packing_field_offset: 7,
-        size_is_default_offset: 12,
-        specified_packing_field_offset: 13,
-        packing_is_default_offset: 11,
+                let collection = make_type_context_collection(
+                    &mut metadata,
+                    cli.gen_generic_methods_specializations,
+                );
+
+                let mut graph = graph::make_graph(&collection, &edges);
+                graph.filter(&namespace, &root);
+                if cycles {
+                    graph.retain_cycles();
+                }
+                info!(
+                    "Graph has {} types and {} edges",
+                    graph.nodes.len(),
+                    graph.edges.len()
+                );
+
+                let dot = Path::new("./cordl_graph.dot");
+                let graphml = Path::new("./cordl_graph.graphml");
+                println!("Writing graph files {dot:?} {graphml:?}");
+                graph::write_dot(&graph, dot)?;
+                graph::write_graphml(&graph, graphml)?;
+            }
+            Commands::Symbolize { addresses } => {
+                use analysis::symbolize::Symbolizer;
+
+                let symbolizer = Symbolizer::new(&metadata);
+                match addresses.is_empty() {
+                    true => {
+                        for line in std::io::stdin().lines() {
+                            println!("{}", symbolizer.symbolize_line(&line?));
+                        }
+                    }
+                    false => {
+                        for address in &addresses {
+                            println!("{}", symbolizer.symbolize_line(address));
+                        }
+                    }
+                }
+            }
+            Commands::Stats {
+                top,
+                #[cfg(feature = "json")]
+                json,
+            } => {
+                use analysis::stats;
+
+                blacklist_types(&mut metadata);
+                let stats = stats::make_stats(&metadata, &global_metadata_data, top)?;
+
+                #[cfg(feature = "json")]
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&stats)?);
+                    return Ok(());
+                }
+                print!("{}", stats::to_text(&stats)?);
+            }
+            Commands::Icalls => {
+                for name in internal_call_names(&metadata).sorted() {
+                    let address = metadata
+                        .icall_address(&name)
+                        .map(|a| format!("0x{a:X}"))
+                        .unwrap_or_default();
+                    println!("{address:>12}  {name}");
+                }
+            }
+            Commands::Strings { output } => {
+                use analysis::{string_literals, xrefs::XrefAnalysis};
+
+                // slots are only found by disassembling, the literals are still useful without
+                let xrefs = XrefAnalysis::analyze(&metadata)
+                    .inspect_err(|e| warn!("Unable to find string literal usages: {e:?}"))
+                    .ok();
+                let literals = string_literals::collect_string_literals(&metadata, xrefs.as_ref());
+                info!("Found {} string literals", literals.len());
+
+                match output {
+                    StringsFormat::Csv => {
+                        let file = Path::new("./cordl_strings.csv");
+                        println!("Writing string literals file {file:?}");
+                        string_literals::write_csv(&literals, &metadata, file)?;
+                    }
+                    #[cfg(feature = "json")]
+                    StringsFormat::Json => {
+                        let file = Path::new("./cordl_strings.json");
+                        println!("Writing string literals file {file:?}");
+                        string_literals::write_json(&literals, file, cli.format)?;
+                    }
+                }
+            }
+            #[cfg(feature = "json")]
+            Commands::Search {
+                pattern,
+                glob,
+                ignore_case,
+                kind,
+                returns,
+                param_type,
+                only_static,
+                namespace,
+                min_size,
+                json,
+            } => {
+                use analysis::search;
+
+                let make_pattern = |p: &str| search::Pattern::new(p, glob, ignore_case);
+                let query = search::SearchQuery {
+                    pattern: make_pattern(&pattern)?,
+                    kinds: kind,
+                    returns: returns.as_deref().map(make_pattern).transpose()?,
+                    param_type: param_type.as_deref().map(make_pattern).transpose()?,
+                    only_static,
+                    namespaces: namespace,
+                    min_size,
+                };
+
+                let collection = make_type_context_collection(
+                    &mut metadata,
+                    cli.gen_generic_methods_specializations,
+                );
+                let index = search::SearchIndex::new(&metadata, &collection);
+                let results = index.search(&query);
+                info!("Found {} results", results.len());
+
+                match json {
+                    true => println!("{}", serde_json::to_string_pretty(&results)?),
+                    false => print!("{}", search::to_text(&results)),
+                }
+            }
+            #[cfg(feature = "json")]
+            Commands::Match { .. } | Commands::Diff { .. } => {
+                unreachable!("Handled before loading metadata")
+            }
+        }
+
+        return Ok(());
+    }
+
+    let Some(target) = cli.target else {
+        color_eyre::eyre::bail!("No target language or subcommand given");
     };
-    let t = time::Instant::now();
-    info!("Parsing metadata methods");
-    metadata.parse();
-    info!("Finished in {}ms", t.elapsed().as_millis());
 
-    let mut cs_context_collection = TypeContextCollection::new();
+    let cs_context_collection =
+        make_type_context_collection(&mut metadata, cli.gen_generic_methods_specializations);
 
-    // blacklist types
+    if cli.remove_verbose_comments {
+        // TODO: uncomment
+        // remove_coments(&mut cpp_context_collection)?;
+    }
+
+    match target {
+        #[cfg(feature = "cpp")]
+        TargetLang::Cpp => {
+            use generate::cpp;
+
+            cpp::cpp_main::run_cpp(
+                cs_context_collection,
+                &metadata,
+                cli.format,
+                cli.pinvoke_bindings,
+            )?;
+            Ok(())
+        }
+        #[cfg(feature = "json")]
+        TargetLang::SingleJSON => {
+            use generate::json;
+
+            let json = Path::new("./cordl.json");
+            println!("Writing json file {json:?}");
+            let string_literals = match cli.string_literals {
+                true => analysis::string_literals::collect_string_literals(
+                    &metadata,
+                    xrefs_analysis.as_ref(),
+                ),
+                false => vec![],
+            };
+            json::make_json(
+                &metadata,
+                &cs_context_collection,
+                json,
+                cli.format,
+                string_literals,
+            )?;
+            Ok(())
+        }
+        #[cfg(feature = "json")]
+        TargetLang::MultiJSON => {
+            use generate::json;
+
+            let json_folder = Path::new("./multi_json");
+
+            println!("Writing json file {json_folder:?}");
+            json::make_json_folder(&metadata, &cs_context_collection, json_folder)?;
+            Ok(())
+        }
+
+        #[cfg(feature = "rust")]
+        TargetLang::Rust => {
+            use generate::rust;
+            rust::rust_main::run_rust(cs_context_collection, &metadata)?;
+
+            Ok(())
+        }
+        #[cfg(feature = "disasm")]
+        TargetLang::Disasm => {
+            use generate::disasm;
+
+            disasm::disasm_main::run_disasm(&cs_context_collection, &metadata)?;
+            Ok(())
+        }
+        #[cfg(feature = "disasm")]
+        TargetLang::CHeader => {
+            use generate::disasm;
+
+            disasm::disasm_header::run_header(&cs_context_collection, &metadata)?;
+            Ok(())
+        }
+        #[cfg(feature = "disasm")]
+        TargetLang::Dwarf => {
+            use generate::disasm;
+
+            disasm::disasm_dwarf::run_dwarf(&cs_context_collection, &metadata)?;
+            Ok(())
+        }
+        #[cfg(feature = "dumper")]
+        TargetLang::Dumper => {
+            use generate::dumper;
+
+            dumper::dumper_main::run_dumper(&cs_context_collection, &metadata, cli.format)?;
+            Ok(())
+        }
+        #[cfg(feature = "dummy_dll")]
+        TargetLang::DummyDll => {
+            use generate::dummy_dll;
+
+            dummy_dll::dummy_dll_main::run_dummy_dll(&cs_context_collection, &metadata)?;
+            Ok(())
+        }
+        #[cfg(feature = "frida")]
+        TargetLang::Frida => {
+            use generate::frida;
+
+            frida::frida_main::run_frida(&cs_context_collection, &metadata)?;
+            Ok(())
+        }
+        #[cfg(feature = "sqlite")]
+        TargetLang::Sqlite => {
+            use generate::sqlite;
+
+            sqlite::sqlite_main::run_sqlite(&cs_context_collection, &metadata)?;
+            Ok(())
+        }
+        #[cfg(feature = "html")]
+        TargetLang::Html => {
+            use generate::html;
+
+            html::html_main::run_html(&cs_context_collection, &metadata)?;
+            Ok(())
+        }
+        _ => color_eyre::Result::<()>::Ok(()),
+    }?;
+
+    Ok(())
+}
+
+/// Types cordl can't generate correctly, skipped by every target
+fn blacklist_types(metadata: &mut CordlMetadata) {
     {
         let mut blacklist_type = |full_name: &str| {
             let tdi = metadata
@@ -259,6 +730,16 @@ fn main() -> color_eyre::Result<()> {
         };
         // blacklist_types("<>c__DisplayClass");
     }
+}
+
+/// Makes and fills every type, the shared input of every target
+fn make_type_context_collection(
+    metadata: &mut CordlMetadata,
+    gen_generic_methods_specializations: bool,
+) -> TypeContextCollection {
+    let mut cs_context_collection = TypeContextCollection::new();
+
+    blacklist_types(metadata);
     {
         // First, make all the contexts
         info!("Making types");
@@ -279,11 +760,11 @@ fn main() -> color_eyre::Result<()> {
                 "Making types {:.4}% ({tdi_u64}/{total})",
                 (tdi_u64 as f64 / total as f64 * 100.0)
             );
-            cs_context_collection.make_from(&metadata, TypeData::TypeDefinitionIndex(tdi), None);
+            cs_context_collection.make_from(metadata, TypeData::TypeDefinitionIndex(tdi), None);
             cs_context_collection.alias_nested_types_il2cpp(
                 tdi,
                 CsTypeTag::TypeDefinitionIndex(tdi),
-                &metadata,
+                metadata,
             );
         }
     }
@@ -305,11 +786,11 @@ fn main() -> color_eyre::Result<()> {
                 "Making nested types {:.4}% ({tdi_u64}/{total})",
                 (tdi_u64 as f64 / total as f64 * 100.0)
             );
-            cs_context_collection.make_nested_from(&metadata, tdi);
+            cs_context_collection.make_nested_from(metadata, tdi);
         }
     }
 
-    if cli.gen_generic_methods_specializations {
+    if gen_generic_methods_specializations {
         {
             let total = metadata.metadata_registration.generic_method_table.len() as f64;
             info!("Making generic type instantiations");
@@ -329,7 +810,7 @@ fn main() -> color_eyre::Result<()> {
                     .get(generic_class.generic_method_index as usize)
                     .unwrap();
 
-                cs_context_collection.make_generic_from(method_spec, &mut metadata);
+                cs_context_collection.make_generic_from(method_spec, metadata);
             }
         }
         {
@@ -351,7 +832,7 @@ fn main() -> color_eyre::Result<()> {
                     .get(generic_class.generic_method_index as usize)
                     .unwrap();
 
-                cs_context_collection.fill_generic_class_inst(method_spec, &metadata);
+                cs_context_collection.fill_generic_class_inst(method_spec, metadata);
             }
         }
 
@@ -373,7 +854,7 @@ fn main() -> color_eyre::Result<()> {
                 .get(generic_class.generic_method_index as usize)
                 .unwrap();
 
-            cs_context_collection.fill_generic_method_inst(method_spec, &metadata);
+            cs_context_collection.fill_generic_method_inst(method_spec, metadata);
         }
     }
 
@@ -399,52 +880,216 @@ fn main() -> color_eyre::Result<()> {
                 (tdi_u64 as f64 / total as f64 * 100.0)
             );
 
-            cs_context_collection.fill(CsTypeTag::TypeDefinitionIndex(tdi), &metadata);
+            cs_context_collection.fill(CsTypeTag::TypeDefinitionIndex(tdi), metadata);
         }
     }
 
-    if cli.remove_verbose_comments {
-        // TODO: uncomment
-        // remove_coments(&mut cpp_context_collection)?;
-    }
+    cs_context_collection
+}
 
-    match cli.target {
-        #[cfg(feature = "cpp")]
-        TargetLang::Cpp => {
-            use generate::cpp;
+/// Registration name of every internal call
+fn internal_call_names(metadata: &CordlMetadata) -> impl Iterator<Item = String> {
+    metadata
+        .metadata
+        .global_metadata
+        .methods
+        .as_vec()
+        .iter()
+        .enumerate()
+        .filter(|(_, method)| method.is_internal_call())
+        .map(|(i, _)| metadata.icall_name(MethodIndex::new(i as u32)))
+}
 
-            cpp::cpp_main::run_cpp(cs_context_collection, &metadata, cli.format)?;
-            Ok(())
-        }
-        #[cfg(feature = "json")]
-        TargetLang::SingleJSON => {
-            use generate::json;
+/// Finds the native function of every internal call in libunity.so
+fn resolve_icalls(metadata: &mut CordlMetadata, libunity: &Path) -> color_eyre::Result<()> {
+    let libunity_data = fs::read(libunity)
+        .with_context(|| format!("libunity.so not found {}", libunity.display()))?;
 
-            let json = Path::new("./cordl.json");
-            println!("Writing json file {json:?}");
-            json::make_json(&metadata, &cs_context_collection, json, cli.format)?;
-            Ok(())
-        }
-        #[cfg(feature = "json")]
-        TargetLang::MultiJSON => {
-            use generate::json;
+    // registered with or without the signature
+    let names: HashSet<String> = internal_call_names(metadata)
+        .flat_map(|name| {
+            let without_signature = name.split_once('(').map(|(n, _)| n.to_string());
+            [Some(name), without_signature]
+        })
+        .flatten()
+        .collect();
 
-            let json_folder = Path::new("./multi_json");
+    let t = time::Instant::now();
+    info!("Finding {} internal calls in libunity.so", names.len());
+    metadata.icall_addresses = data::icalls::find_icalls(&libunity_data, &names)?;
+    info!(
+        "Found {} in {}ms",
+        metadata.icall_addresses.len(),
+        t.elapsed().as_millis()
+    );
 
-            println!("Writing json file {json_folder:?}");
-            json::make_json_folder(&metadata, &cs_context_collection, json_folder)?;
-            Ok(())
-        }
+    Ok(())
+}
 
-        #[cfg(feature = "rust")]
-        TargetLang::Rust => {
-            use generate::rust;
-            rust::rust_main::run_rust(cs_context_collection, &metadata)?;
+/// Reads global-metadata.dat and libil2cpp.so
+fn read_inputs(metadata: &Path, libil2cpp: &Path) -> color_eyre::Result<(Vec<u8>, Vec<u8>)> {
+    let global_metadata_data = fs::read(metadata)
+        .with_context(|| format!("il2cpp metadata not found {}", metadata.display()))?;
+    let elf_data = fs::read(libil2cpp).with_context(|| {
+        format!(
+            "libil2cpp.so shared object not found {}",
+            libil2cpp.display()
+        )
+    })?;
 
-            Ok(())
-        }
-        _ => color_eyre::Result::<()>::Ok(()),
-    }?;
+    Ok((global_metadata_data, elf_data))
+}
+
+fn make_cordl_metadata<'a>(
+    il2cpp_metadata: &'a brocolib::Metadata<'a, 'a>,
+    global_metadata_data: &[u8],
+    elf_data: &'a [u8],
+) -> CordlMetadata<'a> {
+    let get_tdi = |full_name: &str| {
+        let tdi = il2cpp_metadata
+            .global_metadata
+            .type_definitions
+            .as_vec()
+            .iter()
+            .position(|t| t.full_name(il2cpp_metadata, false) == full_name)
+            .unwrap_or_else(|| panic!("Unable to find TDI for {full_name}"));
+
+        TypeDefinitionIndex::new(tdi as u32)
+    };
+
+    let unity_object_tdi_idx = get_tdi("UnityEngine.Object");
+    let object_tdi_idx = get_tdi("System.Object");
+    let str_tdi_idx = get_tdi("System.String");
+
+    // only the game's own attributes are lost, keep going without them
+    let custom_attributes =
+        data::custom_attributes::CustomAttributes::parse(global_metadata_data, il2cpp_metadata)
+            .inspect_err(|e| warn!("Unable to read custom attributes: {e:?}"))
+            .unwrap_or_default();
+    let compiler_generated =
+        data::compiler_generated::find_generated_members(il2cpp_metadata, &custom_attributes);
+
+    let mut metadata = CordlMetadata {
+        metadata: il2cpp_metadata,
+        code_registration: &il2cpp_metadata.runtime_metadata.code_registration,
+        metadata_registration: &il2cpp_metadata.runtime_metadata.metadata_registration,
+        elf_data,
+        method_calculations: Default::default(),
+        function_starts: Default::default(),
+        icall_addresses: Default::default(),
+        pinvoke_imports: Default::default(),
+        custom_attributes,
+        compiler_generated,
+        parent_to_child_map: Default::default(),
+        child_to_parent_map: Default::default(),
+
+        unity_object_tdi: unity_object_tdi_idx,
+        object_tdi: object_tdi_idx,
+        string_tdi: str_tdi_idx,
+
+        name_to_tdi: Default::default(),
+        blacklisted_types: Default::default(),
+        pointer_size: generate::metadata::PointerSize::Bytes8,
+        // For most il2cpp versions
+        packing_field_offset: 7,
+        size_is_default_offset: 12,
+        specified_packing_field_offset: 13,
+        packing_is_default_offset: 11,
+    };
+    let t = time::Instant::now();
+    info!("Parsing metadata methods");
+    metadata.parse();
+    info!("Finished in {}ms", t.elapsed().as_millis());
+
+    // only the library and entry point of P/Invoke methods are lost, keep going without them
+    metadata.pinvoke_imports = data::pinvoke::find_pinvoke_imports(&metadata)
+        .inspect_err(|e| warn!("Unable to read P/Invoke wrappers: {e:?}"))
+        .unwrap_or_default();
+
+    metadata
+}
+
+#[cfg(feature = "json")]
+fn run_match(old: &[PathBuf], new: &[PathBuf], cli: &Cli) -> color_eyre::Result<()> {
+    use analysis::{matching, xrefs::XrefAnalysis};
+
+    let (old_global_metadata_data, old_elf_data) = read_inputs(&old[0], &old[1])?;
+    let old_il2cpp_metadata = brocolib::Metadata::parse(&old_global_metadata_data, &old_elf_data)?;
+    let old_metadata = make_cordl_metadata(
+        &old_il2cpp_metadata,
+        &old_global_metadata_data,
+        &old_elf_data,
+    );
+    let old_xrefs = XrefAnalysis::analyze(&old_metadata)?;
+
+    let (new_global_metadata_data, new_elf_data) = read_inputs(&new[0], &new[1])?;
+    let new_il2cpp_metadata = brocolib::Metadata::parse(&new_global_metadata_data, &new_elf_data)?;
+    let new_metadata = make_cordl_metadata(
+        &new_il2cpp_metadata,
+        &new_global_metadata_data,
+        &new_elf_data,
+    );
+    let new_xrefs = XrefAnalysis::analyze(&new_metadata)?;
+
+    let old_build = matching::Build {
+        metadata: &old_metadata,
+        xrefs: &old_xrefs,
+    };
+    let new_build = matching::Build {
+        metadata: &new_metadata,
+        xrefs: &new_xrefs,
+    };
+
+    let t = time::Instant::now();
+    info!("Matching builds");
+    let matches = matching::match_builds(&old_build, &new_build);
+    info!("Finished in {}ms", t.elapsed().as_millis());
+
+    let file = Path::new("./cordl_match.json");
+    println!("Writing match file {file:?}");
+    matching::json::make_json(&matches, &old_build, &new_build, file, cli.format)?;
+
+    Ok(())
+}
+
+#[cfg(feature = "json")]
+fn run_diff(old: &[PathBuf], new: &[PathBuf], cli: &Cli) -> color_eyre::Result<()> {
+    use analysis::api_diff;
+
+    let (old_global_metadata_data, old_elf_data) = read_inputs(&old[0], &old[1])?;
+    let old_il2cpp_metadata = brocolib::Metadata::parse(&old_global_metadata_data, &old_elf_data)?;
+    let mut old_metadata = make_cordl_metadata(
+        &old_il2cpp_metadata,
+        &old_global_metadata_data,
+        &old_elf_data,
+    );
+    let old_collection =
+        make_type_context_collection(&mut old_metadata, cli.gen_generic_methods_specializations);
+    let old_types = api_diff::collect_types(&old_metadata, &old_collection);
+
+    let (new_global_metadata_data, new_elf_data) = read_inputs(&new[0], &new[1])?;
+    let new_il2cpp_metadata = brocolib::Metadata::parse(&new_global_metadata_data, &new_elf_data)?;
+    let mut new_metadata = make_cordl_metadata(
+        &new_il2cpp_metadata,
+        &new_global_metadata_data,
+        &new_elf_data,
+    );
+    let new_collection =
+        make_type_context_collection(&mut new_metadata, cli.gen_generic_methods_specializations);
+    let new_types = api_diff::collect_types(&new_metadata, &new_collection);
+
+    let diff = api_diff::diff(&old_types, &new_types);
+    info!(
+        "{} types added, {} removed, {} changed",
+        diff.added_types.len(),
+        diff.removed_types.len(),
+        diff.changed_types.len()
+    );
+
+    let markdown = Path::new("./cordl_diff.md");
+    let json = Path::new("./cordl_diff.json");
+    println!("Writing diff files {markdown:?} {json:?}");
+    api_diff::write_diff(&diff, markdown, json, cli.format)?;
 
     Ok(())
 }